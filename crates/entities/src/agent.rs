@@ -73,8 +73,12 @@ pub struct AgentSession {
     pub started_at: Option<DateTime<Utc>>,
     /// When the session completed.
     pub completed_at: Option<DateTime<Utc>>,
-    /// Output log from the agent.
+    /// Output log from the agent, rewritten in full on every update. Long
+    /// running output belongs in the task store's append-only session log.
     pub output_log: Option<String>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
 }
@@ -90,6 +94,7 @@ impl AgentSession {
             started_at: None,
             completed_at: None,
             output_log: None,
+            revision: 0,
             created_at: Utc::now(),
         }
     }
@@ -123,6 +128,9 @@ pub struct AgentTask {
     pub ai_agent_type: Option<AiAgentType>,
     /// Optional default model.
     pub ai_agent_model: Option<String>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
 }
@@ -136,6 +144,7 @@ impl AgentTask {
             agent_sessions: Vec::new(),
             ai_agent_type: None,
             ai_agent_model: None,
+            revision: 0,
             created_at: Utc::now(),
         }
     }
@@ -0,0 +1,140 @@
+//! Audit log entity definitions.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Kind of principal that performed an audited action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditActorType {
+    /// An authenticated user.
+    User,
+    /// A worker acting on behalf of the server.
+    Worker,
+    /// The server itself, such as a background job.
+    #[default]
+    System,
+}
+
+/// Principal that performed an audited action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub struct AuditActor {
+    /// Kind of actor.
+    pub actor_type: AuditActorType,
+    /// User or worker ID. `None` for the system actor.
+    pub id: Option<Uuid>,
+}
+
+impl AuditActor {
+    /// Creates an actor for a user, identified by the subject of their
+    /// access token.
+    pub fn user(id: Uuid) -> Self {
+        Self {
+            actor_type: AuditActorType::User,
+            id: Some(id),
+        }
+    }
+
+    /// Creates an actor for a worker.
+    pub fn worker(id: Uuid) -> Self {
+        Self {
+            actor_type: AuditActorType::Worker,
+            id: Some(id),
+        }
+    }
+
+    /// Creates the system actor.
+    pub fn system() -> Self {
+        Self::default()
+    }
+}
+
+/// A record of one state-changing action.
+///
+/// Audit events are immutable once recorded. `before` and `after` hold
+/// JSON snapshots of the entity around the action; creates have no
+/// `before` and deletes have no `after`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Unique identifier.
+    pub id: Uuid,
+    /// Who performed the action.
+    pub actor: AuditActor,
+    /// Name of the action, such as the RPC method `task.approve`.
+    pub action: String,
+    /// Type name of the affected entity, such as `UnitTask`.
+    pub entity_type: String,
+    /// ID of the affected entity.
+    pub entity_id: Uuid,
+    /// Entity before the action.
+    pub before: Option<serde_json::Value>,
+    /// Entity after the action.
+    pub after: Option<serde_json::Value>,
+    /// When the action was performed.
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditEvent {
+    /// Creates a new audit event without snapshots.
+    pub fn new(
+        actor: AuditActor,
+        action: impl Into<String>,
+        entity_type: impl Into<String>,
+        entity_id: Uuid,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            actor,
+            action: action.into(),
+            entity_type: entity_type.into(),
+            entity_id,
+            before: None,
+            after: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Sets the snapshot of the entity before the action.
+    pub fn with_before(mut self, before: &impl Serialize) -> serde_json::Result<Self> {
+        self.before = Some(serde_json::to_value(before)?);
+        Ok(self)
+    }
+
+    /// Sets the snapshot of the entity after the action.
+    pub fn with_after(mut self, after: &impl Serialize) -> serde_json::Result<Self> {
+        self.after = Some(serde_json::to_value(after)?);
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Repository, VcsProviderType};
+
+    #[test]
+    fn test_audit_event_snapshots() {
+        let user_id = Uuid::new_v4();
+        let repository = Repository::new(
+            Uuid::new_v4(),
+            "delidev",
+            "https://github.com/delinoio/delidev",
+            VcsProviderType::Github,
+        );
+        let event = AuditEvent::new(
+            AuditActor::user(user_id),
+            "repository.delete",
+            "Repository",
+            repository.id,
+        )
+        .with_before(&repository)
+        .unwrap();
+
+        assert_eq!(event.actor.actor_type, AuditActorType::User);
+        assert_eq!(event.actor.id, Some(user_id));
+        assert_eq!(event.before.as_ref().unwrap()["name"], "delidev");
+        assert!(event.after.is_none());
+        assert_eq!(AuditActor::system().id, None);
+    }
+}
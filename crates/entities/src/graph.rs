@@ -0,0 +1,421 @@
+//! Dependency graph of a composite task's nodes.
+
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use uuid::Uuid;
+
+use crate::{AgentTask, CompositeTaskNode, UnitTask, UnitTaskStatus};
+
+/// A problem with the shape of a task graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GraphError {
+    /// Two nodes have the same ID.
+    DuplicateNode(Uuid),
+    /// A node depends on a node that is not in the graph.
+    UnknownDependency { node_id: Uuid, dependency_id: Uuid },
+    /// The dependencies form a cycle, listed starting and ending with the
+    /// same node.
+    Cycle(Vec<Uuid>),
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateNode(id) => write!(f, "Duplicate node {id}"),
+            Self::UnknownDependency {
+                node_id,
+                dependency_id,
+            } => write!(f, "Node {node_id} depends on unknown node {dependency_id}"),
+            Self::Cycle(cycle) => {
+                let ids: Vec<String> = cycle.iter().map(Uuid::to_string).collect();
+                write!(f, "Cyclic dependency {}", ids.join(" -> "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+/// Read-only view of a composite task's nodes as a dependency graph.
+///
+/// Methods that return several nodes keep the order the nodes were given
+/// in wherever the dependencies leave it free.
+#[derive(Debug, Clone)]
+pub struct TaskGraph<'a> {
+    nodes: &'a [CompositeTaskNode],
+    index: HashMap<Uuid, usize>,
+    /// Dependencies of each node, without repeats.
+    dependencies: Vec<Vec<usize>>,
+    /// Nodes depending on each node.
+    dependents: Vec<Vec<usize>>,
+}
+
+impl<'a> TaskGraph<'a> {
+    /// Builds the graph of `nodes`, which must include every node they
+    /// depend on.
+    pub fn new(nodes: &'a [CompositeTaskNode]) -> Result<Self, GraphError> {
+        let mut index = HashMap::with_capacity(nodes.len());
+        for (i, node) in nodes.iter().enumerate() {
+            if index.insert(node.id, i).is_some() {
+                return Err(GraphError::DuplicateNode(node.id));
+            }
+        }
+
+        let mut dependencies = vec![Vec::new(); nodes.len()];
+        let mut dependents = vec![Vec::new(); nodes.len()];
+        for (i, node) in nodes.iter().enumerate() {
+            for dependency_id in &node.depends_on_ids {
+                let Some(&j) = index.get(dependency_id) else {
+                    return Err(GraphError::UnknownDependency {
+                        node_id: node.id,
+                        dependency_id: *dependency_id,
+                    });
+                };
+                if !dependencies[i].contains(&j) {
+                    dependencies[i].push(j);
+                    dependents[j].push(i);
+                }
+            }
+        }
+        Ok(Self {
+            nodes,
+            index,
+            dependencies,
+            dependents,
+        })
+    }
+
+    /// Returns the nodes.
+    pub fn nodes(&self) -> &'a [CompositeTaskNode] {
+        self.nodes
+    }
+
+    /// Returns the node with ID `id`.
+    pub fn node(&self, id: Uuid) -> Option<&'a CompositeTaskNode> {
+        self.index.get(&id).map(|&i| &self.nodes[i])
+    }
+
+    /// Returns the IDs of the nodes that depend directly on `id`.
+    pub fn dependents(&self, id: Uuid) -> Vec<Uuid> {
+        self.index
+            .get(&id)
+            .map(|&i| self.ids(&self.dependents[i]))
+            .unwrap_or_default()
+    }
+
+    /// Returns a dependency cycle, if there is one.
+    pub fn find_cycle(&self) -> Option<Vec<Uuid>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Unvisited,
+            OnPath,
+            Done,
+        }
+
+        let mut state = vec![State::Unvisited; self.nodes.len()];
+        for start in 0..self.nodes.len() {
+            if state[start] != State::Unvisited {
+                continue;
+            }
+            // Each frame is a node on the current path and the index of its
+            // next dependency to visit.
+            let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+            state[start] = State::OnPath;
+            while let Some((node, next)) = path.last_mut() {
+                let node = *node;
+                let Some(&dependency) = self.dependencies[node].get(*next) else {
+                    state[node] = State::Done;
+                    path.pop();
+                    continue;
+                };
+                *next += 1;
+                match state[dependency] {
+                    State::Unvisited => {
+                        state[dependency] = State::OnPath;
+                        path.push((dependency, 0));
+                    }
+                    State::OnPath => {
+                        let from = path.iter().position(|&(n, _)| n == dependency)?;
+                        let mut cycle: Vec<usize> = path[from..].iter().map(|&(n, _)| n).collect();
+                        cycle.push(dependency);
+                        return Some(self.ids(&cycle));
+                    }
+                    State::Done => {}
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the node IDs in an order where every node comes after its
+    /// dependencies.
+    pub fn topological_order(&self) -> Result<Vec<Uuid>, GraphError> {
+        Ok(self.ids(&self.order()?))
+    }
+
+    /// Returns the status of each node's unit task, keyed by node ID.
+    pub fn node_statuses(&self, unit_tasks: &[UnitTask]) -> HashMap<Uuid, UnitTaskStatus> {
+        let by_unit_task: HashMap<Uuid, UnitTaskStatus> =
+            unit_tasks.iter().map(|t| (t.id, t.status)).collect();
+        self.nodes
+            .iter()
+            .filter_map(|n| Some((n.id, *by_unit_task.get(&n.unit_task_id)?)))
+            .collect()
+    }
+
+    /// Returns the nodes whose unit task has been dispatched to an agent:
+    /// its agent task has a session, or it has moved past `InProgress`.
+    ///
+    /// Unit tasks are created `InProgress` before any agent runs them, so
+    /// the status alone does not tell whether a node has started.
+    pub fn dispatched_nodes(
+        &self,
+        unit_tasks: &[UnitTask],
+        agent_tasks: &[AgentTask],
+    ) -> HashSet<Uuid> {
+        let by_unit_task: HashMap<Uuid, &UnitTask> = unit_tasks.iter().map(|t| (t.id, t)).collect();
+        let has_sessions: HashMap<Uuid, bool> = agent_tasks
+            .iter()
+            .map(|t| (t.id, !t.agent_sessions.is_empty()))
+            .collect();
+        self.nodes
+            .iter()
+            .filter(|n| {
+                by_unit_task.get(&n.unit_task_id).is_some_and(|t| {
+                    t.status != UnitTaskStatus::InProgress
+                        || has_sessions.get(&t.agent_task_id) == Some(&true)
+                })
+            })
+            .map(|n| n.id)
+            .collect()
+    }
+
+    /// Returns the nodes that may start now: nodes not in `dispatched`
+    /// whose dependencies are all `Done`.
+    ///
+    /// `statuses` holds the status of each node, keyed by node ID, as
+    /// returned by [`node_statuses`](Self::node_statuses), and `dispatched`
+    /// the nodes returned by [`dispatched_nodes`](Self::dispatched_nodes).
+    pub fn ready_nodes(
+        &self,
+        statuses: &HashMap<Uuid, UnitTaskStatus>,
+        dispatched: &HashSet<Uuid>,
+    ) -> Vec<Uuid> {
+        let is_done = |i: usize| statuses.get(&self.nodes[i].id) == Some(&UnitTaskStatus::Done);
+        let ready: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| !dispatched.contains(&self.nodes[i].id))
+            .filter(|&i| self.dependencies[i].iter().all(|&j| is_done(j)))
+            .collect();
+        self.ids(&ready)
+    }
+
+    /// Returns the nodes that can never run because a node they depend on,
+    /// directly or through other nodes, was rejected.
+    ///
+    /// Rejection is how a unit task fails, so it is the only status that
+    /// blocks. Nodes that already finished are not blocked.
+    pub fn blocked_nodes(&self, statuses: &HashMap<Uuid, UnitTaskStatus>) -> Vec<Uuid> {
+        let status = |i: usize| statuses.get(&self.nodes[i].id).copied();
+        let mut blocked = vec![false; self.nodes.len()];
+        let mut stack: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| status(i) == Some(UnitTaskStatus::Rejected))
+            .collect();
+        while let Some(i) = stack.pop() {
+            for &dependent in &self.dependents[i] {
+                if !blocked[dependent] {
+                    blocked[dependent] = true;
+                    stack.push(dependent);
+                }
+            }
+        }
+        let blocked: Vec<usize> = (0..self.nodes.len())
+            .filter(|&i| {
+                blocked[i]
+                    && !matches!(
+                        status(i),
+                        Some(UnitTaskStatus::Done | UnitTaskStatus::Rejected)
+                    )
+            })
+            .collect();
+        self.ids(&blocked)
+    }
+
+    /// Returns the longest chain of dependent nodes, from a node without
+    /// dependencies to the last node to finish.
+    ///
+    /// Its length is the least number of rounds of parallel execution
+    /// needed to run the whole graph.
+    pub fn critical_path(&self) -> Result<Vec<Uuid>, GraphError> {
+        // Length of the longest chain ending at each node, and the node
+        // before it on that chain.
+        let mut length = vec![0usize; self.nodes.len()];
+        let mut previous = vec![None; self.nodes.len()];
+        for i in self.order()? {
+            let longest = self.dependencies[i]
+                .iter()
+                .copied()
+                .max_by_key(|&j| (length[j], Reverse(j)));
+            length[i] = longest.map_or(0, |j| length[j]) + 1;
+            previous[i] = longest;
+        }
+        let end = (0..self.nodes.len()).max_by_key(|&i| (length[i], Reverse(i)));
+
+        let mut path = Vec::new();
+        let mut current = end;
+        while let Some(i) = current {
+            path.push(i);
+            current = previous[i];
+        }
+        path.reverse();
+        Ok(self.ids(&path))
+    }
+
+    /// Returns the node indices in dependency order, taking the earliest
+    /// ready node first.
+    fn order(&self) -> Result<Vec<usize>, GraphError> {
+        let mut remaining: Vec<usize> = self.dependencies.iter().map(Vec::len).collect();
+        let mut ready: Vec<usize> = (0..self.nodes.len())
+            .rev()
+            .filter(|&i| remaining[i] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(i) = ready.pop() {
+            order.push(i);
+            for &dependent in &self.dependents[i] {
+                remaining[dependent] -= 1;
+                if remaining[dependent] == 0 {
+                    ready.push(dependent);
+                }
+            }
+            ready.sort_unstable_by(|a, b| b.cmp(a));
+        }
+        if order.len() < self.nodes.len() {
+            return Err(GraphError::Cycle(self.find_cycle().unwrap_or_default()));
+        }
+        Ok(order)
+    }
+
+    fn ids(&self, indices: &[usize]) -> Vec<Uuid> {
+        indices.iter().map(|&i| self.nodes[i].id).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AgentSession, AiAgentType};
+
+    /// Builds the example graph from docs/plan-yaml.md: setup-db,
+    /// setup-auth-utils, auth-api, auth-middleware, auth-ui and tests.
+    fn example() -> (Vec<CompositeTaskNode>, Vec<Uuid>) {
+        let composite_task_id = Uuid::new_v4();
+        let mut nodes: Vec<CompositeTaskNode> = (0..6)
+            .map(|_| CompositeTaskNode::new(composite_task_id, Uuid::new_v4()))
+            .collect();
+        let ids: Vec<Uuid> = nodes.iter().map(|n| n.id).collect();
+        let edges = [(1, 0), (2, 0), (2, 1), (3, 1), (4, 2), (5, 2), (5, 3)];
+        for (node, dependency) in edges {
+            nodes[node].depends_on(ids[dependency]);
+        }
+        (nodes, ids)
+    }
+
+    #[test]
+    fn test_order_and_critical_path() {
+        let (nodes, ids) = example();
+        let graph = TaskGraph::new(&nodes).unwrap();
+        assert_eq!(graph.find_cycle(), None);
+        assert_eq!(graph.topological_order().unwrap(), ids);
+        assert_eq!(
+            graph.critical_path().unwrap(),
+            [ids[0], ids[1], ids[2], ids[4]]
+        );
+        assert_eq!(graph.dependents(ids[2]), [ids[4], ids[5]]);
+
+        let reversed: Vec<CompositeTaskNode> = nodes.iter().rev().cloned().collect();
+        let graph = TaskGraph::new(&reversed).unwrap();
+        let order = graph.topological_order().unwrap();
+        assert_eq!(order, [ids[0], ids[1], ids[3], ids[2], ids[5], ids[4]]);
+    }
+
+    #[test]
+    fn test_ready_and_blocked_nodes() {
+        let (nodes, ids) = example();
+        let graph = TaskGraph::new(&nodes).unwrap();
+        let mut statuses = HashMap::new();
+        let mut dispatched = HashSet::new();
+        assert_eq!(graph.ready_nodes(&statuses, &dispatched), [ids[0]]);
+
+        statuses.insert(ids[0], UnitTaskStatus::Done);
+        statuses.insert(ids[1], UnitTaskStatus::Done);
+        dispatched.extend([ids[0], ids[1]]);
+        assert_eq!(graph.ready_nodes(&statuses, &dispatched), [ids[2], ids[3]]);
+
+        statuses.insert(ids[2], UnitTaskStatus::InReview);
+        statuses.insert(ids[3], UnitTaskStatus::Rejected);
+        dispatched.extend([ids[2], ids[3]]);
+        assert!(graph.ready_nodes(&statuses, &dispatched).is_empty());
+        assert_eq!(graph.blocked_nodes(&statuses), [ids[5]]);
+
+        let mut unit_task = UnitTask::new(Uuid::new_v4(), Uuid::new_v4(), "Set up the database");
+        unit_task.id = nodes[0].unit_task_id;
+        unit_task.status = UnitTaskStatus::PrOpen;
+        assert_eq!(
+            graph.node_statuses(&[unit_task]),
+            HashMap::from([(ids[0], UnitTaskStatus::PrOpen)])
+        );
+    }
+
+    #[test]
+    fn test_dispatched_nodes() {
+        let (nodes, ids) = example();
+        let graph = TaskGraph::new(&nodes).unwrap();
+        let mut agent_tasks = vec![AgentTask::new(), AgentTask::new(), AgentTask::new()];
+        let mut unit_tasks: Vec<UnitTask> = (0..3)
+            .map(|i| {
+                let mut unit_task = UnitTask::new(Uuid::new_v4(), agent_tasks[i].id, "Prompt");
+                unit_task.id = nodes[i].unit_task_id;
+                unit_task
+            })
+            .collect();
+        assert!(graph.dispatched_nodes(&unit_tasks, &agent_tasks).is_empty());
+
+        let session = AgentSession::new(agent_tasks[0].id, AiAgentType::ClaudeCode);
+        agent_tasks[0].agent_sessions.push(session);
+        unit_tasks[1].status = UnitTaskStatus::Rejected;
+        assert_eq!(
+            graph.dispatched_nodes(&unit_tasks, &agent_tasks),
+            HashSet::from([ids[0], ids[1]])
+        );
+    }
+
+    #[test]
+    fn test_invalid_graphs() {
+        let (mut nodes, ids) = example();
+        nodes[0].depends_on(ids[5]);
+        let graph = TaskGraph::new(&nodes).unwrap();
+        let cycle = graph.find_cycle().unwrap();
+        assert_eq!(cycle.first(), cycle.last());
+        assert!(cycle.contains(&ids[0]) && cycle.contains(&ids[5]));
+        assert!(matches!(
+            graph.topological_order(),
+            Err(GraphError::Cycle(_))
+        ));
+        assert!(matches!(graph.critical_path(), Err(GraphError::Cycle(_))));
+
+        nodes[0].depends_on(Uuid::new_v4());
+        assert!(matches!(
+            TaskGraph::new(&nodes),
+            Err(GraphError::UnknownDependency { node_id, .. }) if node_id == ids[0]
+        ));
+        let duplicated = vec![nodes[1].clone(), nodes[1].clone()];
+        assert_eq!(
+            TaskGraph::new(&duplicated).unwrap_err(),
+            GraphError::DuplicateNode(ids[1])
+        );
+    }
+}
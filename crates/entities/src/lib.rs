@@ -5,17 +5,27 @@
 //! more.
 
 mod agent;
+mod audit;
+mod graph;
+mod plan;
 mod repository;
+mod status;
 mod task;
 mod todo;
 mod tty;
 mod user;
+mod worker;
 mod workspace;
 
 pub use agent::*;
+pub use audit::*;
+pub use graph::*;
+pub use plan::*;
 pub use repository::*;
+pub use status::*;
 pub use task::*;
 pub use todo::*;
 pub use tty::*;
 pub use user::*;
+pub use worker::*;
 pub use workspace::*;
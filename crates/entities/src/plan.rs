@@ -0,0 +1,107 @@
+//! Plan revision entity definitions.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::CompositeTask;
+
+/// Who produced a plan revision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAuthorType {
+    /// The composite task's planning agent.
+    #[default]
+    PlanningAgent,
+    /// A user who edited the plan.
+    User,
+}
+
+/// One version of a composite task's PLAN.yaml.
+///
+/// The planning agent produces the first revision, and every edit a user
+/// submits before approval adds another. Revisions never change once
+/// created, except that the approved one records who approved it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanRevision {
+    /// Unique identifier.
+    pub id: Uuid,
+    /// Associated CompositeTask ID.
+    pub composite_task_id: Uuid,
+    /// Position among the composite task's revisions, starting at 1.
+    /// Assigned by the task store.
+    pub number: u32,
+    /// Contents of the PLAN.yaml file.
+    pub yaml: String,
+    /// Who produced the revision.
+    pub author_type: PlanAuthorType,
+    /// Planning agent task ID or user ID, depending on `author_type`.
+    pub author_id: Uuid,
+    /// User who approved the revision, if it was approved.
+    pub approved_by: Option<Uuid>,
+    /// When the revision was approved.
+    pub approved_at: Option<DateTime<Utc>>,
+    /// When this record was created.
+    pub created_at: DateTime<Utc>,
+}
+
+impl PlanRevision {
+    /// Creates a revision generated by the planning agent of `task`.
+    pub fn from_planning_agent(task: &CompositeTask, yaml: impl Into<String>) -> Self {
+        Self::new(
+            task,
+            PlanAuthorType::PlanningAgent,
+            task.planning_task_id,
+            yaml,
+        )
+    }
+
+    /// Creates a revision edited by a user.
+    pub fn from_user(task: &CompositeTask, user_id: Uuid, yaml: impl Into<String>) -> Self {
+        Self::new(task, PlanAuthorType::User, user_id, yaml)
+    }
+
+    fn new(
+        task: &CompositeTask,
+        author_type: PlanAuthorType,
+        author_id: Uuid,
+        yaml: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            composite_task_id: task.id,
+            number: 0,
+            yaml: yaml.into(),
+            author_type,
+            author_id,
+            approved_by: None,
+            approved_at: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Returns true if the revision was approved.
+    pub fn is_approved(&self) -> bool {
+        self.approved_at.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_revision_creation() {
+        let task = CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Add auth");
+        let generated = PlanRevision::from_planning_agent(&task, "tasks: []\n");
+        assert_eq!(generated.composite_task_id, task.id);
+        assert_eq!(generated.author_type, PlanAuthorType::PlanningAgent);
+        assert_eq!(generated.author_id, task.planning_task_id);
+        assert!(!generated.is_approved());
+
+        let user_id = Uuid::new_v4();
+        let edited = PlanRevision::from_user(&task, user_id, "tasks: []\n");
+        assert_eq!(edited.author_type, PlanAuthorType::User);
+        assert_eq!(edited.author_id, user_id);
+    }
+}
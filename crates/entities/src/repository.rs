@@ -23,10 +23,16 @@ pub struct Repository {
     pub vcs_type: VcsType,
     /// VCS provider type.
     pub vcs_provider_type: VcsProviderType,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
     pub updated_at: DateTime<Utc>,
+    /// When this record was moved to the trash, if it was.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Repository {
@@ -46,8 +52,10 @@ impl Repository {
             default_branch: "main".to_string(),
             vcs_type: VcsType::Git,
             vcs_provider_type,
+            revision: 0,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
         }
     }
 
@@ -83,6 +91,9 @@ pub struct RepositoryGroup {
     pub name: Option<String>,
     /// Repository IDs in this group.
     pub repository_ids: Vec<Uuid>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
@@ -98,6 +109,7 @@ impl RepositoryGroup {
             workspace_id,
             name: None,
             repository_ids: Vec::new(),
+            revision: 0,
             created_at: now,
             updated_at: now,
         }
@@ -0,0 +1,13 @@
+//! Status state machine definitions.
+
+/// A status enum with a fixed set of legal transitions.
+///
+/// Staying in the same status is always allowed, so a record can be updated
+/// without changing its status.
+pub trait StateMachine: Copy + Eq {
+    /// Returns true if the status may move from `self` to `next`.
+    fn can_transition_to(self, next: Self) -> bool;
+
+    /// Returns true if no other status can be reached from `self`.
+    fn is_terminal(self) -> bool;
+}
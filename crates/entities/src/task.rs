@@ -4,7 +4,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-use crate::AiAgentType;
+use crate::{AiAgentType, StateMachine};
 
 /// Status of a UnitTask.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
@@ -25,6 +25,26 @@ pub enum UnitTaskStatus {
     Rejected,
 }
 
+impl StateMachine for UnitTaskStatus {
+    fn can_transition_to(self, next: Self) -> bool {
+        use UnitTaskStatus::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (InProgress, InReview | Rejected)
+                    // Request changes sends the task back to the agent.
+                    | (InReview, InProgress | Approved | PrOpen | Done | Rejected)
+                    | (Approved, PrOpen | Done | Rejected)
+                    | (PrOpen, Done | Rejected)
+            )
+    }
+
+    fn is_terminal(self) -> bool {
+        matches!(self, Self::Done | Self::Rejected)
+    }
+}
+
 /// A single task unit visible to users.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnitTask {
@@ -50,10 +70,16 @@ pub struct UnitTask {
     pub auto_fix_task_ids: Vec<Uuid>,
     /// Current status.
     pub status: UnitTaskStatus,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
     pub updated_at: DateTime<Utc>,
+    /// When this record was moved to the trash, if it was.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl UnitTask {
@@ -72,8 +98,10 @@ impl UnitTask {
             end_commit: None,
             auto_fix_task_ids: Vec::new(),
             status: UnitTaskStatus::InProgress,
+            revision: 0,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
         }
     }
 
@@ -107,6 +135,25 @@ pub enum CompositeTaskStatus {
     Rejected,
 }
 
+impl StateMachine for CompositeTaskStatus {
+    fn can_transition_to(self, next: Self) -> bool {
+        use CompositeTaskStatus::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Planning, PendingApproval | Rejected)
+                    // Editing the plan sends it back to planning.
+                    | (PendingApproval, Planning | InProgress | Rejected)
+                    | (InProgress, Done)
+            )
+    }
+
+    fn is_terminal(self) -> bool {
+        matches!(self, Self::Done | Self::Rejected)
+    }
+}
+
 /// A node in a CompositeTask graph.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompositeTaskNode {
@@ -118,6 +165,9 @@ pub struct CompositeTaskNode {
     pub unit_task_id: Uuid,
     /// IDs of nodes this node depends on.
     pub depends_on_ids: Vec<Uuid>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
 }
@@ -130,13 +180,16 @@ impl CompositeTaskNode {
             composite_task_id,
             unit_task_id,
             depends_on_ids: Vec::new(),
+            revision: 0,
             created_at: Utc::now(),
         }
     }
 
-    /// Adds a dependency to this node.
+    /// Adds a dependency to this node, unless it already has it.
     pub fn depends_on(&mut self, node_id: Uuid) {
-        self.depends_on_ids.push(node_id);
+        if !self.depends_on_ids.contains(&node_id) {
+            self.depends_on_ids.push(node_id);
+        }
     }
 }
 
@@ -159,10 +212,16 @@ pub struct CompositeTask {
     pub status: CompositeTaskStatus,
     /// Agent type for UnitTasks.
     pub execution_agent_type: Option<AiAgentType>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
     pub updated_at: DateTime<Utc>,
+    /// When this record was moved to the trash, if it was.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl CompositeTask {
@@ -182,8 +241,10 @@ impl CompositeTask {
             node_ids: Vec::new(),
             status: CompositeTaskStatus::Planning,
             execution_agent_type: None,
+            revision: 0,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
         }
     }
 
@@ -280,6 +341,33 @@ mod tests {
         assert_eq!(task.execution_agent_type, Some(AiAgentType::ClaudeCode));
     }
 
+    #[test]
+    fn test_unit_task_status_transitions() {
+        use UnitTaskStatus::*;
+
+        assert!(InProgress.can_transition_to(InReview));
+        assert!(InReview.can_transition_to(InProgress));
+        assert!(Approved.can_transition_to(PrOpen));
+        assert!(PrOpen.can_transition_to(Done));
+        assert!(Done.can_transition_to(Done));
+        assert!(!Rejected.can_transition_to(PrOpen));
+        assert!(!InProgress.can_transition_to(Approved));
+        assert!(!Done.can_transition_to(InProgress));
+        assert!(Rejected.is_terminal());
+        assert!(!PrOpen.is_terminal());
+    }
+
+    #[test]
+    fn test_composite_task_status_transitions() {
+        use CompositeTaskStatus::*;
+
+        assert!(Planning.can_transition_to(PendingApproval));
+        assert!(PendingApproval.can_transition_to(InProgress));
+        assert!(PendingApproval.can_transition_to(Planning));
+        assert!(!Planning.can_transition_to(InProgress));
+        assert!(!Rejected.can_transition_to(Planning));
+    }
+
     #[test]
     fn test_task_enum() {
         let repo_group_id = Uuid::new_v4();
@@ -4,6 +4,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::StateMachine;
+
 /// Status of a TodoItem.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -19,6 +21,22 @@ pub enum TodoItemStatus {
     Dismissed,
 }
 
+impl StateMachine for TodoItemStatus {
+    fn can_transition_to(self, next: Self) -> bool {
+        use TodoItemStatus::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Pending, InProgress | Completed | Dismissed) | (InProgress, Completed | Dismissed)
+            )
+    }
+
+    fn is_terminal(self) -> bool {
+        matches!(self, Self::Completed | Self::Dismissed)
+    }
+}
+
 /// Source of a TodoItem.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -91,6 +109,9 @@ pub struct TodoItem {
     pub repository_id: Uuid,
     /// Type-specific data.
     pub data: TodoItemData,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
@@ -113,6 +134,7 @@ impl TodoItem {
                 suggested_labels: Vec::new(),
                 suggested_assignees: Vec::new(),
             }),
+            revision: 0,
             created_at: now,
             updated_at: now,
         }
@@ -138,6 +160,7 @@ impl TodoItem {
                 changed_files_count,
                 ai_summary: None,
             }),
+            revision: 0,
             created_at: now,
             updated_at: now,
         }
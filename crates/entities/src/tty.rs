@@ -4,6 +4,8 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::StateMachine;
+
 /// Type of TTY input.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -34,6 +36,18 @@ pub enum TtyInputStatus {
     Cancelled,
 }
 
+impl StateMachine for TtyInputStatus {
+    fn can_transition_to(self, next: Self) -> bool {
+        use TtyInputStatus::*;
+
+        self == next || matches!((self, next), (Pending, Responded | Timeout | Cancelled))
+    }
+
+    fn is_terminal(self) -> bool {
+        self != Self::Pending
+    }
+}
+
 /// A request for TTY input from an AI agent.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TtyInputRequest {
@@ -53,6 +67,9 @@ pub struct TtyInputRequest {
     pub status: TtyInputStatus,
     /// User's response.
     pub response: Option<String>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When the user responded.
@@ -71,6 +88,7 @@ impl TtyInputRequest {
             options: None,
             status: TtyInputStatus::Pending,
             response: None,
+            revision: 0,
             created_at: Utc::now(),
             responded_at: None,
         }
@@ -151,4 +169,15 @@ mod tests {
         assert_eq!(request.response, Some("My answer".to_string()));
         assert!(request.responded_at.is_some());
     }
+
+    #[test]
+    fn test_tty_input_status_transitions() {
+        use TtyInputStatus::*;
+
+        assert!(Pending.can_transition_to(Responded));
+        assert!(Pending.can_transition_to(Timeout));
+        assert!(!Timeout.can_transition_to(Pending));
+        assert!(!Responded.can_transition_to(Cancelled));
+        assert!(Cancelled.is_terminal());
+    }
 }
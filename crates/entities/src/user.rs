@@ -13,6 +13,9 @@ pub struct User {
     pub email: String,
     /// Display name.
     pub name: Option<String>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
@@ -27,6 +30,7 @@ impl User {
             id: Uuid::new_v4(),
             email: email.into(),
             name: None,
+            revision: 0,
             created_at: now,
             updated_at: now,
         }
@@ -0,0 +1,100 @@
+//! Worker entity definitions.
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Status of a worker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkerStatus {
+    /// Ready to accept a task.
+    #[default]
+    Idle,
+    /// Running a task.
+    Busy,
+    /// Missed its heartbeats or reported a failure.
+    Unhealthy,
+}
+
+/// A worker server registered with the main server.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Worker {
+    /// Unique identifier.
+    pub id: Uuid,
+    /// Display name, usually the worker's host name.
+    pub name: String,
+    /// URL the main server reaches the worker at.
+    pub endpoint_url: String,
+    /// Capability tags advertised by the worker, such as the AI agents it
+    /// can run.
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Current status.
+    pub status: WorkerStatus,
+    /// UnitTask the worker is running, if any.
+    pub current_task_id: Option<Uuid>,
+    /// When the worker last sent a heartbeat.
+    pub last_heartbeat: DateTime<Utc>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
+    /// When the worker registered.
+    pub created_at: DateTime<Utc>,
+}
+
+impl Worker {
+    /// Creates a new idle worker.
+    pub fn new(name: impl Into<String>, endpoint_url: impl Into<String>) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            endpoint_url: endpoint_url.into(),
+            capabilities: Vec::new(),
+            status: WorkerStatus::Idle,
+            current_task_id: None,
+            last_heartbeat: now,
+            revision: 0,
+            created_at: now,
+        }
+    }
+
+    /// Sets the capability tags.
+    pub fn with_capabilities(mut self, capabilities: Vec<String>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    /// Returns true if the worker advertises `capability`.
+    pub fn has_capability(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Returns true if the worker has not sent a heartbeat within `timeout`
+    /// of `now`.
+    pub fn is_stale(&self, timeout: TimeDelta, now: DateTime<Utc>) -> bool {
+        self.last_heartbeat + timeout <= now
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_worker_creation() {
+        let worker = Worker::new("worker-1", "http://10.0.0.5:54872")
+            .with_capabilities(vec!["claude_code".to_string(), "aider".to_string()]);
+
+        assert_eq!(worker.status, WorkerStatus::Idle);
+        assert_eq!(worker.current_task_id, None);
+        assert!(worker.has_capability("aider"));
+        assert!(!worker.has_capability("codex_cli"));
+        assert!(!worker.is_stale(TimeDelta::seconds(30), worker.last_heartbeat));
+        assert!(worker.is_stale(
+            TimeDelta::seconds(30),
+            worker.last_heartbeat + TimeDelta::seconds(30)
+        ));
+    }
+}
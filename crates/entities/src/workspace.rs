@@ -15,10 +15,16 @@ pub struct Workspace {
     pub description: Option<String>,
     /// Associated user ID (None in single-user mode).
     pub user_id: Option<Uuid>,
+    /// Revision counter, incremented by the task store on every update.
+    #[serde(default)]
+    pub revision: u64,
     /// When this record was created.
     pub created_at: DateTime<Utc>,
     /// When this record was last updated.
     pub updated_at: DateTime<Utc>,
+    /// When this record was moved to the trash, if it was.
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
 impl Workspace {
@@ -30,8 +36,10 @@ impl Workspace {
             name: name.into(),
             description: None,
             user_id: None,
+            revision: 0,
             created_at: now,
             updated_at: now,
+            deleted_at: None,
         }
     }
 
@@ -0,0 +1,224 @@
+//! Structural differences between two versions of a plan.
+
+use std::collections::HashMap;
+
+use crate::{Plan, PlanTask};
+
+/// A field of a plan task other than its ID and dependencies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlanTaskField {
+    /// `title`.
+    Title,
+    /// `prompt`.
+    Prompt,
+    /// `branchName`.
+    BranchName,
+}
+
+/// A task present in both plans whose fields differ.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanTaskChange {
+    /// ID of the task.
+    pub task_id: String,
+    /// Fields that differ, in declaration order.
+    pub fields: Vec<PlanTaskField>,
+}
+
+/// A dependency between two tasks of a plan.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlanEdge {
+    /// ID of the dependent task.
+    pub task_id: String,
+    /// ID of the task it depends on.
+    pub depends_on: String,
+}
+
+/// How one plan differs from another.
+///
+/// Tasks are matched by ID, so renaming a task shows up as removing it and
+/// adding another. Changes to a task's dependencies are reported as edges
+/// rather than as a changed field. Lists follow file order, taking removed
+/// tasks and edges from the older plan and the rest from the newer one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanDiff {
+    /// IDs of tasks only in the newer plan.
+    pub added_tasks: Vec<String>,
+    /// IDs of tasks only in the older plan.
+    pub removed_tasks: Vec<String>,
+    /// Tasks in both plans whose fields differ.
+    pub changed_tasks: Vec<PlanTaskChange>,
+    /// Dependencies only in the newer plan.
+    pub added_edges: Vec<PlanEdge>,
+    /// Dependencies only in the older plan.
+    pub removed_edges: Vec<PlanEdge>,
+}
+
+impl PlanDiff {
+    /// Returns true if the plans have the same structure.
+    pub fn is_empty(&self) -> bool {
+        self.added_tasks.is_empty()
+            && self.removed_tasks.is_empty()
+            && self.changed_tasks.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl Plan {
+    /// Compares this plan with a newer version of it.
+    pub fn diff(&self, newer: &Plan) -> PlanDiff {
+        let old_tasks = tasks_by_id(self);
+        let new_tasks = tasks_by_id(newer);
+        let old_edges = edges(self);
+        let new_edges = edges(newer);
+
+        PlanDiff {
+            added_tasks: newer
+                .tasks
+                .iter()
+                .filter(|t| !old_tasks.contains_key(t.id.as_str()))
+                .map(|t| t.id.clone())
+                .collect(),
+            removed_tasks: self
+                .tasks
+                .iter()
+                .filter(|t| !new_tasks.contains_key(t.id.as_str()))
+                .map(|t| t.id.clone())
+                .collect(),
+            changed_tasks: newer
+                .tasks
+                .iter()
+                .filter_map(|new| {
+                    let old = old_tasks.get(new.id.as_str())?;
+                    let fields = changed_fields(old, new);
+                    (!fields.is_empty()).then(|| PlanTaskChange {
+                        task_id: new.id.clone(),
+                        fields,
+                    })
+                })
+                .collect(),
+            added_edges: new_edges
+                .iter()
+                .filter(|e| !old_edges.contains(e))
+                .cloned()
+                .collect(),
+            removed_edges: old_edges
+                .iter()
+                .filter(|e| !new_edges.contains(e))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+/// Maps task IDs to the first task with each ID.
+fn tasks_by_id(plan: &Plan) -> HashMap<&str, &PlanTask> {
+    let mut tasks = HashMap::with_capacity(plan.tasks.len());
+    for task in &plan.tasks {
+        tasks.entry(task.id.as_str()).or_insert(task);
+    }
+    tasks
+}
+
+/// Returns the dependencies of a plan without repeats.
+fn edges(plan: &Plan) -> Vec<PlanEdge> {
+    let mut edges = Vec::new();
+    for task in &plan.tasks {
+        for dependency in &task.depends_on {
+            let edge = PlanEdge {
+                task_id: task.id.clone(),
+                depends_on: dependency.clone(),
+            };
+            if !edges.contains(&edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    edges
+}
+
+fn changed_fields(old: &PlanTask, new: &PlanTask) -> Vec<PlanTaskField> {
+    let mut fields = Vec::new();
+    if old.title != new.title {
+        fields.push(PlanTaskField::Title);
+    }
+    if old.prompt != new.prompt {
+        fields.push(PlanTaskField::Prompt);
+    }
+    if old.branch_name != new.branch_name {
+        fields.push(PlanTaskField::BranchName);
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edge(task_id: &str, depends_on: &str) -> PlanEdge {
+        PlanEdge {
+            task_id: task_id.to_string(),
+            depends_on: depends_on.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Plan::parse(
+            "\
+tasks:
+  - id: db
+    prompt: Create the schema
+  - id: api
+    prompt: Implement the API
+    dependsOn: [db]
+  - id: docs
+    prompt: Document the API
+    dependsOn: [api]
+",
+        )
+        .unwrap();
+        let new = Plan::parse(
+            "\
+tasks:
+  - id: db
+    title: Database
+    prompt: Create the schema and seed data
+    branchName: feature/db
+  - id: api
+    prompt: Implement the API
+    dependsOn: [db, db]
+  - id: tests
+    prompt: Write tests
+    dependsOn: [api, db]
+",
+        )
+        .unwrap();
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_tasks, ["tests"]);
+        assert_eq!(diff.removed_tasks, ["docs"]);
+        assert_eq!(
+            diff.changed_tasks,
+            [PlanTaskChange {
+                task_id: "db".to_string(),
+                fields: vec![
+                    PlanTaskField::Title,
+                    PlanTaskField::Prompt,
+                    PlanTaskField::BranchName
+                ],
+            }]
+        );
+        assert_eq!(
+            diff.added_edges,
+            [edge("tests", "api"), edge("tests", "db")]
+        );
+        assert_eq!(diff.removed_edges, [edge("docs", "api")]);
+        assert!(!diff.is_empty());
+
+        assert!(new.diff(&new).is_empty());
+        let reverse = new.diff(&old);
+        assert_eq!(reverse.added_tasks, ["docs"]);
+        assert_eq!(reverse.removed_edges, diff.added_edges);
+    }
+}
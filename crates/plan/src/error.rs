@@ -0,0 +1,172 @@
+//! Plan error types.
+
+use std::fmt;
+
+use entities::GraphError;
+use thiserror::Error;
+use uuid::Uuid;
+
+/// Position in a PLAN.yaml file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Location {
+    /// Line, starting at 1.
+    pub line: usize,
+    /// Column, starting at 1.
+    pub column: usize,
+}
+
+impl Location {
+    /// Creates a location.
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// Formats an optional location as a message suffix.
+fn at(location: &Option<Location>) -> String {
+    location.map_or_else(String::new, |location| format!(" at {location}"))
+}
+
+/// A problem found while parsing or validating a plan.
+///
+/// Locations are known for plans parsed from YAML and absent for plans
+/// built in code.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PlanError {
+    /// The file is not well-formed YAML.
+    #[error("Invalid YAML{}: {message}", at(.location))]
+    Syntax {
+        message: String,
+        location: Option<Location>,
+    },
+
+    /// A field is missing, unknown or has the wrong type.
+    #[error("Invalid plan{}: {message}", at(.location))]
+    InvalidField {
+        message: String,
+        location: Option<Location>,
+    },
+
+    /// Two tasks have the same ID.
+    #[error("Duplicate task ID {id}{}", at(.location))]
+    DuplicateTaskId {
+        id: String,
+        location: Option<Location>,
+    },
+
+    /// `dependsOn` references a task ID that is not in the plan.
+    #[error("Task {task_id} depends on unknown task {dependency}{}", at(.location))]
+    InvalidDependency {
+        task_id: String,
+        dependency: String,
+        location: Option<Location>,
+    },
+
+    /// The dependencies form a cycle. `cycle` lists the task IDs along it,
+    /// starting and ending with the same task.
+    #[error("Cyclic dependency {}{}", .cycle.join(" -> "), at(.location))]
+    CyclicDependency {
+        cycle: Vec<String>,
+        location: Option<Location>,
+    },
+
+    /// A task has an empty or missing prompt.
+    #[error("Task {task_id} has an empty prompt{}", at(.location))]
+    EmptyPrompt {
+        task_id: String,
+        location: Option<Location>,
+    },
+}
+
+impl PlanError {
+    /// Returns the error's name as used in the PLAN.yaml specification.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Syntax { .. } => "InvalidYaml",
+            Self::InvalidField { .. } => "InvalidField",
+            Self::DuplicateTaskId { .. } => "DuplicateTaskId",
+            Self::InvalidDependency { .. } => "InvalidDependency",
+            Self::CyclicDependency { .. } => "CyclicDependency",
+            Self::EmptyPrompt { .. } => "EmptyPrompt",
+        }
+    }
+
+    /// Returns where in the file the problem is, if known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            Self::Syntax { location, .. }
+            | Self::InvalidField { location, .. }
+            | Self::DuplicateTaskId { location, .. }
+            | Self::InvalidDependency { location, .. }
+            | Self::CyclicDependency { location, .. }
+            | Self::EmptyPrompt { location, .. } => *location,
+        }
+    }
+}
+
+/// Every problem found in a plan, in file order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanErrors(Vec<PlanError>);
+
+impl PlanErrors {
+    /// Wraps a non-empty list of errors, ordering them by location.
+    pub(crate) fn new(mut errors: Vec<PlanError>) -> Self {
+        errors.sort_by_key(PlanError::location);
+        Self(errors)
+    }
+
+    /// Returns the errors.
+    pub fn errors(&self) -> &[PlanError] {
+        &self.0
+    }
+}
+
+impl From<PlanError> for PlanErrors {
+    fn from(error: PlanError) -> Self {
+        Self(vec![error])
+    }
+}
+
+impl IntoIterator for PlanErrors {
+    type IntoIter = std::vec::IntoIter<PlanError>;
+    type Item = PlanError;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl fmt::Display for PlanErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for PlanErrors {}
+
+/// Result type for plan operations.
+pub type PlanResult<T> = Result<T, PlanErrors>;
+
+/// A problem turning a stored composite task back into a plan.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ExportError {
+    /// The nodes do not form a valid task graph.
+    #[error("Invalid task graph: {0}")]
+    Graph(#[from] GraphError),
+
+    /// A node's unit task was not given.
+    #[error("Unit task {unit_task_id} of node {node_id} not found")]
+    MissingUnitTask { node_id: Uuid, unit_task_id: Uuid },
+}
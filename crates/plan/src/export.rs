@@ -0,0 +1,296 @@
+//! Turning a stored composite task back into PLAN.yaml.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use entities::{CompositeTask, CompositeTaskNode, TaskGraph, UnitTask, UnitTaskStatus};
+use uuid::Uuid;
+
+use crate::{ExportError, Plan, PlanTask, slug};
+
+/// A plan recovered from the entities of a composite task.
+#[derive(Debug, Clone)]
+pub struct ExportedPlan {
+    /// The plan, with tasks in dependency order.
+    pub plan: Plan,
+    /// Node ID of each task, in the same order.
+    pub node_ids: Vec<Uuid>,
+    /// Status of each task's unit task, keyed by plan task ID.
+    pub statuses: HashMap<String, UnitTaskStatus>,
+}
+
+impl Plan {
+    /// Rebuilds the plan of `composite_task` from its nodes and their unit
+    /// tasks.
+    ///
+    /// Stored entities do not keep plan task IDs, so each task is named
+    /// after the slug of its unit task's title, falling back to `task-{n}`
+    /// and adding `-2`, `-3`, … to repeats. Tasks follow the composite
+    /// task's `node_ids` wherever the dependencies allow, so exporting the
+    /// same entities always gives the same plan. Branch names containing
+    /// the unit task's ID are left out, as they were generated from the
+    /// branch template and would name the old task.
+    pub fn from_composite_task(
+        composite_task: &CompositeTask,
+        nodes: &[CompositeTaskNode],
+        unit_tasks: &[UnitTask],
+    ) -> Result<ExportedPlan, ExportError> {
+        let position: HashMap<Uuid, usize> = composite_task
+            .node_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (*id, i))
+            .collect();
+        let mut nodes = nodes.to_vec();
+        nodes.sort_by_key(|n| position.get(&n.id).copied().unwrap_or(usize::MAX));
+        let graph = TaskGraph::new(&nodes)?;
+        let order = graph.topological_order()?;
+
+        let unit_tasks: HashMap<Uuid, &UnitTask> = unit_tasks.iter().map(|t| (t.id, t)).collect();
+        let mut task_ids: HashMap<Uuid, String> = HashMap::with_capacity(order.len());
+        let mut used = HashSet::with_capacity(order.len());
+        let mut exported = ExportedPlan {
+            plan: Plan::default(),
+            node_ids: Vec::with_capacity(order.len()),
+            statuses: HashMap::with_capacity(order.len()),
+        };
+        for (n, node_id) in order.into_iter().enumerate() {
+            let node = graph.node(node_id).expect("ordered nodes are in the graph");
+            let unit_task =
+                unit_tasks
+                    .get(&node.unit_task_id)
+                    .ok_or(ExportError::MissingUnitTask {
+                        node_id,
+                        unit_task_id: node.unit_task_id,
+                    })?;
+
+            let id = unique_id(unit_task, n + 1, &mut used);
+            let mut task = PlanTask::new(id.clone(), unit_task.prompt.clone());
+            task.title = unit_task.title.clone().filter(|title| *title != id);
+            task.branch_name = unit_task
+                .branch_name
+                .clone()
+                .filter(|branch| !branch.contains(&unit_task.id.to_string()));
+            for dependency in &node.depends_on_ids {
+                let dependency = task_ids[dependency].clone();
+                if !task.depends_on.contains(&dependency) {
+                    task.depends_on.push(dependency);
+                }
+            }
+
+            task_ids.insert(node_id, id.clone());
+            exported.statuses.insert(id, unit_task.status);
+            exported.node_ids.push(node_id);
+            exported.plan.tasks.push(task);
+        }
+        Ok(exported)
+    }
+
+    /// Writes the plan as canonical PLAN.yaml.
+    ///
+    /// Fields appear in the order the specification lists them, strings
+    /// are double-quoted and multi-line prompts use literal blocks. Parsing
+    /// the output gives back an equal plan.
+    pub fn to_yaml(&self) -> String {
+        if self.tasks.is_empty() {
+            return "tasks: []\n".to_string();
+        }
+        let mut yaml = String::from("tasks:\n");
+        for (i, task) in self.tasks.iter().enumerate() {
+            if i > 0 {
+                yaml.push('\n');
+            }
+            let _ = writeln!(yaml, "  - id: {}", quoted(&task.id));
+            if let Some(title) = &task.title {
+                let _ = writeln!(yaml, "    title: {}", quoted(title));
+            }
+            let _ = writeln!(yaml, "    prompt: {}", prompt(&task.prompt));
+            if let Some(branch_name) = &task.branch_name {
+                let _ = writeln!(yaml, "    branchName: {}", quoted(branch_name));
+            }
+            if !task.depends_on.is_empty() {
+                let ids: Vec<String> = task.depends_on.iter().map(|id| quoted(id)).collect();
+                let _ = writeln!(yaml, "    dependsOn: [{}]", ids.join(", "));
+            }
+        }
+        yaml
+    }
+}
+
+/// Returns an ID for `unit_task`, the `n`th task of the plan, that is not
+/// in `used`, and adds it.
+fn unique_id(unit_task: &UnitTask, n: usize, used: &mut HashSet<String>) -> String {
+    let base = unit_task
+        .title
+        .as_deref()
+        .map(slug)
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| format!("task-{n}"));
+    let mut id = base.clone();
+    let mut repeat = 1;
+    while used.contains(&id) {
+        repeat += 1;
+        id = format!("{base}-{repeat}");
+    }
+    used.insert(id.clone());
+    id
+}
+
+/// Formats a prompt, as a literal block if it spans several lines.
+///
+/// Prompts a literal block cannot hold exactly, such as those ending in
+/// blank lines or starting with spaces, are quoted instead.
+fn prompt(text: &str) -> String {
+    let body = text.trim_end_matches('\n');
+    let literal = body.contains('\n')
+        && text.len() - body.len() <= 1
+        && !body.starts_with([' ', '\n'])
+        && !text.chars().any(|c| c.is_control() && c != '\n');
+    if !literal {
+        return quoted(text);
+    }
+    let chomping = if text.ends_with('\n') { "" } else { "-" };
+    let mut block = format!("|{chomping}");
+    for line in text.split_terminator('\n') {
+        block.push('\n');
+        if !line.is_empty() {
+            block.push_str("      ");
+            block.push_str(line);
+        }
+    }
+    block
+}
+
+/// Formats a string as a double-quoted YAML scalar.
+fn quoted(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_composite_task() {
+        let plan = Plan::parse(
+            "\
+tasks:
+  - id: api
+    title: REST API
+    prompt: Implement the API
+    dependsOn: [db]
+  - id: db
+    prompt: Create the schema
+    branchName: feature/auth-database
+  - id: docs
+    title: REST API
+    prompt: Document the API
+    dependsOn: [api, db]
+",
+        )
+        .unwrap();
+        let mut composite = CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Add auth");
+        let mut materialized = plan.materialize(&mut composite, None).unwrap();
+        materialized.unit_tasks[2].status = UnitTaskStatus::InReview;
+        // Stores list nodes by creation time, not plan order.
+        materialized.nodes.reverse();
+
+        let exported =
+            Plan::from_composite_task(&composite, &materialized.nodes, &materialized.unit_tasks)
+                .unwrap();
+        assert_eq!(exported.node_ids, composite.node_ids);
+        assert_eq!(
+            exported.plan,
+            Plan {
+                tasks: vec![
+                    PlanTask::new("db", "Create the schema")
+                        .with_branch_name("feature/auth-database"),
+                    PlanTask::new("rest-api", "Implement the API")
+                        .with_title("REST API")
+                        .depends_on("db"),
+                    PlanTask::new("rest-api-2", "Document the API")
+                        .with_title("REST API")
+                        .depends_on("rest-api")
+                        .depends_on("db"),
+                ],
+            }
+        );
+        assert_eq!(exported.statuses["rest-api-2"], UnitTaskStatus::InReview);
+        assert_eq!(exported.statuses["db"], UnitTaskStatus::InProgress);
+
+        let missing = Plan::from_composite_task(
+            &composite,
+            &materialized.nodes,
+            &materialized.unit_tasks[1..],
+        );
+        assert!(matches!(missing, Err(ExportError::MissingUnitTask { .. })));
+    }
+
+    #[test]
+    fn test_to_yaml() {
+        let plan = Plan {
+            tasks: vec![
+                PlanTask::new("setup-db", "Create the schema")
+                    .with_title("Setup \"DB\"")
+                    .with_branch_name("feature/auth-database"),
+                PlanTask::new("auth-api", "Implement endpoints:\n\n- login\n- signup\n")
+                    .depends_on("setup-db"),
+                PlanTask::new("notes", "  indented\nsecond line").depends_on("auth-api"),
+                PlanTask::new("steps", "First\n  then second").depends_on("notes"),
+                PlanTask::new("trailing", "one\ntwo\n\n"),
+            ],
+        };
+        let yaml = plan.to_yaml();
+        assert_eq!(
+            yaml,
+            r#"tasks:
+  - id: "setup-db"
+    title: "Setup \"DB\""
+    prompt: "Create the schema"
+    branchName: "feature/auth-database"
+
+  - id: "auth-api"
+    prompt: |
+      Implement endpoints:
+
+      - login
+      - signup
+    dependsOn: ["setup-db"]
+
+  - id: "notes"
+    prompt: "  indented\nsecond line"
+    dependsOn: ["auth-api"]
+
+  - id: "steps"
+    prompt: |-
+      First
+        then second
+    dependsOn: ["notes"]
+
+  - id: "trailing"
+    prompt: "one\ntwo\n\n"
+"#
+        );
+        assert_eq!(Plan::parse(&yaml).unwrap(), plan);
+        assert_eq!(Plan::default().to_yaml(), "tasks: []\n");
+        assert_eq!(Plan::parse("tasks: []\n").unwrap(), Plan::default());
+    }
+}
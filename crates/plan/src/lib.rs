@@ -0,0 +1,24 @@
+//! PLAN.yaml support for DeliDev.
+//!
+//! This crate provides:
+//! - Parsing `PLAN-{random}.yaml` files with line and column diagnostics
+//! - Validation against the rules in `docs/plan-yaml.md`
+//! - Materializing a plan into the agent tasks, unit tasks and nodes of a
+//!   composite task
+//! - Structural diffs between versions of a plan
+//! - Exporting a stored composite task as canonical PLAN.yaml, and rendering
+//!   plans as Mermaid or Graphviz DOT graphs
+
+mod diff;
+mod error;
+mod export;
+mod materialize;
+mod plan;
+mod render;
+mod yaml;
+
+pub use diff::*;
+pub use error::*;
+pub use export::*;
+pub use materialize::*;
+pub use plan::*;
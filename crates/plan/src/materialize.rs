@@ -0,0 +1,232 @@
+//! Turning a plan into the entities of a composite task.
+
+use entities::{AgentTask, CompositeTask, CompositeTaskNode, UnitTask};
+use git_ops::branch_name_for_task;
+use uuid::Uuid;
+
+use crate::{Plan, PlanResult, PlanTask};
+
+/// Entities created from a plan for one composite task.
+///
+/// Each plan task becomes an agent task, a unit task running it and a node
+/// of the composite task's graph. The vectors are in dependency order:
+/// every node comes after the nodes it depends on, so the entities can be
+/// written to a task store one at a time.
+#[derive(Debug, Clone)]
+pub struct MaterializedPlan {
+    /// Agent tasks, one per plan task.
+    pub agent_tasks: Vec<AgentTask>,
+    /// Unit tasks, one per plan task.
+    pub unit_tasks: Vec<UnitTask>,
+    /// Graph nodes, one per plan task, with dependencies wired up.
+    pub nodes: Vec<CompositeTaskNode>,
+    /// Plan task ID of each node, in the same order.
+    pub plan_task_ids: Vec<String>,
+}
+
+impl Plan {
+    /// Creates the agent tasks, unit tasks and nodes of `composite_task`
+    /// from this plan and sets its `node_ids`.
+    ///
+    /// Agent tasks use the composite task's execution agent type. Branch
+    /// names follow the specification's priority: the task's `branchName`,
+    /// then `branch_template` from the repository settings, then
+    /// `delidev/${taskId}`.
+    ///
+    /// Fails if the plan does not pass [`validate`](Plan::validate).
+    pub fn materialize(
+        &self,
+        composite_task: &mut CompositeTask,
+        branch_template: Option<&str>,
+    ) -> PlanResult<MaterializedPlan> {
+        self.validate()?;
+
+        let node_ids: Vec<Uuid> = self.tasks.iter().map(|_| Uuid::new_v4()).collect();
+        let mut materialized = MaterializedPlan {
+            agent_tasks: Vec::with_capacity(self.tasks.len()),
+            unit_tasks: Vec::with_capacity(self.tasks.len()),
+            nodes: Vec::with_capacity(self.tasks.len()),
+            plan_task_ids: Vec::with_capacity(self.tasks.len()),
+        };
+        for i in self.dependency_order() {
+            let task = &self.tasks[i];
+            let mut agent_task = AgentTask::new();
+            agent_task.ai_agent_type = composite_task.execution_agent_type;
+            let unit_task = unit_task(task, composite_task, &agent_task, branch_template);
+
+            let mut node = CompositeTaskNode::new(composite_task.id, unit_task.id);
+            node.id = node_ids[i];
+            for dependency in &task.depends_on {
+                let j = self.tasks.iter().position(|t| t.id == *dependency).unwrap();
+                node.depends_on(node_ids[j]);
+            }
+
+            materialized.agent_tasks.push(agent_task);
+            materialized.unit_tasks.push(unit_task);
+            materialized.nodes.push(node);
+            materialized.plan_task_ids.push(task.id.clone());
+        }
+        composite_task.node_ids = materialized.nodes.iter().map(|n| n.id).collect();
+        Ok(materialized)
+    }
+}
+
+fn unit_task(
+    task: &PlanTask,
+    composite_task: &CompositeTask,
+    agent_task: &AgentTask,
+    branch_template: Option<&str>,
+) -> UnitTask {
+    let mut unit_task = UnitTask::new(
+        composite_task.repository_group_id,
+        agent_task.id,
+        task.prompt.clone(),
+    )
+    .with_title(task.display_title());
+    let branch_name = match &task.branch_name {
+        Some(branch_name) => branch_name.clone(),
+        None => {
+            let slug = slug(task.display_title());
+            branch_name_for_task(
+                &unit_task.id.to_string(),
+                (!slug.is_empty()).then_some(slug.as_str()),
+                branch_template,
+            )
+        }
+    };
+    unit_task.branch_name = Some(branch_name);
+    unit_task
+}
+
+/// Returns a URL-safe form of a title: lowercase ASCII letters and digits
+/// with single hyphens between words.
+pub fn slug(title: &str) -> String {
+    let mut slug = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+    slug
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::{AgentSession, AiAgentType, TaskGraph};
+
+    use super::*;
+
+    #[test]
+    fn test_materialize() {
+        let plan = Plan::parse(
+            "\
+tasks:
+  - id: api
+    title: REST API Endpoints
+    prompt: Implement the API
+    dependsOn: [db, db]
+  - id: db
+    prompt: Create the schema
+    branchName: feature/auth-database
+  - id: tests
+    prompt: Write tests
+    dependsOn: [api]
+",
+        )
+        .unwrap();
+        let mut composite = CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Add auth")
+            .with_execution_agent_type(AiAgentType::Aider);
+
+        let materialized = plan
+            .materialize(&mut composite, Some("feature/${taskId}-${slug}"))
+            .unwrap();
+        assert_eq!(materialized.plan_task_ids, ["db", "api", "tests"]);
+        assert_eq!(
+            composite.node_ids,
+            materialized.nodes.iter().map(|n| n.id).collect::<Vec<_>>()
+        );
+
+        let [db, api, _] = &materialized.unit_tasks[..] else {
+            panic!("expected three unit tasks");
+        };
+        assert_eq!(db.branch_name.as_deref(), Some("feature/auth-database"));
+        assert_eq!(db.title.as_deref(), Some("db"));
+        assert_eq!(
+            api.branch_name,
+            Some(format!("feature/{}-rest-api-endpoints", api.id))
+        );
+        assert_eq!(api.repository_group_id, composite.repository_group_id);
+        assert_eq!(api.agent_task_id, materialized.agent_tasks[1].id);
+        assert_eq!(
+            materialized.agent_tasks[1].ai_agent_type,
+            Some(AiAgentType::Aider)
+        );
+
+        let nodes = &materialized.nodes;
+        assert!(nodes.iter().all(|n| n.composite_task_id == composite.id));
+        assert_eq!(nodes[0].unit_task_id, db.id);
+        assert!(nodes[0].depends_on_ids.is_empty());
+        assert_eq!(nodes[1].depends_on_ids, [nodes[0].id], "repeats dropped");
+        assert_eq!(nodes[2].depends_on_ids, [nodes[1].id]);
+
+        let mut default = CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Add auth");
+        let materialized = plan.materialize(&mut default, None).unwrap();
+        let tests = &materialized.unit_tasks[2];
+        assert_eq!(tests.branch_name, Some(format!("delidev/{}", tests.id)));
+    }
+
+    #[test]
+    fn test_materialized_ready_nodes() {
+        let plan = Plan::parse(
+            "\
+tasks:
+  - id: db
+    prompt: Create the schema
+  - id: utils
+    prompt: Write the helpers
+  - id: api
+    prompt: Implement the API
+    dependsOn: [db, utils]
+",
+        )
+        .unwrap();
+        let mut composite = CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Add auth");
+        let mut materialized = plan.materialize(&mut composite, None).unwrap();
+        let graph = TaskGraph::new(&materialized.nodes).unwrap();
+        let roots = [materialized.nodes[0].id, materialized.nodes[1].id];
+
+        let statuses = graph.node_statuses(&materialized.unit_tasks);
+        let dispatched =
+            graph.dispatched_nodes(&materialized.unit_tasks, &materialized.agent_tasks);
+        assert_eq!(graph.ready_nodes(&statuses, &dispatched), roots);
+
+        let agent_task = &mut materialized.agent_tasks[0];
+        let session = AgentSession::new(agent_task.id, AiAgentType::ClaudeCode);
+        agent_task.agent_sessions.push(session);
+        let dispatched =
+            graph.dispatched_nodes(&materialized.unit_tasks, &materialized.agent_tasks);
+        assert_eq!(graph.ready_nodes(&statuses, &dispatched), [roots[1]]);
+    }
+
+    #[test]
+    fn test_materialize_invalid_plan() {
+        let plan = Plan {
+            tasks: vec![PlanTask::new("a", "First").depends_on("b")],
+        };
+        let mut composite = CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Broken");
+        assert!(plan.materialize(&mut composite, None).is_err());
+        assert!(composite.node_ids.is_empty());
+    }
+
+    #[test]
+    fn test_slug() {
+        assert_eq!(slug("Login/Signup UI"), "login-signup-ui");
+        assert_eq!(slug("  Setup   DB! "), "setup-db");
+        assert_eq!(slug("日本語"), "");
+    }
+}
@@ -0,0 +1,559 @@
+//! Plan definitions, parsing and validation.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Location, PlanError, PlanErrors, PlanResult,
+    yaml::{self, Node, Value},
+};
+
+/// A task in a plan.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanTask {
+    /// Unique identifier within the plan.
+    pub id: String,
+    /// Optional human-readable title. Defaults to the ID.
+    pub title: Option<String>,
+    /// Description of what the AI agent should do.
+    pub prompt: String,
+    /// Optional custom branch name. Uses the branch template if not set.
+    pub branch_name: Option<String>,
+    /// IDs of tasks that must complete first.
+    pub depends_on: Vec<String>,
+}
+
+impl PlanTask {
+    /// Creates a new task without dependencies.
+    pub fn new(id: impl Into<String>, prompt: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            title: None,
+            prompt: prompt.into(),
+            branch_name: None,
+            depends_on: Vec::new(),
+        }
+    }
+
+    /// Sets the title.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the branch name.
+    pub fn with_branch_name(mut self, branch_name: impl Into<String>) -> Self {
+        self.branch_name = Some(branch_name.into());
+        self
+    }
+
+    /// Adds a dependency.
+    pub fn depends_on(mut self, task_id: impl Into<String>) -> Self {
+        self.depends_on.push(task_id.into());
+        self
+    }
+
+    /// Returns the title, or the ID if the task has none.
+    pub fn display_title(&self) -> &str {
+        self.title.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// A task graph from a `PLAN-{random}.yaml` file.
+///
+/// See `docs/plan-yaml.md` for the format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    /// Tasks in file order.
+    pub tasks: Vec<PlanTask>,
+}
+
+/// Where a parsed task and its dependencies are in the file.
+#[derive(Debug, Clone)]
+struct TaskLocations {
+    task: Location,
+    prompt: Location,
+    depends_on: Vec<Location>,
+}
+
+impl Plan {
+    /// Parses and validates a plan.
+    ///
+    /// Returns every problem found, each with its line and column.
+    pub fn parse(source: &str) -> PlanResult<Self> {
+        let root = yaml::parse(source)?;
+        let mut errors = Vec::new();
+        let (plan, locations) = read_plan(root.as_ref(), &mut errors);
+        check(&plan.tasks, Some(&locations), &mut errors);
+        if errors.is_empty() {
+            Ok(plan)
+        } else {
+            Err(PlanErrors::new(errors))
+        }
+    }
+
+    /// Validates a plan built in code against the specification's rules:
+    /// unique IDs, known dependencies, no cycles and non-empty prompts.
+    pub fn validate(&self) -> PlanResult<()> {
+        let mut errors = Vec::new();
+        check(&self.tasks, None, &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(PlanErrors::new(errors))
+        }
+    }
+
+    /// Returns the task with ID `id`.
+    pub fn task(&self, id: &str) -> Option<&PlanTask> {
+        self.tasks.iter().find(|t| t.id == id)
+    }
+
+    /// Returns the indices of the tasks in an order where every task comes
+    /// after its dependencies, keeping file order where it is free to.
+    ///
+    /// Tasks on a cycle and unknown dependencies are left out, so this is
+    /// only complete for a valid plan.
+    pub(crate) fn dependency_order(&self) -> Vec<usize> {
+        let index: HashMap<&str, usize> = first_indices(&self.tasks);
+        let mut remaining: Vec<usize> = self
+            .tasks
+            .iter()
+            .map(|t| dependency_indices(t, &index).len())
+            .collect();
+        let mut dependents: Vec<Vec<usize>> = vec![Vec::new(); self.tasks.len()];
+        for (i, task) in self.tasks.iter().enumerate() {
+            for dependency in dependency_indices(task, &index) {
+                dependents[dependency].push(i);
+            }
+        }
+
+        let mut order = Vec::with_capacity(self.tasks.len());
+        let mut ready: Vec<usize> = (0..self.tasks.len())
+            .rev()
+            .filter(|&i| remaining[i] == 0)
+            .collect();
+        while let Some(i) = ready.pop() {
+            order.push(i);
+            let mut unblocked = Vec::new();
+            for &dependent in &dependents[i] {
+                remaining[dependent] -= 1;
+                if remaining[dependent] == 0 {
+                    unblocked.push(dependent);
+                }
+            }
+            ready.extend(unblocked);
+            ready.sort_unstable_by(|a, b| b.cmp(a));
+        }
+        order
+    }
+}
+
+/// Maps each task ID to the index of its first task.
+pub(crate) fn first_indices(tasks: &[PlanTask]) -> HashMap<&str, usize> {
+    let mut index = HashMap::new();
+    for (i, task) in tasks.iter().enumerate() {
+        index.entry(task.id.as_str()).or_insert(i);
+    }
+    index
+}
+
+/// Returns the indices of a task's known dependencies, without repeats.
+pub(crate) fn dependency_indices(task: &PlanTask, index: &HashMap<&str, usize>) -> Vec<usize> {
+    let mut indices: Vec<usize> = task
+        .depends_on
+        .iter()
+        .filter_map(|id| index.get(id.as_str()).copied())
+        .collect();
+    indices.sort_unstable();
+    indices.dedup();
+    indices
+}
+
+// =============================================================================
+// Reading
+// =============================================================================
+
+/// Reads the plan from a YAML document, recording problems with its shape
+/// in `errors`.
+fn read_plan(root: Option<&Node>, errors: &mut Vec<PlanError>) -> (Plan, Vec<TaskLocations>) {
+    let mut plan = Plan::default();
+    let mut locations = Vec::new();
+    let Some(root) = root else {
+        errors.push(invalid("expected a mapping with a `tasks` list", None));
+        return (plan, locations);
+    };
+    let Value::Mapping(entries) = &root.value else {
+        errors.push(invalid(
+            format!(
+                "expected a mapping with a `tasks` list, found {}",
+                root.kind()
+            ),
+            Some(root.location),
+        ));
+        return (plan, locations);
+    };
+
+    let mut tasks = None;
+    for (key, value) in entries {
+        match key_name(key, errors) {
+            Some("tasks") => tasks = Some(value),
+            Some(name) => errors.push(invalid(
+                format!("unknown field `{name}`"),
+                Some(key.location),
+            )),
+            None => {}
+        }
+    }
+    let Some(tasks) = tasks else {
+        errors.push(invalid("missing field `tasks`", Some(root.location)));
+        return (plan, locations);
+    };
+    let items = match &tasks.value {
+        Value::Sequence(items) => items.as_slice(),
+        Value::Null => &[],
+        _ => {
+            errors.push(invalid(
+                format!("`tasks` must be a list, found {}", tasks.kind()),
+                Some(tasks.location),
+            ));
+            return (plan, locations);
+        }
+    };
+    for item in items {
+        if let Some((task, task_locations)) = read_task(item, errors) {
+            plan.tasks.push(task);
+            locations.push(task_locations);
+        }
+    }
+    (plan, locations)
+}
+
+/// Reads one task, or returns `None` if it has no usable ID.
+fn read_task(node: &Node, errors: &mut Vec<PlanError>) -> Option<(PlanTask, TaskLocations)> {
+    let Value::Mapping(entries) = &node.value else {
+        errors.push(invalid(
+            format!("a task must be a mapping, found {}", node.kind()),
+            Some(node.location),
+        ));
+        return None;
+    };
+
+    let mut id = None;
+    let mut task = PlanTask::new("", "");
+    let mut locations = TaskLocations {
+        task: node.location,
+        prompt: node.location,
+        depends_on: Vec::new(),
+    };
+    for (key, value) in entries {
+        match key_name(key, errors) {
+            Some("id") => id = string(value, "id", errors),
+            Some("title") => task.title = optional_string(value, "title", errors),
+            Some("prompt") => {
+                task.prompt = optional_string(value, "prompt", errors).unwrap_or_default();
+                locations.prompt = value.location;
+            }
+            Some("branchName") => {
+                task.branch_name = optional_string(value, "branchName", errors);
+            }
+            Some("dependsOn") => match &value.value {
+                Value::Null => {}
+                Value::Sequence(items) => {
+                    for item in items {
+                        if let Some(dependency) = string(item, "dependsOn", errors) {
+                            task.depends_on.push(dependency);
+                            locations.depends_on.push(item.location);
+                        }
+                    }
+                }
+                _ => errors.push(invalid(
+                    format!("`dependsOn` must be a list, found {}", value.kind()),
+                    Some(value.location),
+                )),
+            },
+            Some(name) => errors.push(invalid(
+                format!("unknown task field `{name}`"),
+                Some(key.location),
+            )),
+            None => {}
+        }
+    }
+
+    let Some(id) = id else {
+        if !entries
+            .iter()
+            .any(|(key, _)| matches!(&key.value, Value::Scalar(name) if name == "id"))
+        {
+            errors.push(invalid("missing task field `id`", Some(node.location)));
+        }
+        return None;
+    };
+    task.id = id;
+    Some((task, locations))
+}
+
+/// Returns the name of a mapping key, recording an error unless it is a
+/// string.
+fn key_name<'a>(key: &'a Node, errors: &mut Vec<PlanError>) -> Option<&'a str> {
+    match &key.value {
+        Value::Scalar(name) => Some(name),
+        _ => {
+            errors.push(invalid(
+                format!("expected a field name, found {}", key.kind()),
+                Some(key.location),
+            ));
+            None
+        }
+    }
+}
+
+/// Reads a required non-null string.
+fn string(node: &Node, field: &str, errors: &mut Vec<PlanError>) -> Option<String> {
+    match &node.value {
+        Value::Scalar(text) => Some(text.clone()),
+        _ => {
+            errors.push(invalid(
+                format!("`{field}` must be a string, found {}", node.kind()),
+                Some(node.location),
+            ));
+            None
+        }
+    }
+}
+
+/// Reads an optional string, where null means absent.
+fn optional_string(node: &Node, field: &str, errors: &mut Vec<PlanError>) -> Option<String> {
+    match node.value {
+        Value::Null => None,
+        _ => string(node, field, errors),
+    }
+}
+
+fn invalid(message: impl Into<String>, location: Option<Location>) -> PlanError {
+    PlanError::InvalidField {
+        message: message.into(),
+        location,
+    }
+}
+
+// =============================================================================
+// Validation
+// =============================================================================
+
+/// Checks the specification's validation rules, recording every violation
+/// in `errors`. `locations` holds the position of each task when it was
+/// parsed from a file.
+fn check(tasks: &[PlanTask], locations: Option<&[TaskLocations]>, errors: &mut Vec<PlanError>) {
+    let task_location = |i: usize| locations.map(|l| l[i].task);
+
+    let mut seen = HashSet::new();
+    for (i, task) in tasks.iter().enumerate() {
+        if !seen.insert(task.id.as_str()) {
+            errors.push(PlanError::DuplicateTaskId {
+                id: task.id.clone(),
+                location: task_location(i),
+            });
+        }
+        if task.prompt.trim().is_empty() {
+            errors.push(PlanError::EmptyPrompt {
+                task_id: task.id.clone(),
+                location: locations.map(|l| l[i].prompt),
+            });
+        }
+        for (j, dependency) in task.depends_on.iter().enumerate() {
+            if !tasks.iter().any(|t| t.id == *dependency) {
+                errors.push(PlanError::InvalidDependency {
+                    task_id: task.id.clone(),
+                    dependency: dependency.clone(),
+                    location: locations.map(|l| l[i].depends_on[j]),
+                });
+            }
+        }
+    }
+
+    for cycle in find_cycles(tasks) {
+        errors.push(PlanError::CyclicDependency {
+            location: task_location(cycle[0]),
+            cycle: cycle.iter().map(|&i| tasks[i].id.clone()).collect(),
+        });
+    }
+}
+
+/// Returns the dependency cycles found by a depth-first search from each
+/// task in file order, as task indices starting and ending with the same
+/// task.
+fn find_cycles(tasks: &[PlanTask]) -> Vec<Vec<usize>> {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Unvisited,
+        OnPath,
+        Done,
+    }
+
+    let index = first_indices(tasks);
+    let dependencies: Vec<Vec<usize>> = tasks
+        .iter()
+        .map(|t| dependency_indices(t, &index))
+        .collect();
+    let mut state = vec![State::Unvisited; tasks.len()];
+    let mut cycles = Vec::new();
+
+    for start in 0..tasks.len() {
+        if state[start] != State::Unvisited {
+            continue;
+        }
+        // Each frame is a task on the current path and the next dependency
+        // of it to visit.
+        let mut path: Vec<(usize, usize)> = vec![(start, 0)];
+        state[start] = State::OnPath;
+        while let Some((task, next)) = path.last_mut() {
+            let task = *task;
+            let Some(&dependency) = dependencies[task].get(*next) else {
+                state[task] = State::Done;
+                path.pop();
+                continue;
+            };
+            *next += 1;
+            match state[dependency] {
+                State::Unvisited => {
+                    state[dependency] = State::OnPath;
+                    path.push((dependency, 0));
+                }
+                State::OnPath => {
+                    let from = path.iter().position(|&(t, _)| t == dependency).unwrap();
+                    let mut cycle: Vec<usize> = path[from..].iter().map(|&(t, _)| t).collect();
+                    cycle.push(dependency);
+                    cycles.push(cycle);
+                }
+                State::Done => {}
+            }
+        }
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+tasks:
+  - id: "setup-db"
+    title: "Setup Database Schema"
+    prompt: "Create database schema for user authentication"
+    branchName: "feature/auth-database"
+
+  - id: "setup-auth-utils"
+    title: "Implement Auth Utilities"
+    prompt: "Implement authentication utilities"
+    dependsOn: ["setup-db"]
+
+  - id: "auth-api"
+    prompt: |
+      Implement REST API endpoints for login, signup, logout, and password
+      reset
+    dependsOn:
+      - setup-db
+      - setup-auth-utils
+"#;
+
+    #[test]
+    fn test_parse_example() {
+        let plan = Plan::parse(EXAMPLE).unwrap();
+        assert_eq!(plan.tasks.len(), 3);
+        assert_eq!(
+            plan.tasks[0],
+            PlanTask::new("setup-db", "Create database schema for user authentication")
+                .with_title("Setup Database Schema")
+                .with_branch_name("feature/auth-database")
+        );
+        let api = plan.task("auth-api").unwrap();
+        assert_eq!(api.display_title(), "auth-api");
+        assert_eq!(api.depends_on, ["setup-db", "setup-auth-utils"]);
+        assert!(api.prompt.ends_with("password\nreset\n"));
+        assert_eq!(plan.dependency_order(), [0, 1, 2]);
+        assert!(plan.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validation_errors_have_locations() {
+        let source = "\
+tasks:
+  - id: a
+    prompt: First
+    dependsOn: [c, missing]
+  - id: a
+    prompt: \"  \"
+  - id: c
+    prompt: Third
+    dependsOn: [a]
+";
+        let errors = Plan::parse(source).unwrap_err();
+        let found: Vec<(&str, Option<Location>)> = errors
+            .errors()
+            .iter()
+            .map(|e| (e.code(), e.location()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("CyclicDependency", Some(Location::new(2, 5))),
+                ("InvalidDependency", Some(Location::new(4, 20))),
+                ("DuplicateTaskId", Some(Location::new(5, 5))),
+                ("EmptyPrompt", Some(Location::new(6, 13))),
+            ]
+        );
+        assert!(matches!(
+            &errors.errors()[0],
+            PlanError::CyclicDependency { cycle, .. } if cycle == &["a", "c", "a"]
+        ));
+        assert_eq!(
+            errors.errors()[1].to_string(),
+            "Task a depends on unknown task missing at line 4, column 20"
+        );
+    }
+
+    #[test]
+    fn test_shape_errors() {
+        let errors =
+            Plan::parse("tasks:\n  - title: No ID\n    prompt: [x]\n    extra: 1\n").unwrap_err();
+        let messages: Vec<String> = errors.into_iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            messages,
+            [
+                "Invalid plan at line 2, column 5: missing task field `id`",
+                "Invalid plan at line 3, column 13: `prompt` must be a string, found a list",
+                "Invalid plan at line 4, column 5: unknown task field `extra`",
+            ]
+        );
+
+        let errors = Plan::parse("").unwrap_err();
+        assert_eq!(errors.errors()[0].code(), "InvalidField");
+        let errors = Plan::parse("tasks: [").unwrap_err();
+        assert_eq!(errors.errors()[0].code(), "InvalidYaml");
+    }
+
+    #[test]
+    fn test_validate_built_plan() {
+        let plan = Plan {
+            tasks: vec![
+                PlanTask::new("b", "Second").depends_on("a"),
+                PlanTask::new("a", "First").depends_on("a"),
+            ],
+        };
+        let errors = plan.validate().unwrap_err();
+        assert!(matches!(
+            errors.errors(),
+            [PlanError::CyclicDependency { cycle, location: None }] if cycle == &["a", "a"]
+        ));
+
+        let plan = Plan {
+            tasks: vec![
+                PlanTask::new("b", "Second").depends_on("a"),
+                PlanTask::new("a", "First"),
+                PlanTask::new("c", "Third"),
+            ],
+        };
+        assert_eq!(plan.dependency_order(), [1, 0, 2]);
+    }
+}
@@ -0,0 +1,193 @@
+//! Rendering a plan as a Mermaid flowchart or a Graphviz DOT graph.
+
+use std::{collections::HashMap, fmt::Write};
+
+use entities::UnitTaskStatus;
+
+use crate::{Plan, dependency_indices, first_indices};
+
+/// Every status, in the order styles are declared.
+const STATUSES: [UnitTaskStatus; 6] = [
+    UnitTaskStatus::InProgress,
+    UnitTaskStatus::InReview,
+    UnitTaskStatus::Approved,
+    UnitTaskStatus::PrOpen,
+    UnitTaskStatus::Done,
+    UnitTaskStatus::Rejected,
+];
+
+/// Returns the fill and border colors of a node in `status`.
+fn colors(status: UnitTaskStatus) -> (&'static str, &'static str) {
+    match status {
+        UnitTaskStatus::InProgress => ("#dbeafe", "#2563eb"),
+        UnitTaskStatus::InReview => ("#fef3c7", "#d97706"),
+        UnitTaskStatus::Approved => ("#d1fae5", "#059669"),
+        UnitTaskStatus::PrOpen => ("#ede9fe", "#7c3aed"),
+        UnitTaskStatus::Done => ("#bbf7d0", "#15803d"),
+        UnitTaskStatus::Rejected => ("#fee2e2", "#dc2626"),
+    }
+}
+
+/// Returns the name of `status` as used in the API.
+fn status_name(status: UnitTaskStatus) -> &'static str {
+    match status {
+        UnitTaskStatus::InProgress => "in_progress",
+        UnitTaskStatus::InReview => "in_review",
+        UnitTaskStatus::Approved => "approved",
+        UnitTaskStatus::PrOpen => "pr_open",
+        UnitTaskStatus::Done => "done",
+        UnitTaskStatus::Rejected => "rejected",
+    }
+}
+
+impl Plan {
+    /// Renders the plan as a Mermaid flowchart, for pasting into Markdown.
+    ///
+    /// Each task is a node labelled with its title, and arrows point from a
+    /// task to the tasks depending on it, once per pair. Tasks with an
+    /// entry in `statuses`, keyed by task ID, are colored by it; pass an
+    /// empty map for a plan that has not run.
+    pub fn to_mermaid(&self, statuses: &HashMap<String, UnitTaskStatus>) -> String {
+        let index = first_indices(&self.tasks);
+        let mut chart = String::from("flowchart TD\n");
+        for (i, task) in self.tasks.iter().enumerate() {
+            let label = task
+                .display_title()
+                .replace('"', "#quot;")
+                .replace('\n', " ");
+            let _ = writeln!(chart, "    t{i}[\"{label}\"]");
+        }
+        for (i, task) in self.tasks.iter().enumerate() {
+            for j in dependency_indices(task, &index) {
+                let _ = writeln!(chart, "    t{j} --> t{i}");
+            }
+        }
+        for status in STATUSES {
+            let nodes: Vec<String> = self
+                .tasks
+                .iter()
+                .enumerate()
+                .filter(|(_, task)| statuses.get(&task.id) == Some(&status))
+                .map(|(i, _)| format!("t{i}"))
+                .collect();
+            if nodes.is_empty() {
+                continue;
+            }
+            let (fill, stroke) = colors(status);
+            let name = status_name(status);
+            let _ = writeln!(chart, "    classDef {name} fill:{fill},stroke:{stroke}");
+            let _ = writeln!(chart, "    class {} {name}", nodes.join(","));
+        }
+        chart
+    }
+
+    /// Renders the plan as a Graphviz DOT digraph.
+    ///
+    /// Nodes are named by task ID and labelled with the title, with the
+    /// same arrows and status colors as [`to_mermaid`](Plan::to_mermaid).
+    pub fn to_dot(&self, statuses: &HashMap<String, UnitTaskStatus>) -> String {
+        let index = first_indices(&self.tasks);
+        let mut graph = String::from("digraph plan {\n");
+        graph.push_str("    node [shape=box, style=\"rounded,filled\", fillcolor=\"#ffffff\"];\n");
+        for task in &self.tasks {
+            let _ = write!(
+                graph,
+                "    {} [label={}",
+                dot_string(&task.id),
+                dot_string(task.display_title())
+            );
+            if let Some(&status) = statuses.get(&task.id) {
+                let (fill, stroke) = colors(status);
+                let _ = write!(graph, ", fillcolor=\"{fill}\", color=\"{stroke}\"");
+            }
+            graph.push_str("];\n");
+        }
+        for task in &self.tasks {
+            for j in dependency_indices(task, &index) {
+                let _ = writeln!(
+                    graph,
+                    "    {} -> {};",
+                    dot_string(&self.tasks[j].id),
+                    dot_string(&task.id)
+                );
+            }
+        }
+        graph.push_str("}\n");
+        graph
+    }
+}
+
+/// Formats a string as a quoted DOT ID.
+fn dot_string(text: &str) -> String {
+    let escaped = text
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+    format!("\"{escaped}\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlanTask;
+
+    fn example() -> (Plan, HashMap<String, UnitTaskStatus>) {
+        let plan = Plan {
+            tasks: vec![
+                PlanTask::new("db", "Create the schema").with_title("Setup \"DB\""),
+                PlanTask::new("api", "Implement the API").depends_on("db"),
+                PlanTask::new("ui", "Build the UI")
+                    .depends_on("api")
+                    .depends_on("db")
+                    .depends_on("api"),
+            ],
+        };
+        let statuses = HashMap::from([
+            ("db".to_string(), UnitTaskStatus::Done),
+            ("api".to_string(), UnitTaskStatus::InReview),
+            ("ui".to_string(), UnitTaskStatus::InReview),
+        ]);
+        (plan, statuses)
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let (plan, statuses) = example();
+        assert_eq!(
+            plan.to_mermaid(&statuses),
+            "\
+flowchart TD
+    t0[\"Setup #quot;DB#quot;\"]
+    t1[\"api\"]
+    t2[\"ui\"]
+    t0 --> t1
+    t0 --> t2
+    t1 --> t2
+    classDef in_review fill:#fef3c7,stroke:#d97706
+    class t1,t2 in_review
+    classDef done fill:#bbf7d0,stroke:#15803d
+    class t0 done
+"
+        );
+        assert!(!plan.to_mermaid(&HashMap::new()).contains("classDef"));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let (plan, statuses) = example();
+        assert_eq!(
+            plan.to_dot(&statuses),
+            "\
+digraph plan {
+    node [shape=box, style=\"rounded,filled\", fillcolor=\"#ffffff\"];
+    \"db\" [label=\"Setup \\\"DB\\\"\", fillcolor=\"#bbf7d0\", color=\"#15803d\"];
+    \"api\" [label=\"api\", fillcolor=\"#fef3c7\", color=\"#d97706\"];
+    \"ui\" [label=\"ui\", fillcolor=\"#fef3c7\", color=\"#d97706\"];
+    \"db\" -> \"api\";
+    \"db\" -> \"ui\";
+    \"api\" -> \"ui\";
+}
+"
+        );
+    }
+}
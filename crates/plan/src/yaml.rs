@@ -0,0 +1,282 @@
+//! YAML documents that remember where each node starts.
+
+use std::collections::HashMap;
+
+use yaml_rust2::{
+    Event,
+    parser::{MarkedEventReceiver, Parser},
+    scanner::{Marker, TScalarStyle},
+};
+
+use crate::{Location, PlanError};
+
+/// Value of a YAML node.
+#[derive(Debug, Clone)]
+pub(crate) enum Value {
+    /// `null`, `~` or an empty plain scalar.
+    Null,
+    /// Any other scalar, as written.
+    Scalar(String),
+    /// A sequence.
+    Sequence(Vec<Node>),
+    /// A mapping, in file order.
+    Mapping(Vec<(Node, Node)>),
+}
+
+/// A YAML node and where it starts.
+#[derive(Debug, Clone)]
+pub(crate) struct Node {
+    pub value: Value,
+    pub location: Location,
+}
+
+impl Node {
+    /// Returns a name for the node's type, for error messages.
+    pub fn kind(&self) -> &'static str {
+        match self.value {
+            Value::Null => "null",
+            Value::Scalar(_) => "a string",
+            Value::Sequence(_) => "a list",
+            Value::Mapping(_) => "a mapping",
+        }
+    }
+}
+
+/// Most nodes a document may hold, counting each alias as a copy of its
+/// anchor, so that nested aliases cannot expand until memory runs out.
+const MAX_NODES: usize = 100_000;
+
+/// Parses the first document of `source`. An empty source has no document.
+pub(crate) fn parse(source: &str) -> Result<Option<Node>, PlanError> {
+    let mut builder = Builder::default();
+    Parser::new_from_str(source)
+        .load(&mut builder, false)
+        .map_err(|e| PlanError::Syntax {
+            message: e.info().to_string(),
+            location: Some(location(e.marker())),
+        })?;
+    match builder.error {
+        Some(error) => Err(error),
+        None => Ok(builder.root),
+    }
+}
+
+fn location(marker: &Marker) -> Location {
+    Location::new(marker.line(), marker.col() + 1)
+}
+
+/// A sequence or mapping whose children are still being read.
+struct Open {
+    node: Node,
+    anchor: usize,
+    /// Nodes counted before this one.
+    first: usize,
+    /// Keys and values of a mapping, alternating.
+    children: Vec<Node>,
+}
+
+/// Builds a tree of [`Node`]s from parser events.
+#[derive(Default)]
+struct Builder {
+    open: Vec<Open>,
+    /// Anchored nodes and the number of nodes in each.
+    anchors: HashMap<usize, (Node, usize)>,
+    root: Option<Node>,
+    /// Nodes read so far, with aliases expanded.
+    nodes: usize,
+    error: Option<PlanError>,
+}
+
+impl Builder {
+    /// Counts `nodes` more nodes read at `marker`, failing once the
+    /// document holds more than [`MAX_NODES`].
+    fn count(&mut self, nodes: usize, marker: &Marker) -> bool {
+        self.nodes += nodes;
+        if self.nodes > MAX_NODES {
+            self.error = Some(PlanError::Syntax {
+                message: format!("document expands to more than {MAX_NODES} nodes"),
+                location: Some(location(marker)),
+            });
+            return false;
+        }
+        true
+    }
+
+    fn open(&mut self, value: Value, anchor: usize, marker: Marker) {
+        let first = self.nodes;
+        if !self.count(1, &marker) {
+            return;
+        }
+        self.open.push(Open {
+            node: Node {
+                value,
+                location: location(&marker),
+            },
+            anchor,
+            first,
+            children: Vec::new(),
+        });
+    }
+
+    fn close(&mut self) {
+        let Some(Open {
+            mut node,
+            anchor,
+            first,
+            children,
+        }) = self.open.pop()
+        else {
+            return;
+        };
+        match &mut node.value {
+            Value::Sequence(items) => *items = children,
+            Value::Mapping(entries) => {
+                // The parser marks a block mapping at its first `:`, so
+                // start it at its first key instead.
+                if let Some(key) = children.first() {
+                    node.location = node.location.min(key.location);
+                }
+                let mut children = children.into_iter();
+                while let (Some(key), Some(value)) = (children.next(), children.next()) {
+                    entries.push((key, value));
+                }
+            }
+            Value::Null | Value::Scalar(_) => {}
+        }
+        let nodes = self.nodes - first;
+        self.finish(node, anchor, nodes);
+    }
+
+    /// Adds a complete node of `nodes` nodes to its parent.
+    fn finish(&mut self, node: Node, anchor: usize, nodes: usize) {
+        if anchor > 0 {
+            self.anchors.insert(anchor, (node.clone(), nodes));
+        }
+        match self.open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => self.root = Some(node),
+        }
+    }
+}
+
+impl MarkedEventReceiver for Builder {
+    fn on_event(&mut self, event: Event, marker: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+        match event {
+            Event::Scalar(text, style, anchor, _) => {
+                if !self.count(1, &marker) {
+                    return;
+                }
+                let is_null = style == TScalarStyle::Plain
+                    && matches!(text.as_str(), "" | "~" | "null" | "Null" | "NULL");
+                let value = if is_null {
+                    Value::Null
+                } else {
+                    Value::Scalar(text)
+                };
+                let node = Node {
+                    value,
+                    location: location(&marker),
+                };
+                self.finish(node, anchor, 1);
+            }
+            Event::Alias(anchor) => {
+                let Some(&(_, nodes)) = self.anchors.get(&anchor) else {
+                    return;
+                };
+                // Count the copy before making it.
+                if !self.count(nodes, &marker) {
+                    return;
+                }
+                let mut node = self.anchors[&anchor].0.clone();
+                node.location = location(&marker);
+                self.finish(node, 0, nodes);
+            }
+            Event::SequenceStart(anchor, _) => {
+                self.open(Value::Sequence(Vec::new()), anchor, marker)
+            }
+            Event::MappingStart(anchor, _) => self.open(Value::Mapping(Vec::new()), anchor, marker),
+            Event::SequenceEnd | Event::MappingEnd => self.close(),
+            Event::Nothing
+            | Event::StreamStart
+            | Event::StreamEnd
+            | Event::DocumentStart
+            | Event::DocumentEnd => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locations() {
+        let root = parse("tasks:\n  - id: a\n    dependsOn: [b, c]\n")
+            .unwrap()
+            .unwrap();
+        let Value::Mapping(entries) = &root.value else {
+            panic!("expected a mapping, got {}", root.kind());
+        };
+        let (key, tasks) = &entries[0];
+        assert_eq!(key.location, Location::new(1, 1));
+        let Value::Sequence(tasks) = &tasks.value else {
+            panic!("expected a list");
+        };
+        assert_eq!(tasks[0].location, Location::new(2, 5));
+        let Value::Mapping(fields) = &tasks[0].value else {
+            panic!("expected a mapping");
+        };
+        let (_, depends_on) = &fields[1];
+        let Value::Sequence(ids) = &depends_on.value else {
+            panic!("expected a list");
+        };
+        assert_eq!(ids[1].location, Location::new(3, 20));
+
+        assert!(parse("").unwrap().is_none());
+        let error = parse("tasks: [a\n").unwrap_err();
+        assert!(matches!(
+            error,
+            PlanError::Syntax {
+                location: Some(_),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_aliases() {
+        let root = parse(
+            "base: &base [a, b]
+copy: *base
+",
+        )
+        .unwrap()
+        .unwrap();
+        let Value::Mapping(entries) = &root.value else {
+            panic!("expected a mapping");
+        };
+        let Value::Sequence(items) = &entries[1].1.value else {
+            panic!("expected the alias to expand to a list");
+        };
+        assert_eq!(items.len(), 2);
+        assert_eq!(entries[1].1.location, Location::new(2, 7));
+
+        // Each level repeats the one before ten times, 10^9 nodes in all.
+        let mut bomb = String::from("l0: &l0 [x, x, x, x, x, x, x, x, x, x]\n");
+        for level in 1..9 {
+            let aliases = vec![format!("*l{}", level - 1); 10].join(", ");
+            bomb.push_str(&format!("l{level}: &l{level} [{aliases}]\n"));
+        }
+        let error = parse(&bomb).unwrap_err();
+        assert!(
+            matches!(
+                &error,
+                PlanError::Syntax { message, location: Some(_) } if message.contains("100000")
+            ),
+            "{error:?}"
+        );
+    }
+}
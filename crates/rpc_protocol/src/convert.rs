@@ -0,0 +1,893 @@
+//! Conversions between RPC types and entities.
+//!
+//! Entities convert into RPC types with `From`. RPC types convert back with
+//! `TryFrom`, failing with [`RpcError::InvalidParams`] naming the offending
+//! field, such as `node_ids[1]: invalid UUID "x"`, for malformed IDs,
+//! `Unspecified` enum values, negative counts and todo items whose data
+//! does not match their type.
+//!
+//! RPC types leave out some entity fields, which take their defaults when
+//! converting back: every entity's `revision` and `deleted_at`, a todo
+//! item's `source` and a worker's `capabilities`. A TTY input request
+//! without options has an empty `options` list in RPC types and `None` in
+//! entities.
+
+use uuid::Uuid;
+
+use crate::{RpcError, types::*};
+
+/// Returns the error for a field with an invalid value.
+fn invalid(field: &str, problem: impl std::fmt::Display) -> RpcError {
+    RpcError::InvalidParams(format!("{field}: {problem}"))
+}
+
+/// Prefixes the field named by an error from converting a nested value.
+fn within(prefix: &str) -> impl Fn(RpcError) -> RpcError + '_ {
+    move |error| match error {
+        RpcError::InvalidParams(message) => RpcError::InvalidParams(format!("{prefix}.{message}")),
+        error => error,
+    }
+}
+
+fn parse_id(value: &str, field: &str) -> Result<Uuid, RpcError> {
+    Uuid::parse_str(value).map_err(|_| invalid(field, format_args!("invalid UUID {value:?}")))
+}
+
+fn parse_opt_id(value: Option<&str>, field: &str) -> Result<Option<Uuid>, RpcError> {
+    value.map(|value| parse_id(value, field)).transpose()
+}
+
+fn parse_ids(values: &[String], field: &str) -> Result<Vec<Uuid>, RpcError> {
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| parse_id(value, &format!("{field}[{i}]")))
+        .collect()
+}
+
+fn id_strings(ids: &[Uuid]) -> Vec<String> {
+    ids.iter().map(Uuid::to_string).collect()
+}
+
+/// Converts an RPC enum value, rejecting `Unspecified`.
+fn parse_enum<T, E>(value: T, field: &str) -> Result<E, RpcError>
+where
+    E: TryFrom<T, Error = RpcError>,
+{
+    E::try_from(value).map_err(|_| invalid(field, "must be specified"))
+}
+
+fn parse_opt_enum<T, E>(value: Option<T>, field: &str) -> Result<Option<E>, RpcError>
+where
+    E: TryFrom<T, Error = RpcError>,
+{
+    value.map(|value| parse_enum(value, field)).transpose()
+}
+
+fn parse_count(value: i32, field: &str) -> Result<u32, RpcError> {
+    u32::try_from(value)
+        .map_err(|_| invalid(field, format_args!("must not be negative, got {value}")))
+}
+
+/// Converts a count to `i32`, saturating at `i32::MAX`.
+fn count(value: u32) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+// ============================================================================
+// Enums
+// ============================================================================
+
+/// Implements both conversions for an enum whose RPC type has the same
+/// variants plus `Unspecified`.
+macro_rules! convert_enum {
+    ($name:ident { $($variant:ident),* $(,)? }) => {
+        impl From<entities::$name> for $name {
+            fn from(value: entities::$name) -> Self {
+                match value {
+                    $(entities::$name::$variant => Self::$variant,)*
+                }
+            }
+        }
+
+        impl TryFrom<$name> for entities::$name {
+            type Error = RpcError;
+
+            fn try_from(value: $name) -> Result<Self, RpcError> {
+                match value {
+                    $($name::$variant => Ok(Self::$variant),)*
+                    $name::Unspecified => Err(RpcError::InvalidParams(
+                        concat!(stringify!($name), " must be specified").to_string(),
+                    )),
+                }
+            }
+        }
+    };
+}
+
+convert_enum!(VcsType { Git });
+convert_enum!(VcsProviderType {
+    Github,
+    Gitlab,
+    Bitbucket
+});
+convert_enum!(AiAgentType {
+    ClaudeCode,
+    OpenCode,
+    GeminiCli,
+    CodexCli,
+    Aider,
+    Amp,
+});
+convert_enum!(UnitTaskStatus {
+    InProgress,
+    InReview,
+    Approved,
+    PrOpen,
+    Done,
+    Rejected,
+});
+convert_enum!(CompositeTaskStatus {
+    Planning,
+    PendingApproval,
+    InProgress,
+    Done,
+    Rejected,
+});
+convert_enum!(TtyInputType {
+    Text,
+    Select,
+    Confirm,
+    Password
+});
+convert_enum!(TtyInputStatus {
+    Pending,
+    Responded,
+    Timeout,
+    Cancelled
+});
+convert_enum!(WorkerStatus {
+    Idle,
+    Busy,
+    Unhealthy
+});
+convert_enum!(TodoItemType {
+    IssueTriage,
+    PrReview
+});
+convert_enum!(TodoItemStatus {
+    Pending,
+    InProgress,
+    Completed,
+    Dismissed
+});
+convert_enum!(PlanAuthorType {
+    PlanningAgent,
+    User
+});
+
+// ============================================================================
+// Agents
+// ============================================================================
+
+impl From<entities::BaseRemote> for BaseRemote {
+    fn from(remote: entities::BaseRemote) -> Self {
+        Self {
+            git_remote_dir_path: remote.git_remote_dir_path,
+            git_branch_name: remote.git_branch_name,
+        }
+    }
+}
+
+impl From<BaseRemote> for entities::BaseRemote {
+    fn from(remote: BaseRemote) -> Self {
+        Self {
+            git_remote_dir_path: remote.git_remote_dir_path,
+            git_branch_name: remote.git_branch_name,
+        }
+    }
+}
+
+impl From<entities::AgentSession> for AgentSession {
+    fn from(session: entities::AgentSession) -> Self {
+        Self {
+            id: session.id.to_string(),
+            agent_task_id: session.agent_task_id.to_string(),
+            ai_agent_type: session.ai_agent_type.into(),
+            ai_agent_model: session.ai_agent_model,
+            started_at: session.started_at,
+            completed_at: session.completed_at,
+            output_log: session.output_log,
+            created_at: session.created_at,
+        }
+    }
+}
+
+impl TryFrom<AgentSession> for entities::AgentSession {
+    type Error = RpcError;
+
+    fn try_from(session: AgentSession) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&session.id, "id")?,
+            agent_task_id: parse_id(&session.agent_task_id, "agent_task_id")?,
+            ai_agent_type: parse_enum(session.ai_agent_type, "ai_agent_type")?,
+            ai_agent_model: session.ai_agent_model,
+            started_at: session.started_at,
+            completed_at: session.completed_at,
+            output_log: session.output_log,
+            revision: 0,
+            created_at: session.created_at,
+        })
+    }
+}
+
+impl From<entities::AgentTask> for AgentTask {
+    fn from(task: entities::AgentTask) -> Self {
+        Self {
+            id: task.id.to_string(),
+            base_remotes: task.base_remotes.into_iter().map(Into::into).collect(),
+            agent_sessions: task.agent_sessions.into_iter().map(Into::into).collect(),
+            ai_agent_type: task.ai_agent_type.map(Into::into),
+            ai_agent_model: task.ai_agent_model,
+            created_at: task.created_at,
+        }
+    }
+}
+
+impl TryFrom<AgentTask> for entities::AgentTask {
+    type Error = RpcError;
+
+    fn try_from(task: AgentTask) -> Result<Self, RpcError> {
+        let agent_sessions = task
+            .agent_sessions
+            .into_iter()
+            .enumerate()
+            .map(|(i, session)| {
+                session
+                    .try_into()
+                    .map_err(within(&format!("agent_sessions[{i}]")))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            id: parse_id(&task.id, "id")?,
+            base_remotes: task.base_remotes.into_iter().map(Into::into).collect(),
+            agent_sessions,
+            ai_agent_type: parse_opt_enum(task.ai_agent_type, "ai_agent_type")?,
+            ai_agent_model: task.ai_agent_model,
+            revision: 0,
+            created_at: task.created_at,
+        })
+    }
+}
+
+// ============================================================================
+// Tasks
+// ============================================================================
+
+impl From<entities::UnitTask> for UnitTask {
+    fn from(task: entities::UnitTask) -> Self {
+        Self {
+            id: task.id.to_string(),
+            repository_group_id: task.repository_group_id.to_string(),
+            agent_task_id: task.agent_task_id.to_string(),
+            prompt: task.prompt,
+            title: task.title,
+            branch_name: task.branch_name,
+            linked_pr_url: task.linked_pr_url,
+            base_commit: task.base_commit,
+            end_commit: task.end_commit,
+            auto_fix_task_ids: id_strings(&task.auto_fix_task_ids),
+            status: task.status.into(),
+            created_at: task.created_at,
+            updated_at: task.updated_at,
+        }
+    }
+}
+
+impl TryFrom<UnitTask> for entities::UnitTask {
+    type Error = RpcError;
+
+    fn try_from(task: UnitTask) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&task.id, "id")?,
+            repository_group_id: parse_id(&task.repository_group_id, "repository_group_id")?,
+            agent_task_id: parse_id(&task.agent_task_id, "agent_task_id")?,
+            prompt: task.prompt,
+            title: task.title,
+            branch_name: task.branch_name,
+            linked_pr_url: task.linked_pr_url,
+            base_commit: task.base_commit,
+            end_commit: task.end_commit,
+            auto_fix_task_ids: parse_ids(&task.auto_fix_task_ids, "auto_fix_task_ids")?,
+            status: parse_enum(task.status, "status")?,
+            revision: 0,
+            created_at: task.created_at,
+            updated_at: task.updated_at,
+            deleted_at: None,
+        })
+    }
+}
+
+impl From<entities::CompositeTaskNode> for CompositeTaskNode {
+    fn from(node: entities::CompositeTaskNode) -> Self {
+        Self {
+            id: node.id.to_string(),
+            composite_task_id: node.composite_task_id.to_string(),
+            unit_task_id: node.unit_task_id.to_string(),
+            depends_on_ids: id_strings(&node.depends_on_ids),
+            created_at: node.created_at,
+        }
+    }
+}
+
+impl TryFrom<CompositeTaskNode> for entities::CompositeTaskNode {
+    type Error = RpcError;
+
+    fn try_from(node: CompositeTaskNode) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&node.id, "id")?,
+            composite_task_id: parse_id(&node.composite_task_id, "composite_task_id")?,
+            unit_task_id: parse_id(&node.unit_task_id, "unit_task_id")?,
+            depends_on_ids: parse_ids(&node.depends_on_ids, "depends_on_ids")?,
+            revision: 0,
+            created_at: node.created_at,
+        })
+    }
+}
+
+impl From<entities::CompositeTask> for CompositeTask {
+    fn from(task: entities::CompositeTask) -> Self {
+        Self {
+            id: task.id.to_string(),
+            repository_group_id: task.repository_group_id.to_string(),
+            planning_task_id: task.planning_task_id.to_string(),
+            prompt: task.prompt,
+            title: task.title,
+            node_ids: id_strings(&task.node_ids),
+            status: task.status.into(),
+            execution_agent_type: task.execution_agent_type.map(Into::into),
+            created_at: task.created_at,
+            updated_at: task.updated_at,
+        }
+    }
+}
+
+impl TryFrom<CompositeTask> for entities::CompositeTask {
+    type Error = RpcError;
+
+    fn try_from(task: CompositeTask) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&task.id, "id")?,
+            repository_group_id: parse_id(&task.repository_group_id, "repository_group_id")?,
+            planning_task_id: parse_id(&task.planning_task_id, "planning_task_id")?,
+            prompt: task.prompt,
+            title: task.title,
+            node_ids: parse_ids(&task.node_ids, "node_ids")?,
+            status: parse_enum(task.status, "status")?,
+            execution_agent_type: parse_opt_enum(
+                task.execution_agent_type,
+                "execution_agent_type",
+            )?,
+            revision: 0,
+            created_at: task.created_at,
+            updated_at: task.updated_at,
+            deleted_at: None,
+        })
+    }
+}
+
+impl From<entities::PlanRevision> for PlanRevision {
+    fn from(revision: entities::PlanRevision) -> Self {
+        Self {
+            id: revision.id.to_string(),
+            composite_task_id: revision.composite_task_id.to_string(),
+            number: count(revision.number),
+            yaml: revision.yaml,
+            author_type: revision.author_type.into(),
+            author_id: revision.author_id.to_string(),
+            approved_by: revision.approved_by.map(|id| id.to_string()),
+            approved_at: revision.approved_at,
+            created_at: revision.created_at,
+        }
+    }
+}
+
+impl TryFrom<PlanRevision> for entities::PlanRevision {
+    type Error = RpcError;
+
+    fn try_from(revision: PlanRevision) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&revision.id, "id")?,
+            composite_task_id: parse_id(&revision.composite_task_id, "composite_task_id")?,
+            number: parse_count(revision.number, "number")?,
+            yaml: revision.yaml,
+            author_type: parse_enum(revision.author_type, "author_type")?,
+            author_id: parse_id(&revision.author_id, "author_id")?,
+            approved_by: parse_opt_id(revision.approved_by.as_deref(), "approved_by")?,
+            approved_at: revision.approved_at,
+            created_at: revision.created_at,
+        })
+    }
+}
+
+// ============================================================================
+// Repositories and Workspaces
+// ============================================================================
+
+impl From<entities::Repository> for Repository {
+    fn from(repository: entities::Repository) -> Self {
+        Self {
+            id: repository.id.to_string(),
+            workspace_id: repository.workspace_id.to_string(),
+            name: repository.name,
+            remote_url: repository.remote_url,
+            default_branch: repository.default_branch,
+            vcs_type: repository.vcs_type.into(),
+            vcs_provider_type: repository.vcs_provider_type.into(),
+            created_at: repository.created_at,
+            updated_at: repository.updated_at,
+        }
+    }
+}
+
+impl TryFrom<Repository> for entities::Repository {
+    type Error = RpcError;
+
+    fn try_from(repository: Repository) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&repository.id, "id")?,
+            workspace_id: parse_id(&repository.workspace_id, "workspace_id")?,
+            name: repository.name,
+            remote_url: repository.remote_url,
+            default_branch: repository.default_branch,
+            vcs_type: parse_enum(repository.vcs_type, "vcs_type")?,
+            vcs_provider_type: parse_enum(repository.vcs_provider_type, "vcs_provider_type")?,
+            revision: 0,
+            created_at: repository.created_at,
+            updated_at: repository.updated_at,
+            deleted_at: None,
+        })
+    }
+}
+
+impl From<entities::RepositoryGroup> for RepositoryGroup {
+    fn from(group: entities::RepositoryGroup) -> Self {
+        Self {
+            id: group.id.to_string(),
+            workspace_id: group.workspace_id.to_string(),
+            name: group.name,
+            repository_ids: id_strings(&group.repository_ids),
+            created_at: group.created_at,
+            updated_at: group.updated_at,
+        }
+    }
+}
+
+impl TryFrom<RepositoryGroup> for entities::RepositoryGroup {
+    type Error = RpcError;
+
+    fn try_from(group: RepositoryGroup) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&group.id, "id")?,
+            workspace_id: parse_id(&group.workspace_id, "workspace_id")?,
+            name: group.name,
+            repository_ids: parse_ids(&group.repository_ids, "repository_ids")?,
+            revision: 0,
+            created_at: group.created_at,
+            updated_at: group.updated_at,
+        })
+    }
+}
+
+impl From<entities::Workspace> for Workspace {
+    fn from(workspace: entities::Workspace) -> Self {
+        Self {
+            id: workspace.id.to_string(),
+            name: workspace.name,
+            description: workspace.description,
+            user_id: workspace.user_id.map(|id| id.to_string()),
+            created_at: workspace.created_at,
+            updated_at: workspace.updated_at,
+        }
+    }
+}
+
+impl TryFrom<Workspace> for entities::Workspace {
+    type Error = RpcError;
+
+    fn try_from(workspace: Workspace) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&workspace.id, "id")?,
+            name: workspace.name,
+            description: workspace.description,
+            user_id: parse_opt_id(workspace.user_id.as_deref(), "user_id")?,
+            revision: 0,
+            created_at: workspace.created_at,
+            updated_at: workspace.updated_at,
+            deleted_at: None,
+        })
+    }
+}
+
+impl From<entities::User> for User {
+    fn from(user: entities::User) -> Self {
+        Self {
+            id: user.id.to_string(),
+            email: user.email,
+            name: user.name,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        }
+    }
+}
+
+impl TryFrom<User> for entities::User {
+    type Error = RpcError;
+
+    fn try_from(user: User) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&user.id, "id")?,
+            email: user.email,
+            name: user.name,
+            revision: 0,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+        })
+    }
+}
+
+// ============================================================================
+// TTY Input, Workers and Todo Items
+// ============================================================================
+
+impl From<entities::TtyInputRequest> for TtyInputRequest {
+    fn from(request: entities::TtyInputRequest) -> Self {
+        Self {
+            id: request.id.to_string(),
+            task_id: request.task_id.to_string(),
+            session_id: request.session_id.to_string(),
+            prompt: request.prompt,
+            input_type: request.input_type.into(),
+            options: request.options.unwrap_or_default(),
+            status: request.status.into(),
+            response: request.response,
+            created_at: request.created_at,
+            responded_at: request.responded_at,
+        }
+    }
+}
+
+impl TryFrom<TtyInputRequest> for entities::TtyInputRequest {
+    type Error = RpcError;
+
+    fn try_from(request: TtyInputRequest) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&request.id, "id")?,
+            task_id: parse_id(&request.task_id, "task_id")?,
+            session_id: parse_id(&request.session_id, "session_id")?,
+            prompt: request.prompt,
+            input_type: parse_enum(request.input_type, "input_type")?,
+            options: (!request.options.is_empty()).then_some(request.options),
+            status: parse_enum(request.status, "status")?,
+            response: request.response,
+            revision: 0,
+            created_at: request.created_at,
+            responded_at: request.responded_at,
+        })
+    }
+}
+
+impl From<entities::Worker> for Worker {
+    fn from(worker: entities::Worker) -> Self {
+        Self {
+            id: worker.id.to_string(),
+            name: worker.name,
+            endpoint_url: worker.endpoint_url,
+            status: worker.status.into(),
+            last_heartbeat: worker.last_heartbeat,
+            current_task_id: worker.current_task_id.map(|id| id.to_string()),
+            registered_at: worker.created_at,
+        }
+    }
+}
+
+impl TryFrom<Worker> for entities::Worker {
+    type Error = RpcError;
+
+    fn try_from(worker: Worker) -> Result<Self, RpcError> {
+        Ok(Self {
+            id: parse_id(&worker.id, "id")?,
+            name: worker.name,
+            endpoint_url: worker.endpoint_url,
+            capabilities: Vec::new(),
+            status: parse_enum(worker.status, "status")?,
+            current_task_id: parse_opt_id(worker.current_task_id.as_deref(), "current_task_id")?,
+            last_heartbeat: worker.last_heartbeat,
+            revision: 0,
+            created_at: worker.registered_at,
+        })
+    }
+}
+
+impl From<entities::IssueTriageData> for IssueTriageData {
+    fn from(data: entities::IssueTriageData) -> Self {
+        Self {
+            issue_url: data.issue_url,
+            issue_title: data.issue_title,
+            suggested_labels: data.suggested_labels,
+            suggested_assignees: data.suggested_assignees,
+        }
+    }
+}
+
+impl From<IssueTriageData> for entities::IssueTriageData {
+    fn from(data: IssueTriageData) -> Self {
+        Self {
+            issue_url: data.issue_url,
+            issue_title: data.issue_title,
+            suggested_labels: data.suggested_labels,
+            suggested_assignees: data.suggested_assignees,
+        }
+    }
+}
+
+impl From<entities::PrReviewData> for PrReviewData {
+    fn from(data: entities::PrReviewData) -> Self {
+        Self {
+            pr_url: data.pr_url,
+            pr_title: data.pr_title,
+            changed_files_count: count(data.changed_files_count),
+            ai_summary: data.ai_summary,
+        }
+    }
+}
+
+impl TryFrom<PrReviewData> for entities::PrReviewData {
+    type Error = RpcError;
+
+    fn try_from(data: PrReviewData) -> Result<Self, RpcError> {
+        Ok(Self {
+            pr_url: data.pr_url,
+            pr_title: data.pr_title,
+            changed_files_count: parse_count(data.changed_files_count, "changed_files_count")?,
+            ai_summary: data.ai_summary,
+        })
+    }
+}
+
+impl From<entities::TodoItem> for TodoItem {
+    fn from(item: entities::TodoItem) -> Self {
+        let (issue_triage, pr_review) = match item.data {
+            entities::TodoItemData::IssueTriage(data) => (Some(data.into()), None),
+            entities::TodoItemData::PrReview(data) => (None, Some(data.into())),
+        };
+        Self {
+            id: item.id.to_string(),
+            item_type: item.item_type.into(),
+            status: item.status.into(),
+            repository_id: item.repository_id.to_string(),
+            issue_triage,
+            pr_review,
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+        }
+    }
+}
+
+impl TryFrom<TodoItem> for entities::TodoItem {
+    type Error = RpcError;
+
+    /// Requires exactly the data field matching `item_type` to be set.
+    fn try_from(item: TodoItem) -> Result<Self, RpcError> {
+        let item_type = parse_enum(item.item_type, "item_type")?;
+        let data = match (item_type, item.issue_triage, item.pr_review) {
+            (entities::TodoItemType::IssueTriage, Some(data), None) => {
+                entities::TodoItemData::IssueTriage(data.into())
+            }
+            (entities::TodoItemType::PrReview, None, Some(data)) => {
+                entities::TodoItemData::PrReview(data.try_into().map_err(within("pr_review"))?)
+            }
+            (entities::TodoItemType::IssueTriage, _, Some(_)) => {
+                return Err(invalid(
+                    "pr_review",
+                    "must not be set for an issue_triage item",
+                ));
+            }
+            (entities::TodoItemType::PrReview, Some(_), _) => {
+                return Err(invalid(
+                    "issue_triage",
+                    "must not be set for a pr_review item",
+                ));
+            }
+            (entities::TodoItemType::IssueTriage, None, None) => {
+                return Err(invalid("issue_triage", "required for an issue_triage item"));
+            }
+            (entities::TodoItemType::PrReview, None, None) => {
+                return Err(invalid("pr_review", "required for a pr_review item"));
+            }
+        };
+        Ok(Self {
+            id: parse_id(&item.id, "id")?,
+            item_type,
+            source: entities::TodoItemSource::default(),
+            status: parse_enum(item.status, "status")?,
+            repository_id: parse_id(&item.repository_id, "repository_id")?,
+            data,
+            revision: 0,
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use chrono::Utc;
+    use serde::Serialize;
+
+    use super::*;
+
+    /// Converts `entity` to its RPC type and back, and checks that nothing
+    /// but the fields RPC types leave out changed.
+    fn round_trip<E, R>(entity: E)
+    where
+        E: Clone + Debug + Serialize + TryFrom<R, Error = RpcError>,
+        R: From<E>,
+    {
+        let rpc = R::from(entity.clone());
+        let back = E::try_from(rpc).unwrap();
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&entity).unwrap()
+        );
+    }
+
+    /// Asserts that `result` failed with invalid params starting with
+    /// `field`.
+    fn assert_invalid<T: Debug>(result: Result<T, RpcError>, field: &str) {
+        match result {
+            Err(RpcError::InvalidParams(message)) if message.starts_with(field) => {}
+            other => panic!("expected invalid {field}, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let mut agent_task = entities::AgentTask::new();
+        agent_task.add_base_remote("/repos/delidev", "main");
+        let mut session = entities::AgentSession::new(agent_task.id, entities::AiAgentType::Amp)
+            .with_model("amp-1");
+        session.started_at = Some(Utc::now());
+        agent_task.add_session(session);
+        round_trip::<_, AgentTask>(agent_task.clone());
+
+        let mut unit_task = entities::UnitTask::new(Uuid::new_v4(), agent_task.id, "Fix it")
+            .with_title("Fix")
+            .with_branch_name("fix/it");
+        unit_task.auto_fix_task_ids.push(Uuid::new_v4());
+        unit_task.status = entities::UnitTaskStatus::PrOpen;
+        round_trip::<_, UnitTask>(unit_task.clone());
+
+        let mut composite = entities::CompositeTask::new(Uuid::new_v4(), agent_task.id, "Build it")
+            .with_execution_agent_type(entities::AiAgentType::Aider);
+        let mut node = entities::CompositeTaskNode::new(composite.id, unit_task.id);
+        node.depends_on(Uuid::new_v4());
+        composite.node_ids.push(node.id);
+        round_trip::<_, CompositeTaskNode>(node);
+        round_trip::<_, CompositeTask>(composite.clone());
+
+        let user = entities::User::new("ada@example.com").with_name("Ada");
+        let mut revision = entities::PlanRevision::from_user(&composite, user.id, "tasks: []\n");
+        revision.number = 2;
+        revision.approved_by = Some(user.id);
+        revision.approved_at = Some(Utc::now());
+        round_trip::<_, PlanRevision>(revision);
+        round_trip::<_, User>(user.clone());
+
+        let workspace = entities::Workspace::new("Home").with_user_id(user.id);
+        round_trip::<_, Workspace>(workspace.clone());
+        let repository = entities::Repository::new(
+            workspace.id,
+            "delidev",
+            "https://gitlab.com/delinoio/delidev",
+            entities::VcsProviderType::Gitlab,
+        );
+        round_trip::<_, Repository>(repository.clone());
+        let mut group = entities::RepositoryGroup::new(workspace.id);
+        group.add_repository(repository.id);
+        round_trip::<_, RepositoryGroup>(group);
+
+        let mut worker = entities::Worker::new("worker-1", "http://worker-1:8080");
+        worker.status = entities::WorkerStatus::Busy;
+        worker.current_task_id = Some(unit_task.id);
+        round_trip::<_, Worker>(worker);
+
+        let mut triage = entities::TodoItem::issue_triage(
+            repository.id,
+            "https://github.com/delinoio/delidev/issues/1".to_string(),
+            "Crash".to_string(),
+        );
+        triage.status = entities::TodoItemStatus::Dismissed;
+        round_trip::<_, TodoItem>(triage);
+        round_trip::<_, TodoItem>(entities::TodoItem::pr_review(
+            repository.id,
+            "https://github.com/delinoio/delidev/pull/2".to_string(),
+            "Fix crash".to_string(),
+            3,
+        ));
+
+        let text = entities::TtyInputRequest::new(unit_task.id, Uuid::new_v4(), "Name?");
+        round_trip::<_, TtyInputRequest>(text.clone());
+        let select = entities::TtyInputRequest::new(unit_task.id, Uuid::new_v4(), "Pick one")
+            .with_input_type(entities::TtyInputType::Select)
+            .with_options(vec!["a".to_string(), "b".to_string()]);
+        round_trip::<_, TtyInputRequest>(select.clone());
+        assert!(TtyInputRequest::from(text).options.is_empty());
+        assert_eq!(TtyInputRequest::from(select).options, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let composite = entities::CompositeTask::new(Uuid::new_v4(), Uuid::new_v4(), "Build");
+        let mut rpc = CompositeTask::from(composite.clone());
+        rpc.planning_task_id = "not-a-uuid".to_string();
+        assert_invalid(
+            entities::CompositeTask::try_from(rpc),
+            "planning_task_id: invalid UUID \"not-a-uuid\"",
+        );
+        let mut rpc = CompositeTask::from(composite.clone());
+        rpc.node_ids = vec![Uuid::new_v4().to_string(), "x".to_string()];
+        assert_invalid(entities::CompositeTask::try_from(rpc), "node_ids[1]:");
+        let mut rpc = CompositeTask::from(composite);
+        rpc.status = CompositeTaskStatus::Unspecified;
+        assert_invalid(
+            entities::CompositeTask::try_from(rpc),
+            "status: must be specified",
+        );
+
+        let mut agent_task = AgentTask::from(entities::AgentTask::new());
+        let mut session = AgentSession::from(entities::AgentSession::new(
+            Uuid::new_v4(),
+            entities::AiAgentType::ClaudeCode,
+        ));
+        session.ai_agent_type = AiAgentType::Unspecified;
+        agent_task.agent_sessions.push(session);
+        assert_invalid(
+            entities::AgentTask::try_from(agent_task),
+            "agent_sessions[0].ai_agent_type",
+        );
+
+        let repository_id = Uuid::new_v4();
+        let mut review = TodoItem::from(entities::TodoItem::pr_review(
+            repository_id,
+            "https://github.com/delinoio/delidev/pull/2".to_string(),
+            "Fix crash".to_string(),
+            3,
+        ));
+        review.pr_review.as_mut().unwrap().changed_files_count = -1;
+        assert_invalid(
+            entities::TodoItem::try_from(review.clone()),
+            "pr_review.changed_files_count",
+        );
+        review.pr_review = None;
+        assert_invalid(entities::TodoItem::try_from(review.clone()), "pr_review:");
+        review.item_type = TodoItemType::IssueTriage;
+        assert_invalid(
+            entities::TodoItem::try_from(review.clone()),
+            "issue_triage:",
+        );
+        review.item_type = TodoItemType::Unspecified;
+        assert_invalid(entities::TodoItem::try_from(review), "item_type:");
+
+        assert_invalid(
+            entities::WorkerStatus::try_from(WorkerStatus::Unspecified),
+            "WorkerStatus must be specified",
+        );
+        assert_eq!(
+            entities::WorkerStatus::try_from(WorkerStatus::Busy).unwrap(),
+            entities::WorkerStatus::Busy
+        );
+    }
+}
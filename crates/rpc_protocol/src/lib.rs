@@ -2,7 +2,12 @@
 //!
 //! This crate contains the request/response types for DeliDev's RPC API.
 //! The API is designed to be compatible with Connect RPC protocol.
+//!
+//! Every RPC type mirroring an entity converts from it with `From` and back
+//! with `TryFrom`, which rejects malformed IDs and unspecified enum values
+//! as [`RpcError::InvalidParams`].
 
+mod convert;
 mod error;
 mod types;
 
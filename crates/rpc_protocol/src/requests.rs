@@ -1,5 +1,6 @@
 //! RPC request types.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 use crate::types::*;
@@ -74,6 +75,38 @@ pub struct RequestChangesRequest {
     pub feedback: String,
 }
 
+// ============================================================================
+// Plan Service Requests
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitPlanRequest {
+    pub composite_task_id: String,
+    pub yaml: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListPlanRevisionsRequest {
+    pub composite_task_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffPlanRevisionsRequest {
+    pub from_revision_id: String,
+    pub to_revision_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovePlanRevisionRequest {
+    pub revision_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPlanRequest {
+    pub composite_task_id: String,
+    pub format: PlanExportFormat,
+}
+
 // ============================================================================
 // Session Service Requests
 // ============================================================================
@@ -81,6 +114,12 @@ pub struct RequestChangesRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetLogRequest {
     pub session_id: String,
+    /// Byte offset to read from. Defaults to the start of the log.
+    pub offset: Option<u64>,
+    /// Maximum number of bytes to read. Defaults to the rest of the log.
+    pub limit: Option<u64>,
+    /// Read only the newest `tail` chunks, ignoring `offset` and `limit`.
+    pub tail: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -210,6 +249,17 @@ pub struct DismissTodoRequest {
     pub item_id: String,
 }
 
+// ============================================================================
+// Stats Service Requests
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTaskStatsRequest {
+    pub repository_group_id: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
 // ============================================================================
 // Secrets Service Requests
 // ============================================================================
@@ -58,13 +58,51 @@ pub struct RequestChangesResponse {
     pub task: UnitTask,
 }
 
+// ============================================================================
+// Plan Service Responses
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmitPlanResponse {
+    pub revision: PlanRevision,
+    /// Changes from the previous revision, if there is one.
+    pub diff: Option<PlanDiff>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListPlanRevisionsResponse {
+    pub revisions: Vec<PlanRevision>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffPlanRevisionsResponse {
+    pub diff: PlanDiff,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovePlanRevisionResponse {
+    pub revision: PlanRevision,
+    pub task: CompositeTask,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportPlanResponse {
+    pub content: String,
+}
+
 // ============================================================================
 // Session Service Responses
 // ============================================================================
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GetLogResponse {
+    /// Text of the chunks read. Chunks are returned whole, so the text may
+    /// start before the requested offset and run past the limit.
     pub log: String,
+    /// Byte offset of the first byte of `log`.
+    pub offset: u64,
+    /// Size of the whole log in bytes.
+    pub total_size: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -166,6 +204,16 @@ pub struct UpdateTodoStatusResponse {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DismissTodoResponse {}
 
+// ============================================================================
+// Stats Service Responses
+// ============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetTaskStatsResponse {
+    pub status_counts: Vec<TaskStatusCount>,
+    pub agents: Vec<AgentTaskStats>,
+}
+
 // ============================================================================
 // Secrets Service Responses
 // ============================================================================
@@ -111,6 +111,35 @@ pub enum TodoItemStatus {
     Dismissed,
 }
 
+/// Plan author type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanAuthorType {
+    Unspecified,
+    PlanningAgent,
+    User,
+}
+
+/// Format of an exported plan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanExportFormat {
+    Unspecified,
+    Yaml,
+    Mermaid,
+    Dot,
+}
+
+/// Plan task field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlanTaskField {
+    Unspecified,
+    Title,
+    Prompt,
+    BranchName,
+}
+
 /// Base remote information.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BaseRemote {
@@ -185,6 +214,44 @@ pub struct CompositeTask {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Plan revision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanRevision {
+    pub id: String,
+    pub composite_task_id: String,
+    pub number: i32,
+    pub yaml: String,
+    pub author_type: PlanAuthorType,
+    pub author_id: String,
+    pub approved_by: Option<String>,
+    pub approved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Plan task whose fields changed between two revisions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanTaskChange {
+    pub task_id: String,
+    pub fields: Vec<PlanTaskField>,
+}
+
+/// Dependency between two plan tasks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanEdge {
+    pub task_id: String,
+    pub depends_on: String,
+}
+
+/// Structural diff between two plan revisions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanDiff {
+    pub added_tasks: Vec<String>,
+    pub removed_tasks: Vec<String>,
+    pub changed_tasks: Vec<PlanTaskChange>,
+    pub added_edges: Vec<PlanEdge>,
+    pub removed_edges: Vec<PlanEdge>,
+}
+
 /// Repository.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repository {
@@ -289,6 +356,38 @@ pub struct TodoItem {
     pub updated_at: DateTime<Utc>,
 }
 
+/// Number of unit tasks in one status within a repository group.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskStatusCount {
+    pub repository_group_id: String,
+    pub status: UnitTaskStatus,
+    pub count: i32,
+}
+
+/// Distribution of a set of durations, in seconds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DurationStats {
+    pub count: i32,
+    pub min_seconds: f64,
+    pub p50_seconds: f64,
+    pub p90_seconds: f64,
+    pub p99_seconds: f64,
+    pub max_seconds: f64,
+}
+
+/// Task statistics for one AI agent type.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentTaskStats {
+    pub ai_agent_type: AiAgentType,
+    pub task_count: i32,
+    pub approved_count: i32,
+    pub rejected_count: i32,
+    pub time_to_review: Option<DurationStats>,
+    pub session_duration: Option<DurationStats>,
+    pub approval_rate: Option<f64>,
+    pub rejection_rate: Option<f64>,
+}
+
 /// Secret key-value pair.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Secret {
@@ -0,0 +1,162 @@
+-- PostgreSQL schema for multi-user mode.
+--
+-- This follows the normalized layout documented in docs/main-server.md.
+-- Join tables carry a `position` column so list-valued entity fields keep
+-- their order across a round trip.
+
+CREATE TABLE IF NOT EXISTS users (
+    id UUID PRIMARY KEY,
+    email VARCHAR(255) UNIQUE NOT NULL,
+    name VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS workspaces (
+    id UUID PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    user_id UUID REFERENCES users(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS repositories (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES workspaces(id),
+    name VARCHAR(255) NOT NULL,
+    remote_url TEXT NOT NULL,
+    default_branch VARCHAR(255) NOT NULL DEFAULT 'main',
+    vcs_type VARCHAR(50) NOT NULL DEFAULT 'git',
+    vcs_provider_type VARCHAR(50) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS repository_groups (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES workspaces(id),
+    name VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS repository_group_members (
+    group_id UUID NOT NULL REFERENCES repository_groups(id) ON DELETE CASCADE,
+    repository_id UUID NOT NULL REFERENCES repositories(id),
+    position INTEGER NOT NULL,
+    PRIMARY KEY (group_id, repository_id)
+);
+
+CREATE TABLE IF NOT EXISTS agent_tasks (
+    id UUID PRIMARY KEY,
+    ai_agent_type VARCHAR(50),
+    ai_agent_model VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS agent_task_base_remotes (
+    agent_task_id UUID NOT NULL REFERENCES agent_tasks(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    git_remote_dir_path TEXT NOT NULL,
+    git_branch_name VARCHAR(255) NOT NULL,
+    PRIMARY KEY (agent_task_id, position)
+);
+
+CREATE TABLE IF NOT EXISTS agent_sessions (
+    id UUID PRIMARY KEY,
+    agent_task_id UUID NOT NULL REFERENCES agent_tasks(id),
+    ai_agent_type VARCHAR(50) NOT NULL,
+    ai_agent_model VARCHAR(255),
+    started_at TIMESTAMPTZ,
+    completed_at TIMESTAMPTZ,
+    output_log TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS unit_tasks (
+    id UUID PRIMARY KEY,
+    repository_group_id UUID NOT NULL REFERENCES repository_groups(id),
+    agent_task_id UUID NOT NULL REFERENCES agent_tasks(id),
+    branch_name VARCHAR(255),
+    linked_pr_url TEXT,
+    base_commit VARCHAR(40),
+    end_commit VARCHAR(40),
+    status VARCHAR(50) NOT NULL DEFAULT 'in_progress',
+    prompt TEXT NOT NULL,
+    title VARCHAR(255),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS unit_task_auto_fix_tasks (
+    unit_task_id UUID NOT NULL REFERENCES unit_tasks(id) ON DELETE CASCADE,
+    agent_task_id UUID NOT NULL REFERENCES agent_tasks(id),
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (unit_task_id, agent_task_id)
+);
+
+CREATE TABLE IF NOT EXISTS composite_tasks (
+    id UUID PRIMARY KEY,
+    repository_group_id UUID NOT NULL REFERENCES repository_groups(id),
+    planning_task_id UUID NOT NULL REFERENCES agent_tasks(id),
+    status VARCHAR(50) NOT NULL DEFAULT 'planning',
+    execution_agent_type VARCHAR(50),
+    prompt TEXT NOT NULL,
+    title VARCHAR(255),
+    node_ids UUID[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS composite_task_nodes (
+    id UUID PRIMARY KEY,
+    composite_task_id UUID NOT NULL REFERENCES composite_tasks(id),
+    unit_task_id UUID NOT NULL REFERENCES unit_tasks(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS composite_task_node_dependencies (
+    node_id UUID NOT NULL REFERENCES composite_task_nodes(id) ON DELETE CASCADE,
+    depends_on_node_id UUID NOT NULL REFERENCES composite_task_nodes(id),
+    position INTEGER NOT NULL,
+    PRIMARY KEY (node_id, depends_on_node_id)
+);
+
+CREATE TABLE IF NOT EXISTS todo_items (
+    id UUID PRIMARY KEY,
+    type VARCHAR(50) NOT NULL,
+    source VARCHAR(50) NOT NULL DEFAULT 'auto',
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    repository_id UUID NOT NULL REFERENCES repositories(id),
+    data JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS tty_input_requests (
+    id UUID PRIMARY KEY,
+    task_id UUID NOT NULL REFERENCES unit_tasks(id),
+    session_id UUID NOT NULL REFERENCES agent_sessions(id),
+    prompt TEXT NOT NULL,
+    input_type VARCHAR(50) NOT NULL,
+    options JSONB,
+    status VARCHAR(50) NOT NULL DEFAULT 'pending',
+    response TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    responded_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS idx_workspaces_user_id ON workspaces(user_id);
+CREATE INDEX IF NOT EXISTS idx_repositories_workspace_id ON repositories(workspace_id);
+CREATE INDEX IF NOT EXISTS idx_repository_groups_workspace_id ON repository_groups(workspace_id);
+CREATE INDEX IF NOT EXISTS idx_agent_sessions_agent_task_id ON agent_sessions(agent_task_id);
+CREATE INDEX IF NOT EXISTS idx_unit_tasks_repository_group_id ON unit_tasks(repository_group_id);
+CREATE INDEX IF NOT EXISTS idx_unit_tasks_status ON unit_tasks(status);
+CREATE INDEX IF NOT EXISTS idx_composite_tasks_repository_group_id ON composite_tasks(repository_group_id);
+CREATE INDEX IF NOT EXISTS idx_composite_tasks_status ON composite_tasks(status);
+CREATE INDEX IF NOT EXISTS idx_composite_task_nodes_composite_task_id ON composite_task_nodes(composite_task_id);
+CREATE INDEX IF NOT EXISTS idx_todo_items_repository_id ON todo_items(repository_id);
+CREATE INDEX IF NOT EXISTS idx_tty_input_requests_task_id ON tty_input_requests(task_id);
+CREATE INDEX IF NOT EXISTS idx_tty_input_requests_session_id ON tty_input_requests(session_id);
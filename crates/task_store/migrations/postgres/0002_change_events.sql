@@ -0,0 +1,17 @@
+-- Outbox of entity changes read by change feed subscribers.
+--
+-- Sequence numbers are allocated before commit, so concurrent transactions
+-- can commit them out of order. `transaction_id` lets readers stop at the
+-- first row whose transaction may still be in progress.
+
+CREATE TABLE change_events (
+    sequence BIGSERIAL PRIMARY KEY,
+    entity_kind VARCHAR(50) NOT NULL,
+    entity_id UUID NOT NULL,
+    operation VARCHAR(20) NOT NULL,
+    value JSONB,
+    occurred_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    transaction_id XID8 NOT NULL DEFAULT pg_current_xact_id()
+);
+
+CREATE INDEX idx_change_events_occurred_at ON change_events(occurred_at);
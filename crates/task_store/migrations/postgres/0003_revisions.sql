@@ -0,0 +1,13 @@
+-- Revision counters for optimistic concurrency control.
+
+ALTER TABLE users ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE workspaces ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE repositories ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE repository_groups ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE agent_tasks ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE agent_sessions ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE unit_tasks ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE composite_tasks ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE composite_task_nodes ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE todo_items ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE tty_input_requests ADD COLUMN revision BIGINT NOT NULL DEFAULT 0;
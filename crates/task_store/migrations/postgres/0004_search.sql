@@ -0,0 +1,15 @@
+-- Full-text search over task prompts and titles and session output logs.
+--
+-- Text is indexed as the `simple` configuration's tsvector of the column with
+-- every run of non-alphanumeric characters replaced by a space, so paths and
+-- identifiers split into words the same way in every store. Searches must use
+-- exactly this expression for the indexes to apply.
+
+CREATE INDEX idx_unit_tasks_title_search ON unit_tasks
+    USING GIN (to_tsvector('simple', regexp_replace(title, '[^[:alnum:]]+', ' ', 'g')));
+CREATE INDEX idx_unit_tasks_prompt_search ON unit_tasks
+    USING GIN (to_tsvector('simple', regexp_replace(prompt, '[^[:alnum:]]+', ' ', 'g')));
+CREATE INDEX idx_composite_tasks_prompt_search ON composite_tasks
+    USING GIN (to_tsvector('simple', regexp_replace(prompt, '[^[:alnum:]]+', ' ', 'g')));
+CREATE INDEX idx_agent_sessions_output_log_search ON agent_sessions
+    USING GIN (to_tsvector('simple', regexp_replace(output_log, '[^[:alnum:]]+', ' ', 'g')));
@@ -0,0 +1,12 @@
+-- Trash timestamps for soft-deleted rows. Rows with a deleted_at are hidden
+-- from lists by default and removed for good by the purge job.
+
+ALTER TABLE workspaces ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE repositories ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE unit_tasks ADD COLUMN deleted_at TIMESTAMPTZ;
+ALTER TABLE composite_tasks ADD COLUMN deleted_at TIMESTAMPTZ;
+
+CREATE INDEX idx_workspaces_deleted_at ON workspaces(deleted_at);
+CREATE INDEX idx_repositories_deleted_at ON repositories(deleted_at);
+CREATE INDEX idx_unit_tasks_deleted_at ON unit_tasks(deleted_at);
+CREATE INDEX idx_composite_tasks_deleted_at ON composite_tasks(deleted_at);
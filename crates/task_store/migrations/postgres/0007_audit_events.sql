@@ -0,0 +1,20 @@
+-- Append-only audit log of state-changing actions.
+--
+-- Rows are never updated or deleted. Entity references are kept as plain
+-- IDs, so the history of an entity outlives the entity itself.
+
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY,
+    actor_type VARCHAR(50) NOT NULL,
+    actor_id UUID,
+    action VARCHAR(255) NOT NULL,
+    entity_type VARCHAR(50) NOT NULL,
+    entity_id UUID NOT NULL,
+    before JSONB,
+    after JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_events_entity ON audit_events(entity_type, entity_id);
+CREATE INDEX idx_audit_events_actor ON audit_events(actor_type, actor_id);
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at);
@@ -0,0 +1,20 @@
+-- Durable work queue handing agent tasks out to workers under leases.
+--
+-- An agent task is enqueued at most once, and its entry is deleted with it.
+
+CREATE TABLE work_queue (
+    id UUID PRIMARY KEY,
+    agent_task_id UUID NOT NULL UNIQUE REFERENCES agent_tasks(id) ON DELETE CASCADE,
+    status VARCHAR(50) NOT NULL DEFAULT 'queued',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    worker_id UUID,
+    lease_expires_at TIMESTAMPTZ,
+    last_error TEXT,
+    revision BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_work_queue_status ON work_queue(status, created_at);
+CREATE INDEX idx_work_queue_worker_id ON work_queue(worker_id);
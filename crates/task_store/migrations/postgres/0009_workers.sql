@@ -0,0 +1,20 @@
+-- Registry of worker servers, so registrations survive a main server
+-- restart.
+--
+-- Purging a worker's current task clears it rather than failing.
+
+CREATE TABLE workers (
+    id UUID PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    endpoint_url TEXT NOT NULL,
+    capabilities JSONB NOT NULL DEFAULT '[]',
+    status VARCHAR(50) NOT NULL DEFAULT 'idle',
+    current_task_id UUID REFERENCES unit_tasks(id) ON DELETE SET NULL,
+    last_heartbeat TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    revision BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_workers_status ON workers(status);
+CREATE INDEX idx_workers_last_heartbeat ON workers(last_heartbeat);
+CREATE INDEX idx_workers_current_task_id ON workers(current_task_id);
@@ -0,0 +1,8 @@
+-- Indexes for looking up tasks by branch name, pull request URL and agent
+-- task.
+
+CREATE INDEX idx_unit_tasks_branch_name ON unit_tasks(branch_name);
+CREATE INDEX idx_unit_tasks_linked_pr_url ON unit_tasks(linked_pr_url);
+CREATE INDEX idx_unit_tasks_agent_task_id ON unit_tasks(agent_task_id);
+CREATE INDEX idx_unit_task_auto_fix_tasks_agent_task_id ON unit_task_auto_fix_tasks(agent_task_id);
+CREATE INDEX idx_composite_task_nodes_unit_task_id ON composite_task_nodes(unit_task_id);
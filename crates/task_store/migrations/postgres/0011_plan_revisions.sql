@@ -0,0 +1,18 @@
+-- Versions of each composite task's PLAN.yaml, as generated by the planning
+-- agent and edited by users before approval.
+--
+-- Revisions are numbered from 1 within their composite task and are deleted
+-- with it.
+
+CREATE TABLE plan_revisions (
+    id UUID PRIMARY KEY,
+    composite_task_id UUID NOT NULL REFERENCES composite_tasks(id) ON DELETE CASCADE,
+    number INTEGER NOT NULL,
+    yaml TEXT NOT NULL,
+    author_type VARCHAR(50) NOT NULL,
+    author_id UUID NOT NULL,
+    approved_by UUID,
+    approved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE (composite_task_id, number)
+);
@@ -0,0 +1,5 @@
+-- Full-text search over session log chunks, with the same expression as the
+-- entity field indexes in 0004.
+
+CREATE INDEX idx_session_log_chunks_content_search ON session_log_chunks
+    USING GIN (to_tsvector('simple', regexp_replace(content, '[^[:alnum:]]+', ' ', 'g')));
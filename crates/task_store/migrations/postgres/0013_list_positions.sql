@@ -0,0 +1,14 @@
+-- Key ID list tables by position instead of by member, so a list can name
+-- the same entity more than once, as it can in the other backends.
+
+ALTER TABLE repository_group_members
+    DROP CONSTRAINT repository_group_members_pkey,
+    ADD PRIMARY KEY (group_id, position);
+
+ALTER TABLE unit_task_auto_fix_tasks
+    DROP CONSTRAINT unit_task_auto_fix_tasks_pkey,
+    ADD PRIMARY KEY (unit_task_id, position);
+
+ALTER TABLE composite_task_node_dependencies
+    DROP CONSTRAINT composite_task_node_dependencies_pkey,
+    ADD PRIMARY KEY (node_id, position);
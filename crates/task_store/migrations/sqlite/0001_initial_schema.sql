@@ -0,0 +1,136 @@
+-- SQLite schema for single-user mode.
+--
+-- Identifiers are stored as hyphenated UUID text and timestamps as RFC 3339
+-- text. List-valued fields that have no relational meaning of their own are
+-- stored as JSON text.
+
+CREATE TABLE IF NOT EXISTS users (
+    id TEXT PRIMARY KEY NOT NULL,
+    email TEXT NOT NULL UNIQUE,
+    name TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS workspaces (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    user_id TEXT REFERENCES users(id),
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS repositories (
+    id TEXT PRIMARY KEY NOT NULL,
+    workspace_id TEXT NOT NULL REFERENCES workspaces(id),
+    name TEXT NOT NULL,
+    remote_url TEXT NOT NULL,
+    default_branch TEXT NOT NULL DEFAULT 'main',
+    vcs_type TEXT NOT NULL DEFAULT 'git',
+    vcs_provider_type TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS repository_groups (
+    id TEXT PRIMARY KEY NOT NULL,
+    workspace_id TEXT NOT NULL REFERENCES workspaces(id),
+    name TEXT,
+    repository_ids TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS agent_tasks (
+    id TEXT PRIMARY KEY NOT NULL,
+    base_remotes TEXT NOT NULL DEFAULT '[]',
+    ai_agent_type TEXT,
+    ai_agent_model TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS agent_sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    agent_task_id TEXT NOT NULL REFERENCES agent_tasks(id),
+    ai_agent_type TEXT NOT NULL,
+    ai_agent_model TEXT,
+    started_at TEXT,
+    completed_at TEXT,
+    output_log TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS unit_tasks (
+    id TEXT PRIMARY KEY NOT NULL,
+    repository_group_id TEXT NOT NULL REFERENCES repository_groups(id),
+    agent_task_id TEXT NOT NULL REFERENCES agent_tasks(id),
+    prompt TEXT NOT NULL,
+    title TEXT,
+    branch_name TEXT,
+    linked_pr_url TEXT,
+    base_commit TEXT,
+    end_commit TEXT,
+    auto_fix_task_ids TEXT NOT NULL DEFAULT '[]',
+    status TEXT NOT NULL DEFAULT 'in_progress',
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS composite_tasks (
+    id TEXT PRIMARY KEY NOT NULL,
+    repository_group_id TEXT NOT NULL REFERENCES repository_groups(id),
+    planning_task_id TEXT NOT NULL REFERENCES agent_tasks(id),
+    prompt TEXT NOT NULL,
+    title TEXT,
+    node_ids TEXT NOT NULL DEFAULT '[]',
+    status TEXT NOT NULL DEFAULT 'planning',
+    execution_agent_type TEXT,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS composite_task_nodes (
+    id TEXT PRIMARY KEY NOT NULL,
+    composite_task_id TEXT NOT NULL REFERENCES composite_tasks(id),
+    unit_task_id TEXT NOT NULL REFERENCES unit_tasks(id),
+    depends_on_ids TEXT NOT NULL DEFAULT '[]',
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS todo_items (
+    id TEXT PRIMARY KEY NOT NULL,
+    item_type TEXT NOT NULL,
+    source TEXT NOT NULL DEFAULT 'auto',
+    status TEXT NOT NULL DEFAULT 'pending',
+    repository_id TEXT NOT NULL REFERENCES repositories(id),
+    data TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS tty_input_requests (
+    id TEXT PRIMARY KEY NOT NULL,
+    task_id TEXT NOT NULL REFERENCES unit_tasks(id),
+    session_id TEXT NOT NULL REFERENCES agent_sessions(id),
+    prompt TEXT NOT NULL,
+    input_type TEXT NOT NULL,
+    options TEXT,
+    status TEXT NOT NULL DEFAULT 'pending',
+    response TEXT,
+    created_at TEXT NOT NULL,
+    responded_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_workspaces_user_id ON workspaces(user_id);
+CREATE INDEX IF NOT EXISTS idx_repositories_workspace_id ON repositories(workspace_id);
+CREATE INDEX IF NOT EXISTS idx_repository_groups_workspace_id ON repository_groups(workspace_id);
+CREATE INDEX IF NOT EXISTS idx_agent_sessions_agent_task_id ON agent_sessions(agent_task_id);
+CREATE INDEX IF NOT EXISTS idx_unit_tasks_repository_group_id ON unit_tasks(repository_group_id);
+CREATE INDEX IF NOT EXISTS idx_unit_tasks_status ON unit_tasks(status);
+CREATE INDEX IF NOT EXISTS idx_composite_tasks_repository_group_id ON composite_tasks(repository_group_id);
+CREATE INDEX IF NOT EXISTS idx_composite_tasks_status ON composite_tasks(status);
+CREATE INDEX IF NOT EXISTS idx_composite_task_nodes_composite_task_id ON composite_task_nodes(composite_task_id);
+CREATE INDEX IF NOT EXISTS idx_todo_items_repository_id ON todo_items(repository_id);
+CREATE INDEX IF NOT EXISTS idx_tty_input_requests_task_id ON tty_input_requests(task_id);
+CREATE INDEX IF NOT EXISTS idx_tty_input_requests_session_id ON tty_input_requests(session_id);
@@ -0,0 +1,12 @@
+-- Outbox of entity changes read by change feed subscribers.
+
+CREATE TABLE change_events (
+    sequence INTEGER PRIMARY KEY AUTOINCREMENT,
+    entity_kind TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    operation TEXT NOT NULL,
+    value TEXT,
+    occurred_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_change_events_occurred_at ON change_events(occurred_at);
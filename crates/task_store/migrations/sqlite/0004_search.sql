@@ -0,0 +1,98 @@
+-- Full-text search over task prompts and titles and session output logs.
+--
+-- `search_documents` holds one row per searchable field and is kept in sync
+-- with the entity tables by triggers. `search_index` is an FTS5 index over
+-- its `body` column.
+
+CREATE TABLE search_documents (
+    rowid INTEGER PRIMARY KEY,
+    kind TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    body TEXT NOT NULL,
+    UNIQUE (kind, entity_id, field)
+);
+
+CREATE VIRTUAL TABLE search_index USING fts5(
+    body,
+    content = 'search_documents',
+    content_rowid = 'rowid',
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+
+CREATE TRIGGER search_documents_insert AFTER INSERT ON search_documents BEGIN
+    INSERT INTO search_index (rowid, body) VALUES (NEW.rowid, NEW.body);
+END;
+
+CREATE TRIGGER search_documents_delete AFTER DELETE ON search_documents BEGIN
+    INSERT INTO search_index (search_index, rowid, body) VALUES ('delete', OLD.rowid, OLD.body);
+END;
+
+-- Unit tasks: title and prompt.
+
+CREATE TRIGGER unit_tasks_search_insert AFTER INSERT ON unit_tasks BEGIN
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    SELECT 'unit_task', NEW.id, 'title', NEW.title WHERE NEW.title IS NOT NULL;
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    VALUES ('unit_task', NEW.id, 'prompt', NEW.prompt);
+END;
+
+CREATE TRIGGER unit_tasks_search_update AFTER UPDATE OF title, prompt ON unit_tasks BEGIN
+    DELETE FROM search_documents WHERE kind = 'unit_task' AND entity_id = OLD.id;
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    SELECT 'unit_task', NEW.id, 'title', NEW.title WHERE NEW.title IS NOT NULL;
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    VALUES ('unit_task', NEW.id, 'prompt', NEW.prompt);
+END;
+
+CREATE TRIGGER unit_tasks_search_delete AFTER DELETE ON unit_tasks BEGIN
+    DELETE FROM search_documents WHERE kind = 'unit_task' AND entity_id = OLD.id;
+END;
+
+-- Composite tasks: prompt.
+
+CREATE TRIGGER composite_tasks_search_insert AFTER INSERT ON composite_tasks BEGIN
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    VALUES ('composite_task', NEW.id, 'prompt', NEW.prompt);
+END;
+
+CREATE TRIGGER composite_tasks_search_update AFTER UPDATE OF prompt ON composite_tasks BEGIN
+    DELETE FROM search_documents WHERE kind = 'composite_task' AND entity_id = OLD.id;
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    VALUES ('composite_task', NEW.id, 'prompt', NEW.prompt);
+END;
+
+CREATE TRIGGER composite_tasks_search_delete AFTER DELETE ON composite_tasks BEGIN
+    DELETE FROM search_documents WHERE kind = 'composite_task' AND entity_id = OLD.id;
+END;
+
+-- Agent sessions: output log.
+
+CREATE TRIGGER agent_sessions_search_insert AFTER INSERT ON agent_sessions BEGIN
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    SELECT 'agent_session', NEW.id, 'output_log', NEW.output_log
+    WHERE NEW.output_log IS NOT NULL;
+END;
+
+CREATE TRIGGER agent_sessions_search_update AFTER UPDATE OF output_log ON agent_sessions BEGIN
+    DELETE FROM search_documents WHERE kind = 'agent_session' AND entity_id = OLD.id;
+    INSERT INTO search_documents (kind, entity_id, field, body)
+    SELECT 'agent_session', NEW.id, 'output_log', NEW.output_log
+    WHERE NEW.output_log IS NOT NULL;
+END;
+
+CREATE TRIGGER agent_sessions_search_delete AFTER DELETE ON agent_sessions BEGIN
+    DELETE FROM search_documents WHERE kind = 'agent_session' AND entity_id = OLD.id;
+END;
+
+-- Index rows written before this migration.
+
+INSERT INTO search_documents (kind, entity_id, field, body)
+SELECT 'unit_task', id, 'title', title FROM unit_tasks WHERE title IS NOT NULL;
+INSERT INTO search_documents (kind, entity_id, field, body)
+SELECT 'unit_task', id, 'prompt', prompt FROM unit_tasks;
+INSERT INTO search_documents (kind, entity_id, field, body)
+SELECT 'composite_task', id, 'prompt', prompt FROM composite_tasks;
+INSERT INTO search_documents (kind, entity_id, field, body)
+SELECT 'agent_session', id, 'output_log', output_log FROM agent_sessions
+WHERE output_log IS NOT NULL;
@@ -0,0 +1,16 @@
+-- Append-only session output logs.
+--
+-- Each chunk records its sequence number within the session and the byte
+-- offset at which it starts, so logs can be read by either without scanning
+-- earlier chunks.
+
+CREATE TABLE session_log_chunks (
+    session_id TEXT NOT NULL REFERENCES agent_sessions(id) ON DELETE CASCADE,
+    sequence INTEGER NOT NULL,
+    byte_offset INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (session_id, sequence)
+);
+
+CREATE INDEX idx_session_log_chunks_byte_offset ON session_log_chunks(session_id, byte_offset);
@@ -0,0 +1,20 @@
+-- Append-only audit log of state-changing actions.
+--
+-- Rows are never updated or deleted. Entity references are kept as plain
+-- IDs, so the history of an entity outlives the entity itself.
+
+CREATE TABLE audit_events (
+    id TEXT PRIMARY KEY NOT NULL,
+    actor_type TEXT NOT NULL,
+    actor_id TEXT,
+    action TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    before TEXT,
+    after TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_audit_events_entity ON audit_events(entity_type, entity_id);
+CREATE INDEX idx_audit_events_actor ON audit_events(actor_type, actor_id);
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at);
@@ -0,0 +1,20 @@
+-- Durable work queue handing agent tasks out to workers under leases.
+--
+-- An agent task is enqueued at most once, and its entry is deleted with it.
+
+CREATE TABLE work_queue (
+    id TEXT PRIMARY KEY NOT NULL,
+    agent_task_id TEXT NOT NULL UNIQUE REFERENCES agent_tasks(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'queued',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    max_attempts INTEGER NOT NULL,
+    worker_id TEXT,
+    lease_expires_at TEXT,
+    last_error TEXT,
+    revision INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_work_queue_status ON work_queue(status, created_at);
+CREATE INDEX idx_work_queue_worker_id ON work_queue(worker_id);
@@ -0,0 +1,20 @@
+-- Registry of worker servers, so registrations survive a main server
+-- restart.
+--
+-- Purging a worker's current task clears it rather than failing.
+
+CREATE TABLE workers (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    endpoint_url TEXT NOT NULL,
+    capabilities TEXT NOT NULL DEFAULT '[]',
+    status TEXT NOT NULL DEFAULT 'idle',
+    current_task_id TEXT REFERENCES unit_tasks(id) ON DELETE SET NULL,
+    last_heartbeat TEXT NOT NULL,
+    revision INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX idx_workers_status ON workers(status);
+CREATE INDEX idx_workers_last_heartbeat ON workers(last_heartbeat);
+CREATE INDEX idx_workers_current_task_id ON workers(current_task_id);
@@ -0,0 +1,38 @@
+-- Indexes for looking up tasks by branch name, pull request URL and agent
+-- task.
+--
+-- `unit_task_auto_fix_tasks` maps each auto-fix agent task to its unit task
+-- and is kept in sync with `unit_tasks.auto_fix_task_ids` by triggers.
+
+CREATE INDEX idx_unit_tasks_branch_name ON unit_tasks(branch_name);
+CREATE INDEX idx_unit_tasks_linked_pr_url ON unit_tasks(linked_pr_url);
+CREATE INDEX idx_unit_tasks_agent_task_id ON unit_tasks(agent_task_id);
+CREATE INDEX idx_composite_task_nodes_unit_task_id ON composite_task_nodes(unit_task_id);
+
+CREATE TABLE unit_task_auto_fix_tasks (
+    unit_task_id TEXT NOT NULL,
+    agent_task_id TEXT NOT NULL,
+    PRIMARY KEY (unit_task_id, agent_task_id)
+);
+
+CREATE INDEX idx_unit_task_auto_fix_tasks_agent_task_id ON unit_task_auto_fix_tasks(agent_task_id);
+
+CREATE TRIGGER unit_tasks_auto_fix_insert AFTER INSERT ON unit_tasks BEGIN
+    INSERT OR IGNORE INTO unit_task_auto_fix_tasks (unit_task_id, agent_task_id)
+    SELECT NEW.id, value FROM json_each(NEW.auto_fix_task_ids);
+END;
+
+CREATE TRIGGER unit_tasks_auto_fix_update AFTER UPDATE OF auto_fix_task_ids ON unit_tasks BEGIN
+    DELETE FROM unit_task_auto_fix_tasks WHERE unit_task_id = OLD.id;
+    INSERT OR IGNORE INTO unit_task_auto_fix_tasks (unit_task_id, agent_task_id)
+    SELECT NEW.id, value FROM json_each(NEW.auto_fix_task_ids);
+END;
+
+CREATE TRIGGER unit_tasks_auto_fix_delete AFTER DELETE ON unit_tasks BEGIN
+    DELETE FROM unit_task_auto_fix_tasks WHERE unit_task_id = OLD.id;
+END;
+
+-- Index rows written before this migration.
+
+INSERT OR IGNORE INTO unit_task_auto_fix_tasks (unit_task_id, agent_task_id)
+SELECT unit_tasks.id, json_each.value FROM unit_tasks, json_each(unit_tasks.auto_fix_task_ids);
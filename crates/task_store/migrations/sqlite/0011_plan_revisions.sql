@@ -0,0 +1,18 @@
+-- Versions of each composite task's PLAN.yaml, as generated by the planning
+-- agent and edited by users before approval.
+--
+-- Revisions are numbered from 1 within their composite task and are deleted
+-- with it.
+
+CREATE TABLE plan_revisions (
+    id TEXT PRIMARY KEY NOT NULL,
+    composite_task_id TEXT NOT NULL REFERENCES composite_tasks(id) ON DELETE CASCADE,
+    number INTEGER NOT NULL,
+    yaml TEXT NOT NULL,
+    author_type TEXT NOT NULL,
+    author_id TEXT NOT NULL,
+    approved_by TEXT,
+    approved_at TEXT,
+    created_at TEXT NOT NULL,
+    UNIQUE (composite_task_id, number)
+);
@@ -0,0 +1,45 @@
+-- Full-text search over session log chunks.
+--
+-- `session_log_documents` holds a copy of each chunk with a stable rowid and
+-- is kept in sync with `session_log_chunks` by triggers, like
+-- `search_documents` for the entity fields. `session_log_index` is an FTS5
+-- index over its `body` column with the same tokenizer as `search_index`.
+
+CREATE TABLE session_log_documents (
+    rowid INTEGER PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    sequence INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    UNIQUE (session_id, sequence)
+);
+
+CREATE VIRTUAL TABLE session_log_index USING fts5(
+    body,
+    content = 'session_log_documents',
+    content_rowid = 'rowid',
+    tokenize = 'unicode61 remove_diacritics 0'
+);
+
+CREATE TRIGGER session_log_documents_insert AFTER INSERT ON session_log_documents BEGIN
+    INSERT INTO session_log_index (rowid, body) VALUES (NEW.rowid, NEW.body);
+END;
+
+CREATE TRIGGER session_log_documents_delete AFTER DELETE ON session_log_documents BEGIN
+    INSERT INTO session_log_index (session_log_index, rowid, body)
+    VALUES ('delete', OLD.rowid, OLD.body);
+END;
+
+CREATE TRIGGER session_log_chunks_search_insert AFTER INSERT ON session_log_chunks BEGIN
+    INSERT INTO session_log_documents (session_id, sequence, body)
+    VALUES (NEW.session_id, NEW.sequence, NEW.content);
+END;
+
+CREATE TRIGGER session_log_chunks_search_delete AFTER DELETE ON session_log_chunks BEGIN
+    DELETE FROM session_log_documents
+    WHERE session_id = OLD.session_id AND sequence = OLD.sequence;
+END;
+
+-- Index chunks written before this migration.
+
+INSERT INTO session_log_documents (session_id, sequence, body)
+SELECT session_id, sequence, content FROM session_log_chunks;
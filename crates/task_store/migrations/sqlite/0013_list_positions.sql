@@ -0,0 +1,37 @@
+-- Key `unit_task_auto_fix_tasks` by position instead of by agent task, as in
+-- the PostgreSQL schema, so repeated auto-fix task IDs are all indexed.
+
+DROP TRIGGER unit_tasks_auto_fix_insert;
+DROP TRIGGER unit_tasks_auto_fix_update;
+DROP TRIGGER unit_tasks_auto_fix_delete;
+DROP TABLE unit_task_auto_fix_tasks;
+
+CREATE TABLE unit_task_auto_fix_tasks (
+    unit_task_id TEXT NOT NULL,
+    agent_task_id TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (unit_task_id, position)
+);
+
+CREATE INDEX idx_unit_task_auto_fix_tasks_agent_task_id ON unit_task_auto_fix_tasks(agent_task_id);
+
+CREATE TRIGGER unit_tasks_auto_fix_insert AFTER INSERT ON unit_tasks BEGIN
+    INSERT INTO unit_task_auto_fix_tasks (unit_task_id, agent_task_id, position)
+    SELECT NEW.id, value, key FROM json_each(NEW.auto_fix_task_ids);
+END;
+
+CREATE TRIGGER unit_tasks_auto_fix_update AFTER UPDATE OF auto_fix_task_ids ON unit_tasks BEGIN
+    DELETE FROM unit_task_auto_fix_tasks WHERE unit_task_id = OLD.id;
+    INSERT INTO unit_task_auto_fix_tasks (unit_task_id, agent_task_id, position)
+    SELECT NEW.id, value, key FROM json_each(NEW.auto_fix_task_ids);
+END;
+
+CREATE TRIGGER unit_tasks_auto_fix_delete AFTER DELETE ON unit_tasks BEGIN
+    DELETE FROM unit_task_auto_fix_tasks WHERE unit_task_id = OLD.id;
+END;
+
+-- Index rows written before this migration.
+
+INSERT INTO unit_task_auto_fix_tasks (unit_task_id, agent_task_id, position)
+SELECT unit_tasks.id, json_each.value, json_each.key
+FROM unit_tasks, json_each(unit_tasks.auto_fix_task_ids);
@@ -0,0 +1,382 @@
+//! Aggregate statistics over unit tasks for dashboards.
+//!
+//! [`task_stats`] counts unit tasks by repository group and status, and
+//! breaks them down by AI agent type into review latencies, session
+//! durations and review outcomes. Stores keep no history of status changes,
+//! so latencies are derived from timestamps: a task's time to review runs
+//! from its creation until its agent's last session completed, which is
+//! when the task moved from `InProgress` to `InReview`. A task still in
+//! review without a completed session falls back to its `updated_at`.
+//!
+//! Statistics are computed on the fly from the store's list operations,
+//! so they work against every backend.
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::{DateTime, Utc};
+use entities::{AgentTask, AiAgentType, UnitTaskStatus};
+use futures::{FutureExt, TryFutureExt};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{TaskFilter, TaskStore, TaskStoreResult, sort::all_pages};
+
+/// Number of unit tasks fetched per page while computing statistics.
+const STATS_PAGE_SIZE: u32 = 500;
+
+/// Unit task statuses in lifecycle order, the order of
+/// [`TaskStats::status_counts`].
+const STATUS_ORDER: [UnitTaskStatus; 6] = [
+    UnitTaskStatus::InProgress,
+    UnitTaskStatus::InReview,
+    UnitTaskStatus::Approved,
+    UnitTaskStatus::PrOpen,
+    UnitTaskStatus::Done,
+    UnitTaskStatus::Rejected,
+];
+
+/// Which unit tasks statistics are computed over. Tasks in the trash are
+/// always left out.
+#[derive(Debug, Clone, Default)]
+pub struct StatsFilter {
+    /// Only count tasks of this repository group.
+    pub repository_group_id: Option<Uuid>,
+    /// Only count tasks created at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only count tasks created before this time.
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// Number of unit tasks of one repository group in one status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StatusCount {
+    /// Repository group of the tasks.
+    pub repository_group_id: Uuid,
+    /// Status of the tasks.
+    pub status: UnitTaskStatus,
+    /// Number of tasks.
+    pub count: u64,
+}
+
+/// Distribution of a set of durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DurationStats {
+    /// Number of durations.
+    pub count: u64,
+    /// Shortest duration.
+    pub min: Duration,
+    /// Median duration.
+    pub p50: Duration,
+    /// 90th percentile.
+    pub p90: Duration,
+    /// 99th percentile.
+    pub p99: Duration,
+    /// Longest duration.
+    pub max: Duration,
+}
+
+impl DurationStats {
+    /// Computes the distribution of `samples`, or `None` if there are none.
+    ///
+    /// Percentiles use the nearest-rank method, so every reported value is
+    /// one of the samples.
+    pub fn from_samples(mut samples: Vec<Duration>) -> Option<Self> {
+        samples.sort_unstable();
+        let percentile = |p: usize| {
+            let rank = (p * samples.len()).div_ceil(100).max(1);
+            samples[rank - 1]
+        };
+        Some(Self {
+            count: samples.len() as u64,
+            min: *samples.first()?,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: *samples.last()?,
+        })
+    }
+}
+
+/// Statistics of the unit tasks run by one AI agent type.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AgentStats {
+    /// Agent type, taken from the task's agent task or else its latest
+    /// session. `None` for tasks that have neither.
+    pub ai_agent_type: Option<AiAgentType>,
+    /// Number of tasks.
+    pub tasks: u64,
+    /// Time from creation until review, over tasks that reached review.
+    pub time_to_review: Option<DurationStats>,
+    /// Time from start to completion of the tasks' agent sessions.
+    pub session_duration: Option<DurationStats>,
+    /// Number of tasks approved, including those with an open or merged PR.
+    pub approved: u64,
+    /// Number of tasks rejected.
+    pub rejected: u64,
+}
+
+impl AgentStats {
+    /// Returns the share of reviewed tasks that were approved, or `None`
+    /// before any was reviewed.
+    pub fn approval_rate(&self) -> Option<f64> {
+        let reviewed = self.approved + self.rejected;
+        (reviewed > 0).then(|| self.approved as f64 / reviewed as f64)
+    }
+
+    /// Returns the share of reviewed tasks that were rejected, or `None`
+    /// before any was reviewed.
+    pub fn rejection_rate(&self) -> Option<f64> {
+        self.approval_rate().map(|rate| 1.0 - rate)
+    }
+}
+
+/// Aggregate statistics over unit tasks.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TaskStats {
+    /// Task counts by repository group and status, ordered by group ID and
+    /// then by status in lifecycle order. Statuses without tasks are left
+    /// out.
+    pub status_counts: Vec<StatusCount>,
+    /// Statistics by agent type, ordered by descending task count.
+    pub agents: Vec<AgentStats>,
+}
+
+impl TaskStats {
+    /// Returns the number of tasks of a repository group in a status.
+    pub fn count(&self, repository_group_id: Uuid, status: UnitTaskStatus) -> u64 {
+        self.status_counts
+            .iter()
+            .find(|c| c.repository_group_id == repository_group_id && c.status == status)
+            .map_or(0, |c| c.count)
+    }
+
+    /// Returns the statistics of an agent type.
+    pub fn agent(&self, ai_agent_type: Option<AiAgentType>) -> Option<&AgentStats> {
+        self.agents
+            .iter()
+            .find(|a| a.ai_agent_type == ai_agent_type)
+    }
+}
+
+/// Samples collected for one agent type.
+#[derive(Default)]
+struct AgentSamples {
+    tasks: u64,
+    time_to_review: Vec<Duration>,
+    session_duration: Vec<Duration>,
+    approved: u64,
+    rejected: u64,
+}
+
+/// Computes statistics over the unit tasks matching `filter`.
+pub async fn task_stats(store: &dyn TaskStore, filter: StatsFilter) -> TaskStoreResult<TaskStats> {
+    let tasks = all_pages(STATS_PAGE_SIZE, |after| {
+        store
+            .list_unit_tasks(TaskFilter {
+                repository_group_id: filter.repository_group_id,
+                created_since: filter.since,
+                created_until: filter.until,
+                after,
+                limit: Some(STATS_PAGE_SIZE),
+                ..Default::default()
+            })
+            .map_ok(|(rows, _)| rows)
+            .boxed()
+    })
+    .await?;
+
+    let mut counts: HashMap<(Uuid, UnitTaskStatus), u64> = HashMap::new();
+    let mut agents: HashMap<Option<AiAgentType>, AgentSamples> = HashMap::new();
+    // Agent tasks come with their sessions, oldest first, and are fetched a
+    // page of unit tasks at a time.
+    for page in tasks.chunks(STATS_PAGE_SIZE as usize) {
+        let agent_task_ids: Vec<Uuid> = page.iter().map(|t| t.agent_task_id).collect();
+        let agent_tasks: HashMap<Uuid, AgentTask> = store
+            .get_agent_tasks(&agent_task_ids)
+            .await?
+            .into_iter()
+            .map(|t| (t.id, t))
+            .collect();
+        for task in page {
+            *counts
+                .entry((task.repository_group_id, task.status))
+                .or_default() += 1;
+
+            let (agent_type, sessions) = agent_tasks
+                .get(&task.agent_task_id)
+                .map(|t| (t.ai_agent_type, t.agent_sessions.as_slice()))
+                .unwrap_or_default();
+            let ai_agent_type = agent_type.or_else(|| sessions.last().map(|s| s.ai_agent_type));
+            let samples = agents.entry(ai_agent_type).or_default();
+            samples.tasks += 1;
+
+            let completed_at = sessions.iter().filter_map(|s| s.completed_at).max();
+            let reviewed_at = match task.status {
+                UnitTaskStatus::InProgress => None,
+                UnitTaskStatus::InReview => completed_at.or(Some(task.updated_at)),
+                _ => completed_at,
+            };
+            if let Some(reviewed_at) = reviewed_at {
+                samples
+                    .time_to_review
+                    .push(elapsed(task.created_at, reviewed_at));
+            }
+            samples.session_duration.extend(
+                sessions
+                    .iter()
+                    .filter_map(|s| Some(elapsed(s.started_at?, s.completed_at?))),
+            );
+            match task.status {
+                UnitTaskStatus::Approved | UnitTaskStatus::PrOpen | UnitTaskStatus::Done => {
+                    samples.approved += 1;
+                }
+                UnitTaskStatus::Rejected => samples.rejected += 1,
+                UnitTaskStatus::InProgress | UnitTaskStatus::InReview => {}
+            }
+        }
+    }
+
+    let mut status_counts: Vec<StatusCount> = counts
+        .into_iter()
+        .map(|((repository_group_id, status), count)| StatusCount {
+            repository_group_id,
+            status,
+            count,
+        })
+        .collect();
+    status_counts.sort_by_key(|c| {
+        let position = STATUS_ORDER.iter().position(|s| *s == c.status);
+        (c.repository_group_id, position)
+    });
+    let mut agents: Vec<AgentStats> = agents
+        .into_iter()
+        .map(|(ai_agent_type, samples)| AgentStats {
+            ai_agent_type,
+            tasks: samples.tasks,
+            time_to_review: DurationStats::from_samples(samples.time_to_review),
+            session_duration: DurationStats::from_samples(samples.session_duration),
+            approved: samples.approved,
+            rejected: samples.rejected,
+        })
+        .collect();
+    agents.sort_by_key(|a| {
+        (
+            std::cmp::Reverse(a.tasks),
+            a.ai_agent_type.map(|t| t.command()),
+        )
+    });
+
+    Ok(TaskStats {
+        status_counts,
+        agents,
+    })
+}
+
+/// Returns the time from `start` to `end`, or zero if the clock went
+/// backwards.
+fn elapsed(start: DateTime<Utc>, end: DateTime<Utc>) -> Duration {
+    (end - start).to_std().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use entities::{AgentSession, AgentTask, RepositoryGroup, UnitTask, Workspace};
+
+    use super::*;
+    use crate::{MemoryTaskStore, SqliteTaskStore};
+
+    #[test]
+    fn test_duration_percentiles() {
+        let samples = (1..=10).map(Duration::from_secs).collect();
+        let stats = DurationStats::from_samples(samples).unwrap();
+        assert_eq!(stats.count, 10);
+        assert_eq!(stats.min, Duration::from_secs(1));
+        assert_eq!(stats.p50, Duration::from_secs(5));
+        assert_eq!(stats.p90, Duration::from_secs(9));
+        assert_eq!(stats.p99, Duration::from_secs(10));
+        assert_eq!(stats.max, Duration::from_secs(10));
+        assert!(DurationStats::from_samples(Vec::new()).is_none());
+    }
+
+    async fn check_stats(store: &dyn TaskStore) {
+        let workspace = store
+            .create_workspace(Workspace::new("Stats"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let start = Utc::now() - TimeDelta::hours(1);
+        let statuses = [
+            (AiAgentType::ClaudeCode, UnitTaskStatus::InReview, 10),
+            (AiAgentType::ClaudeCode, UnitTaskStatus::Done, 20),
+            (AiAgentType::ClaudeCode, UnitTaskStatus::Rejected, 30),
+            (AiAgentType::Aider, UnitTaskStatus::Approved, 40),
+            (AiAgentType::Aider, UnitTaskStatus::InProgress, 0),
+        ];
+        for (ai_agent_type, status, minutes) in statuses {
+            let mut agent_task = AgentTask::new();
+            agent_task.ai_agent_type = Some(ai_agent_type);
+            let agent_task = store.create_agent_task(agent_task).await.unwrap();
+            let mut session = AgentSession::new(agent_task.id, ai_agent_type);
+            session.started_at = Some(start + TimeDelta::minutes(1));
+            if minutes > 0 {
+                session.completed_at = Some(start + TimeDelta::minutes(minutes));
+            }
+            store.create_agent_session(session).await.unwrap();
+            let mut task = UnitTask::new(group.id, agent_task.id, "Work");
+            task.status = status;
+            task.created_at = start;
+            store.create_unit_task(task).await.unwrap();
+        }
+
+        let filter = StatsFilter {
+            repository_group_id: Some(group.id),
+            ..Default::default()
+        };
+        let stats = task_stats(store, filter.clone()).await.unwrap();
+        assert_eq!(stats.count(group.id, UnitTaskStatus::InReview), 1);
+        assert_eq!(stats.count(group.id, UnitTaskStatus::Done), 1);
+        assert_eq!(stats.count(group.id, UnitTaskStatus::PrOpen), 0);
+        assert_eq!(stats.status_counts.len(), 5);
+        assert_eq!(stats.status_counts[0].status, UnitTaskStatus::InProgress);
+
+        let claude = stats.agent(Some(AiAgentType::ClaudeCode)).unwrap();
+        assert_eq!(stats.agents[0].ai_agent_type, Some(AiAgentType::ClaudeCode));
+        assert_eq!(claude.tasks, 3);
+        let time_to_review = claude.time_to_review.unwrap();
+        assert_eq!(time_to_review.count, 3);
+        assert_eq!(time_to_review.p50, Duration::from_secs(20 * 60));
+        assert_eq!(
+            claude.session_duration.unwrap().min,
+            Duration::from_secs(9 * 60)
+        );
+        assert_eq!((claude.approved, claude.rejected), (1, 1));
+        assert_eq!(claude.rejection_rate(), Some(0.5));
+
+        let aider = stats.agent(Some(AiAgentType::Aider)).unwrap();
+        assert_eq!(
+            aider.time_to_review.unwrap().count,
+            1,
+            "in-progress task skipped"
+        );
+        assert_eq!(aider.session_duration.unwrap().count, 1);
+        assert_eq!(aider.approval_rate(), Some(1.0));
+
+        let later = StatsFilter {
+            since: Some(start + TimeDelta::seconds(1)),
+            ..filter
+        };
+        let stats = task_stats(store, later).await.unwrap();
+        assert!(stats.status_counts.is_empty());
+        assert!(stats.agents.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_task_stats() {
+        check_stats(&MemoryTaskStore::new()).await;
+        check_stats(&SqliteTaskStore::in_memory().await.unwrap()).await;
+    }
+}
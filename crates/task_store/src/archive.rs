@@ -0,0 +1,965 @@
+//! Export and import of a whole task store.
+//!
+//! An archive is a directory holding a `manifest.json` and one NDJSON file
+//! per [`ArchiveSection`], each line a serialized row. Sections are written
+//! in dependency order, so importing them in the same order creates every
+//! row after the rows it references. Both directions work against any
+//! [`TaskStore`], which makes archives usable for backups as well as for
+//! moving data between backends, for example from a local SQLite database
+//! to the team PostgreSQL server.
+//!
+//! Rows keep their IDs, revisions and timestamps across an import, and rows
+//! in the trash are exported and imported like any other. Session log chunks
+//! are re-appended, so they keep their sequence numbers and byte offsets but
+//! get a new `created_at`, and plan revisions are re-created in order, so
+//! they keep their numbers. The work queue is not archived, as its leases
+//! belong to the workers of the exporting server; enqueue pending agent
+//! tasks again after an import. Neither is the worker registry: workers
+//! register again with the server they run against.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::ErrorKind,
+    path::Path,
+};
+
+use chrono::{DateTime, Utc};
+use entities::{
+    AgentSession, AgentTask, AuditEvent, CompositeTask, CompositeTaskNode, PlanRevision,
+    Repository, RepositoryGroup, TodoItem, TtyInputRequest, UnitTask, User, Workspace,
+};
+use futures::{FutureExt, TryFutureExt, future::BoxFuture};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter, Lines},
+};
+use uuid::Uuid;
+
+use crate::{
+    AuditFilter, DeletedFilter, LogChunk, LogRange, RepositoryFilter, SCHEMA_VERSION, TaskFilter,
+    TaskStore, TaskStoreError, TaskStoreResult, TodoFilter, TtyInputFilter, WorkspaceFilter,
+    sort::{Cursor, Sortable, next_page},
+};
+
+/// Version of the archive format written by [`export_archive`].
+pub const ARCHIVE_VERSION: u32 = 3;
+
+/// Name of the manifest file inside an archive directory.
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Number of rows fetched per page while exporting paged lists.
+const EXPORT_PAGE_SIZE: u32 = 500;
+
+/// A kind of row stored in an archive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveSection {
+    Users,
+    Workspaces,
+    Repositories,
+    RepositoryGroups,
+    AgentTasks,
+    AgentSessions,
+    SessionLogChunks,
+    UnitTasks,
+    CompositeTasks,
+    CompositeTaskNodes,
+    PlanRevisions,
+    TodoItems,
+    TtyInputRequests,
+    AuditEvents,
+}
+
+impl ArchiveSection {
+    /// Every section, in the order rows must be imported.
+    pub const ALL: [Self; 14] = [
+        Self::Users,
+        Self::Workspaces,
+        Self::Repositories,
+        Self::RepositoryGroups,
+        Self::AgentTasks,
+        Self::AgentSessions,
+        Self::SessionLogChunks,
+        Self::UnitTasks,
+        Self::CompositeTasks,
+        Self::CompositeTaskNodes,
+        Self::PlanRevisions,
+        Self::TodoItems,
+        Self::TtyInputRequests,
+        Self::AuditEvents,
+    ];
+
+    /// Returns the snake_case name of the section.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Users => "users",
+            Self::Workspaces => "workspaces",
+            Self::Repositories => "repositories",
+            Self::RepositoryGroups => "repository_groups",
+            Self::AgentTasks => "agent_tasks",
+            Self::AgentSessions => "agent_sessions",
+            Self::SessionLogChunks => "session_log_chunks",
+            Self::UnitTasks => "unit_tasks",
+            Self::CompositeTasks => "composite_tasks",
+            Self::CompositeTaskNodes => "composite_task_nodes",
+            Self::PlanRevisions => "plan_revisions",
+            Self::TodoItems => "todo_items",
+            Self::TtyInputRequests => "tty_input_requests",
+            Self::AuditEvents => "audit_events",
+        }
+    }
+
+    /// Returns the name of the file holding the section's rows.
+    pub fn file_name(self) -> String {
+        format!("{}.ndjson", self.name())
+    }
+}
+
+/// Describes the contents of an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    /// Archive format version.
+    pub format_version: u32,
+    /// Schema version of the store the archive was exported from.
+    pub schema_version: u32,
+    /// When the export finished.
+    pub exported_at: DateTime<Utc>,
+    /// Number of rows in each section.
+    pub counts: BTreeMap<ArchiveSection, u64>,
+}
+
+/// What to do when an imported row's ID already exists in the target store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Abort the import with [`TaskStoreError::AlreadyExists`].
+    #[default]
+    Fail,
+    /// Keep the existing row and skip the imported one. Log chunks of a
+    /// skipped session are skipped too.
+    Skip,
+}
+
+/// Options for [`import_archive`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImportOptions {
+    /// Validate the archive against the target store and report what would
+    /// be imported, without keeping any changes.
+    pub dry_run: bool,
+    /// How to handle rows whose ID already exists.
+    pub on_conflict: ConflictPolicy,
+}
+
+/// Outcome of an import.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ImportReport {
+    /// True if the import was a dry run and nothing was kept.
+    pub dry_run: bool,
+    /// Number of rows created in each section.
+    pub created: BTreeMap<ArchiveSection, u64>,
+    /// Number of rows skipped because of conflicts in each section.
+    pub skipped: BTreeMap<ArchiveSection, u64>,
+}
+
+impl ImportReport {
+    fn record(&mut self, section: ArchiveSection, created: bool) {
+        let counts = if created {
+            &mut self.created
+        } else {
+            &mut self.skipped
+        };
+        *counts.entry(section).or_default() += 1;
+    }
+}
+
+/// Exports every row of `store` into an archive in `dir`.
+///
+/// The directory is created if needed and existing archive files in it are
+/// overwritten. The manifest is written last, so a directory without one
+/// holds an incomplete export.
+///
+/// Every section is read through one transaction, which is rolled back once
+/// the export is done. On SQLite that transaction holds the write lock, so
+/// the archive is a consistent snapshot and writers wait for the export.
+/// PostgreSQL transactions see rows committed before each statement and the
+/// in-memory store's see its live tables, so stop writers while exporting
+/// from those to keep rows written mid-export from breaking references.
+pub async fn export_archive(store: &dyn TaskStore, dir: &Path) -> TaskStoreResult<ArchiveManifest> {
+    fs::create_dir_all(dir).await?;
+    let tx = store.begin().await?;
+    let result = export_sections(tx.as_ref(), dir).await;
+    tx.rollback().await?;
+    result
+}
+
+/// Writes every section of `store` into an archive in `dir`.
+async fn export_sections<S: TaskStore + ?Sized>(
+    store: &S,
+    dir: &Path,
+) -> TaskStoreResult<ArchiveManifest> {
+    let mut writer = ArchiveWriter::create(dir).await?;
+
+    for user in store.list_users().await? {
+        writer.write(ArchiveSection::Users, &user).await?;
+    }
+    writer
+        .write_all(ArchiveSection::Workspaces, |after| {
+            store
+                .list_workspaces(WorkspaceFilter {
+                    after,
+                    deleted: DeletedFilter::Include,
+                    limit: Some(EXPORT_PAGE_SIZE),
+                    ..Default::default()
+                })
+                .map_ok(|(rows, _)| rows)
+                .boxed()
+        })
+        .await?;
+    writer
+        .write_all(ArchiveSection::Repositories, |after| {
+            store
+                .list_repositories(RepositoryFilter {
+                    after,
+                    deleted: DeletedFilter::Include,
+                    limit: Some(EXPORT_PAGE_SIZE),
+                    ..Default::default()
+                })
+                .map_ok(|(rows, _)| rows)
+                .boxed()
+        })
+        .await?;
+    for group in store.list_repository_groups(None).await? {
+        writer
+            .write(ArchiveSection::RepositoryGroups, &group)
+            .await?;
+    }
+
+    for mut task in store.list_agent_tasks().await? {
+        // Sessions are exported in their own section.
+        task.agent_sessions.clear();
+        writer.write(ArchiveSection::AgentTasks, &task).await?;
+        for session in store.list_agent_sessions(task.id).await? {
+            writer
+                .write(ArchiveSection::AgentSessions, &session)
+                .await?;
+            for chunk in store.read_session_log(session.id, LogRange::all()).await? {
+                writer
+                    .write(ArchiveSection::SessionLogChunks, &chunk)
+                    .await?;
+            }
+        }
+    }
+
+    writer
+        .write_all(ArchiveSection::UnitTasks, |after| {
+            store
+                .list_unit_tasks(TaskFilter {
+                    after,
+                    deleted: DeletedFilter::Include,
+                    limit: Some(EXPORT_PAGE_SIZE),
+                    ..Default::default()
+                })
+                .map_ok(|(rows, _)| rows)
+                .boxed()
+        })
+        .await?;
+    // Each section has its own file, so a page of composite tasks can be
+    // written along with their nodes and plan revisions.
+    let mut after = None;
+    loop {
+        let composite_tasks = store
+            .list_composite_tasks(TaskFilter {
+                after,
+                deleted: DeletedFilter::Include,
+                limit: Some(EXPORT_PAGE_SIZE),
+                ..Default::default()
+            })
+            .await?
+            .0;
+        for task in &composite_tasks {
+            writer.write(ArchiveSection::CompositeTasks, task).await?;
+            let nodes = store.list_composite_task_nodes(task.id).await?;
+            for node in dependencies_first(nodes) {
+                writer
+                    .write(ArchiveSection::CompositeTaskNodes, &node)
+                    .await?;
+            }
+            for revision in store.list_plan_revisions(task.id).await? {
+                writer
+                    .write(ArchiveSection::PlanRevisions, &revision)
+                    .await?;
+            }
+        }
+        after = next_page(&composite_tasks, EXPORT_PAGE_SIZE)?;
+        if after.is_none() {
+            break;
+        }
+    }
+
+    writer
+        .write_all(ArchiveSection::TodoItems, |after| {
+            store
+                .list_todo_items(TodoFilter {
+                    after,
+                    limit: Some(EXPORT_PAGE_SIZE),
+                    ..Default::default()
+                })
+                .map_ok(|(rows, _)| rows)
+                .boxed()
+        })
+        .await?;
+    writer
+        .write_all(ArchiveSection::TtyInputRequests, |after| {
+            store
+                .list_tty_input_requests(TtyInputFilter {
+                    after,
+                    limit: Some(EXPORT_PAGE_SIZE),
+                    ..Default::default()
+                })
+                .boxed()
+        })
+        .await?;
+    writer
+        .write_all(ArchiveSection::AuditEvents, |after| {
+            store
+                .list_audit_events(AuditFilter {
+                    after,
+                    limit: Some(EXPORT_PAGE_SIZE),
+                    ..Default::default()
+                })
+                .map_ok(|(rows, _)| rows)
+                .boxed()
+        })
+        .await?;
+
+    writer.finish(dir).await
+}
+
+/// Imports an archive from `dir` into `store`.
+///
+/// The whole import runs in one transaction: it is committed only if every
+/// row was imported, and always rolled back for a dry run. Fails with
+/// [`TaskStoreError::InvalidArchive`] if the archive was written by a newer
+/// format version or its files do not match the manifest.
+pub async fn import_archive(
+    store: &dyn TaskStore,
+    dir: &Path,
+    options: ImportOptions,
+) -> TaskStoreResult<ImportReport> {
+    let manifest = read_manifest(dir).await?;
+    let tx = store.begin().await?;
+    match import_sections(tx.as_ref(), dir, &manifest, options).await {
+        Ok(report) if options.dry_run => {
+            tx.rollback().await?;
+            Ok(report)
+        }
+        Ok(report) => {
+            tx.commit().await?;
+            Ok(report)
+        }
+        Err(err) => {
+            tx.rollback().await?;
+            Err(err)
+        }
+    }
+}
+
+/// Reads and checks the manifest of the archive in `dir`.
+pub async fn read_manifest(dir: &Path) -> TaskStoreResult<ArchiveManifest> {
+    let bytes = fs::read(dir.join(MANIFEST_FILE)).await?;
+    let manifest: ArchiveManifest = serde_json::from_slice(&bytes)
+        .map_err(|err| TaskStoreError::InvalidArchive(format!("{MANIFEST_FILE}: {err}")))?;
+    if manifest.format_version > ARCHIVE_VERSION {
+        return Err(TaskStoreError::InvalidArchive(format!(
+            "archive format version {} is newer than the supported version {ARCHIVE_VERSION}",
+            manifest.format_version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// Imports every section of an archive through `store`.
+async fn import_sections<S: TaskStore + ?Sized>(
+    store: &S,
+    dir: &Path,
+    manifest: &ArchiveManifest,
+    options: ImportOptions,
+) -> TaskStoreResult<ImportReport> {
+    let mut report = ImportReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    let skip = options.on_conflict == ConflictPolicy::Skip;
+
+    // Creates a row unless skipping conflicts and a row with its ID exists.
+    macro_rules! import {
+        ($section:expr, $ty:ty, $get:ident, $create:ident) => {{
+            let mut reader = SectionReader::open(dir, manifest, $section).await?;
+            while let Some(row) = reader.next::<$ty>().await? {
+                let created = !(skip && store.$get(row.id).await?.is_some());
+                if created {
+                    store.$create(row).await?;
+                }
+                report.record($section, created);
+            }
+        }};
+    }
+
+    import!(ArchiveSection::Users, User, get_user, create_user);
+    import!(
+        ArchiveSection::Workspaces,
+        Workspace,
+        get_workspace,
+        create_workspace
+    );
+    import!(
+        ArchiveSection::Repositories,
+        Repository,
+        get_repository,
+        create_repository
+    );
+    import!(
+        ArchiveSection::RepositoryGroups,
+        RepositoryGroup,
+        get_repository_group,
+        create_repository_group
+    );
+
+    let section = ArchiveSection::AgentTasks;
+    let mut reader = SectionReader::open(dir, manifest, section).await?;
+    while let Some(mut task) = reader.next::<AgentTask>().await? {
+        let created = !(skip && store.get_agent_task(task.id).await?.is_some());
+        if created {
+            task.agent_sessions.clear();
+            store.create_agent_task(task).await?;
+        }
+        report.record(section, created);
+    }
+
+    let section = ArchiveSection::AgentSessions;
+    let mut imported_sessions = HashSet::new();
+    let mut reader = SectionReader::open(dir, manifest, section).await?;
+    while let Some(session) = reader.next::<AgentSession>().await? {
+        let created = !(skip && store.get_agent_session(session.id).await?.is_some());
+        if created {
+            imported_sessions.insert(session.id);
+            store.create_agent_session(session).await?;
+        }
+        report.record(section, created);
+    }
+
+    let section = ArchiveSection::SessionLogChunks;
+    let mut reader = SectionReader::open(dir, manifest, section).await?;
+    while let Some(chunk) = reader.next::<LogChunk>().await? {
+        let created = imported_sessions.contains(&chunk.session_id);
+        if created {
+            let appended = store
+                .append_session_log(chunk.session_id, chunk.content)
+                .await?;
+            if appended.sequence != chunk.sequence || appended.offset != chunk.offset {
+                return Err(TaskStoreError::InvalidArchive(format!(
+                    "log chunk {} of session {} is out of order",
+                    chunk.sequence, chunk.session_id
+                )));
+            }
+        }
+        report.record(section, created);
+    }
+
+    import!(
+        ArchiveSection::UnitTasks,
+        UnitTask,
+        get_unit_task,
+        create_unit_task
+    );
+    import!(
+        ArchiveSection::CompositeTasks,
+        CompositeTask,
+        get_composite_task,
+        create_composite_task
+    );
+    import!(
+        ArchiveSection::CompositeTaskNodes,
+        CompositeTaskNode,
+        get_composite_task_node,
+        create_composite_task_node
+    );
+
+    let section = ArchiveSection::PlanRevisions;
+    let mut reader = SectionReader::open(dir, manifest, section).await?;
+    while let Some(revision) = reader.next::<PlanRevision>().await? {
+        let created = !(skip && store.get_plan_revision(revision.id).await?.is_some());
+        if created {
+            let number = revision.number;
+            let composite_task_id = revision.composite_task_id;
+            if store.create_plan_revision(revision).await?.number != number {
+                return Err(TaskStoreError::InvalidArchive(format!(
+                    "plan revision {number} of composite task {composite_task_id} is out of order"
+                )));
+            }
+        }
+        report.record(section, created);
+    }
+    import!(
+        ArchiveSection::TodoItems,
+        TodoItem,
+        get_todo_item,
+        create_todo_item
+    );
+    import!(
+        ArchiveSection::TtyInputRequests,
+        TtyInputRequest,
+        get_tty_input_request,
+        create_tty_input_request
+    );
+    import!(
+        ArchiveSection::AuditEvents,
+        AuditEvent,
+        get_audit_event,
+        record_audit_event
+    );
+
+    Ok(report)
+}
+
+/// Orders the nodes of one composite task so that every node comes after
+/// the nodes it depends on. Nodes caught in a cycle keep their relative
+/// order at the end.
+pub(crate) fn dependencies_first(nodes: Vec<CompositeTaskNode>) -> Vec<CompositeTaskNode> {
+    let ids: HashSet<Uuid> = nodes.iter().map(|node| node.id).collect();
+    let mut pending: HashMap<Uuid, usize> = nodes
+        .iter()
+        .map(|node| {
+            let count = node
+                .depends_on_ids
+                .iter()
+                .filter(|id| ids.contains(id))
+                .count();
+            (node.id, count)
+        })
+        .collect();
+
+    let mut ordered = Vec::with_capacity(nodes.len());
+    let mut remaining = nodes;
+    loop {
+        let (ready, blocked): (Vec<_>, Vec<_>) = remaining
+            .into_iter()
+            .partition(|node| pending[&node.id] == 0);
+        if ready.is_empty() {
+            ordered.extend(blocked);
+            return ordered;
+        }
+        for node in &ready {
+            for other in &blocked {
+                if other.depends_on_ids.contains(&node.id) {
+                    *pending.get_mut(&other.id).expect("node is pending") -= 1;
+                }
+            }
+        }
+        ordered.extend(ready);
+        remaining = blocked;
+    }
+}
+
+/// Writes the section files of an archive.
+struct ArchiveWriter {
+    files: BTreeMap<ArchiveSection, BufWriter<File>>,
+    counts: BTreeMap<ArchiveSection, u64>,
+}
+
+impl ArchiveWriter {
+    async fn create(dir: &Path) -> TaskStoreResult<Self> {
+        let mut files = BTreeMap::new();
+        for section in ArchiveSection::ALL {
+            let file = File::create(dir.join(section.file_name())).await?;
+            files.insert(section, BufWriter::new(file));
+        }
+        Ok(Self {
+            files,
+            counts: ArchiveSection::ALL.map(|section| (section, 0)).into(),
+        })
+    }
+
+    async fn write<T: Serialize>(
+        &mut self,
+        section: ArchiveSection,
+        row: &T,
+    ) -> TaskStoreResult<()> {
+        let mut line = serde_json::to_vec(row)?;
+        line.push(b'\n');
+        let file = self.files.get_mut(&section).expect("section file is open");
+        file.write_all(&line).await?;
+        *self.counts.entry(section).or_default() += 1;
+        Ok(())
+    }
+
+    /// Writes every row of a list sorted by creation time, fetching one
+    /// page at a time.
+    async fn write_all<'a, T, F>(
+        &mut self,
+        section: ArchiveSection,
+        mut fetch: F,
+    ) -> TaskStoreResult<()>
+    where
+        T: Serialize + Sortable,
+        F: FnMut(Option<Cursor>) -> BoxFuture<'a, TaskStoreResult<Vec<T>>>,
+    {
+        let mut after = None;
+        loop {
+            let page = fetch(after).await?;
+            for row in &page {
+                self.write(section, row).await?;
+            }
+            after = next_page(&page, EXPORT_PAGE_SIZE)?;
+            if after.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    async fn finish(self, dir: &Path) -> TaskStoreResult<ArchiveManifest> {
+        for mut file in self.files.into_values() {
+            file.flush().await?;
+            file.into_inner().sync_all().await?;
+        }
+        let manifest = ArchiveManifest {
+            format_version: ARCHIVE_VERSION,
+            schema_version: SCHEMA_VERSION,
+            exported_at: Utc::now(),
+            counts: self.counts,
+        };
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&manifest)?,
+        )
+        .await?;
+        Ok(manifest)
+    }
+}
+
+/// Reads the rows of one section file, checking them against the manifest.
+///
+/// A section without rows in the manifest may have no file, as in archives
+/// written before the section was added.
+struct SectionReader {
+    section: ArchiveSection,
+    lines: Option<Lines<BufReader<File>>>,
+    line: u64,
+    expected: u64,
+}
+
+impl SectionReader {
+    async fn open(
+        dir: &Path,
+        manifest: &ArchiveManifest,
+        section: ArchiveSection,
+    ) -> TaskStoreResult<Self> {
+        let expected = manifest.counts.get(&section).copied().unwrap_or(0);
+        let lines = match File::open(dir.join(section.file_name())).await {
+            Ok(file) => Some(BufReader::new(file).lines()),
+            Err(err) if err.kind() == ErrorKind::NotFound && expected == 0 => None,
+            Err(err) => {
+                return Err(TaskStoreError::InvalidArchive(format!(
+                    "{}: {err}",
+                    section.file_name()
+                )));
+            }
+        };
+        Ok(Self {
+            section,
+            lines,
+            line: 0,
+            expected,
+        })
+    }
+
+    /// Returns the next row, or `None` once the file is exhausted.
+    async fn next<T: DeserializeOwned>(&mut self) -> TaskStoreResult<Option<T>> {
+        let Some(lines) = &mut self.lines else {
+            return Ok(None);
+        };
+        let Some(text) = lines.next_line().await? else {
+            if self.line != self.expected {
+                return Err(self.invalid(format!(
+                    "expected {} rows but found {}",
+                    self.expected, self.line
+                )));
+            }
+            return Ok(None);
+        };
+        self.line += 1;
+        if self.line > self.expected {
+            return Err(self.invalid(format!("expected {} rows", self.expected)));
+        }
+        serde_json::from_str(&text)
+            .map(Some)
+            .map_err(|err| self.invalid(format!("line {}: {err}", self.line)))
+    }
+
+    fn invalid(&self, message: String) -> TaskStoreError {
+        TaskStoreError::InvalidArchive(format!("{}: {message}", self.section.file_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::{
+        AgentSession, AgentTask, AiAgentType, AuditActor, CompositeTask, Repository,
+        RepositoryGroup, TodoItem, TtyInputRequest, UnitTask, User, VcsProviderType, Workspace,
+    };
+
+    use super::*;
+    use crate::{MemoryTaskStore, SqliteTaskStore};
+
+    /// Fills `store` with one row of every kind and returns the user.
+    async fn populate(store: &dyn TaskStore) -> User {
+        let user = store
+            .create_user(User::new("ada@example.com"))
+            .await
+            .unwrap();
+        let mut workspace = Workspace::new("Archive");
+        workspace.user_id = Some(user.id);
+        let workspace = store.create_workspace(workspace).await.unwrap();
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                "archive",
+                "https://github.com/delinoio/archive",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        let mut group = RepositoryGroup::new(workspace.id);
+        group.add_repository(repository.id);
+        let group = store.create_repository_group(group).await.unwrap();
+
+        let planning_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let session = store
+            .create_agent_session(AgentSession::new(agent_task.id, AiAgentType::ClaudeCode))
+            .await
+            .unwrap();
+        store
+            .append_session_log(session.id, "hello ".to_string())
+            .await
+            .unwrap();
+        store
+            .append_session_log(session.id, "world".to_string())
+            .await
+            .unwrap();
+
+        let first = store
+            .create_unit_task(UnitTask::new(group.id, agent_task.id, "First"))
+            .await
+            .unwrap();
+        let second_agent = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let second = store
+            .create_unit_task(UnitTask::new(group.id, second_agent.id, "Second"))
+            .await
+            .unwrap();
+        let composite = store
+            .create_composite_task(CompositeTask::new(group.id, planning_task.id, "Both"))
+            .await
+            .unwrap();
+        let first_node = store
+            .create_composite_task_node(CompositeTaskNode::new(composite.id, first.id))
+            .await
+            .unwrap();
+        let mut second_node = CompositeTaskNode::new(composite.id, second.id);
+        second_node.depends_on_ids.push(first_node.id);
+        store.create_composite_task_node(second_node).await.unwrap();
+        store
+            .create_plan_revision(PlanRevision::from_planning_agent(&composite, "tasks: []\n"))
+            .await
+            .unwrap();
+        let edited = store
+            .create_plan_revision(PlanRevision::from_user(&composite, user.id, "tasks: []\n"))
+            .await
+            .unwrap();
+        store
+            .approve_plan_revision(edited.id, user.id)
+            .await
+            .unwrap();
+
+        store
+            .create_todo_item(TodoItem::issue_triage(
+                repository.id,
+                "https://github.com/delinoio/archive/issues/1".to_string(),
+                "Crash".to_string(),
+            ))
+            .await
+            .unwrap();
+        store
+            .create_tty_input_request(TtyInputRequest::new(first.id, session.id, "Continue?"))
+            .await
+            .unwrap();
+        // Rows in the trash are archived too.
+        store.delete_composite_task(composite.id).await.unwrap();
+        let event = AuditEvent::new(
+            AuditActor::user(user.id),
+            "composite_task.delete",
+            "CompositeTask",
+            composite.id,
+        )
+        .with_before(&composite)
+        .unwrap();
+        store.record_audit_event(event).await.unwrap();
+        user
+    }
+
+    /// Returns the rows of every section except log chunks, whose creation
+    /// times are not preserved.
+    async fn rows(dir: &Path) -> BTreeMap<ArchiveSection, Vec<serde_json::Value>> {
+        let mut rows = BTreeMap::new();
+        for section in ArchiveSection::ALL {
+            let text = fs::read_to_string(dir.join(section.file_name()))
+                .await
+                .unwrap();
+            let values = text
+                .lines()
+                .map(|line| {
+                    let mut value: serde_json::Value = serde_json::from_str(line).unwrap();
+                    if section == ArchiveSection::SessionLogChunks {
+                        value.as_object_mut().unwrap().remove("created_at");
+                    }
+                    value
+                })
+                .collect();
+            rows.insert(section, values);
+        }
+        rows
+    }
+
+    #[test]
+    fn test_dependencies_first() {
+        let composite_task_id = Uuid::new_v4();
+        let first = CompositeTaskNode::new(composite_task_id, Uuid::new_v4());
+        let mut second = CompositeTaskNode::new(composite_task_id, Uuid::new_v4());
+        second.depends_on_ids.push(first.id);
+        let mut third = CompositeTaskNode::new(composite_task_id, Uuid::new_v4());
+        third.depends_on_ids = vec![second.id, Uuid::new_v4()];
+
+        let ordered = dependencies_first(vec![third.clone(), second.clone(), first.clone()]);
+        let ids: Vec<Uuid> = ordered.iter().map(|node| node.id).collect();
+        assert_eq!(ids, [first.id, second.id, third.id]);
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let source = MemoryTaskStore::new();
+        populate(&source).await;
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = export_archive(&source, dir.path()).await.unwrap();
+        assert_eq!(manifest.format_version, ARCHIVE_VERSION);
+        assert_eq!(manifest.counts[&ArchiveSection::AgentTasks], 3);
+        assert_eq!(manifest.counts[&ArchiveSection::SessionLogChunks], 2);
+        assert_eq!(manifest.counts[&ArchiveSection::PlanRevisions], 2);
+        assert_eq!(read_manifest(dir.path()).await.unwrap(), manifest);
+
+        let target = SqliteTaskStore::in_memory().await.unwrap();
+        let report = import_archive(&target, dir.path(), ImportOptions::default())
+            .await
+            .unwrap();
+        assert!(!report.dry_run);
+        assert_eq!(report.created, manifest.counts);
+        assert!(report.skipped.is_empty());
+
+        let copy = tempfile::tempdir().unwrap();
+        let copied = export_archive(&target, copy.path()).await.unwrap();
+        assert_eq!(copied.counts, manifest.counts);
+        assert_eq!(rows(copy.path()).await, rows(dir.path()).await);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_and_conflicts() {
+        let source = MemoryTaskStore::new();
+        let user = populate(&source).await;
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = export_archive(&source, dir.path()).await.unwrap();
+
+        let target = MemoryTaskStore::new();
+        let dry_run = ImportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = import_archive(&target, dir.path(), dry_run).await.unwrap();
+        assert!(report.dry_run);
+        assert_eq!(report.created, manifest.counts);
+        assert!(target.list_users().await.unwrap().is_empty());
+
+        target.create_user(user.clone()).await.unwrap();
+        let err = import_archive(&target, dir.path(), ImportOptions::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, TaskStoreError::AlreadyExists { .. }),
+            "{err:?}"
+        );
+        assert!(target.list_agent_tasks().await.unwrap().is_empty());
+
+        let skip = ImportOptions {
+            on_conflict: ConflictPolicy::Skip,
+            ..Default::default()
+        };
+        let report = import_archive(&target, dir.path(), skip).await.unwrap();
+        assert_eq!(report.skipped, [(ArchiveSection::Users, 1)].into());
+        assert_eq!(report.created[&ArchiveSection::Workspaces], 1);
+
+        // Importing again skips everything, including the skipped sessions' logs.
+        let report = import_archive(&target, dir.path(), skip).await.unwrap();
+        assert!(report.created.is_empty());
+        assert_eq!(report.skipped, manifest.counts);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_archive() {
+        let source = MemoryTaskStore::new();
+        populate(&source).await;
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = export_archive(&source, dir.path()).await.unwrap();
+        let target = MemoryTaskStore::new();
+
+        // Archives from before the audit log have no audit events file.
+        let mut older = manifest.clone();
+        older.format_version = 1;
+        older.counts.remove(&ArchiveSection::AuditEvents);
+        fs::write(
+            dir.path().join(MANIFEST_FILE),
+            serde_json::to_vec(&older).unwrap(),
+        )
+        .await
+        .unwrap();
+        fs::remove_file(dir.path().join(ArchiveSection::AuditEvents.file_name()))
+            .await
+            .unwrap();
+        let dry_run = ImportOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let report = import_archive(&target, dir.path(), dry_run).await.unwrap();
+        assert_eq!(report.created, older.counts);
+
+        manifest.counts.insert(ArchiveSection::TodoItems, 2);
+        fs::write(
+            dir.path().join(MANIFEST_FILE),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .await
+        .unwrap();
+        let err = import_archive(&target, dir.path(), ImportOptions::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, TaskStoreError::InvalidArchive(_)), "{err:?}");
+        assert!(target.list_users().await.unwrap().is_empty());
+
+        manifest.format_version = ARCHIVE_VERSION + 1;
+        fs::write(
+            dir.path().join(MANIFEST_FILE),
+            serde_json::to_vec(&manifest).unwrap(),
+        )
+        .await
+        .unwrap();
+        let err = read_manifest(dir.path()).await.unwrap_err();
+        assert!(matches!(err, TaskStoreError::InvalidArchive(_)), "{err:?}");
+    }
+}
@@ -0,0 +1,1128 @@
+//! Read-through cache in front of another task store.
+//!
+//! [`CachedTaskStore`] serves `get_workspace`, `get_repository` and
+//! `get_repository_group` from memory, since dashboards read these hot,
+//! rarely changing rows for every card they render. Every other operation
+//! goes straight to the wrapped store. Writes through the wrapper invalidate
+//! the rows they touch; writes made through a transaction invalidate them
+//! once it commits. Writes that bypass the wrapper, such as those of another
+//! server instance sharing the database, are only picked up once the cached
+//! row's time to live runs out.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use entities::{
+    AgentSession, AgentTask, AuditEvent, CompositeTask, CompositeTaskNode, PlanRevision,
+    Repository, RepositoryGroup, TodoItem, TtyInputRequest, UnitTask, User, Worker, WorkerStatus,
+    Workspace,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    AuditFilter, ChangeFilter, ChangeStream, LogChunk, LogRange, LogSize, QueueEntry, QueueFilter,
+    RepositoryFilter, SearchHit, SearchQuery, TaskFilter, TaskStore, TaskStoreResult,
+    TaskStoreTransaction, TodoFilter, TtyInputFilter, WorkerFilter, WorkspaceFilter,
+};
+
+/// Size and freshness limits of a [`CachedTaskStore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// Maximum number of cached rows. The least recently used row is
+    /// evicted to make room for a new one. Zero disables caching.
+    pub capacity: usize,
+    /// How long a cached row is served before it is read again.
+    pub ttl: Duration,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            capacity: 10_000,
+            ttl: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Counters describing how well a [`CachedTaskStore`] performs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    /// Reads served from the cache.
+    pub hits: u64,
+    /// Reads that went to the wrapped store.
+    pub misses: u64,
+    /// Rows dropped to make room for others.
+    pub evictions: u64,
+    /// Rows dropped because they were written.
+    pub invalidations: u64,
+    /// Rows currently cached, including expired ones not yet dropped.
+    pub entries: usize,
+}
+
+impl CacheStats {
+    /// Returns the share of reads served from the cache, or zero before the
+    /// first read.
+    pub fn hit_rate(&self) -> f64 {
+        let reads = self.hits + self.misses;
+        if reads == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / reads as f64
+    }
+}
+
+/// Identifies a cached row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Workspace(Uuid),
+    Repository(Uuid),
+    RepositoryGroup(Uuid),
+}
+
+/// A cached row.
+#[derive(Debug, Clone)]
+enum CachedValue {
+    Workspace(Workspace),
+    Repository(Repository),
+    RepositoryGroup(RepositoryGroup),
+}
+
+/// An entity served from the cache.
+trait Cached: Clone + Sized {
+    fn key(id: Uuid) -> CacheKey;
+
+    fn wrap(self) -> CachedValue;
+
+    fn unwrap(value: &CachedValue) -> Option<Self>;
+}
+
+macro_rules! impl_cached {
+    ($($entity:ident),* $(,)?) => {
+        $(
+            impl Cached for $entity {
+                fn key(id: Uuid) -> CacheKey {
+                    CacheKey::$entity(id)
+                }
+
+                fn wrap(self) -> CachedValue {
+                    CachedValue::$entity(self)
+                }
+
+                fn unwrap(value: &CachedValue) -> Option<Self> {
+                    match value {
+                        CachedValue::$entity(row) => Some(row.clone()),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_cached!(Workspace, Repository, RepositoryGroup);
+
+#[derive(Debug)]
+struct Entry {
+    value: CachedValue,
+    expires_at: Instant,
+    /// Value of [`CacheState::clock`] when the entry was last read.
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<CacheKey, Entry>,
+    /// Incremented on every read, to order entries by recency.
+    clock: u64,
+    /// Incremented on every invalidation. A read that missed only fills the
+    /// cache if no invalidation happened while it went to the store, so a
+    /// row read before a write never outlives it.
+    generation: u64,
+    stats: CacheStats,
+}
+
+/// Cache shared by a store and its transactions.
+#[derive(Debug)]
+struct Cache {
+    config: CacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl Cache {
+    fn state(&self) -> MutexGuard<'_, CacheState> {
+        // The state stays consistent even if a holder panicked.
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Returns the cached row for `key`, or the current generation on a
+    /// miss.
+    fn get<T: Cached>(&self, key: CacheKey) -> Result<T, u64> {
+        let mut state = self.state();
+        let now = Instant::now();
+        state.clock += 1;
+        let clock = state.clock;
+        let hit = match state.entries.get_mut(&key) {
+            Some(entry) if entry.expires_at > now => {
+                entry.last_used = clock;
+                T::unwrap(&entry.value)
+            }
+            Some(_) => {
+                state.entries.remove(&key);
+                None
+            }
+            None => None,
+        };
+        match hit {
+            Some(row) => {
+                state.stats.hits += 1;
+                Ok(row)
+            }
+            None => {
+                state.stats.misses += 1;
+                Err(state.generation)
+            }
+        }
+    }
+
+    /// Caches a row read at `generation`, unless it was invalidated since.
+    fn insert(&self, key: CacheKey, value: CachedValue, generation: u64) {
+        let mut state = self.state();
+        if self.config.capacity == 0 || state.generation != generation {
+            return;
+        }
+        let now = Instant::now();
+        if !state.entries.contains_key(&key) && state.entries.len() >= self.config.capacity {
+            state.entries.retain(|_, entry| entry.expires_at > now);
+        }
+        while !state.entries.contains_key(&key) && state.entries.len() >= self.config.capacity {
+            let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            state.entries.remove(&oldest);
+            state.stats.evictions += 1;
+        }
+        let last_used = state.clock;
+        state.entries.insert(
+            key,
+            Entry {
+                value,
+                expires_at: now + self.config.ttl,
+                last_used,
+            },
+        );
+    }
+
+    fn invalidate(&self, keys: impl IntoIterator<Item = CacheKey>) {
+        let mut state = self.state();
+        state.generation += 1;
+        for key in keys {
+            if state.entries.remove(&key).is_some() {
+                state.stats.invalidations += 1;
+            }
+        }
+    }
+}
+
+/// Store a [`CachedTaskStore`] can wrap: a task store, or a transaction
+/// begun on one.
+pub trait WrappedStore: Send + Sync {
+    /// Returns the wrapped store.
+    fn store(&self) -> &dyn TaskStore;
+}
+
+impl<S: TaskStore> WrappedStore for S {
+    fn store(&self) -> &dyn TaskStore {
+        self
+    }
+}
+
+impl WrappedStore for Box<dyn TaskStoreTransaction> {
+    fn store(&self) -> &dyn TaskStore {
+        self.as_ref()
+    }
+}
+
+/// Task store decorator caching reads of workspaces, repositories and
+/// repository groups.
+///
+/// Wrap any store and use the wrapper in its place, for example behind an
+/// `Arc<dyn TaskStore>`:
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use task_store::{CacheConfig, CachedTaskStore, MemoryTaskStore, TaskStore};
+///
+/// let store: Arc<dyn TaskStore> = Arc::new(CachedTaskStore::new(
+///     MemoryTaskStore::new(),
+///     CacheConfig::default(),
+/// ));
+/// ```
+///
+/// Transactions begun on the wrapper read from the wrapped transaction
+/// directly, so they always see their own writes.
+#[derive(Debug)]
+pub struct CachedTaskStore<S> {
+    inner: S,
+    cache: Arc<Cache>,
+    /// Rows written through this transaction, invalidated when it commits.
+    /// `None` unless this is a transaction.
+    pending: Option<Arc<Mutex<Vec<CacheKey>>>>,
+}
+
+impl<S: WrappedStore> CachedTaskStore<S> {
+    /// Wraps `inner` in a cache with the given limits.
+    pub fn new(inner: S, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Cache {
+                config,
+                state: Mutex::default(),
+            }),
+            pending: None,
+        }
+    }
+
+    /// Returns the wrapped store.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the cache's counters.
+    pub fn stats(&self) -> CacheStats {
+        let state = self.cache.state();
+        CacheStats {
+            entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    /// Drops every cached row.
+    pub fn clear(&self) {
+        let mut state = self.cache.state();
+        state.generation += 1;
+        state.entries.clear();
+    }
+
+    fn store(&self) -> &dyn TaskStore {
+        self.inner.store()
+    }
+
+    /// Reads a row through the cache.
+    async fn read<T: Cached>(
+        &self,
+        id: Uuid,
+        fetch: impl Future<Output = TaskStoreResult<Option<T>>>,
+    ) -> TaskStoreResult<Option<T>> {
+        if self.pending.is_some() {
+            return fetch.await;
+        }
+        let key = T::key(id);
+        let generation = match self.cache.get::<T>(key) {
+            Ok(row) => return Ok(Some(row)),
+            Err(generation) => generation,
+        };
+        let row = fetch.await?;
+        if let Some(row) = &row {
+            self.cache.insert(key, row.clone().wrap(), generation);
+        }
+        Ok(row)
+    }
+
+    /// Runs a write, then invalidates the row it touched.
+    ///
+    /// The row is invalidated even if the write failed, as a failure does
+    /// not prove the row is unchanged.
+    async fn write<T>(
+        &self,
+        key: CacheKey,
+        write: impl Future<Output = TaskStoreResult<T>>,
+    ) -> TaskStoreResult<T> {
+        let result = write.await;
+        match &self.pending {
+            Some(pending) => pending
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .push(key),
+            None => self.cache.invalidate([key]),
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<S: WrappedStore> TaskStore for CachedTaskStore<S> {
+    // =========================================================================
+    // Transactions
+    // =========================================================================
+
+    async fn begin(&self) -> TaskStoreResult<Box<dyn TaskStoreTransaction>> {
+        let transaction = self.store().begin().await?;
+        Ok(Box::new(CachedTaskStore {
+            inner: transaction,
+            cache: Arc::clone(&self.cache),
+            pending: Some(self.pending.clone().unwrap_or_default()),
+        }))
+    }
+
+    // =========================================================================
+    // Change feed
+    // =========================================================================
+
+    async fn subscribe(&self, filter: ChangeFilter) -> TaskStoreResult<ChangeStream> {
+        self.store().subscribe(filter).await
+    }
+
+    // =========================================================================
+    // User operations
+    // =========================================================================
+
+    async fn create_user(&self, user: User) -> TaskStoreResult<User> {
+        self.store().create_user(user).await
+    }
+
+    async fn get_user(&self, id: Uuid) -> TaskStoreResult<Option<User>> {
+        self.store().get_user(id).await
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> TaskStoreResult<Option<User>> {
+        self.store().get_user_by_email(email).await
+    }
+
+    async fn list_users(&self) -> TaskStoreResult<Vec<User>> {
+        self.store().list_users().await
+    }
+
+    async fn update_user(&self, user: User) -> TaskStoreResult<User> {
+        self.store().update_user(user).await
+    }
+
+    async fn delete_user(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_user(id).await
+    }
+
+    // =========================================================================
+    // Workspace operations
+    // =========================================================================
+
+    async fn create_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace> {
+        self.store().create_workspace(workspace).await
+    }
+
+    async fn get_workspace(&self, id: Uuid) -> TaskStoreResult<Option<Workspace>> {
+        self.read(id, self.store().get_workspace(id)).await
+    }
+
+    async fn list_workspaces(
+        &self,
+        filter: WorkspaceFilter,
+    ) -> TaskStoreResult<(Vec<Workspace>, u32)> {
+        self.store().list_workspaces(filter).await
+    }
+
+    async fn update_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace> {
+        self.write(
+            CacheKey::Workspace(workspace.id),
+            self.store().update_workspace(workspace),
+        )
+        .await
+    }
+
+    async fn delete_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.write(CacheKey::Workspace(id), self.store().delete_workspace(id))
+            .await
+    }
+
+    async fn restore_workspace(&self, id: Uuid) -> TaskStoreResult<Workspace> {
+        self.write(CacheKey::Workspace(id), self.store().restore_workspace(id))
+            .await
+    }
+
+    async fn purge_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.write(CacheKey::Workspace(id), self.store().purge_workspace(id))
+            .await
+    }
+
+    // =========================================================================
+    // Repository operations
+    // =========================================================================
+
+    async fn create_repository(&self, repository: Repository) -> TaskStoreResult<Repository> {
+        self.store().create_repository(repository).await
+    }
+
+    async fn get_repository(&self, id: Uuid) -> TaskStoreResult<Option<Repository>> {
+        self.read(id, self.store().get_repository(id)).await
+    }
+
+    async fn list_repositories(
+        &self,
+        filter: RepositoryFilter,
+    ) -> TaskStoreResult<(Vec<Repository>, u32)> {
+        self.store().list_repositories(filter).await
+    }
+
+    async fn update_repository(&self, repository: Repository) -> TaskStoreResult<Repository> {
+        self.write(
+            CacheKey::Repository(repository.id),
+            self.store().update_repository(repository),
+        )
+        .await
+    }
+
+    async fn delete_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.write(CacheKey::Repository(id), self.store().delete_repository(id))
+            .await
+    }
+
+    async fn restore_repository(&self, id: Uuid) -> TaskStoreResult<Repository> {
+        self.write(
+            CacheKey::Repository(id),
+            self.store().restore_repository(id),
+        )
+        .await
+    }
+
+    async fn purge_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.write(CacheKey::Repository(id), self.store().purge_repository(id))
+            .await
+    }
+
+    // =========================================================================
+    // Repository Group operations
+    // =========================================================================
+
+    async fn create_repository_group(
+        &self,
+        group: RepositoryGroup,
+    ) -> TaskStoreResult<RepositoryGroup> {
+        self.store().create_repository_group(group).await
+    }
+
+    async fn get_repository_group(&self, id: Uuid) -> TaskStoreResult<Option<RepositoryGroup>> {
+        self.read(id, self.store().get_repository_group(id)).await
+    }
+
+    async fn list_repository_groups(
+        &self,
+        workspace_id: Option<Uuid>,
+    ) -> TaskStoreResult<Vec<RepositoryGroup>> {
+        self.store().list_repository_groups(workspace_id).await
+    }
+
+    async fn update_repository_group(
+        &self,
+        group: RepositoryGroup,
+    ) -> TaskStoreResult<RepositoryGroup> {
+        self.write(
+            CacheKey::RepositoryGroup(group.id),
+            self.store().update_repository_group(group),
+        )
+        .await
+    }
+
+    async fn delete_repository_group(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.write(
+            CacheKey::RepositoryGroup(id),
+            self.store().delete_repository_group(id),
+        )
+        .await
+    }
+
+    // =========================================================================
+    // Agent Task operations
+    // =========================================================================
+
+    async fn create_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask> {
+        self.store().create_agent_task(task).await
+    }
+
+    async fn get_agent_task(&self, id: Uuid) -> TaskStoreResult<Option<AgentTask>> {
+        self.store().get_agent_task(id).await
+    }
+
+    async fn get_agent_tasks(&self, ids: &[Uuid]) -> TaskStoreResult<Vec<AgentTask>> {
+        self.store().get_agent_tasks(ids).await
+    }
+
+    async fn list_agent_tasks(&self) -> TaskStoreResult<Vec<AgentTask>> {
+        self.store().list_agent_tasks().await
+    }
+
+    async fn update_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask> {
+        self.store().update_agent_task(task).await
+    }
+
+    async fn delete_agent_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_agent_task(id).await
+    }
+
+    // =========================================================================
+    // Agent Session operations
+    // =========================================================================
+
+    async fn create_agent_session(&self, session: AgentSession) -> TaskStoreResult<AgentSession> {
+        self.store().create_agent_session(session).await
+    }
+
+    async fn get_agent_session(&self, id: Uuid) -> TaskStoreResult<Option<AgentSession>> {
+        self.store().get_agent_session(id).await
+    }
+
+    async fn list_agent_sessions(&self, agent_task_id: Uuid) -> TaskStoreResult<Vec<AgentSession>> {
+        self.store().list_agent_sessions(agent_task_id).await
+    }
+
+    async fn update_agent_session(&self, session: AgentSession) -> TaskStoreResult<AgentSession> {
+        self.store().update_agent_session(session).await
+    }
+
+    async fn delete_agent_session(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_agent_session(id).await
+    }
+
+    // =========================================================================
+    // Session log operations
+    // =========================================================================
+
+    async fn append_session_log(
+        &self,
+        session_id: Uuid,
+        content: String,
+    ) -> TaskStoreResult<LogChunk> {
+        self.store().append_session_log(session_id, content).await
+    }
+
+    async fn read_session_log(
+        &self,
+        session_id: Uuid,
+        range: LogRange,
+    ) -> TaskStoreResult<Vec<LogChunk>> {
+        self.store().read_session_log(session_id, range).await
+    }
+
+    async fn session_log_size(&self, session_id: Uuid) -> TaskStoreResult<LogSize> {
+        self.store().session_log_size(session_id).await
+    }
+
+    // =========================================================================
+    // Unit Task operations
+    // =========================================================================
+
+    async fn create_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask> {
+        self.store().create_unit_task(task).await
+    }
+
+    async fn get_unit_task(&self, id: Uuid) -> TaskStoreResult<Option<UnitTask>> {
+        self.store().get_unit_task(id).await
+    }
+
+    async fn list_unit_tasks(&self, filter: TaskFilter) -> TaskStoreResult<(Vec<UnitTask>, u32)> {
+        self.store().list_unit_tasks(filter).await
+    }
+
+    async fn find_unit_task_by_pr_url(&self, pr_url: &str) -> TaskStoreResult<Option<UnitTask>> {
+        self.store().find_unit_task_by_pr_url(pr_url).await
+    }
+
+    async fn find_unit_task_by_branch_name(
+        &self,
+        branch_name: &str,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        self.store()
+            .find_unit_task_by_branch_name(branch_name)
+            .await
+    }
+
+    async fn find_unit_task_by_agent_task(
+        &self,
+        agent_task_id: Uuid,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        self.store()
+            .find_unit_task_by_agent_task(agent_task_id)
+            .await
+    }
+
+    async fn update_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask> {
+        self.store().update_unit_task(task).await
+    }
+
+    async fn delete_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_unit_task(id).await
+    }
+
+    async fn restore_unit_task(&self, id: Uuid) -> TaskStoreResult<UnitTask> {
+        self.store().restore_unit_task(id).await
+    }
+
+    async fn purge_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().purge_unit_task(id).await
+    }
+
+    // =========================================================================
+    // Composite Task operations
+    // =========================================================================
+
+    async fn create_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask> {
+        self.store().create_composite_task(task).await
+    }
+
+    async fn get_composite_task(&self, id: Uuid) -> TaskStoreResult<Option<CompositeTask>> {
+        self.store().get_composite_task(id).await
+    }
+
+    async fn list_composite_tasks(
+        &self,
+        filter: TaskFilter,
+    ) -> TaskStoreResult<(Vec<CompositeTask>, u32)> {
+        self.store().list_composite_tasks(filter).await
+    }
+
+    async fn find_composite_task_by_node_unit_task(
+        &self,
+        unit_task_id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTask>> {
+        self.store()
+            .find_composite_task_by_node_unit_task(unit_task_id)
+            .await
+    }
+
+    async fn update_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask> {
+        self.store().update_composite_task(task).await
+    }
+
+    async fn delete_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_composite_task(id).await
+    }
+
+    async fn restore_composite_task(&self, id: Uuid) -> TaskStoreResult<CompositeTask> {
+        self.store().restore_composite_task(id).await
+    }
+
+    async fn purge_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().purge_composite_task(id).await
+    }
+
+    // =========================================================================
+    // Composite Task Node operations
+    // =========================================================================
+
+    async fn create_composite_task_node(
+        &self,
+        node: CompositeTaskNode,
+    ) -> TaskStoreResult<CompositeTaskNode> {
+        self.store().create_composite_task_node(node).await
+    }
+
+    async fn get_composite_task_node(
+        &self,
+        id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTaskNode>> {
+        self.store().get_composite_task_node(id).await
+    }
+
+    async fn list_composite_task_nodes(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<CompositeTaskNode>> {
+        self.store()
+            .list_composite_task_nodes(composite_task_id)
+            .await
+    }
+
+    async fn update_composite_task_node(
+        &self,
+        node: CompositeTaskNode,
+    ) -> TaskStoreResult<CompositeTaskNode> {
+        self.store().update_composite_task_node(node).await
+    }
+
+    async fn delete_composite_task_node(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_composite_task_node(id).await
+    }
+
+    // =========================================================================
+    // Plan Revision operations
+    // =========================================================================
+
+    async fn create_plan_revision(&self, revision: PlanRevision) -> TaskStoreResult<PlanRevision> {
+        self.store().create_plan_revision(revision).await
+    }
+
+    async fn get_plan_revision(&self, id: Uuid) -> TaskStoreResult<Option<PlanRevision>> {
+        self.store().get_plan_revision(id).await
+    }
+
+    async fn list_plan_revisions(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<PlanRevision>> {
+        self.store().list_plan_revisions(composite_task_id).await
+    }
+
+    async fn approve_plan_revision(
+        &self,
+        id: Uuid,
+        approved_by: Uuid,
+    ) -> TaskStoreResult<PlanRevision> {
+        self.store().approve_plan_revision(id, approved_by).await
+    }
+
+    // =========================================================================
+    // Todo Item operations
+    // =========================================================================
+
+    async fn create_todo_item(&self, item: TodoItem) -> TaskStoreResult<TodoItem> {
+        self.store().create_todo_item(item).await
+    }
+
+    async fn get_todo_item(&self, id: Uuid) -> TaskStoreResult<Option<TodoItem>> {
+        self.store().get_todo_item(id).await
+    }
+
+    async fn list_todo_items(&self, filter: TodoFilter) -> TaskStoreResult<(Vec<TodoItem>, u32)> {
+        self.store().list_todo_items(filter).await
+    }
+
+    async fn update_todo_item(&self, item: TodoItem) -> TaskStoreResult<TodoItem> {
+        self.store().update_todo_item(item).await
+    }
+
+    async fn delete_todo_item(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_todo_item(id).await
+    }
+
+    // =========================================================================
+    // TTY Input Request operations
+    // =========================================================================
+
+    async fn create_tty_input_request(
+        &self,
+        request: TtyInputRequest,
+    ) -> TaskStoreResult<TtyInputRequest> {
+        self.store().create_tty_input_request(request).await
+    }
+
+    async fn get_tty_input_request(&self, id: Uuid) -> TaskStoreResult<Option<TtyInputRequest>> {
+        self.store().get_tty_input_request(id).await
+    }
+
+    async fn list_tty_input_requests(
+        &self,
+        filter: TtyInputFilter,
+    ) -> TaskStoreResult<Vec<TtyInputRequest>> {
+        self.store().list_tty_input_requests(filter).await
+    }
+
+    async fn update_tty_input_request(
+        &self,
+        request: TtyInputRequest,
+    ) -> TaskStoreResult<TtyInputRequest> {
+        self.store().update_tty_input_request(request).await
+    }
+
+    async fn delete_tty_input_request(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_tty_input_request(id).await
+    }
+
+    // =========================================================================
+    // Worker operations
+    // =========================================================================
+
+    async fn register_worker(&self, worker: Worker) -> TaskStoreResult<Worker> {
+        self.store().register_worker(worker).await
+    }
+
+    async fn get_worker(&self, id: Uuid) -> TaskStoreResult<Option<Worker>> {
+        self.store().get_worker(id).await
+    }
+
+    async fn list_workers(&self, filter: WorkerFilter) -> TaskStoreResult<(Vec<Worker>, u32)> {
+        self.store().list_workers(filter).await
+    }
+
+    async fn update_worker(&self, worker: Worker) -> TaskStoreResult<Worker> {
+        self.store().update_worker(worker).await
+    }
+
+    async fn delete_worker(&self, id: Uuid) -> TaskStoreResult<()> {
+        self.store().delete_worker(id).await
+    }
+
+    async fn record_worker_heartbeat(
+        &self,
+        id: Uuid,
+        status: WorkerStatus,
+        current_task_id: Option<Uuid>,
+    ) -> TaskStoreResult<Worker> {
+        self.store()
+            .record_worker_heartbeat(id, status, current_task_id)
+            .await
+    }
+
+    async fn mark_stale_workers(&self, before: DateTime<Utc>) -> TaskStoreResult<Vec<Worker>> {
+        self.store().mark_stale_workers(before).await
+    }
+
+    // =========================================================================
+    // Work queue operations
+    // =========================================================================
+
+    async fn enqueue_task(&self, entry: QueueEntry) -> TaskStoreResult<QueueEntry> {
+        self.store().enqueue_task(entry).await
+    }
+
+    async fn get_queue_entry(&self, id: Uuid) -> TaskStoreResult<Option<QueueEntry>> {
+        self.store().get_queue_entry(id).await
+    }
+
+    async fn list_queue_entries(
+        &self,
+        filter: QueueFilter,
+    ) -> TaskStoreResult<(Vec<QueueEntry>, u32)> {
+        self.store().list_queue_entries(filter).await
+    }
+
+    async fn claim_task(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Option<QueueEntry>> {
+        self.store().claim_task(worker_id, lease).await
+    }
+
+    async fn renew_leases(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Vec<QueueEntry>> {
+        self.store().renew_leases(worker_id, lease).await
+    }
+
+    async fn complete_task(&self, id: Uuid, worker_id: Uuid) -> TaskStoreResult<QueueEntry> {
+        self.store().complete_task(id, worker_id).await
+    }
+
+    async fn fail_task(
+        &self,
+        id: Uuid,
+        worker_id: Uuid,
+        error: String,
+    ) -> TaskStoreResult<QueueEntry> {
+        self.store().fail_task(id, worker_id, error).await
+    }
+
+    async fn requeue_expired_leases(&self) -> TaskStoreResult<u64> {
+        self.store().requeue_expired_leases().await
+    }
+
+    // =========================================================================
+    // Audit log operations
+    // =========================================================================
+
+    async fn record_audit_event(&self, event: AuditEvent) -> TaskStoreResult<AuditEvent> {
+        self.store().record_audit_event(event).await
+    }
+
+    async fn get_audit_event(&self, id: Uuid) -> TaskStoreResult<Option<AuditEvent>> {
+        self.store().get_audit_event(id).await
+    }
+
+    async fn list_audit_events(
+        &self,
+        filter: AuditFilter,
+    ) -> TaskStoreResult<(Vec<AuditEvent>, u32)> {
+        self.store().list_audit_events(filter).await
+    }
+
+    // =========================================================================
+    // Search
+    // =========================================================================
+
+    async fn search(&self, query: SearchQuery) -> TaskStoreResult<Vec<SearchHit>> {
+        self.store().search(query).await
+    }
+}
+
+#[async_trait]
+impl TaskStoreTransaction for CachedTaskStore<Box<dyn TaskStoreTransaction>> {
+    async fn commit(self: Box<Self>) -> TaskStoreResult<()> {
+        let Self {
+            inner,
+            cache,
+            pending,
+        } = *self;
+        inner.commit().await?;
+        if let Some(pending) = pending {
+            let keys = std::mem::take(
+                &mut *pending
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+            cache.invalidate(keys);
+        }
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> TaskStoreResult<()> {
+        self.inner.rollback().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::VcsProviderType;
+
+    use super::*;
+    use crate::{MemoryTaskStore, SqliteTaskStore};
+
+    fn cached(config: CacheConfig) -> CachedTaskStore<MemoryTaskStore> {
+        CachedTaskStore::new(MemoryTaskStore::new(), config)
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        crate::run_conformance(|| async { cached(CacheConfig::default()) }).await;
+        crate::run_conformance(|| async {
+            CachedTaskStore::new(
+                SqliteTaskStore::in_memory().await.unwrap(),
+                CacheConfig::default(),
+            )
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_reads_and_invalidation() {
+        let store = cached(CacheConfig::default());
+        let workspace = store
+            .create_workspace(Workspace::new("Cached"))
+            .await
+            .unwrap();
+        let id = workspace.id;
+
+        store.get_workspace(id).await.unwrap().unwrap();
+        store.get_workspace(id).await.unwrap().unwrap();
+        assert!(store.get_workspace(Uuid::new_v4()).await.unwrap().is_none());
+        let stats = store.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+
+        // Writes that bypass the wrapper are not seen until invalidated.
+        let mut renamed = workspace.clone();
+        renamed.name = "Renamed".to_string();
+        store.inner().update_workspace(renamed).await.unwrap();
+        assert_eq!(
+            store.get_workspace(id).await.unwrap().unwrap().name,
+            "Cached"
+        );
+
+        let mut renamed = store.inner().get_workspace(id).await.unwrap().unwrap();
+        renamed.name = "Renamed again".to_string();
+        store.update_workspace(renamed).await.unwrap();
+        assert_eq!(store.stats().invalidations, 1);
+        let fetched = store.get_workspace(id).await.unwrap().unwrap();
+        assert_eq!(fetched.name, "Renamed again");
+
+        store.delete_workspace(id).await.unwrap();
+        let fetched = store.get_workspace(id).await.unwrap().unwrap();
+        assert!(fetched.deleted_at.is_some(), "soft deletes invalidate");
+    }
+
+    #[tokio::test]
+    async fn test_transactions_invalidate_on_commit() {
+        let store = cached(CacheConfig::default());
+        let workspace = store
+            .create_workspace(Workspace::new("Cached"))
+            .await
+            .unwrap();
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                "cached",
+                "https://github.com/delinoio/cached",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        store.get_repository(repository.id).await.unwrap();
+
+        let tx = store.begin().await.unwrap();
+        let mut renamed = repository.clone();
+        renamed.name = "renamed".to_string();
+        tx.update_repository(renamed).await.unwrap();
+        assert_eq!(
+            tx.get_repository(repository.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .name,
+            "renamed",
+            "transactions see their own writes"
+        );
+        assert_eq!(
+            store
+                .get_repository(repository.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .name,
+            "cached"
+        );
+        tx.commit().await.unwrap();
+        assert_eq!(
+            store
+                .get_repository(repository.id)
+                .await
+                .unwrap()
+                .unwrap()
+                .name,
+            "renamed"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_capacity_and_ttl() {
+        let store = cached(CacheConfig {
+            capacity: 2,
+            ttl: Duration::from_secs(60),
+        });
+        let mut ids = Vec::new();
+        for name in ["First", "Second", "Third"] {
+            let workspace = store.create_workspace(Workspace::new(name)).await.unwrap();
+            ids.push(workspace.id);
+        }
+        store.get_workspace(ids[0]).await.unwrap();
+        store.get_workspace(ids[1]).await.unwrap();
+        store.get_workspace(ids[0]).await.unwrap();
+        store.get_workspace(ids[2]).await.unwrap();
+        let stats = store.stats();
+        assert_eq!((stats.entries, stats.evictions), (2, 1));
+        store.get_workspace(ids[0]).await.unwrap();
+        assert_eq!(store.stats().hits, 2, "the least recently used row goes");
+
+        let store = cached(CacheConfig {
+            capacity: 2,
+            ttl: Duration::ZERO,
+        });
+        let workspace = store
+            .create_workspace(Workspace::new("Expiring"))
+            .await
+            .unwrap();
+        store.get_workspace(workspace.id).await.unwrap();
+        store.get_workspace(workspace.id).await.unwrap();
+        assert_eq!(store.stats().hits, 0);
+    }
+}
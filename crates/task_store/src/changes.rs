@@ -0,0 +1,345 @@
+//! Change feed types shared by the task store implementations.
+
+use std::{collections::VecDeque, future::Future, pin::Pin, time::Duration};
+
+use chrono::{DateTime, Utc};
+use entities::{
+    AgentSession, AgentTask, CompositeTask, CompositeTaskNode, PlanRevision, Repository,
+    RepositoryGroup, TodoItem, TtyInputRequest, UnitTask, User, Worker, Workspace,
+};
+use futures::{Stream, stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+use crate::{TaskStoreError, TaskStoreResult};
+
+/// Number of events buffered for each in-process subscriber.
+const BROADCAST_CAPACITY: usize = 1024;
+
+/// How often SQL-backed subscriptions poll the change outbox.
+pub(crate) const CHANGE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Maximum number of outbox rows read per poll.
+pub(crate) const CHANGE_POLL_BATCH: i64 = 256;
+
+/// Stream of change events returned by [`TaskStore::subscribe`].
+///
+/// [`TaskStore::subscribe`]: crate::TaskStore::subscribe
+pub type ChangeStream = Pin<Box<dyn Stream<Item = TaskStoreResult<ChangeEvent>> + Send>>;
+
+/// Kind of entity a change applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityKind {
+    User,
+    Workspace,
+    Repository,
+    RepositoryGroup,
+    AgentTask,
+    AgentSession,
+    UnitTask,
+    CompositeTask,
+    CompositeTaskNode,
+    PlanRevision,
+    TodoItem,
+    TtyInputRequest,
+    Worker,
+}
+
+/// Operation that produced a change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOperation {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// Entity value carried by a change event.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum EntityValue {
+    User(User),
+    Workspace(Workspace),
+    Repository(Repository),
+    RepositoryGroup(RepositoryGroup),
+    AgentTask(AgentTask),
+    AgentSession(AgentSession),
+    UnitTask(UnitTask),
+    CompositeTask(CompositeTask),
+    CompositeTaskNode(CompositeTaskNode),
+    PlanRevision(PlanRevision),
+    TodoItem(TodoItem),
+    TtyInputRequest(TtyInputRequest),
+    Worker(Worker),
+}
+
+impl EntityValue {
+    /// Returns the kind of the contained entity.
+    pub fn kind(&self) -> EntityKind {
+        match self {
+            Self::User(_) => EntityKind::User,
+            Self::Workspace(_) => EntityKind::Workspace,
+            Self::Repository(_) => EntityKind::Repository,
+            Self::RepositoryGroup(_) => EntityKind::RepositoryGroup,
+            Self::AgentTask(_) => EntityKind::AgentTask,
+            Self::AgentSession(_) => EntityKind::AgentSession,
+            Self::UnitTask(_) => EntityKind::UnitTask,
+            Self::CompositeTask(_) => EntityKind::CompositeTask,
+            Self::CompositeTaskNode(_) => EntityKind::CompositeTaskNode,
+            Self::PlanRevision(_) => EntityKind::PlanRevision,
+            Self::TodoItem(_) => EntityKind::TodoItem,
+            Self::TtyInputRequest(_) => EntityKind::TtyInputRequest,
+            Self::Worker(_) => EntityKind::Worker,
+        }
+    }
+
+    /// Returns the ID of the contained entity.
+    pub fn id(&self) -> Uuid {
+        match self {
+            Self::User(user) => user.id,
+            Self::Workspace(workspace) => workspace.id,
+            Self::Repository(repository) => repository.id,
+            Self::RepositoryGroup(group) => group.id,
+            Self::AgentTask(task) => task.id,
+            Self::AgentSession(session) => session.id,
+            Self::UnitTask(task) => task.id,
+            Self::CompositeTask(task) => task.id,
+            Self::CompositeTaskNode(node) => node.id,
+            Self::PlanRevision(revision) => revision.id,
+            Self::TodoItem(item) => item.id,
+            Self::TtyInputRequest(request) => request.id,
+            Self::Worker(worker) => worker.id,
+        }
+    }
+}
+
+/// A change to a stored entity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Kind of the changed entity.
+    pub kind: EntityKind,
+    /// ID of the changed entity.
+    pub id: Uuid,
+    /// Operation that produced the change.
+    pub operation: ChangeOperation,
+    /// Entity after the change, `None` for deletions.
+    pub value: Option<EntityValue>,
+    /// When the change was made.
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl ChangeEvent {
+    /// Creates an event for a newly created entity.
+    pub fn created(value: EntityValue) -> Self {
+        Self::with_value(ChangeOperation::Created, value)
+    }
+
+    /// Creates an event for an updated entity.
+    pub fn updated(value: EntityValue) -> Self {
+        Self::with_value(ChangeOperation::Updated, value)
+    }
+
+    /// Creates an event for a deleted entity.
+    pub fn deleted(kind: EntityKind, id: Uuid) -> Self {
+        Self {
+            kind,
+            id,
+            operation: ChangeOperation::Deleted,
+            value: None,
+            occurred_at: Utc::now(),
+        }
+    }
+
+    fn with_value(operation: ChangeOperation, value: EntityValue) -> Self {
+        Self {
+            kind: value.kind(),
+            id: value.id(),
+            operation,
+            value: Some(value),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Filter for change feed subscriptions.
+///
+/// Empty lists match everything.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeFilter {
+    /// Only deliver changes to these entity kinds.
+    pub kinds: Vec<EntityKind>,
+    /// Only deliver changes to this entity.
+    pub entity_id: Option<Uuid>,
+    /// Only deliver these operations.
+    pub operations: Vec<ChangeOperation>,
+}
+
+impl ChangeFilter {
+    /// Creates a filter matching every change.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the filter to an entity kind.
+    pub fn with_kind(mut self, kind: EntityKind) -> Self {
+        self.kinds.push(kind);
+        self
+    }
+
+    /// Restricts the filter to a single entity.
+    pub fn with_entity_id(mut self, id: Uuid) -> Self {
+        self.entity_id = Some(id);
+        self
+    }
+
+    /// Restricts the filter to an operation.
+    pub fn with_operation(mut self, operation: ChangeOperation) -> Self {
+        self.operations.push(operation);
+        self
+    }
+
+    /// Returns true if `event` passes the filter.
+    pub fn matches(&self, event: &ChangeEvent) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && self.entity_id.is_none_or(|id| id == event.id)
+            && (self.operations.is_empty() || self.operations.contains(&event.operation))
+    }
+}
+
+/// In-process change feed backed by a broadcast channel.
+#[derive(Debug, Clone)]
+pub(crate) struct ChangeBroadcaster {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for ChangeBroadcaster {
+    fn default() -> Self {
+        let (sender, _) = broadcast::channel(BROADCAST_CAPACITY);
+        Self { sender }
+    }
+}
+
+impl ChangeBroadcaster {
+    /// Delivers an event to current subscribers.
+    pub(crate) fn publish(&self, event: ChangeEvent) {
+        // Sending only fails when nobody is subscribed.
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribes to events published from now on.
+    ///
+    /// A subscriber that falls more than [`BROADCAST_CAPACITY`] events
+    /// behind receives [`TaskStoreError::ChangeFeedLagged`] and continues with
+    /// the oldest event still buffered.
+    pub(crate) fn subscribe(&self, filter: ChangeFilter) -> ChangeStream {
+        let receiver = self.sender.subscribe();
+        Box::pin(stream::unfold(
+            (receiver, filter),
+            |(mut receiver, filter)| async move {
+                loop {
+                    let item = match receiver.recv().await {
+                        Ok(event) if filter.matches(&event) => Ok(event),
+                        Ok(_) => continue,
+                        Err(RecvError::Lagged(skipped)) => {
+                            Err(TaskStoreError::ChangeFeedLagged { skipped })
+                        }
+                        Err(RecvError::Closed) => return None,
+                    };
+                    return Some((item, (receiver, filter)));
+                }
+            },
+        ))
+    }
+}
+
+/// Outbox rows read by one poll, as `(sequence, event)` pairs.
+pub(crate) type OutboxBatch = Vec<(i64, TaskStoreResult<ChangeEvent>)>;
+
+/// Streams events from a SQL change outbox.
+///
+/// `fetch` returns the rows after a sequence number in ascending order. The
+/// stream polls every [`CHANGE_POLL_INTERVAL`] while the outbox is idle and
+/// ends once the connection pool is closed.
+pub(crate) fn poll_outbox<F, Fut>(after: i64, filter: ChangeFilter, fetch: F) -> ChangeStream
+where
+    F: Fn(i64) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = TaskStoreResult<OutboxBatch>> + Send,
+{
+    let state = (after, VecDeque::new(), filter, fetch);
+    Box::pin(stream::unfold(
+        state,
+        |(mut after, mut buffered, filter, fetch)| async move {
+            loop {
+                if let Some(item) = buffered.pop_front() {
+                    return Some((item, (after, buffered, filter, fetch)));
+                }
+                match fetch(after).await {
+                    Ok(rows) if rows.is_empty() => tokio::time::sleep(CHANGE_POLL_INTERVAL).await,
+                    Ok(rows) => {
+                        for (sequence, event) in rows {
+                            after = sequence;
+                            match event {
+                                Ok(event) if !filter.matches(&event) => {}
+                                item => buffered.push_back(item),
+                            }
+                        }
+                    }
+                    Err(TaskStoreError::Database(sqlx::Error::PoolClosed)) => return None,
+                    Err(err) => {
+                        tokio::time::sleep(CHANGE_POLL_INTERVAL).await;
+                        return Some((Err(err), (after, buffered, filter, fetch)));
+                    }
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::Workspace;
+
+    use super::*;
+
+    #[test]
+    fn test_filter_matches() {
+        let workspace = Workspace::new("Test Workspace");
+        let event = ChangeEvent::created(EntityValue::Workspace(workspace.clone()));
+        assert_eq!(event.kind, EntityKind::Workspace);
+        assert_eq!(event.id, workspace.id);
+
+        assert!(ChangeFilter::new().matches(&event));
+        assert!(
+            ChangeFilter::new()
+                .with_kind(EntityKind::UnitTask)
+                .with_kind(EntityKind::Workspace)
+                .matches(&event)
+        );
+        assert!(
+            !ChangeFilter::new()
+                .with_kind(EntityKind::UnitTask)
+                .matches(&event)
+        );
+        assert!(
+            !ChangeFilter::new()
+                .with_entity_id(Uuid::new_v4())
+                .matches(&event)
+        );
+        assert!(
+            !ChangeFilter::new()
+                .with_operation(ChangeOperation::Deleted)
+                .matches(&event)
+        );
+    }
+
+    #[test]
+    fn test_event_serde_round_trip() {
+        let event = ChangeEvent::updated(EntityValue::Workspace(Workspace::new("Test")));
+        let json = serde_json::to_string(&event).unwrap();
+        let parsed: ChangeEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.operation, ChangeOperation::Updated);
+        assert!(matches!(parsed.value, Some(EntityValue::Workspace(_))));
+    }
+}
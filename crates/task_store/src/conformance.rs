@@ -0,0 +1,2682 @@
+//! Conformance suite for [`TaskStore`] implementations.
+//!
+//! [`run_conformance`] checks that a store behaves like [`MemoryTaskStore`]:
+//! the same errors for missing, duplicate and dangling rows, stale revisions
+//! and invalid status transitions, the same filters, totals, ordering and
+//! cursors for list queries, and the same trash, audit log, transaction, change
+//! feed and search semantics. A failed check panics, so the suite is meant to
+//! be called from a test:
+//!
+//! ```no_run
+//! # async fn example() {
+//! use task_store::{MemoryTaskStore, run_conformance};
+//!
+//! run_conformance(|| async { MemoryTaskStore::new() }).await;
+//! # }
+//! ```
+//!
+//! Every check writes rows with fresh IDs and only lists rows under parents
+//! it created, so the suite can also run against a shared database that
+//! already holds data.
+//!
+//! [`MemoryTaskStore`]: crate::MemoryTaskStore
+
+use std::{collections::HashSet, fmt::Debug, future::Future, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use entities::{
+    AgentSession, AgentTask, AiAgentType, AuditActor, AuditActorType, AuditEvent, CompositeTask,
+    CompositeTaskNode, CompositeTaskStatus, PlanRevision, Repository, RepositoryGroup, TodoItem,
+    TodoItemStatus, TtyInputRequest, TtyInputStatus, UnitTask, UnitTaskStatus, User,
+    VcsProviderType, Worker, WorkerStatus, Workspace,
+};
+use futures::{StreamExt, future::join_all};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    AuditFilter, ChangeEvent, ChangeFilter, ChangeOperation, ChangeStream, Cursor, DeletedFilter,
+    EntityKind, EntityValue, LogChunk, LogRange, LogSize, QueueEntry, QueueFilter, QueueStatus,
+    RepositoryFilter, SNIPPET_MATCH_END, SNIPPET_MATCH_START, SearchField, SearchQuery, SortKey,
+    SortOrder, TaskFilter, TaskStore, TaskStoreError, TaskStoreResult, TodoFilter, TtyInputFilter,
+    WorkerFilter, WorkspaceFilter,
+};
+
+/// How long to wait for a change event before failing.
+const CHANGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timestamp fields, which are left out of round-trip comparisons because
+/// backends store them at different precisions.
+const TIMESTAMP_FIELDS: [&str; 7] = [
+    "created_at",
+    "updated_at",
+    "deleted_at",
+    "started_at",
+    "completed_at",
+    "responded_at",
+    "last_heartbeat",
+];
+
+/// Runs every conformance check, each against a fresh store from
+/// `new_store`.
+///
+/// Panics with a description of the first behavior that differs from
+/// [`MemoryTaskStore`](crate::MemoryTaskStore).
+pub async fn run_conformance<S, F, Fut>(new_store: F)
+where
+    S: TaskStore,
+    F: Fn() -> Fut,
+    Fut: Future<Output = S>,
+{
+    check_users(&new_store().await).await;
+    check_workspaces(&new_store().await).await;
+    check_repositories(&new_store().await).await;
+    check_repository_groups(&new_store().await).await;
+    check_agent_tasks(&new_store().await).await;
+    check_unit_tasks(&new_store().await).await;
+    check_composite_tasks(&new_store().await).await;
+    check_plan_revisions(&new_store().await).await;
+    check_lookups(&new_store().await).await;
+    check_todo_items(&new_store().await).await;
+    check_tty_input_requests(&new_store().await).await;
+    check_session_logs(&new_store().await).await;
+    check_workers(&new_store().await).await;
+    check_work_queue(&new_store().await).await;
+    check_audit_log(&new_store().await).await;
+    check_missing_rows(&new_store().await).await;
+    check_duplicate_rows(&new_store().await).await;
+    check_list_ordering(&new_store().await).await;
+    check_pagination(&new_store().await).await;
+    check_trash(&new_store().await).await;
+    check_transactions(&new_store().await).await;
+    check_change_feed(&new_store().await).await;
+    check_search(&new_store().await).await;
+}
+
+/// Asserts that `$result` is an error matching `$pattern`.
+macro_rules! assert_err {
+    ($result:expr, $pattern:pat, $context:expr) => {
+        match $result {
+            Err($pattern) => {}
+            other => panic!(
+                "{}: expected {}, got {other:?}",
+                $context,
+                stringify!($pattern)
+            ),
+        }
+    };
+}
+
+/// Asserts that an update was rejected as an invalid status transition.
+fn assert_invalid_transition<T: Debug>(result: TaskStoreResult<T>, from: &str, to: &str) {
+    match result {
+        Err(TaskStoreError::InvalidStateTransition { from: f, to: t }) if f == from && t == to => {}
+        other => panic!("Expected invalid transition from {from} to {to}, got {other:?}"),
+    }
+}
+
+/// Asserts that two entities are equal apart from their timestamps.
+fn assert_same<T: Serialize>(actual: &T, expected: &T, context: &str) {
+    let fields = |entity: &T| {
+        let mut value = serde_json::to_value(entity).unwrap();
+        if let Some(object) = value.as_object_mut() {
+            for field in TIMESTAMP_FIELDS {
+                object.remove(field);
+            }
+        }
+        value
+    };
+    assert_eq!(fields(actual), fields(expected), "{context}");
+}
+
+/// Returns a whole-second timestamp `offset` seconds after a fixed instant,
+/// which every backend stores exactly.
+fn at(offset: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(1_700_000_000 + offset, 0).unwrap()
+}
+
+/// Returns the IDs of `rows` in order.
+fn ids<T>(rows: &[T], id: impl Fn(&T) -> Uuid) -> Vec<Uuid> {
+    rows.iter().map(id).collect()
+}
+
+/// Returns a word that no other check or earlier run writes.
+fn unique_word() -> String {
+    format!("w{}", Uuid::new_v4().simple())
+}
+
+/// Parent rows most checks attach their entities to.
+struct Fixture {
+    workspace: Workspace,
+    repository: Repository,
+    group: RepositoryGroup,
+}
+
+impl Fixture {
+    async fn create(store: &dyn TaskStore) -> Self {
+        let workspace = store
+            .create_workspace(Workspace::new("Conformance"))
+            .await
+            .unwrap();
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                "conformance",
+                "https://github.com/delinoio/conformance",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        let mut group = RepositoryGroup::new(workspace.id);
+        group.add_repository(repository.id);
+        let group = store.create_repository_group(group).await.unwrap();
+        Self {
+            workspace,
+            repository,
+            group,
+        }
+    }
+
+    /// Creates an agent task.
+    async fn agent_task(&self, store: &dyn TaskStore) -> AgentTask {
+        store.create_agent_task(AgentTask::new()).await.unwrap()
+    }
+
+    /// Creates a unit task in the fixture's group with its own agent task.
+    async fn unit_task(
+        &self,
+        store: &dyn TaskStore,
+        task: impl FnOnce(Uuid) -> UnitTask,
+    ) -> UnitTask {
+        let agent_task = self.agent_task(store).await;
+        store.create_unit_task(task(agent_task.id)).await.unwrap()
+    }
+
+    /// Creates a session of the agent task behind `task`.
+    async fn session(&self, store: &dyn TaskStore, task: &UnitTask) -> AgentSession {
+        store
+            .create_agent_session(AgentSession::new(
+                task.agent_task_id,
+                AiAgentType::ClaudeCode,
+            ))
+            .await
+            .unwrap()
+    }
+}
+
+// =============================================================================
+// Entity checks
+// =============================================================================
+
+async fn check_users(store: &dyn TaskStore) {
+    let user = User::new(format!("{}@example.com", Uuid::new_v4())).with_name("Ada");
+    let created = store.create_user(user.clone()).await.unwrap();
+    assert_same(&created, &user, "create_user returns the user");
+    assert_eq!(created.revision, 0, "new users start at revision 0");
+
+    let fetched = store.get_user(user.id).await.unwrap().unwrap();
+    assert_same(&fetched, &user, "get_user returns the stored user");
+    let mut earlier = User::new(format!("{}@example.com", Uuid::new_v4()));
+    earlier.created_at = at(10);
+    store.create_user(earlier.clone()).await.unwrap();
+    let listed: Vec<Uuid> = ids(&store.list_users().await.unwrap(), |u| u.id)
+        .into_iter()
+        .filter(|id| *id == user.id || *id == earlier.id)
+        .collect();
+    assert_eq!(
+        listed,
+        [earlier.id, user.id],
+        "list_users orders by creation time"
+    );
+    let by_email = store.get_user_by_email(&user.email).await.unwrap().unwrap();
+    assert_eq!(by_email.id, user.id, "get_user_by_email finds the user");
+    assert!(
+        store
+            .get_user_by_email("missing@example.com.invalid")
+            .await
+            .unwrap()
+            .is_none(),
+        "get_user_by_email returns None for an unknown email"
+    );
+
+    let mut renamed = fetched.clone();
+    renamed.name = Some("Grace".to_string());
+    let updated = store.update_user(renamed).await.unwrap();
+    assert_eq!(updated.revision, 1, "update_user increments the revision");
+    let fetched = store.get_user(user.id).await.unwrap().unwrap();
+    assert_eq!(fetched.name.as_deref(), Some("Grace"));
+    assert_eq!(fetched.revision, 1, "get_user returns the new revision");
+    assert_err!(
+        store.update_user(user.clone()).await,
+        TaskStoreError::Conflict {
+            current_revision: 1,
+            ..
+        },
+        "update_user with a stale revision"
+    );
+
+    store.delete_user(user.id).await.unwrap();
+    assert!(store.get_user(user.id).await.unwrap().is_none());
+    assert_err!(
+        store.delete_user(user.id).await,
+        TaskStoreError::NotFound { .. },
+        "delete_user of a deleted user"
+    );
+}
+
+async fn check_workspaces(store: &dyn TaskStore) {
+    let user = store
+        .create_user(User::new(format!("{}@example.com", Uuid::new_v4())))
+        .await
+        .unwrap();
+    let owned = Workspace::new("Owned")
+        .with_description("Belongs to the user")
+        .with_user_id(user.id);
+    let created = store.create_workspace(owned.clone()).await.unwrap();
+    assert_same(&created, &owned, "create_workspace returns the workspace");
+    let second = store
+        .create_workspace(Workspace::new("Second").with_user_id(user.id))
+        .await
+        .unwrap();
+    store
+        .create_workspace(Workspace::new("Unowned"))
+        .await
+        .unwrap();
+    assert_err!(
+        store
+            .create_workspace(Workspace::new("Orphan").with_user_id(Uuid::new_v4()))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_workspace with a missing user"
+    );
+
+    let fetched = store.get_workspace(owned.id).await.unwrap().unwrap();
+    assert_same(
+        &fetched,
+        &owned,
+        "get_workspace returns the stored workspace",
+    );
+    let filter = WorkspaceFilter {
+        user_id: Some(user.id),
+        ..Default::default()
+    };
+    let (workspaces, total) = store.list_workspaces(filter.clone()).await.unwrap();
+    assert_eq!(total, 2, "list_workspaces counts the user's workspaces");
+    let listed: HashSet<Uuid> = workspaces.iter().map(|w| w.id).collect();
+    assert_eq!(listed, HashSet::from([owned.id, second.id]));
+
+    let mut updated = fetched;
+    updated.description = None;
+    let updated = store.update_workspace(updated).await.unwrap();
+    assert_eq!(updated.revision, 1);
+    let mut orphaned = updated.clone();
+    orphaned.user_id = Some(Uuid::new_v4());
+    assert_err!(
+        store.update_workspace(orphaned).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "update_workspace to a missing user"
+    );
+    let fetched = store.get_workspace(owned.id).await.unwrap().unwrap();
+    assert_same(&fetched, &updated, "a failed update leaves the workspace");
+
+    assert_err!(
+        store.delete_user(user.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "delete_user of a user that owns workspaces"
+    );
+    store.purge_workspace(owned.id).await.unwrap();
+    assert!(store.get_workspace(owned.id).await.unwrap().is_none());
+    let (_, total) = store.list_workspaces(filter).await.unwrap();
+    assert_eq!(total, 1, "deleted workspaces are no longer listed");
+}
+
+async fn check_repositories(store: &dyn TaskStore) {
+    let workspace = store
+        .create_workspace(Workspace::new("Repositories"))
+        .await
+        .unwrap();
+    let repository = Repository::new(
+        workspace.id,
+        "api",
+        "https://gitlab.com/delinoio/api",
+        VcsProviderType::Gitlab,
+    )
+    .with_default_branch("develop");
+    let created = store.create_repository(repository.clone()).await.unwrap();
+    assert_same(
+        &created,
+        &repository,
+        "create_repository returns the repository",
+    );
+    let fetched = store.get_repository(repository.id).await.unwrap().unwrap();
+    assert_same(
+        &fetched,
+        &repository,
+        "get_repository returns the stored repository",
+    );
+    assert_err!(
+        store
+            .create_repository(Repository::new(
+                Uuid::new_v4(),
+                "orphan",
+                "https://github.com/delinoio/orphan",
+                VcsProviderType::Github,
+            ))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_repository in a missing workspace"
+    );
+
+    let filter = RepositoryFilter {
+        workspace_id: Some(workspace.id),
+        ..Default::default()
+    };
+    let (repositories, total) = store.list_repositories(filter.clone()).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(ids(&repositories, |r| r.id), [repository.id]);
+
+    let mut updated = fetched;
+    updated.default_branch = "main".to_string();
+    let updated = store.update_repository(updated).await.unwrap();
+    assert_eq!(updated.revision, 1);
+    let fetched = store.get_repository(repository.id).await.unwrap().unwrap();
+    assert_eq!(fetched.default_branch, "main");
+
+    assert_err!(
+        store.purge_workspace(workspace.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "purge_workspace of a workspace with repositories"
+    );
+    let item = store
+        .create_todo_item(TodoItem::issue_triage(
+            repository.id,
+            "https://gitlab.com/delinoio/api/issues/1".to_string(),
+            "Crash".to_string(),
+        ))
+        .await
+        .unwrap();
+    assert_err!(
+        store.purge_repository(repository.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "purge_repository of a repository with todo items"
+    );
+    store.delete_todo_item(item.id).await.unwrap();
+    store.purge_repository(repository.id).await.unwrap();
+    assert!(store.get_repository(repository.id).await.unwrap().is_none());
+    let (_, total) = store.list_repositories(filter).await.unwrap();
+    assert_eq!(total, 0);
+    store.purge_workspace(workspace.id).await.unwrap();
+}
+
+async fn check_repository_groups(store: &dyn TaskStore) {
+    let workspace = store
+        .create_workspace(Workspace::new("Groups"))
+        .await
+        .unwrap();
+    let mut repository_ids = Vec::new();
+    for name in ["web", "api", "worker"] {
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                name,
+                format!("https://github.com/delinoio/{name}"),
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        repository_ids.push(repository.id);
+    }
+
+    let mut group = RepositoryGroup::new(workspace.id).with_name("Backend");
+    group.created_at = at(20);
+    for id in repository_ids.iter().rev() {
+        group.add_repository(*id);
+    }
+    let created = store.create_repository_group(group.clone()).await.unwrap();
+    assert_same(
+        &created,
+        &group,
+        "create_repository_group returns the group",
+    );
+    let fetched = store.get_repository_group(group.id).await.unwrap().unwrap();
+    assert_same(&fetched, &group, "members keep their order");
+    let mut earlier = RepositoryGroup::new(workspace.id);
+    earlier.created_at = at(10);
+    earlier.add_repository(repository_ids[0]);
+    earlier.repository_ids.push(repository_ids[0]);
+    store
+        .create_repository_group(earlier.clone())
+        .await
+        .unwrap();
+    let fetched_earlier = store
+        .get_repository_group(earlier.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_same(&fetched_earlier, &earlier, "members keep repeats");
+
+    let mut dangling = RepositoryGroup::new(workspace.id);
+    dangling.add_repository(Uuid::new_v4());
+    assert_err!(
+        store.create_repository_group(dangling).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_repository_group with a missing repository"
+    );
+    assert_err!(
+        store
+            .create_repository_group(RepositoryGroup::new(Uuid::new_v4()))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_repository_group in a missing workspace"
+    );
+
+    let groups = store
+        .list_repository_groups(Some(workspace.id))
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(&groups, |g| g.id),
+        [earlier.id, group.id],
+        "list_repository_groups orders by creation time"
+    );
+
+    let mut updated = fetched;
+    updated.remove_repository(repository_ids[1]);
+    let updated = store.update_repository_group(updated).await.unwrap();
+    assert_eq!(updated.revision, 1);
+    let fetched = store.get_repository_group(group.id).await.unwrap().unwrap();
+    assert_eq!(
+        fetched.repository_ids,
+        [repository_ids[2], repository_ids[0]]
+    );
+    store.purge_repository(repository_ids[1]).await.unwrap();
+    assert_err!(
+        store.purge_repository(repository_ids[0]).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "purge_repository of a group member"
+    );
+
+    let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+    let task = store
+        .create_unit_task(UnitTask::new(group.id, agent_task.id, "Use the group"))
+        .await
+        .unwrap();
+    assert_err!(
+        store.delete_repository_group(group.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "delete_repository_group of a group with tasks"
+    );
+    store.purge_unit_task(task.id).await.unwrap();
+    store.delete_repository_group(group.id).await.unwrap();
+    assert!(
+        store
+            .get_repository_group(group.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let groups = store
+        .list_repository_groups(Some(workspace.id))
+        .await
+        .unwrap();
+    assert_eq!(ids(&groups, |g| g.id), [earlier.id]);
+}
+
+async fn check_agent_tasks(store: &dyn TaskStore) {
+    let mut task = AgentTask::new();
+    task.add_base_remote("/path/to/repo", "main");
+    task.ai_agent_type = Some(AiAgentType::Aider);
+    let created = store.create_agent_task(task.clone()).await.unwrap();
+    assert_same(&created, &task, "create_agent_task returns the task");
+    let fetched = store.get_agent_task(task.id).await.unwrap().unwrap();
+    assert_same(&fetched, &task, "get_agent_task returns the stored task");
+    let mut earlier_task = AgentTask::new();
+    earlier_task.created_at = at(10);
+    store.create_agent_task(earlier_task.clone()).await.unwrap();
+    let listed: Vec<AgentTask> = store
+        .list_agent_tasks()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|t| t.id == task.id || t.id == earlier_task.id)
+        .collect();
+    assert_eq!(
+        ids(&listed, |t| t.id),
+        [earlier_task.id, task.id],
+        "list_agent_tasks orders by creation time"
+    );
+    assert_same(&listed[1], &task, "list_agent_tasks returns stored tasks");
+
+    let mut updated = fetched;
+    updated.ai_agent_model = Some("sonnet".to_string());
+    let updated = store.update_agent_task(updated).await.unwrap();
+    assert_eq!(updated.revision, 1);
+    let fetched = store.get_agent_task(task.id).await.unwrap().unwrap();
+    assert_eq!(fetched.ai_agent_model.as_deref(), Some("sonnet"));
+
+    let mut later = AgentSession::new(task.id, AiAgentType::ClaudeCode).with_model("opus");
+    later.created_at = at(20);
+    later.output_log = Some("Second attempt".to_string());
+    let created = store.create_agent_session(later.clone()).await.unwrap();
+    assert_same(&created, &later, "create_agent_session returns the session");
+    let mut earlier = AgentSession::new(task.id, AiAgentType::ClaudeCode);
+    earlier.created_at = at(10);
+    store.create_agent_session(earlier.clone()).await.unwrap();
+    let fetched = store.get_agent_session(later.id).await.unwrap().unwrap();
+    assert_same(
+        &fetched,
+        &later,
+        "get_agent_session returns the stored session",
+    );
+    assert_err!(
+        store
+            .create_agent_session(AgentSession::new(Uuid::new_v4(), AiAgentType::ClaudeCode))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_agent_session for a missing agent task"
+    );
+
+    let sessions = store.list_agent_sessions(task.id).await.unwrap();
+    assert_eq!(
+        ids(&sessions, |s| s.id),
+        [earlier.id, later.id],
+        "list_agent_sessions orders by creation time"
+    );
+    let fetched_task = store.get_agent_task(task.id).await.unwrap().unwrap();
+    assert_eq!(
+        ids(&fetched_task.agent_sessions, |s| s.id),
+        [earlier.id, later.id],
+        "get_agent_task returns the task's sessions"
+    );
+    let batch = store
+        .get_agent_tasks(&[task.id, Uuid::new_v4(), earlier_task.id, task.id])
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(&batch, |t| t.id),
+        [earlier_task.id, task.id],
+        "get_agent_tasks orders by creation time and skips missing IDs"
+    );
+    assert_same(
+        &batch[1],
+        &fetched_task,
+        "get_agent_tasks returns the tasks with their sessions",
+    );
+    assert!(store.get_agent_tasks(&[]).await.unwrap().is_empty());
+
+    let mut updated = fetched;
+    updated.output_log = Some("Second attempt succeeded".to_string());
+    updated.completed_at = Some(at(30));
+    let updated = store.update_agent_session(updated).await.unwrap();
+    assert_eq!(updated.revision, 1);
+    let fetched = store.get_agent_session(later.id).await.unwrap().unwrap();
+    assert_eq!(
+        fetched.output_log.as_deref(),
+        Some("Second attempt succeeded")
+    );
+    assert_eq!(fetched.completed_at, Some(at(30)));
+
+    store.delete_agent_session(earlier.id).await.unwrap();
+    assert!(store.get_agent_session(earlier.id).await.unwrap().is_none());
+
+    // Sessions are deleted with their agent task.
+    store.delete_agent_task(task.id).await.unwrap();
+    assert!(store.get_agent_task(task.id).await.unwrap().is_none());
+    assert!(store.get_agent_session(later.id).await.unwrap().is_none());
+    assert!(store.list_agent_sessions(task.id).await.unwrap().is_empty());
+}
+
+async fn check_unit_tasks(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let agent_task = fixture.agent_task(store).await;
+    let auto_fix_task = fixture.agent_task(store).await;
+    let mut task = UnitTask::new(fixture.group.id, agent_task.id, "Fix the bug")
+        .with_title("Bug")
+        .with_branch_name("fix/bug");
+    task.base_commit = Some("abc123".to_string());
+    task.created_at = at(0);
+    // ID lists keep repeats, as the entities do.
+    task.auto_fix_task_ids = vec![auto_fix_task.id, auto_fix_task.id];
+    let created = store.create_unit_task(task.clone()).await.unwrap();
+    assert_same(&created, &task, "create_unit_task returns the task");
+    let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+    assert_same(&fetched, &task, "get_unit_task returns the stored task");
+
+    let mut dangling = UnitTask::new(fixture.group.id, agent_task.id, "Dangling");
+    dangling.auto_fix_task_ids.push(Uuid::new_v4());
+    assert_err!(
+        store.create_unit_task(dangling).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_unit_task with a missing auto-fix task"
+    );
+    assert_err!(
+        store
+            .create_unit_task(UnitTask::new(Uuid::new_v4(), agent_task.id, "Dangling"))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_unit_task in a missing repository group"
+    );
+
+    let other = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Other")
+        })
+        .await;
+    let mut reviewed = fetched;
+    reviewed.status = UnitTaskStatus::InReview;
+    reviewed.end_commit = Some("def456".to_string());
+    let reviewed = store.update_unit_task(reviewed).await.unwrap();
+    assert_eq!(reviewed.revision, 1);
+
+    let filter = TaskFilter {
+        repository_group_id: Some(fixture.group.id),
+        ..Default::default()
+    };
+    let (_, total) = store.list_unit_tasks(filter.clone()).await.unwrap();
+    assert_eq!(total, 2, "list_unit_tasks counts the group's tasks");
+    let in_review = TaskFilter {
+        unit_status: Some(UnitTaskStatus::InReview),
+        ..filter.clone()
+    };
+    let (tasks, total) = store.list_unit_tasks(in_review).await.unwrap();
+    assert_eq!(total, 1, "list_unit_tasks filters by status");
+    assert_same(&tasks[0], &reviewed, "list_unit_tasks returns stored tasks");
+    let created_before = TaskFilter {
+        created_until: Some(at(1)),
+        ..filter.clone()
+    };
+    let (tasks, total) = store.list_unit_tasks(created_before).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(
+        ids(&tasks, |t| t.id),
+        [task.id],
+        "list_unit_tasks filters by creation time"
+    );
+    let created_after = TaskFilter {
+        created_since: Some(at(0) + TimeDelta::microseconds(1)),
+        ..filter.clone()
+    };
+    let (tasks, _) = store.list_unit_tasks(created_after).await.unwrap();
+    assert_eq!(ids(&tasks, |t| t.id), [other.id]);
+
+    let mut skipped = reviewed.clone();
+    skipped.status = UnitTaskStatus::InProgress;
+    let reopened = store.update_unit_task(skipped).await.unwrap();
+    let mut done = reopened.clone();
+    done.status = UnitTaskStatus::Done;
+    assert_invalid_transition(store.update_unit_task(done).await, "in_progress", "done");
+    let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+    assert_eq!(fetched.status, UnitTaskStatus::InProgress);
+    assert_eq!(fetched.revision, 2, "a rejected update keeps the revision");
+    assert_err!(
+        store.update_unit_task(reviewed).await,
+        TaskStoreError::Conflict {
+            current_revision: 2,
+            ..
+        },
+        "update_unit_task with a stale revision"
+    );
+
+    assert_err!(
+        store.delete_agent_task(auto_fix_task.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "delete_agent_task of an auto-fix task"
+    );
+    let session = fixture.session(store, &other).await;
+    let request = store
+        .create_tty_input_request(TtyInputRequest::new(other.id, session.id, "Continue?"))
+        .await
+        .unwrap();
+    assert_err!(
+        store.purge_unit_task(other.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "purge_unit_task of a task with input requests"
+    );
+    assert_err!(
+        store.delete_agent_session(session.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "delete_agent_session of a session with input requests"
+    );
+    store.delete_tty_input_request(request.id).await.unwrap();
+    store.purge_unit_task(other.id).await.unwrap();
+    store.purge_unit_task(task.id).await.unwrap();
+    assert!(store.get_unit_task(task.id).await.unwrap().is_none());
+    let (_, total) = store.list_unit_tasks(filter).await.unwrap();
+    assert_eq!(total, 0);
+}
+
+async fn check_composite_tasks(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let planning_task = fixture.agent_task(store).await;
+    let task = CompositeTask::new(fixture.group.id, planning_task.id, "Build the feature")
+        .with_title("Feature")
+        .with_execution_agent_type(AiAgentType::CodexCli);
+    let created = store.create_composite_task(task.clone()).await.unwrap();
+    assert_same(&created, &task, "create_composite_task returns the task");
+    assert_err!(
+        store
+            .create_composite_task(CompositeTask::new(
+                fixture.group.id,
+                Uuid::new_v4(),
+                "Dangling",
+            ))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_composite_task with a missing planning task"
+    );
+
+    let first = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "First step")
+        })
+        .await;
+    let second = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Second step")
+        })
+        .await;
+    let mut first_node = CompositeTaskNode::new(task.id, first.id);
+    first_node.created_at = at(10);
+    store
+        .create_composite_task_node(first_node.clone())
+        .await
+        .unwrap();
+    let mut second_node = CompositeTaskNode::new(task.id, second.id);
+    second_node.created_at = at(20);
+    second_node.depends_on(first_node.id);
+    let created = store
+        .create_composite_task_node(second_node.clone())
+        .await
+        .unwrap();
+    assert_same(
+        &created,
+        &second_node,
+        "create_composite_task_node returns the node",
+    );
+    let fetched = store
+        .get_composite_task_node(second_node.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_same(&fetched, &second_node, "dependencies round-trip");
+    let mut dangling = CompositeTaskNode::new(task.id, second.id);
+    dangling.depends_on(Uuid::new_v4());
+    assert_err!(
+        store.create_composite_task_node(dangling).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_composite_task_node with a missing dependency"
+    );
+    let nodes = store.list_composite_task_nodes(task.id).await.unwrap();
+    assert_eq!(
+        ids(&nodes, |n| n.id),
+        [first_node.id, second_node.id],
+        "list_composite_task_nodes orders by creation time"
+    );
+
+    let mut planned = store.get_composite_task(task.id).await.unwrap().unwrap();
+    planned.node_ids = vec![first_node.id, second_node.id];
+    planned.status = CompositeTaskStatus::PendingApproval;
+    let planned = store.update_composite_task(planned).await.unwrap();
+    assert_eq!(planned.revision, 1);
+    let fetched = store.get_composite_task(task.id).await.unwrap().unwrap();
+    assert_same(&fetched, &planned, "get_composite_task returns the update");
+    let filter = TaskFilter {
+        repository_group_id: Some(fixture.group.id),
+        composite_status: Some(CompositeTaskStatus::PendingApproval),
+        ..Default::default()
+    };
+    let (tasks, total) = store.list_composite_tasks(filter).await.unwrap();
+    assert_eq!(total, 1, "list_composite_tasks filters by status");
+    assert_eq!(ids(&tasks, |t| t.id), [task.id]);
+    let mut done = planned.clone();
+    done.status = CompositeTaskStatus::Done;
+    assert_invalid_transition(
+        store.update_composite_task(done).await,
+        "pending_approval",
+        "done",
+    );
+
+    assert_err!(
+        store.delete_composite_task_node(first_node.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "delete_composite_task_node of a dependency"
+    );
+    assert_err!(
+        store.purge_composite_task(task.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "purge_composite_task of a task with nodes"
+    );
+    assert_err!(
+        store.purge_unit_task(first.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "purge_unit_task of a task in a graph"
+    );
+    assert_err!(
+        store.delete_agent_task(planning_task.id).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "delete_agent_task of a planning task"
+    );
+
+    let mut independent = fetched_node(store, second_node.id).await;
+    independent.depends_on_ids.clear();
+    let independent = store.update_composite_task_node(independent).await.unwrap();
+    assert_eq!(independent.revision, 1);
+    store
+        .delete_composite_task_node(first_node.id)
+        .await
+        .unwrap();
+    store
+        .delete_composite_task_node(second_node.id)
+        .await
+        .unwrap();
+    assert!(
+        store
+            .list_composite_task_nodes(task.id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    store.purge_composite_task(task.id).await.unwrap();
+    assert!(store.get_composite_task(task.id).await.unwrap().is_none());
+}
+
+async fn check_plan_revisions(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let planning_task = fixture.agent_task(store).await;
+    let task = store
+        .create_composite_task(CompositeTask::new(
+            fixture.group.id,
+            planning_task.id,
+            "Plan the feature",
+        ))
+        .await
+        .unwrap();
+    let user = store
+        .create_user(User::new(format!("{}@example.com", Uuid::new_v4())))
+        .await
+        .unwrap();
+
+    let mut generated = PlanRevision::from_planning_agent(&task, "tasks: []\n");
+    generated.number = 7;
+    generated.created_at = at(10);
+    let generated = store.create_plan_revision(generated).await.unwrap();
+    assert_eq!(generated.number, 1, "the first revision is number 1");
+    let mut edited = PlanRevision::from_user(&task, user.id, "tasks:\n  - id: a\n");
+    edited.created_at = at(20);
+    let edited = store.create_plan_revision(edited).await.unwrap();
+    assert_eq!(edited.number, 2, "revisions are numbered in order");
+    let fetched = store.get_plan_revision(edited.id).await.unwrap().unwrap();
+    assert_same(&fetched, &edited, "get_plan_revision returns the revision");
+    assert_err!(
+        store
+            .create_plan_revision(PlanRevision::from_user(
+                &CompositeTask::new(fixture.group.id, planning_task.id, "Missing"),
+                user.id,
+                "tasks: []\n",
+            ))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_plan_revision with a missing composite task"
+    );
+    let revisions = store.list_plan_revisions(task.id).await.unwrap();
+    assert_eq!(
+        ids(&revisions, |r| r.id),
+        [generated.id, edited.id],
+        "list_plan_revisions orders by number"
+    );
+
+    assert_err!(
+        store.approve_plan_revision(generated.id, user.id).await,
+        TaskStoreError::Conflict {
+            current_revision: 2,
+            ..
+        },
+        "approve_plan_revision of an older revision"
+    );
+    let approved = store
+        .approve_plan_revision(edited.id, user.id)
+        .await
+        .unwrap();
+    assert_eq!(approved.approved_by, Some(user.id));
+    assert!(approved.is_approved());
+    let again = store
+        .approve_plan_revision(edited.id, Uuid::new_v4())
+        .await
+        .unwrap();
+    assert_eq!(again, approved, "approving twice keeps the first approval");
+
+    store.purge_composite_task(task.id).await.unwrap();
+    assert!(
+        store
+            .get_plan_revision(generated.id)
+            .await
+            .unwrap()
+            .is_none(),
+        "purge_composite_task deletes its plan revisions"
+    );
+    assert!(store.list_plan_revisions(task.id).await.unwrap().is_empty());
+}
+
+async fn fetched_node(store: &dyn TaskStore, id: Uuid) -> CompositeTaskNode {
+    store.get_composite_task_node(id).await.unwrap().unwrap()
+}
+
+async fn check_lookups(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let branch_name = format!("delidev/{}", unique_word());
+    let pr_url = format!(
+        "https://github.com/delinoio/conformance/pull/{}",
+        unique_word()
+    );
+    let older = fixture
+        .unit_task(store, |agent_task_id| {
+            let mut task = UnitTask::new(fixture.group.id, agent_task_id, "First attempt")
+                .with_branch_name(branch_name.clone());
+            task.linked_pr_url = Some(pr_url.clone());
+            task.created_at = at(10);
+            task
+        })
+        .await;
+    let newer = fixture
+        .unit_task(store, |agent_task_id| {
+            let mut task = UnitTask::new(fixture.group.id, agent_task_id, "Second attempt")
+                .with_branch_name(branch_name.clone());
+            task.created_at = at(20);
+            task
+        })
+        .await;
+    store.delete_unit_task(newer.id).await.unwrap();
+
+    let found = |task: Option<UnitTask>| task.map(|t| t.id);
+    assert_eq!(
+        found(store.find_unit_task_by_pr_url(&pr_url).await.unwrap()),
+        Some(older.id),
+        "find_unit_task_by_pr_url"
+    );
+    assert_eq!(
+        found(
+            store
+                .find_unit_task_by_branch_name(&branch_name)
+                .await
+                .unwrap()
+        ),
+        Some(newer.id),
+        "find_unit_task_by_branch_name returns the newest task, even in the trash"
+    );
+    assert!(
+        store
+            .find_unit_task_by_branch_name(&unique_word())
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    let auto_fix_task = fixture.agent_task(store).await;
+    assert!(
+        store
+            .find_unit_task_by_agent_task(auto_fix_task.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let mut fixing = store.get_unit_task(older.id).await.unwrap().unwrap();
+    fixing.auto_fix_task_ids.push(auto_fix_task.id);
+    store.update_unit_task(fixing).await.unwrap();
+    assert_eq!(
+        found(
+            store
+                .find_unit_task_by_agent_task(older.agent_task_id)
+                .await
+                .unwrap()
+        ),
+        Some(older.id),
+        "find_unit_task_by_agent_task finds the main agent task"
+    );
+    assert_eq!(
+        found(
+            store
+                .find_unit_task_by_agent_task(auto_fix_task.id)
+                .await
+                .unwrap()
+        ),
+        Some(older.id),
+        "find_unit_task_by_agent_task finds an auto-fix task added by an update"
+    );
+
+    let planning_task = fixture.agent_task(store).await;
+    let composite = store
+        .create_composite_task(CompositeTask::new(
+            fixture.group.id,
+            planning_task.id,
+            "Build the feature",
+        ))
+        .await
+        .unwrap();
+    store
+        .create_composite_task_node(CompositeTaskNode::new(composite.id, older.id))
+        .await
+        .unwrap();
+    let parent = store
+        .find_composite_task_by_node_unit_task(older.id)
+        .await
+        .unwrap();
+    assert_eq!(
+        parent.map(|t| t.id),
+        Some(composite.id),
+        "find_composite_task_by_node_unit_task"
+    );
+    assert!(
+        store
+            .find_composite_task_by_node_unit_task(newer.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+}
+
+async fn check_todo_items(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let repository_id = fixture.repository.id;
+    let triage = TodoItem::issue_triage(
+        repository_id,
+        "https://github.com/delinoio/conformance/issues/1".to_string(),
+        "Crash on start".to_string(),
+    );
+    let created = store.create_todo_item(triage.clone()).await.unwrap();
+    assert_same(&created, &triage, "create_todo_item returns the item");
+    let review = TodoItem::pr_review(
+        repository_id,
+        "https://github.com/delinoio/conformance/pull/2".to_string(),
+        "Add feature".to_string(),
+        7,
+    );
+    store.create_todo_item(review.clone()).await.unwrap();
+    let fetched = store.get_todo_item(review.id).await.unwrap().unwrap();
+    assert_same(&fetched, &review, "todo item data round-trips");
+    assert_err!(
+        store
+            .create_todo_item(TodoItem::issue_triage(
+                Uuid::new_v4(),
+                "https://github.com/delinoio/missing/issues/1".to_string(),
+                "Dangling".to_string(),
+            ))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_todo_item for a missing repository"
+    );
+
+    let mut started = fetched;
+    started.status = TodoItemStatus::InProgress;
+    let started = store.update_todo_item(started).await.unwrap();
+    assert_eq!(started.revision, 1);
+    let filter = TodoFilter {
+        repository_id: Some(repository_id),
+        ..Default::default()
+    };
+    let (_, total) = store.list_todo_items(filter.clone()).await.unwrap();
+    assert_eq!(total, 2, "list_todo_items counts the repository's items");
+    let in_progress = TodoFilter {
+        status: Some(TodoItemStatus::InProgress),
+        ..filter.clone()
+    };
+    let (items, total) = store.list_todo_items(in_progress).await.unwrap();
+    assert_eq!(total, 1, "list_todo_items filters by status");
+    assert_eq!(ids(&items, |i| i.id), [review.id]);
+
+    let mut completed = started;
+    completed.status = TodoItemStatus::Completed;
+    let completed = store.update_todo_item(completed).await.unwrap();
+    let mut reopened = completed;
+    reopened.status = TodoItemStatus::Pending;
+    assert_invalid_transition(
+        store.update_todo_item(reopened).await,
+        "completed",
+        "pending",
+    );
+
+    store.delete_todo_item(triage.id).await.unwrap();
+    assert!(store.get_todo_item(triage.id).await.unwrap().is_none());
+    let (_, total) = store.list_todo_items(filter).await.unwrap();
+    assert_eq!(total, 1);
+}
+
+async fn check_tty_input_requests(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Ask questions")
+        })
+        .await;
+    let session = fixture.session(store, &task).await;
+    let other_session = fixture.session(store, &task).await;
+    let select = TtyInputRequest::new(task.id, session.id, "Pick one")
+        .with_options(vec!["a".to_string(), "b".to_string()]);
+    let created = store
+        .create_tty_input_request(select.clone())
+        .await
+        .unwrap();
+    assert_same(
+        &created,
+        &select,
+        "create_tty_input_request returns the request",
+    );
+    let fetched = store
+        .get_tty_input_request(select.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_same(
+        &fetched,
+        &select,
+        "get_tty_input_request returns the request",
+    );
+    let other = store
+        .create_tty_input_request(TtyInputRequest::new(task.id, other_session.id, "Name?"))
+        .await
+        .unwrap();
+    assert_err!(
+        store
+            .create_tty_input_request(TtyInputRequest::new(task.id, Uuid::new_v4(), "Dangling"))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "create_tty_input_request for a missing session"
+    );
+
+    let mut answered = fetched;
+    answered.respond("b");
+    let answered = store.update_tty_input_request(answered).await.unwrap();
+    assert_eq!(answered.revision, 1);
+    let fetched = store
+        .get_tty_input_request(select.id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fetched.status, TtyInputStatus::Responded);
+    assert_eq!(fetched.response.as_deref(), Some("b"));
+    assert!(fetched.responded_at.is_some());
+    let mut reopened = fetched;
+    reopened.status = TtyInputStatus::Pending;
+    assert_invalid_transition(
+        store.update_tty_input_request(reopened).await,
+        "responded",
+        "pending",
+    );
+
+    let by_task = TtyInputFilter {
+        task_id: Some(task.id),
+        ..Default::default()
+    };
+    let requests = store
+        .list_tty_input_requests(by_task.clone())
+        .await
+        .unwrap();
+    assert_eq!(requests.len(), 2, "list_tty_input_requests filters by task");
+    let by_session = TtyInputFilter {
+        session_id: Some(other_session.id),
+        ..Default::default()
+    };
+    let requests = store.list_tty_input_requests(by_session).await.unwrap();
+    assert_eq!(ids(&requests, |r| r.id), [other.id]);
+    let pending = TtyInputFilter {
+        status: Some(TtyInputStatus::Pending),
+        ..by_task.clone()
+    };
+    let requests = store.list_tty_input_requests(pending).await.unwrap();
+    assert_eq!(ids(&requests, |r| r.id), [other.id]);
+
+    store.delete_tty_input_request(select.id).await.unwrap();
+    assert!(
+        store
+            .get_tty_input_request(select.id)
+            .await
+            .unwrap()
+            .is_none()
+    );
+    let requests = store.list_tty_input_requests(by_task).await.unwrap();
+    assert_eq!(ids(&requests, |r| r.id), [other.id]);
+}
+
+async fn check_workers(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Run on a worker")
+        })
+        .await;
+    // A capability no other check or run advertises scopes the lists to
+    // this check's workers.
+    let capability = unique_word();
+    let register = |name: &'static str, offset: i64| {
+        let capability = capability.clone();
+        async move {
+            let mut worker = Worker::new(name, format!("http://{name}:54872"))
+                .with_capabilities(vec![capability, "claude_code".to_string()]);
+            worker.created_at = at(offset);
+            worker.last_heartbeat = at(offset);
+            let registered = store.register_worker(worker.clone()).await.unwrap();
+            assert_same(&registered, &worker, "register_worker returns the worker");
+            worker
+        }
+    };
+    let first = register("worker-b", 0).await;
+    let second = register("worker-a", 1).await;
+    let fetched = store.get_worker(first.id).await.unwrap().unwrap();
+    assert_same(&fetched, &first, "get_worker returns the worker");
+    assert_eq!(fetched.capabilities, first.capabilities);
+    assert_err!(
+        store.register_worker(first.clone()).await,
+        TaskStoreError::AlreadyExists { .. },
+        "register_worker with a duplicate ID"
+    );
+    let mut dangling = Worker::new("worker-c", "http://worker-c:54872");
+    dangling.current_task_id = Some(Uuid::new_v4());
+    assert_err!(
+        store.register_worker(dangling).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "register_worker with a missing current task"
+    );
+
+    let by_capability = WorkerFilter {
+        capability: Some(capability.clone()),
+        sort: SortOrder::new(SortKey::Title),
+        ..Default::default()
+    };
+    let (workers, total) = store.list_workers(by_capability.clone()).await.unwrap();
+    assert_eq!(total, 2);
+    assert_eq!(
+        ids(&workers, |w| w.id),
+        [second.id, first.id],
+        "list_workers sorts by name"
+    );
+
+    let busy = store
+        .record_worker_heartbeat(first.id, WorkerStatus::Busy, Some(task.id))
+        .await
+        .unwrap();
+    assert_eq!(busy.status, WorkerStatus::Busy);
+    assert_eq!(busy.current_task_id, Some(task.id));
+    assert_eq!(
+        busy.revision, 1,
+        "a heartbeat changing the status is an update"
+    );
+    assert!(busy.last_heartbeat > first.last_heartbeat);
+    let alive = store
+        .record_worker_heartbeat(first.id, WorkerStatus::Busy, Some(task.id))
+        .await
+        .unwrap();
+    assert_eq!(alive.revision, 1, "a liveness heartbeat keeps the revision");
+    assert!(alive.last_heartbeat >= busy.last_heartbeat);
+    assert_err!(
+        store
+            .record_worker_heartbeat(first.id, WorkerStatus::Busy, Some(Uuid::new_v4()))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "record_worker_heartbeat with a missing current task"
+    );
+    assert_err!(
+        store
+            .record_worker_heartbeat(Uuid::new_v4(), WorkerStatus::Idle, None)
+            .await,
+        TaskStoreError::NotFound { .. },
+        "record_worker_heartbeat for a missing worker"
+    );
+    let busy_filter = WorkerFilter {
+        status: Some(WorkerStatus::Busy),
+        ..by_capability.clone()
+    };
+    let (workers, _) = store.list_workers(busy_filter).await.unwrap();
+    assert_eq!(ids(&workers, |w| w.id), [first.id]);
+
+    // The second worker still has its backdated heartbeat.
+    let stale = store.mark_stale_workers(at(2)).await.unwrap();
+    assert_eq!(
+        ids(&stale, |w| w.id),
+        [second.id],
+        "mark_stale_workers skips workers with recent heartbeats"
+    );
+    assert_eq!(stale[0].status, WorkerStatus::Unhealthy);
+    assert_eq!(stale[0].revision, 1);
+    assert!(
+        store.mark_stale_workers(at(2)).await.unwrap().is_empty(),
+        "mark_stale_workers skips unhealthy workers"
+    );
+
+    let mut renamed = store.get_worker(second.id).await.unwrap().unwrap();
+    renamed.name = "worker-z".to_string();
+    renamed.capabilities.push("aider".to_string());
+    let renamed = store.update_worker(renamed).await.unwrap();
+    assert_eq!(renamed.revision, 2);
+    let fetched = store.get_worker(second.id).await.unwrap().unwrap();
+    assert_same(&fetched, &renamed, "get_worker returns the update");
+    assert_err!(
+        store.update_worker(stale[0].clone()).await,
+        TaskStoreError::Conflict { .. },
+        "update_worker with a stale revision"
+    );
+
+    // A heartbeat racing a stale sweep either lands first, leaving nothing
+    // to sweep, or revives the swept worker. Neither loses the other's
+    // update.
+    let racer = register("worker-r", 2).await;
+    for _ in 0..10 {
+        let mut backdated = store.get_worker(racer.id).await.unwrap().unwrap();
+        backdated.last_heartbeat = at(2);
+        let backdated = store.update_worker(backdated).await.unwrap();
+        let (swept, beat) = futures::join!(
+            store.mark_stale_workers(at(3)),
+            store.record_worker_heartbeat(racer.id, WorkerStatus::Idle, None)
+        );
+        let swept = swept.unwrap().iter().any(|w| w.id == racer.id);
+        let beat = beat.unwrap();
+        let fetched = store.get_worker(racer.id).await.unwrap().unwrap();
+        assert_same(&fetched, &beat, "the heartbeat is not overwritten");
+        assert_eq!(fetched.status, WorkerStatus::Idle);
+        assert_eq!(
+            fetched.revision,
+            backdated.revision + if swept { 2 } else { 0 },
+            "a sweep before the heartbeat keeps its revision"
+        );
+    }
+    store.delete_worker(racer.id).await.unwrap();
+
+    store.purge_unit_task(task.id).await.unwrap();
+    let fetched = store.get_worker(first.id).await.unwrap().unwrap();
+    assert_eq!(
+        fetched.current_task_id, None,
+        "purging the current task clears it"
+    );
+
+    store.delete_worker(first.id).await.unwrap();
+    assert!(store.get_worker(first.id).await.unwrap().is_none());
+    assert_err!(
+        store.delete_worker(first.id).await,
+        TaskStoreError::NotFound { .. },
+        "delete_worker for a missing worker"
+    );
+    let (workers, total) = store.list_workers(by_capability).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(ids(&workers, |w| w.id), [second.id]);
+}
+
+async fn check_work_queue(store: &dyn TaskStore) {
+    // Entries are backdated so they are the oldest in a shared database and
+    // get claimed first.
+    let enqueue = |offset: i64, max_attempts| async move {
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let mut entry = QueueEntry::new(agent_task.id).with_max_attempts(max_attempts);
+        entry.created_at = at(offset - 1_000_000);
+        entry.updated_at = entry.created_at;
+        let queued = store.enqueue_task(entry.clone()).await.unwrap();
+        assert_same(&queued, &entry, "enqueue_task returns the entry");
+        entry
+    };
+    let first = enqueue(0, 2).await;
+    let second = enqueue(1, 1).await;
+    let fetched = store.get_queue_entry(first.id).await.unwrap().unwrap();
+    assert_same(&fetched, &first, "get_queue_entry returns the entry");
+    assert_err!(
+        store
+            .enqueue_task(QueueEntry::new(first.agent_task_id))
+            .await,
+        TaskStoreError::AlreadyExists { .. },
+        "enqueue_task for an agent task already in the queue"
+    );
+    assert_err!(
+        store.enqueue_task(QueueEntry::new(Uuid::new_v4())).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "enqueue_task for a missing agent task"
+    );
+
+    let worker = Uuid::new_v4();
+    let other_worker = Uuid::new_v4();
+    let claimed = store
+        .claim_task(worker, TimeDelta::minutes(1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claimed.id, first.id, "claim_task takes the oldest entry");
+    assert_eq!(claimed.status, QueueStatus::Leased);
+    assert_eq!(claimed.attempts, 1);
+    assert_eq!(claimed.worker_id, Some(worker));
+    assert_eq!(claimed.revision, 1);
+    // A zero lease expires at once.
+    let expired = store
+        .claim_task(other_worker, TimeDelta::zero())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(expired.id, second.id);
+    let by_worker = QueueFilter {
+        worker_id: Some(worker),
+        ..Default::default()
+    };
+    let (entries, total) = store.list_queue_entries(by_worker).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(ids(&entries, |e| e.id), [first.id]);
+
+    let renewed = store
+        .renew_leases(worker, TimeDelta::minutes(2))
+        .await
+        .unwrap();
+    assert_eq!(ids(&renewed, |e| e.id), [first.id]);
+    assert!(renewed[0].lease_expires_at > claimed.lease_expires_at);
+    assert!(
+        store
+            .renew_leases(other_worker, TimeDelta::minutes(2))
+            .await
+            .unwrap()
+            .is_empty(),
+        "renew_leases skips expired leases"
+    );
+    assert_err!(
+        store.complete_task(second.id, other_worker).await,
+        TaskStoreError::LeaseNotHeld { .. },
+        "complete_task after the lease expired"
+    );
+    assert_err!(
+        store.complete_task(first.id, other_worker).await,
+        TaskStoreError::LeaseNotHeld { .. },
+        "complete_task by another worker"
+    );
+    assert_err!(
+        store.complete_task(Uuid::new_v4(), worker).await,
+        TaskStoreError::NotFound { .. },
+        "complete_task for a missing entry"
+    );
+
+    assert!(store.requeue_expired_leases().await.unwrap() >= 1);
+    let failed = store.get_queue_entry(second.id).await.unwrap().unwrap();
+    assert_eq!(
+        failed.status,
+        QueueStatus::Failed,
+        "an expired lease without attempts left fails the entry"
+    );
+    assert_eq!(failed.worker_id, None);
+    assert!(failed.last_error.is_some());
+
+    let retried = store
+        .fail_task(first.id, worker, "Agent crashed".to_string())
+        .await
+        .unwrap();
+    assert_eq!(retried.status, QueueStatus::Queued);
+    assert_eq!(retried.last_error.as_deref(), Some("Agent crashed"));
+    let claimed = store
+        .claim_task(other_worker, TimeDelta::minutes(1))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(claimed.id, first.id, "a failed attempt requeues the entry");
+    assert_eq!(claimed.attempts, 2);
+    let completed = store.complete_task(first.id, other_worker).await.unwrap();
+    assert_eq!(completed.status, QueueStatus::Completed);
+    assert_eq!(completed.lease_expires_at, None);
+
+    // Concurrent claims never hand out the same entry twice.
+    let mut queued = Vec::new();
+    for offset in 10..15 {
+        queued.push(enqueue(offset, 1).await.id);
+    }
+    let claims =
+        join_all((0..8).map(|_| store.claim_task(Uuid::new_v4(), TimeDelta::minutes(1)))).await;
+    let claimed: Vec<QueueEntry> = claims.into_iter().filter_map(Result::unwrap).collect();
+    let claimed_ids: HashSet<Uuid> = claimed.iter().map(|e| e.id).collect();
+    assert_eq!(claimed_ids.len(), claimed.len(), "claims are exclusive");
+    for id in &queued {
+        assert!(claimed_ids.contains(id), "every queued entry is claimed");
+    }
+    for entry in &claimed {
+        store
+            .complete_task(entry.id, entry.worker_id.unwrap())
+            .await
+            .unwrap();
+    }
+
+    store.delete_agent_task(first.agent_task_id).await.unwrap();
+    assert!(
+        store.get_queue_entry(first.id).await.unwrap().is_none(),
+        "deleting an agent task removes its queue entry"
+    );
+}
+
+async fn check_audit_log(store: &dyn TaskStore) {
+    let user_id = Uuid::new_v4();
+    let worker_id = Uuid::new_v4();
+    let workspace = store
+        .create_workspace(Workspace::new("Audited"))
+        .await
+        .unwrap();
+    let event = |actor, action: &str, offset| {
+        let mut event = AuditEvent::new(actor, action, "Workspace", workspace.id);
+        event.created_at = at(offset);
+        event
+    };
+    let created = event(AuditActor::user(user_id), "workspace.create", 0)
+        .with_after(&workspace)
+        .unwrap();
+    let mut renamed = workspace.clone();
+    renamed.name = "Renamed".to_string();
+    let updated = event(AuditActor::user(user_id), "workspace.update", 10)
+        .with_before(&workspace)
+        .unwrap()
+        .with_after(&renamed)
+        .unwrap();
+    let deleted = event(AuditActor::worker(worker_id), "workspace.delete", 20)
+        .with_before(&renamed)
+        .unwrap();
+    let mut elsewhere = AuditEvent::new(
+        AuditActor::user(user_id),
+        "repository.delete",
+        "Repository",
+        Uuid::new_v4(),
+    );
+    elsewhere.created_at = at(5);
+    for event in [&created, &updated, &deleted, &elsewhere] {
+        let recorded = store.record_audit_event(event.clone()).await.unwrap();
+        assert_eq!(&recorded, event, "record_audit_event returns the event");
+    }
+    let fetched = store.get_audit_event(updated.id).await.unwrap().unwrap();
+    assert_eq!(fetched, updated, "get_audit_event keeps both snapshots");
+    assert_eq!(fetched.before.unwrap()["name"], "Audited");
+    assert_err!(
+        store.record_audit_event(created.clone()).await,
+        TaskStoreError::AlreadyExists { .. },
+        "record_audit_event with a duplicate ID"
+    );
+
+    let by_entity = AuditFilter {
+        entity_type: Some("Workspace".to_string()),
+        entity_id: Some(workspace.id),
+        ..Default::default()
+    };
+    let (events, total) = store.list_audit_events(by_entity.clone()).await.unwrap();
+    assert_eq!(total, 3);
+    assert_eq!(
+        ids(&events, |e| e.id),
+        [created.id, updated.id, deleted.id],
+        "list_audit_events filters by entity, oldest first"
+    );
+    let by_user = AuditFilter {
+        actor_type: Some(AuditActorType::User),
+        actor_id: Some(user_id),
+        ..Default::default()
+    };
+    let (events, _) = store.list_audit_events(by_user).await.unwrap();
+    assert_eq!(
+        ids(&events, |e| e.id),
+        [created.id, elsewhere.id, updated.id],
+        "list_audit_events filters by actor"
+    );
+    let by_worker = AuditFilter {
+        actor_type: Some(AuditActorType::Worker),
+        ..by_entity.clone()
+    };
+    let (events, _) = store.list_audit_events(by_worker).await.unwrap();
+    assert_eq!(ids(&events, |e| e.id), [deleted.id]);
+    let in_range = AuditFilter {
+        since: Some(at(10)),
+        until: Some(at(20)),
+        ..by_entity.clone()
+    };
+    let (events, total) = store.list_audit_events(in_range).await.unwrap();
+    assert_eq!(total, 1);
+    assert_eq!(
+        ids(&events, |e| e.id),
+        [updated.id],
+        "list_audit_events filters by time range, excluding the end"
+    );
+
+    let newest_first = AuditFilter {
+        sort: SortOrder::default().descending(),
+        limit: Some(2),
+        ..by_entity.clone()
+    };
+    let (page, _) = store.list_audit_events(newest_first.clone()).await.unwrap();
+    assert_eq!(ids(&page, |e| e.id), [deleted.id, updated.id]);
+    let after = Cursor::after(page.last().unwrap(), SortKey::CreatedAt).unwrap();
+    let (page, total) = store
+        .list_audit_events(AuditFilter {
+            after: Some(after),
+            ..newest_first
+        })
+        .await
+        .unwrap();
+    assert_eq!(ids(&page, |e| e.id), [created.id]);
+    assert_eq!(total, 3);
+    assert_err!(
+        store
+            .list_audit_events(AuditFilter {
+                sort: SortOrder::new(SortKey::Title),
+                ..by_entity.clone()
+            })
+            .await,
+        TaskStoreError::InvalidQuery(_),
+        "list_audit_events sorted by title"
+    );
+
+    let tx = store.begin().await.unwrap();
+    let rolled_back = tx
+        .record_audit_event(event(AuditActor::system(), "workspace.purge", 30))
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+    assert!(
+        store
+            .get_audit_event(rolled_back.id)
+            .await
+            .unwrap()
+            .is_none(),
+        "rolling back a transaction discards its audit events"
+    );
+}
+
+/// Returns the sequence, offset and content of each chunk, leaving out
+/// timestamps.
+fn chunk_positions(chunks: &[LogChunk]) -> Vec<(u64, u64, &str)> {
+    chunks
+        .iter()
+        .map(|chunk| (chunk.sequence, chunk.offset, chunk.content.as_str()))
+        .collect()
+}
+
+async fn check_session_logs(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Write logs")
+        })
+        .await;
+    let session = fixture.session(store, &task).await;
+    assert_eq!(
+        store.session_log_size(session.id).await.unwrap(),
+        LogSize::default(),
+        "a new session has an empty log"
+    );
+
+    let mut appended = Vec::new();
+    for content in ["Cloning\n", "Édition\n", "Done\n"] {
+        let chunk = store
+            .append_session_log(session.id, content.to_string())
+            .await
+            .unwrap();
+        assert_eq!(chunk.session_id, session.id);
+        appended.push(chunk);
+    }
+    let expected = [(0, 0, "Cloning\n"), (1, 8, "Édition\n"), (2, 17, "Done\n")];
+    assert_eq!(
+        chunk_positions(&appended),
+        expected,
+        "chunks are numbered in append order and offsets count bytes"
+    );
+    assert_eq!(
+        store.session_log_size(session.id).await.unwrap(),
+        LogSize {
+            chunks: 3,
+            bytes: 22
+        }
+    );
+
+    let read = |range| async move { store.read_session_log(session.id, range).await.unwrap() };
+    assert_eq!(chunk_positions(&read(LogRange::all()).await), expected);
+    let range = LogRange::Sequence {
+        start: 1,
+        end: Some(2),
+    };
+    assert_eq!(chunk_positions(&read(range).await), expected[1..2]);
+    let range = LogRange::Sequence {
+        start: 5,
+        end: None,
+    };
+    assert!(
+        read(range).await.is_empty(),
+        "no chunks follow the last one"
+    );
+    let range = LogRange::Bytes {
+        start: 7,
+        end: Some(9),
+    };
+    assert_eq!(
+        chunk_positions(&read(range).await),
+        expected[..2],
+        "byte ranges return every chunk they overlap"
+    );
+    let range = LogRange::Bytes {
+        start: 17,
+        end: None,
+    };
+    assert_eq!(chunk_positions(&read(range).await), expected[2..]);
+    assert_eq!(
+        chunk_positions(&read(LogRange::Tail(2)).await),
+        expected[1..],
+        "tails are returned oldest first"
+    );
+    assert_eq!(read(LogRange::Tail(10)).await.len(), 3);
+
+    let missing = Uuid::new_v4();
+    assert_err!(
+        store.append_session_log(missing, "Lost".to_string()).await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "append_session_log to a missing session"
+    );
+    assert!(
+        store
+            .read_session_log(missing, LogRange::all())
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(
+        store.session_log_size(missing).await.unwrap(),
+        LogSize::default()
+    );
+
+    let tx = store.begin().await.unwrap();
+    let chunk = tx
+        .append_session_log(session.id, "Discarded\n".to_string())
+        .await
+        .unwrap();
+    assert_eq!(chunk.sequence, 3, "a transaction appends after the log");
+    tx.rollback().await.unwrap();
+    assert_eq!(
+        store.session_log_size(session.id).await.unwrap().chunks,
+        3,
+        "rollback discards appended chunks"
+    );
+
+    store.delete_agent_session(session.id).await.unwrap();
+    assert!(
+        store
+            .read_session_log(session.id, LogRange::all())
+            .await
+            .unwrap()
+            .is_empty(),
+        "a session's log is deleted with it"
+    );
+}
+
+// =============================================================================
+// Cross-cutting checks
+// =============================================================================
+
+/// Gets, updates and deletes of rows that do not exist.
+async fn check_missing_rows(store: &dyn TaskStore) {
+    let id = Uuid::new_v4();
+    let other = Uuid::new_v4();
+    assert!(store.get_user(id).await.unwrap().is_none());
+    assert!(store.get_workspace(id).await.unwrap().is_none());
+    assert!(store.get_repository(id).await.unwrap().is_none());
+    assert!(store.get_repository_group(id).await.unwrap().is_none());
+    assert!(store.get_agent_task(id).await.unwrap().is_none());
+    assert!(store.get_agent_session(id).await.unwrap().is_none());
+    assert!(store.get_unit_task(id).await.unwrap().is_none());
+    assert!(store.get_composite_task(id).await.unwrap().is_none());
+    assert!(store.get_composite_task_node(id).await.unwrap().is_none());
+    assert!(store.get_todo_item(id).await.unwrap().is_none());
+    assert!(store.get_tty_input_request(id).await.unwrap().is_none());
+    assert!(store.list_agent_sessions(id).await.unwrap().is_empty());
+    assert!(
+        store
+            .list_composite_task_nodes(id)
+            .await
+            .unwrap()
+            .is_empty()
+    );
+    assert!(
+        store
+            .list_repository_groups(Some(id))
+            .await
+            .unwrap()
+            .is_empty()
+    );
+
+    macro_rules! assert_not_found {
+        ($result:expr) => {
+            assert_err!(
+                $result,
+                TaskStoreError::NotFound { .. },
+                stringify!($result)
+            )
+        };
+    }
+    assert_not_found!(store.update_user(User::new("missing@example.com")).await);
+    assert_not_found!(store.update_workspace(Workspace::new("Missing")).await);
+    assert_not_found!(
+        store
+            .update_repository(Repository::new(
+                id,
+                "missing",
+                "https://github.com/delinoio/missing",
+                VcsProviderType::Github,
+            ))
+            .await
+    );
+    assert_not_found!(
+        store
+            .update_repository_group(RepositoryGroup::new(id))
+            .await
+    );
+    assert_not_found!(store.update_agent_task(AgentTask::new()).await);
+    assert_not_found!(
+        store
+            .update_agent_session(AgentSession::new(id, AiAgentType::ClaudeCode))
+            .await
+    );
+    assert_not_found!(
+        store
+            .update_unit_task(UnitTask::new(id, other, "Missing"))
+            .await
+    );
+    assert_not_found!(
+        store
+            .update_composite_task(CompositeTask::new(id, other, "Missing"))
+            .await
+    );
+    assert_not_found!(
+        store
+            .update_composite_task_node(CompositeTaskNode::new(id, other))
+            .await
+    );
+    assert_not_found!(
+        store
+            .update_todo_item(TodoItem::issue_triage(
+                id,
+                "https://github.com/delinoio/missing/issues/1".to_string(),
+                "Missing".to_string(),
+            ))
+            .await
+    );
+    assert_not_found!(
+        store
+            .update_tty_input_request(TtyInputRequest::new(id, other, "Missing"))
+            .await
+    );
+
+    assert_not_found!(store.delete_user(id).await);
+    assert_not_found!(store.delete_workspace(id).await);
+    assert_not_found!(store.restore_workspace(id).await);
+    assert_not_found!(store.purge_workspace(id).await);
+    assert_not_found!(store.delete_repository(id).await);
+    assert_not_found!(store.restore_repository(id).await);
+    assert_not_found!(store.purge_repository(id).await);
+    assert_not_found!(store.delete_repository_group(id).await);
+    assert_not_found!(store.delete_agent_task(id).await);
+    assert_not_found!(store.delete_agent_session(id).await);
+    assert_not_found!(store.delete_unit_task(id).await);
+    assert_not_found!(store.restore_unit_task(id).await);
+    assert_not_found!(store.purge_unit_task(id).await);
+    assert_not_found!(store.delete_composite_task(id).await);
+    assert_not_found!(store.restore_composite_task(id).await);
+    assert_not_found!(store.purge_composite_task(id).await);
+    assert_not_found!(store.delete_composite_task_node(id).await);
+    assert_not_found!(store.approve_plan_revision(id, id).await);
+    assert_not_found!(store.delete_todo_item(id).await);
+    assert_not_found!(store.delete_tty_input_request(id).await);
+}
+
+/// Creates of rows whose ID is already taken.
+async fn check_duplicate_rows(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Duplicate")
+        })
+        .await;
+    let session = fixture.session(store, &task).await;
+    let planning_task = fixture.agent_task(store).await;
+    let composite = store
+        .create_composite_task(CompositeTask::new(
+            fixture.group.id,
+            planning_task.id,
+            "Duplicate",
+        ))
+        .await
+        .unwrap();
+    let node = store
+        .create_composite_task_node(CompositeTaskNode::new(composite.id, task.id))
+        .await
+        .unwrap();
+    let item = store
+        .create_todo_item(TodoItem::issue_triage(
+            fixture.repository.id,
+            "https://github.com/delinoio/conformance/issues/3".to_string(),
+            "Duplicate".to_string(),
+        ))
+        .await
+        .unwrap();
+    let request = store
+        .create_tty_input_request(TtyInputRequest::new(task.id, session.id, "Duplicate?"))
+        .await
+        .unwrap();
+    let user = store
+        .create_user(User::new(format!("{}@example.com", Uuid::new_v4())))
+        .await
+        .unwrap();
+    let agent_task = store
+        .get_agent_task(planning_task.id)
+        .await
+        .unwrap()
+        .unwrap();
+
+    macro_rules! assert_already_exists {
+        ($result:expr) => {
+            assert_err!(
+                $result,
+                TaskStoreError::AlreadyExists { .. },
+                stringify!($result)
+            )
+        };
+    }
+    assert_already_exists!(store.create_user(user).await);
+    assert_already_exists!(store.create_workspace(fixture.workspace.clone()).await);
+    assert_already_exists!(store.create_repository(fixture.repository.clone()).await);
+    assert_already_exists!(store.create_repository_group(fixture.group.clone()).await);
+    assert_already_exists!(store.create_agent_task(agent_task).await);
+    assert_already_exists!(store.create_agent_session(session).await);
+    assert_already_exists!(store.create_unit_task(task).await);
+    assert_already_exists!(store.create_composite_task(composite).await);
+    assert_already_exists!(store.create_composite_task_node(node).await);
+    assert_already_exists!(store.create_todo_item(item).await);
+    assert_already_exists!(store.create_tty_input_request(request).await);
+}
+
+/// Sort keys, directions and ties.
+async fn check_list_ordering(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let group_id = fixture.group.id;
+    // (title, status, created_at, updated_at)
+    let specs = [
+        ("charlie", UnitTaskStatus::InReview, 30, 40),
+        ("alpha", UnitTaskStatus::InProgress, 10, 60),
+        ("bravo", UnitTaskStatus::Rejected, 20, 50),
+    ];
+    let mut tasks = Vec::new();
+    for (title, status, created, updated) in specs {
+        let task = fixture
+            .unit_task(store, |agent_task_id| {
+                let mut task = UnitTask::new(group_id, agent_task_id, "Ordered").with_title(title);
+                task.status = status;
+                task.created_at = at(created);
+                task.updated_at = at(updated);
+                task
+            })
+            .await;
+        tasks.push(task);
+    }
+    let [charlie, alpha, bravo] = [tasks[0].id, tasks[1].id, tasks[2].id];
+
+    let list = |sort: SortOrder| {
+        let filter = TaskFilter {
+            repository_group_id: Some(group_id),
+            sort,
+            ..Default::default()
+        };
+        async move {
+            let (tasks, _) = store.list_unit_tasks(filter).await.unwrap();
+            tasks
+        }
+    };
+    let listed = list(SortOrder::default()).await;
+    assert_eq!(
+        ids(&listed, |t| t.id),
+        [alpha, bravo, charlie],
+        "lists default to ascending creation time"
+    );
+    assert_eq!(listed[0].created_at, at(10), "timestamps round-trip");
+    assert_eq!(listed[0].updated_at, at(60), "timestamps round-trip");
+    let listed = list(SortOrder::new(SortKey::UpdatedAt).descending()).await;
+    assert_eq!(ids(&listed, |t| t.id), [alpha, bravo, charlie]);
+    let listed = list(SortOrder::new(SortKey::Title)).await;
+    assert_eq!(ids(&listed, |t| t.id), [alpha, bravo, charlie]);
+    // Statuses compare by their snake_case names.
+    let listed = list(SortOrder::new(SortKey::Status)).await;
+    assert_eq!(ids(&listed, |t| t.id), [alpha, charlie, bravo]);
+    let listed = list(SortOrder::new(SortKey::Status).descending()).await;
+    assert_eq!(ids(&listed, |t| t.id), [bravo, charlie, alpha]);
+
+    // Equal sort values are ordered by ID in the sort direction.
+    let workspace = store
+        .create_workspace(Workspace::new("Ties"))
+        .await
+        .unwrap();
+    let mut repository_ids = Vec::new();
+    for name in ["same", "same", "same"] {
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                name,
+                format!("https://github.com/delinoio/{}", Uuid::new_v4()),
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        repository_ids.push(repository.id);
+    }
+    repository_ids.sort();
+    let mut filter = RepositoryFilter {
+        workspace_id: Some(workspace.id),
+        sort: SortOrder::new(SortKey::Title),
+        ..Default::default()
+    };
+    let (repositories, _) = store.list_repositories(filter.clone()).await.unwrap();
+    assert_eq!(
+        ids(&repositories, |r| r.id),
+        repository_ids,
+        "ties break by ID"
+    );
+    filter.sort = filter.sort.descending();
+    let (repositories, _) = store.list_repositories(filter).await.unwrap();
+    repository_ids.reverse();
+    assert_eq!(ids(&repositories, |r| r.id), repository_ids);
+}
+
+/// Limits, offsets, cursors and totals.
+async fn check_pagination(store: &dyn TaskStore) {
+    let workspace = store
+        .create_workspace(Workspace::new("Pages"))
+        .await
+        .unwrap();
+    for name in ["echo", "charlie", "alpha", "delta", "bravo"] {
+        store
+            .create_repository(Repository::new(
+                workspace.id,
+                name,
+                format!("https://github.com/delinoio/{name}"),
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+    }
+    let names = |repositories: &[Repository]| {
+        repositories
+            .iter()
+            .map(|r| r.name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let mut filter = RepositoryFilter {
+        workspace_id: Some(workspace.id),
+        sort: SortOrder::new(SortKey::Title),
+        limit: Some(2),
+        ..Default::default()
+    };
+    let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+    assert_eq!(total, 5, "totals ignore the limit");
+    assert_eq!(names(&page), ["alpha", "bravo"]);
+
+    filter.offset = Some(1);
+    let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+    assert_eq!(total, 5, "totals ignore the offset");
+    assert_eq!(names(&page), ["bravo", "charlie"]);
+
+    // Cursors survive a round trip through their string form, and the
+    // offset applies after the cursor.
+    let cursor = Cursor::after(&page[0], SortKey::Title).unwrap();
+    filter.after = Some(cursor.to_string().parse().unwrap());
+    let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+    assert_eq!(total, 5, "totals ignore the cursor");
+    assert_eq!(names(&page), ["delta", "echo"]);
+
+    filter.offset = None;
+    filter.after = Some(Cursor::after(&page[1], SortKey::Title).unwrap());
+    let (page, _) = store.list_repositories(filter.clone()).await.unwrap();
+    assert!(page.is_empty(), "no rows follow the last row");
+
+    filter.sort = filter.sort.descending();
+    filter.limit = None;
+    let (page, _) = store.list_repositories(filter.clone()).await.unwrap();
+    assert_eq!(names(&page), ["delta", "charlie", "bravo", "alpha"]);
+
+    filter.sort = SortOrder::new(SortKey::Status);
+    filter.after = None;
+    assert_err!(
+        store.list_repositories(filter.clone()).await,
+        TaskStoreError::InvalidQuery(_),
+        "list_repositories sorted by status"
+    );
+    filter.sort = SortOrder::new(SortKey::CreatedAt);
+    filter.after = Some(Cursor::after(&page[0], SortKey::Title).unwrap());
+    assert_err!(
+        store.list_repositories(filter).await,
+        TaskStoreError::InvalidQuery(_),
+        "list_repositories with a cursor for another sort key"
+    );
+    assert_err!(
+        store
+            .list_workspaces(WorkspaceFilter {
+                sort: SortOrder::new(SortKey::Title),
+                after: Some(Cursor::after(&page[0], SortKey::Title).unwrap()),
+                ..Default::default()
+            })
+            .await,
+        TaskStoreError::InvalidQuery(_),
+        "list_workspaces with a cursor from a repository list"
+    );
+
+    // Lists without totals page the same way.
+    let fixture = Fixture::create(store).await;
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(fixture.group.id, agent_task_id, "Paged requests")
+        })
+        .await;
+    let session = fixture.session(store, &task).await;
+    let mut request_ids = Vec::new();
+    for offset in [30, 10, 20] {
+        let mut request = TtyInputRequest::new(task.id, session.id, "Next?");
+        request.created_at = at(offset);
+        request_ids.push((offset, request.id));
+        store.create_tty_input_request(request).await.unwrap();
+    }
+    request_ids.sort();
+    let mut filter = TtyInputFilter {
+        session_id: Some(session.id),
+        limit: Some(2),
+        ..Default::default()
+    };
+    let page = store.list_tty_input_requests(filter.clone()).await.unwrap();
+    assert_eq!(ids(&page, |r| r.id), [request_ids[0].1, request_ids[1].1]);
+    filter.after = Some(Cursor::after(&page[1], SortKey::CreatedAt).unwrap());
+    let page = store.list_tty_input_requests(filter.clone()).await.unwrap();
+    assert_eq!(ids(&page, |r| r.id), [request_ids[2].1]);
+    filter.sort = SortOrder::new(SortKey::Title);
+    filter.after = None;
+    assert_err!(
+        store.list_tty_input_requests(filter).await,
+        TaskStoreError::InvalidQuery(_),
+        "list_tty_input_requests sorted by title"
+    );
+}
+
+async fn check_trash(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let group_id = fixture.group.id;
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(group_id, agent_task_id, "Trashed")
+        })
+        .await;
+    let kept = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(group_id, agent_task_id, "Kept")
+        })
+        .await;
+    let mut changes = store
+        .subscribe(ChangeFilter::new().with_entity_id(task.id))
+        .await
+        .unwrap();
+
+    store.delete_unit_task(task.id).await.unwrap();
+    let deleted = store.get_unit_task(task.id).await.unwrap().unwrap();
+    assert!(deleted.deleted_at.is_some(), "delete sets deleted_at");
+    assert_eq!(
+        deleted.revision,
+        task.revision + 1,
+        "delete bumps the revision"
+    );
+    let event = next_change(&mut changes).await;
+    assert_eq!(
+        event.operation,
+        ChangeOperation::Updated,
+        "moving a row to the trash is an update"
+    );
+    store.delete_unit_task(task.id).await.unwrap();
+    let again = store.get_unit_task(task.id).await.unwrap().unwrap();
+    assert_eq!(
+        again.revision, deleted.revision,
+        "deleting twice does nothing"
+    );
+    assert_eq!(again.deleted_at, deleted.deleted_at);
+    assert_err!(
+        store.update_unit_task(task.clone()).await,
+        TaskStoreError::Conflict { .. },
+        "update_unit_task with the revision from before the delete"
+    );
+
+    let list = |deleted: DeletedFilter| {
+        let filter = TaskFilter {
+            repository_group_id: Some(group_id),
+            deleted,
+            ..Default::default()
+        };
+        async move { store.list_unit_tasks(filter).await.unwrap() }
+    };
+    let (listed, total) = list(DeletedFilter::default()).await;
+    assert_eq!(ids(&listed, |t| t.id), [kept.id], "lists hide deleted rows");
+    assert_eq!(total, 1, "totals skip deleted rows");
+    let (listed, total) = list(DeletedFilter::Only).await;
+    assert_eq!(
+        ids(&listed, |t| t.id),
+        [task.id],
+        "trash lists deleted rows"
+    );
+    assert_eq!(total, 1);
+    let (listed, total) = list(DeletedFilter::Include).await;
+    assert_eq!(ids(&listed, |t| t.id), [task.id, kept.id]);
+    assert_eq!(total, 2);
+
+    let restored = store.restore_unit_task(task.id).await.unwrap();
+    assert!(restored.deleted_at.is_none(), "restore clears deleted_at");
+    assert_eq!(
+        restored.revision,
+        deleted.revision + 1,
+        "restore bumps the revision"
+    );
+    assert_same(
+        &store.get_unit_task(task.id).await.unwrap().unwrap(),
+        &restored,
+        "restore_unit_task returns the stored task",
+    );
+    let again = store.restore_unit_task(task.id).await.unwrap();
+    assert_eq!(
+        again.revision, restored.revision,
+        "restoring twice does nothing"
+    );
+    let (listed, _) = list(DeletedFilter::default()).await;
+    assert_eq!(
+        ids(&listed, |t| t.id),
+        [task.id, kept.id],
+        "restored rows are listed"
+    );
+
+    let planning_task = fixture.agent_task(store).await;
+    let composite = store
+        .create_composite_task(CompositeTask::new(group_id, planning_task.id, "Trashed"))
+        .await
+        .unwrap();
+    store.delete_composite_task(composite.id).await.unwrap();
+    let filter = TaskFilter {
+        repository_group_id: Some(group_id),
+        ..Default::default()
+    };
+    let (listed, total) = store.list_composite_tasks(filter).await.unwrap();
+    assert!(
+        listed.is_empty() && total == 0,
+        "composite task lists hide deleted rows"
+    );
+    store.restore_composite_task(composite.id).await.unwrap();
+
+    // Deleting a parent leaves its children alone.
+    store.delete_workspace(fixture.workspace.id).await.unwrap();
+    store
+        .delete_repository(fixture.repository.id)
+        .await
+        .unwrap();
+    let filter = RepositoryFilter {
+        workspace_id: Some(fixture.workspace.id),
+        ..Default::default()
+    };
+    let (listed, _) = store.list_repositories(filter).await.unwrap();
+    assert!(listed.is_empty(), "repository lists hide deleted rows");
+    let filter = RepositoryFilter {
+        workspace_id: Some(fixture.workspace.id),
+        deleted: DeletedFilter::Only,
+        ..Default::default()
+    };
+    let (listed, _) = store.list_repositories(filter).await.unwrap();
+    assert_eq!(ids(&listed, |r| r.id), [fixture.repository.id]);
+    let filter = WorkspaceFilter {
+        deleted: DeletedFilter::Only,
+        ..Default::default()
+    };
+    let (listed, _) = store.list_workspaces(filter).await.unwrap();
+    assert!(
+        listed.iter().any(|w| w.id == fixture.workspace.id),
+        "workspace trash lists deleted rows"
+    );
+    let (listed, _) = store
+        .list_workspaces(WorkspaceFilter::default())
+        .await
+        .unwrap();
+    assert!(
+        listed.iter().all(|w| w.id != fixture.workspace.id),
+        "workspace lists hide deleted rows"
+    );
+    let (listed, _) = store
+        .list_unit_tasks(TaskFilter {
+            repository_group_id: Some(group_id),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 2, "children of deleted rows stay listed");
+
+    let workspace = store.restore_workspace(fixture.workspace.id).await.unwrap();
+    assert!(workspace.deleted_at.is_none());
+    let repository = store
+        .restore_repository(fixture.repository.id)
+        .await
+        .unwrap();
+    assert!(repository.deleted_at.is_none());
+}
+
+async fn check_transactions(store: &dyn TaskStore) {
+    // Only the transaction handle is used while a transaction is open, as a
+    // store may serialize other operations behind it.
+    let tx = store.begin().await.unwrap();
+    let committed = tx
+        .create_workspace(Workspace::new("Committed"))
+        .await
+        .unwrap();
+    let group = tx
+        .create_repository_group(RepositoryGroup::new(committed.id))
+        .await
+        .unwrap();
+    assert!(
+        tx.get_workspace(committed.id).await.unwrap().is_some(),
+        "a transaction reads its own writes"
+    );
+    assert_err!(
+        tx.begin().await.map(drop),
+        TaskStoreError::Other(_),
+        "begin on a transaction handle"
+    );
+    tx.commit().await.unwrap();
+    assert!(store.get_workspace(committed.id).await.unwrap().is_some());
+    assert!(
+        store
+            .get_repository_group(group.id)
+            .await
+            .unwrap()
+            .is_some(),
+        "commit applies every write"
+    );
+
+    let tx = store.begin().await.unwrap();
+    let rolled_back = tx
+        .create_workspace(Workspace::new("Rolled back"))
+        .await
+        .unwrap();
+    let mut renamed = committed.clone();
+    renamed.name = "Renamed".to_string();
+    tx.update_workspace(renamed).await.unwrap();
+    assert_err!(
+        tx.create_repository_group(RepositoryGroup::new(Uuid::new_v4()))
+            .await,
+        TaskStoreError::ForeignKeyViolation(_),
+        "a failed write inside a transaction"
+    );
+    tx.rollback().await.unwrap();
+    assert!(store.get_workspace(rolled_back.id).await.unwrap().is_none());
+    let fetched = store.get_workspace(committed.id).await.unwrap().unwrap();
+    assert_eq!(fetched.name, "Committed", "rollback discards updates");
+    assert_eq!(fetched.revision, 0);
+
+    let tx = store.begin().await.unwrap();
+    let dropped = tx
+        .create_workspace(Workspace::new("Dropped"))
+        .await
+        .unwrap();
+    drop(tx);
+    assert!(
+        store.get_workspace(dropped.id).await.unwrap().is_none(),
+        "dropping a transaction rolls it back"
+    );
+
+    // A row written in a transaction keeps the rows it references: either
+    // the commit or a concurrent delete of a referenced row fails. The SQL
+    // stores hold the transaction's locks until it ends, so the delete
+    // waits for the commit there, while it runs first in memory.
+    let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+    let deleted_group = store
+        .create_repository_group(RepositoryGroup::new(committed.id))
+        .await
+        .unwrap();
+    let tx = store.begin().await.unwrap();
+    let task = tx
+        .create_unit_task(UnitTask::new(deleted_group.id, agent_task.id, "Orphan"))
+        .await
+        .unwrap();
+    let (deleted, commit) =
+        futures::join!(store.delete_repository_group(deleted_group.id), async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            tx.commit().await
+        });
+    match (deleted, commit) {
+        (Ok(()), Err(TaskStoreError::ForeignKeyViolation(_))) => {
+            assert!(store.get_unit_task(task.id).await.unwrap().is_none());
+        }
+        (Err(TaskStoreError::ForeignKeyViolation(_)), Ok(())) => {
+            assert!(store.get_unit_task(task.id).await.unwrap().is_some());
+        }
+        other => panic!("commit racing a delete of a referenced row: {other:?}"),
+    }
+}
+
+/// Waits for the next event on a change stream.
+async fn next_change(changes: &mut ChangeStream) -> ChangeEvent {
+    tokio::time::timeout(CHANGE_TIMEOUT, changes.next())
+        .await
+        .expect("Timed out waiting for a change event")
+        .expect("Change stream ended")
+        .unwrap()
+}
+
+async fn check_change_feed(store: &dyn TaskStore) {
+    let mut changes = store
+        .subscribe(ChangeFilter::new().with_kind(EntityKind::Workspace))
+        .await
+        .unwrap();
+    let workspace = Workspace::new("Watched");
+    let mut updates = store
+        .subscribe(
+            ChangeFilter::new()
+                .with_entity_id(workspace.id)
+                .with_operation(ChangeOperation::Updated),
+        )
+        .await
+        .unwrap();
+
+    let tx = store.begin().await.unwrap();
+    let discarded = tx
+        .create_workspace(Workspace::new("Discarded"))
+        .await
+        .unwrap();
+    tx.rollback().await.unwrap();
+    let tx = store.begin().await.unwrap();
+    let committed = tx
+        .create_workspace(Workspace::new("Committed"))
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    store.create_workspace(workspace.clone()).await.unwrap();
+    let mut renamed = workspace.clone();
+    renamed.name = "Renamed".to_string();
+    store.update_workspace(renamed).await.unwrap();
+    store.purge_workspace(workspace.id).await.unwrap();
+
+    // Other writers may share the store, so only events for the rows written
+    // here are compared.
+    let mut seen = Vec::new();
+    loop {
+        let event = next_change(&mut changes).await;
+        assert_eq!(event.kind, EntityKind::Workspace, "the kind filter applies");
+        assert_ne!(
+            event.id, discarded.id,
+            "rolled back writes produce no events"
+        );
+        if event.id != workspace.id && event.id != committed.id {
+            continue;
+        }
+        let done = event.id == workspace.id && event.operation == ChangeOperation::Deleted;
+        seen.push(event);
+        if done {
+            break;
+        }
+    }
+    let operations: Vec<(Uuid, ChangeOperation)> =
+        seen.iter().map(|e| (e.id, e.operation)).collect();
+    assert_eq!(
+        operations,
+        [
+            (committed.id, ChangeOperation::Created),
+            (workspace.id, ChangeOperation::Created),
+            (workspace.id, ChangeOperation::Updated),
+            (workspace.id, ChangeOperation::Deleted),
+        ],
+        "every write produces one event, in commit order"
+    );
+    match &seen[2].value {
+        Some(EntityValue::Workspace(value)) => {
+            assert_eq!(value.name, "Renamed");
+            assert_eq!(value.revision, 1, "events carry the stored value");
+        }
+        other => panic!("Expected the updated workspace, got {other:?}"),
+    }
+    assert!(seen[3].value.is_none(), "delete events carry no value");
+
+    let event = next_change(&mut updates).await;
+    assert_eq!(
+        (event.id, event.operation),
+        (workspace.id, ChangeOperation::Updated),
+        "entity and operation filters apply"
+    );
+}
+
+async fn check_search(store: &dyn TaskStore) {
+    let fixture = Fixture::create(store).await;
+    let word = unique_word();
+    let task = fixture
+        .unit_task(store, |agent_task_id| {
+            UnitTask::new(
+                fixture.group.id,
+                agent_task_id,
+                format!("Refactor the {word} module"),
+            )
+            .with_title(format!("{word} cleanup"))
+        })
+        .await;
+    let planning_task = fixture.agent_task(store).await;
+    let composite = store
+        .create_composite_task(CompositeTask::new(
+            fixture.group.id,
+            planning_task.id,
+            format!("Plan the {word} rewrite"),
+        ))
+        .await
+        .unwrap();
+    let mut session = AgentSession::new(task.agent_task_id, AiAgentType::ClaudeCode);
+    session.output_log = Some(format!("Edited src/{word}/invoice.rs"));
+    let session = store.create_agent_session(session).await.unwrap();
+
+    let hits = store.search(SearchQuery::new(word.as_str())).await.unwrap();
+    let found: HashSet<(EntityKind, Uuid, SearchField)> =
+        hits.iter().map(|h| (h.kind, h.id, h.field)).collect();
+    assert_eq!(
+        found,
+        HashSet::from([
+            (EntityKind::UnitTask, task.id, SearchField::Title),
+            (EntityKind::UnitTask, task.id, SearchField::Prompt),
+            (EntityKind::CompositeTask, composite.id, SearchField::Prompt),
+            (EntityKind::AgentSession, session.id, SearchField::OutputLog),
+        ]),
+        "search covers titles, prompts and output logs"
+    );
+    assert!(
+        hits.windows(2).all(|pair| pair[0].score >= pair[1].score),
+        "search returns the best match first"
+    );
+    let marked = format!("{SNIPPET_MATCH_START}{word}{SNIPPET_MATCH_END}");
+    for hit in &hits {
+        assert!(
+            hit.snippet.contains(&marked),
+            "snippet {:?} marks the matched word",
+            hit.snippet
+        );
+    }
+
+    let query = SearchQuery::new(word.to_uppercase()).with_kinds([EntityKind::UnitTask]);
+    let hits = store.search(query).await.unwrap();
+    assert_eq!(hits.len(), 2, "search ignores case and filters by kind");
+    assert!(hits.iter().all(|h| h.id == task.id));
+    let hits = store
+        .search(SearchQuery::new(word.as_str()).with_limit(1))
+        .await
+        .unwrap();
+    assert_eq!(hits.len(), 1, "search applies the limit");
+    let hits = store
+        .search(SearchQuery::new(format!("{word} invoice")))
+        .await
+        .unwrap();
+    assert_eq!(
+        ids(&hits, |h| h.id),
+        [session.id],
+        "a field must contain every word"
+    );
+    assert!(
+        store.search(SearchQuery::new("")).await.unwrap().is_empty(),
+        "an empty query matches nothing"
+    );
+
+    // Appended log chunks are searched one at a time, with one hit for
+    // each session.
+    let logged = store
+        .create_agent_session(AgentSession::new(
+            task.agent_task_id,
+            AiAgentType::ClaudeCode,
+        ))
+        .await
+        .unwrap();
+    for content in [
+        format!("Compiling {word}\n"),
+        format!("Testing {word} and {word} again\n"),
+        "Done\n".to_string(),
+    ] {
+        store.append_session_log(logged.id, content).await.unwrap();
+    }
+    let sessions = SearchQuery::new(word.as_str()).with_kinds([EntityKind::AgentSession]);
+    let hits = store.search(sessions.clone()).await.unwrap();
+    let found: HashSet<(EntityKind, Uuid, SearchField)> =
+        hits.iter().map(|h| (h.kind, h.id, h.field)).collect();
+    assert_eq!(hits.len(), 2);
+    assert_eq!(
+        found,
+        HashSet::from([
+            (EntityKind::AgentSession, session.id, SearchField::OutputLog),
+            (EntityKind::AgentSession, logged.id, SearchField::SessionLog),
+        ]),
+        "search covers appended log chunks"
+    );
+    let hit = hits.iter().find(|h| h.id == logged.id).unwrap();
+    assert!(hit.snippet.contains(&marked), "snippet {:?}", hit.snippet);
+    let hits = store
+        .search(SearchQuery::new(format!("{word} done")))
+        .await
+        .unwrap();
+    assert!(
+        hits.iter().all(|h| h.id != logged.id),
+        "a chunk must contain every word"
+    );
+
+    let appended = unique_word();
+    let tx = store.begin().await.unwrap();
+    tx.append_session_log(logged.id, format!("Retried {appended}\n"))
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    let mut logged = store.get_agent_session(logged.id).await.unwrap().unwrap();
+    logged.output_log = Some("Summary".to_string());
+    store.update_agent_session(logged.clone()).await.unwrap();
+    let hits = store.search(SearchQuery::new(appended)).await.unwrap();
+    assert_eq!(
+        ids(&hits, |h| h.id),
+        [logged.id],
+        "chunks appended in a transaction are indexed and survive updates"
+    );
+    store.delete_agent_session(logged.id).await.unwrap();
+    let hits = store.search(sessions).await.unwrap();
+    assert_eq!(
+        ids(&hits, |h| h.id),
+        [session.id],
+        "deleting a session removes its chunks"
+    );
+
+    let mut session = session;
+    session.output_log = Some("Nothing to see".to_string());
+    store.update_agent_session(session).await.unwrap();
+    store.purge_composite_task(composite.id).await.unwrap();
+    let hits = store.search(SearchQuery::new(word.as_str())).await.unwrap();
+    assert_eq!(
+        ids(&hits, |h| h.id),
+        [task.id, task.id],
+        "updates and deletes are reindexed"
+    );
+}
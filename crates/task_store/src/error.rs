@@ -27,14 +27,53 @@ pub enum TaskStoreError {
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// The entity was modified since the caller read it.
+    #[error("{entity_type} {id} was modified concurrently; current revision is {current_revision}")]
+    Conflict {
+        entity_type: &'static str,
+        id: String,
+        current_revision: u64,
+    },
+
     /// Invalid state transition.
     #[error("Invalid state transition from {from} to {to}")]
     InvalidStateTransition { from: String, to: String },
 
+    /// A list query used an unsupported sort key or a malformed cursor.
+    #[error("Invalid query: {0}")]
+    InvalidQuery(String),
+
     /// Foreign key constraint violation.
     #[error("Foreign key constraint violation: {0}")]
     ForeignKeyViolation(String),
 
+    /// The database schema was migrated by a newer version of DeliDev.
+    #[error(
+        "Database schema version {database_version} is newer than the supported version \
+         {supported_version}"
+    )]
+    SchemaTooNew {
+        database_version: u32,
+        supported_version: u32,
+    },
+
+    /// A change feed subscriber fell behind and missed events.
+    #[error("Change feed subscriber fell behind and skipped {skipped} events")]
+    ChangeFeedLagged { skipped: u64 },
+
+    /// Reading or writing a file failed.
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// An export archive is malformed or was written by a newer version.
+    #[error("Invalid archive: {0}")]
+    InvalidArchive(String),
+
+    /// A worker acted on a queue entry it does not hold an unexpired lease
+    /// on.
+    #[error("Worker {worker_id} does not hold the lease on queue entry {id}")]
+    LeaseNotHeld { id: String, worker_id: String },
+
     /// Other error.
     #[error("{0}")]
     Other(String),
@@ -49,6 +88,19 @@ impl TaskStoreError {
         }
     }
 
+    /// Creates a conflict error.
+    pub fn conflict(
+        entity_type: &'static str,
+        id: impl Into<String>,
+        current_revision: u64,
+    ) -> Self {
+        Self::Conflict {
+            entity_type,
+            id: id.into(),
+            current_revision,
+        }
+    }
+
     /// Creates an already exists error.
     pub fn already_exists(entity_type: &'static str, id: impl Into<String>) -> Self {
         Self::AlreadyExists {
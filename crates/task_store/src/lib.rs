@@ -4,10 +4,40 @@
 //! with implementations for SQLite (single-user mode), PostgreSQL
 //! (multi-user mode), and in-memory (testing).
 
+mod analytics;
+mod archive;
+mod cache;
+mod changes;
+mod conformance;
 mod error;
 mod memory;
+mod migrate;
+mod postgres;
+mod queue;
+mod search;
+mod session_log;
+mod sort;
+mod sql;
+mod sqlite;
 mod traits;
+mod transition;
+mod trash;
 
+pub use analytics::*;
+pub use archive::*;
+pub use cache::{CacheConfig, CacheStats, CachedTaskStore, WrappedStore};
+pub use changes::{
+    ChangeEvent, ChangeFilter, ChangeOperation, ChangeStream, EntityKind, EntityValue,
+};
+pub use conformance::run_conformance;
 pub use error::*;
 pub use memory::*;
+pub use migrate::{MigrationReport, SCHEMA_VERSION};
+pub use postgres::*;
+pub use queue::{DEFAULT_MAX_ATTEMPTS, QueueEntry, QueueStatus};
+pub use search::*;
+pub use session_log::{LogChunk, LogRange, LogSize};
+pub use sort::*;
+pub use sqlite::*;
 pub use traits::*;
+pub use trash::*;
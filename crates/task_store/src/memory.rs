@@ -1,34 +1,836 @@
 //! In-memory task store implementation for testing.
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
 use entities::{
-    AgentSession, AgentTask, CompositeTask, CompositeTaskNode, Repository, RepositoryGroup,
-    TodoItem, TtyInputRequest, UnitTask, User, Workspace,
+    AgentSession, AgentTask, AuditEvent, CompositeTask, CompositeTaskNode, PlanRevision,
+    Repository, RepositoryGroup, TodoItem, TtyInputRequest, UnitTask, User, Worker, WorkerStatus,
+    Workspace,
 };
-use tokio::sync::RwLock;
+use serde::Serialize;
+use tokio::sync::{OnceCell, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use uuid::Uuid;
 
 use crate::{
-    RepositoryFilter, TaskFilter, TaskStore, TaskStoreError, TaskStoreResult, TodoFilter,
-    TtyInputFilter, WorkspaceFilter,
+    AuditFilter, ChangeEvent, ChangeFilter, ChangeStream, Cursor, EntityKind, EntityValue,
+    LogChunk, LogRange, LogSize, QueueEntry, QueueFilter, QueueStatus, RepositoryFilter, SearchHit,
+    SearchQuery, SortKey, SortOrder, Sortable, TaskFilter, TaskStore, TaskStoreError,
+    TaskStoreResult, TaskStoreTransaction, TodoFilter, TtyInputFilter, WorkerFilter,
+    WorkspaceFilter, changes::ChangeBroadcaster, search::SearchIndex, session_log,
+    sort::check_sort, transition::ensure_transition,
 };
 
 /// In-memory task store for testing purposes.
+///
+/// References between entities are enforced like the SQL schema: writes
+/// that point at a missing row and permanent deletes of a row that is still
+/// referenced fail with [`TaskStoreError::ForeignKeyViolation`]. Deleting an
+/// agent task also deletes its sessions, and deleting a session also deletes
+/// its log.
 #[derive(Debug, Default)]
 pub struct MemoryTaskStore {
-    users: Arc<RwLock<HashMap<Uuid, User>>>,
-    workspaces: Arc<RwLock<HashMap<Uuid, Workspace>>>,
-    repositories: Arc<RwLock<HashMap<Uuid, Repository>>>,
-    repository_groups: Arc<RwLock<HashMap<Uuid, RepositoryGroup>>>,
-    agent_tasks: Arc<RwLock<HashMap<Uuid, AgentTask>>>,
-    agent_sessions: Arc<RwLock<HashMap<Uuid, AgentSession>>>,
-    unit_tasks: Arc<RwLock<HashMap<Uuid, UnitTask>>>,
-    composite_tasks: Arc<RwLock<HashMap<Uuid, CompositeTask>>>,
-    composite_task_nodes: Arc<RwLock<HashMap<Uuid, CompositeTaskNode>>>,
-    todo_items: Arc<RwLock<HashMap<Uuid, TodoItem>>>,
-    tty_input_requests: Arc<RwLock<HashMap<Uuid, TtyInputRequest>>>,
+    users: Table<User>,
+    workspaces: Table<Workspace>,
+    repositories: Table<Repository>,
+    repository_groups: Table<RepositoryGroup>,
+    agent_tasks: Table<AgentTask>,
+    agent_sessions: Table<AgentSession>,
+    /// Output log chunks keyed by session ID.
+    session_logs: Table<SessionLog>,
+    unit_tasks: Table<UnitTask>,
+    composite_tasks: Table<CompositeTask>,
+    composite_task_nodes: Table<CompositeTaskNode>,
+    plan_revisions: Table<PlanRevision>,
+    todo_items: Table<TodoItem>,
+    tty_input_requests: Table<TtyInputRequest>,
+    workers: Table<Worker>,
+    queue_entries: Table<QueueEntry>,
+    audit_events: Table<AuditEvent>,
+    /// Full-text index, updated with every published change event. A
+    /// transaction indexes the rows it sees when searched instead.
+    search_index: Arc<Mutex<SearchIndex>>,
+    changes: ChangeBroadcaster,
+    /// Pending commit when this store is a transaction handle.
+    transaction: Option<Box<PendingCommit>>,
+}
+
+/// A table of a store.
+///
+/// In a transaction, the table reads the parent store's table until the
+/// transaction first writes to it, and from then on works on a copy of the
+/// parent's rows taken at that point.
+#[derive(Debug)]
+struct Table<T> {
+    rows: Arc<RwLock<HashMap<Uuid, T>>>,
+    /// Parent store's table, when this table belongs to a transaction.
+    parent: Option<ParentTable<T>>,
+}
+
+/// Table of the store a transaction was begun on.
+#[derive(Debug)]
+struct ParentTable<T> {
+    rows: Arc<RwLock<HashMap<Uuid, T>>>,
+    /// Parent rows as they were when the transaction first wrote to the
+    /// table.
+    base: OnceCell<HashMap<Uuid, T>>,
+}
+
+impl<T> Default for Table<T> {
+    fn default() -> Self {
+        Self {
+            rows: Arc::default(),
+            parent: None,
+        }
+    }
+}
+
+impl<T: Row + Clone> Table<T> {
+    /// Returns a handle sharing this table's rows.
+    fn share(&self) -> Self {
+        Self {
+            rows: Arc::clone(&self.rows),
+            parent: None,
+        }
+    }
+
+    /// Returns a table for a transaction begun on this one.
+    fn begin(&self) -> Self {
+        Self {
+            rows: Arc::default(),
+            parent: Some(ParentTable {
+                rows: Arc::clone(&self.rows),
+                base: OnceCell::new(),
+            }),
+        }
+    }
+
+    /// Locks the rows this handle sees for reading.
+    async fn read(&self) -> RwLockReadGuard<'_, HashMap<Uuid, T>> {
+        match &self.parent {
+            Some(parent) if !parent.base.initialized() => parent.rows.read().await,
+            _ => self.rows.read().await,
+        }
+    }
+
+    /// Locks the rows for writing, first copying the parent's rows in a
+    /// transaction.
+    async fn write(&self) -> RwLockWriteGuard<'_, HashMap<Uuid, T>> {
+        if let Some(parent) = &self.parent {
+            parent
+                .base
+                .get_or_init(|| async {
+                    let rows = parent.rows.read().await.clone();
+                    *self.rows.write().await = rows.clone();
+                    rows
+                })
+                .await;
+        }
+        self.rows.write().await
+    }
+
+    /// Compares a transaction's table with the parent rows it copied.
+    async fn changes(&self) -> TaskStoreResult<TableChanges<T>> {
+        match self.parent.as_ref().and_then(|parent| parent.base.get()) {
+            Some(base) => TableChanges::diff(base, std::mem::take(&mut *self.rows.write().await)),
+            None => Ok(TableChanges::default()),
+        }
+    }
+}
+
+/// State a transaction handle needs to commit into its parent store.
+#[derive(Debug)]
+struct PendingCommit {
+    /// Store the transaction was begun on, sharing its tables.
+    parent: MemoryTaskStore,
+    /// Change events published once the transaction commits.
+    events: Mutex<Vec<ChangeEvent>>,
+}
+
+/// Row type stored in a memory table.
+trait Row: Serialize {
+    /// Entity type name used in errors.
+    const ENTITY_TYPE: &'static str;
+
+    /// Returns the optimistic concurrency revision of the row.
+    fn revision(&self) -> u64;
+}
+
+macro_rules! impl_row {
+    ($($entity:ident),* $(,)?) => {
+        $(
+            impl Row for $entity {
+                const ENTITY_TYPE: &'static str = stringify!($entity);
+
+                fn revision(&self) -> u64 {
+                    self.revision
+                }
+            }
+        )*
+    };
+}
+
+impl_row!(
+    User,
+    Workspace,
+    Repository,
+    RepositoryGroup,
+    AgentTask,
+    AgentSession,
+    UnitTask,
+    CompositeTask,
+    CompositeTaskNode,
+    TodoItem,
+    TtyInputRequest,
+    Worker,
+    QueueEntry,
+);
+
+/// Audit events are never updated, so they have no revision counter.
+impl Row for AuditEvent {
+    const ENTITY_TYPE: &'static str = "AuditEvent";
+
+    fn revision(&self) -> u64 {
+        0
+    }
+}
+
+/// Plan revisions only change when approved, so approval serves as the
+/// revision: two transactions approving the same revision conflict.
+impl Row for PlanRevision {
+    const ENTITY_TYPE: &'static str = "PlanRevision";
+
+    fn revision(&self) -> u64 {
+        self.is_approved() as u64
+    }
+}
+
+/// Row type that can be moved to the trash.
+trait Trashable: Row + Clone {
+    /// Returns the row's `deleted_at` and revision for updating.
+    fn trash_state(&mut self) -> (&mut Option<DateTime<Utc>>, &mut u64);
+}
+
+macro_rules! impl_trashable {
+    ($($entity:ident),* $(,)?) => {
+        $(
+            impl Trashable for $entity {
+                fn trash_state(&mut self) -> (&mut Option<DateTime<Utc>>, &mut u64) {
+                    (&mut self.deleted_at, &mut self.revision)
+                }
+            }
+        )*
+    };
+}
+
+impl_trashable!(Workspace, Repository, UnitTask, CompositeTask);
+
+/// Moves a row into the trash, or out of it when `deleted_at` is `None`.
+///
+/// Returns the row and whether it changed; a change bumps the revision.
+fn set_deleted_at<T: Trashable>(
+    table: &mut HashMap<Uuid, T>,
+    id: Uuid,
+    deleted_at: Option<DateTime<Utc>>,
+) -> TaskStoreResult<(T, bool)> {
+    let row = table
+        .get_mut(&id)
+        .ok_or_else(|| TaskStoreError::not_found(T::ENTITY_TYPE, id.to_string()))?;
+    let (current, revision) = row.trash_state();
+    let changed = current.is_some() != deleted_at.is_some();
+    if changed {
+        *current = deleted_at;
+        *revision += 1;
+    }
+    Ok((row.clone(), changed))
+}
+
+/// Output log of one session, stored as a row of the `session_logs` table.
+#[derive(Debug, Clone, Default, Serialize)]
+struct SessionLog {
+    chunks: Vec<LogChunk>,
+}
+
+impl Row for SessionLog {
+    const ENTITY_TYPE: &'static str = "SessionLog";
+
+    /// Logs are append-only, so their length serves as the revision: a
+    /// transaction that appended to a log conflicts with any other append
+    /// committed since it began.
+    fn revision(&self) -> u64 {
+        self.chunks.len() as u64
+    }
+}
+
+/// Fails with [`TaskStoreError::Conflict`] unless the caller's revision is
+/// the stored one.
+fn check_revision(
+    entity_type: &'static str,
+    id: Uuid,
+    current: u64,
+    expected: u64,
+) -> TaskStoreResult<()> {
+    if current != expected {
+        return Err(TaskStoreError::conflict(
+            entity_type,
+            id.to_string(),
+            current,
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `task` with its sessions from the sessions table, oldest first,
+/// as the SQL stores load them.
+fn with_sessions(task: &AgentTask, sessions: &HashMap<Uuid, AgentSession>) -> AgentTask {
+    let mut task = task.clone();
+    task.agent_sessions = sessions
+        .values()
+        .filter(|s| s.agent_task_id == task.id)
+        .cloned()
+        .collect();
+    task.agent_sessions.sort_by_key(|s| (s.created_at, s.id));
+    task
+}
+
+/// Fails with [`TaskStoreError::ForeignKeyViolation`] unless `table` holds
+/// the referenced row.
+fn ensure_reference<T>(
+    table: &HashMap<Uuid, T>,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreResult<()> {
+    if table.contains_key(&id) {
+        return Ok(());
+    }
+    Err(TaskStoreError::ForeignKeyViolation(format!(
+        "{entity_type} {id} does not exist"
+    )))
+}
+
+/// Fails with [`TaskStoreError::ForeignKeyViolation`] if a row is still
+/// referenced by a row of another table.
+fn ensure_unreferenced(
+    entity_type: &'static str,
+    id: Uuid,
+    referenced_by: &'static str,
+    referenced: bool,
+) -> TaskStoreResult<()> {
+    if !referenced {
+        return Ok(());
+    }
+    Err(TaskStoreError::ForeignKeyViolation(format!(
+        "{entity_type} {id} is still referenced by a {referenced_by}"
+    )))
+}
+
+/// Checks the workspace and member repositories of a group exist.
+fn ensure_group_references(
+    group: &RepositoryGroup,
+    workspaces: &HashMap<Uuid, Workspace>,
+    repositories: &HashMap<Uuid, Repository>,
+) -> TaskStoreResult<()> {
+    ensure_reference(workspaces, "Workspace", group.workspace_id)?;
+    for repository_id in &group.repository_ids {
+        ensure_reference(repositories, "Repository", *repository_id)?;
+    }
+    Ok(())
+}
+
+/// Checks the group, agent task and auto-fix tasks of a unit task exist.
+fn ensure_unit_task_references(
+    task: &UnitTask,
+    groups: &HashMap<Uuid, RepositoryGroup>,
+    agent_tasks: &HashMap<Uuid, AgentTask>,
+) -> TaskStoreResult<()> {
+    ensure_reference(groups, "RepositoryGroup", task.repository_group_id)?;
+    ensure_reference(agent_tasks, "AgentTask", task.agent_task_id)?;
+    for auto_fix_task_id in &task.auto_fix_task_ids {
+        ensure_reference(agent_tasks, "AgentTask", *auto_fix_task_id)?;
+    }
+    Ok(())
+}
+
+/// Checks the composite task, unit task and dependencies of a node exist.
+fn ensure_node_references(
+    node: &CompositeTaskNode,
+    composite_tasks: &HashMap<Uuid, CompositeTask>,
+    unit_tasks: &HashMap<Uuid, UnitTask>,
+    nodes: &HashMap<Uuid, CompositeTaskNode>,
+) -> TaskStoreResult<()> {
+    ensure_reference(composite_tasks, "CompositeTask", node.composite_task_id)?;
+    ensure_reference(unit_tasks, "UnitTask", node.unit_task_id)?;
+    for dependency_id in &node.depends_on_ids {
+        ensure_reference(nodes, "CompositeTaskNode", *dependency_id)?;
+    }
+    Ok(())
+}
+
+/// Sorts the rows matching a list query and applies its cursor, offset and
+/// limit, returning the page and the number of matching rows.
+fn paginate<T: Sortable>(
+    mut rows: Vec<T>,
+    sort: SortOrder,
+    after: Option<&Cursor>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) -> TaskStoreResult<(Vec<T>, u32)> {
+    check_sort::<T>(sort, after)?;
+    let total = rows.len() as u32;
+    rows.sort_by(|a, b| sort.compare(a, b));
+    let page = rows
+        .into_iter()
+        .filter(|row| after.is_none_or(|cursor| sort.is_after(row, cursor)))
+        .skip(offset.unwrap_or(0) as usize)
+        .take(limit.map_or(usize::MAX, |limit| limit as usize))
+        .collect();
+    Ok((page, total))
+}
+
+/// Returns a copy of the most recently created of `rows`, breaking ties by
+/// ID like the SQL stores' `find_` lookups.
+fn newest<'a, T: Sortable + Clone + 'a>(rows: impl Iterator<Item = &'a T>) -> Option<T> {
+    let by_created = SortOrder::new(SortKey::CreatedAt);
+    rows.max_by(|a, b| by_created.compare(*a, *b)).cloned()
+}
+
+/// Returns the number of the latest plan revision of a composite task, or 0
+/// if it has none.
+fn latest_plan_revision(revisions: &HashMap<Uuid, PlanRevision>, composite_task_id: Uuid) -> u32 {
+    revisions
+        .values()
+        .filter(|r| r.composite_task_id == composite_task_id)
+        .map(|r| r.number)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Takes every expired lease in a queue away from its worker and returns
+/// the number of entries changed.
+fn requeue_expired(queue: &mut HashMap<Uuid, QueueEntry>, now: DateTime<Utc>) -> u64 {
+    let mut changed = 0;
+    for entry in queue.values_mut().filter(|e| e.lease_expired(now)) {
+        entry.expire(now);
+        changed += 1;
+    }
+    changed
+}
+
+/// Rows of one table written or removed by a transaction.
+#[derive(Debug)]
+struct TableChanges<T> {
+    /// Written rows with the revision they had when the transaction began,
+    /// `None` for rows the transaction created.
+    upserts: Vec<(Uuid, T, Option<u64>)>,
+    /// Removed rows with the revision they had when the transaction began.
+    removals: Vec<(Uuid, u64)>,
+}
+
+impl<T> Default for TableChanges<T> {
+    fn default() -> Self {
+        Self {
+            upserts: Vec::new(),
+            removals: Vec::new(),
+        }
+    }
+}
+
+impl<T: Row + Clone> TableChanges<T> {
+    /// Compares a table at the start and end of a transaction.
+    fn diff(base: &HashMap<Uuid, T>, staged: HashMap<Uuid, T>) -> TaskStoreResult<Self> {
+        let removals = base
+            .iter()
+            .filter(|(id, _)| !staged.contains_key(id))
+            .map(|(id, row)| (*id, row.revision()))
+            .collect();
+        let mut upserts = Vec::new();
+        for (id, row) in staged {
+            match base.get(&id) {
+                Some(original) => {
+                    if serde_json::to_value(original)? != serde_json::to_value(&row)? {
+                        upserts.push((id, row, Some(original.revision())));
+                    }
+                }
+                None => upserts.push((id, row, None)),
+            }
+        }
+        Ok(Self { upserts, removals })
+    }
+
+    /// Fails if another writer changed a touched row since the transaction
+    /// began.
+    fn check(&self, table: &HashMap<Uuid, T>) -> TaskStoreResult<()> {
+        for (id, _, expected) in &self.upserts {
+            match (table.get(id), expected) {
+                (Some(current), Some(expected)) => {
+                    check_revision(T::ENTITY_TYPE, *id, current.revision(), *expected)?
+                }
+                (None, Some(_)) => {
+                    return Err(TaskStoreError::not_found(T::ENTITY_TYPE, id.to_string()));
+                }
+                (Some(_), None) => {
+                    return Err(TaskStoreError::already_exists(
+                        T::ENTITY_TYPE,
+                        id.to_string(),
+                    ));
+                }
+                (None, None) => {}
+            }
+        }
+        for (id, expected) in &self.removals {
+            if let Some(current) = table.get(id) {
+                check_revision(T::ENTITY_TYPE, *id, current.revision(), *expected)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the changes to `table` and returns the changes that undo
+    /// them. Revisions in the returned changes are not meaningful.
+    fn apply(&self, table: &mut HashMap<Uuid, T>) -> Self {
+        let mut undo = Self::default();
+        for (id, _) in &self.removals {
+            if let Some(row) = table.remove(id) {
+                undo.upserts.push((*id, row, None));
+            }
+        }
+        for (id, row, _) in &self.upserts {
+            match table.insert(*id, row.clone()) {
+                Some(previous) => undo.upserts.push((*id, previous, None)),
+                None => undo.removals.push((*id, 0)),
+            }
+        }
+        undo
+    }
+}
+
+/// Write locks on every table of a store.
+struct TableGuards<'a> {
+    users: RwLockWriteGuard<'a, HashMap<Uuid, User>>,
+    workspaces: RwLockWriteGuard<'a, HashMap<Uuid, Workspace>>,
+    repositories: RwLockWriteGuard<'a, HashMap<Uuid, Repository>>,
+    repository_groups: RwLockWriteGuard<'a, HashMap<Uuid, RepositoryGroup>>,
+    agent_tasks: RwLockWriteGuard<'a, HashMap<Uuid, AgentTask>>,
+    agent_sessions: RwLockWriteGuard<'a, HashMap<Uuid, AgentSession>>,
+    session_logs: RwLockWriteGuard<'a, HashMap<Uuid, SessionLog>>,
+    unit_tasks: RwLockWriteGuard<'a, HashMap<Uuid, UnitTask>>,
+    composite_tasks: RwLockWriteGuard<'a, HashMap<Uuid, CompositeTask>>,
+    composite_task_nodes: RwLockWriteGuard<'a, HashMap<Uuid, CompositeTaskNode>>,
+    plan_revisions: RwLockWriteGuard<'a, HashMap<Uuid, PlanRevision>>,
+    todo_items: RwLockWriteGuard<'a, HashMap<Uuid, TodoItem>>,
+    tty_input_requests: RwLockWriteGuard<'a, HashMap<Uuid, TtyInputRequest>>,
+    workers: RwLockWriteGuard<'a, HashMap<Uuid, Worker>>,
+    queue_entries: RwLockWriteGuard<'a, HashMap<Uuid, QueueEntry>>,
+    audit_events: RwLockWriteGuard<'a, HashMap<Uuid, AuditEvent>>,
+}
+
+/// Rows of every table written or removed by a transaction.
+#[derive(Debug, Default)]
+struct StoreChanges {
+    users: TableChanges<User>,
+    workspaces: TableChanges<Workspace>,
+    repositories: TableChanges<Repository>,
+    repository_groups: TableChanges<RepositoryGroup>,
+    agent_tasks: TableChanges<AgentTask>,
+    agent_sessions: TableChanges<AgentSession>,
+    session_logs: TableChanges<SessionLog>,
+    unit_tasks: TableChanges<UnitTask>,
+    composite_tasks: TableChanges<CompositeTask>,
+    composite_task_nodes: TableChanges<CompositeTaskNode>,
+    plan_revisions: TableChanges<PlanRevision>,
+    todo_items: TableChanges<TodoItem>,
+    tty_input_requests: TableChanges<TtyInputRequest>,
+    workers: TableChanges<Worker>,
+    queue_entries: TableChanges<QueueEntry>,
+    audit_events: TableChanges<AuditEvent>,
+}
+
+impl StoreChanges {
+    /// Fails if another writer changed a row the transaction touched.
+    fn check(&self, tables: &TableGuards<'_>) -> TaskStoreResult<()> {
+        self.users.check(&tables.users)?;
+        self.workspaces.check(&tables.workspaces)?;
+        self.repositories.check(&tables.repositories)?;
+        self.repository_groups.check(&tables.repository_groups)?;
+        self.agent_tasks.check(&tables.agent_tasks)?;
+        self.agent_sessions.check(&tables.agent_sessions)?;
+        self.session_logs.check(&tables.session_logs)?;
+        self.unit_tasks.check(&tables.unit_tasks)?;
+        self.composite_tasks.check(&tables.composite_tasks)?;
+        self.composite_task_nodes
+            .check(&tables.composite_task_nodes)?;
+        self.plan_revisions.check(&tables.plan_revisions)?;
+        self.todo_items.check(&tables.todo_items)?;
+        self.tty_input_requests.check(&tables.tty_input_requests)?;
+        self.workers.check(&tables.workers)?;
+        self.queue_entries.check(&tables.queue_entries)?;
+        self.audit_events.check(&tables.audit_events)?;
+        Ok(())
+    }
+
+    /// Writes the changes to `tables` and returns the changes that undo
+    /// them.
+    fn apply(&self, tables: &mut TableGuards<'_>) -> Self {
+        Self {
+            users: self.users.apply(&mut tables.users),
+            workspaces: self.workspaces.apply(&mut tables.workspaces),
+            repositories: self.repositories.apply(&mut tables.repositories),
+            repository_groups: self.repository_groups.apply(&mut tables.repository_groups),
+            agent_tasks: self.agent_tasks.apply(&mut tables.agent_tasks),
+            agent_sessions: self.agent_sessions.apply(&mut tables.agent_sessions),
+            session_logs: self.session_logs.apply(&mut tables.session_logs),
+            unit_tasks: self.unit_tasks.apply(&mut tables.unit_tasks),
+            composite_tasks: self.composite_tasks.apply(&mut tables.composite_tasks),
+            composite_task_nodes: self
+                .composite_task_nodes
+                .apply(&mut tables.composite_task_nodes),
+            plan_revisions: self.plan_revisions.apply(&mut tables.plan_revisions),
+            todo_items: self.todo_items.apply(&mut tables.todo_items),
+            tty_input_requests: self
+                .tty_input_requests
+                .apply(&mut tables.tty_input_requests),
+            workers: self.workers.apply(&mut tables.workers),
+            queue_entries: self.queue_entries.apply(&mut tables.queue_entries),
+            audit_events: self.audit_events.apply(&mut tables.audit_events),
+        }
+    }
+
+    /// Checks the integrity rules the store enforces on every write against
+    /// the tables after the changes were applied: written rows must point
+    /// at existing rows, and removed rows must not be referenced. Other
+    /// writers may have changed the tables since the transaction checked
+    /// them.
+    fn check_references(&self, tables: &TableGuards<'_>) -> TaskStoreResult<()> {
+        for (_, workspace, _) in &self.workspaces.upserts {
+            if let Some(user_id) = workspace.user_id {
+                ensure_reference(&tables.users, "User", user_id)?;
+            }
+        }
+        for (_, repository, _) in &self.repositories.upserts {
+            ensure_reference(&tables.workspaces, "Workspace", repository.workspace_id)?;
+        }
+        for (_, group, _) in &self.repository_groups.upserts {
+            ensure_group_references(group, &tables.workspaces, &tables.repositories)?;
+        }
+        for (_, session, _) in &self.agent_sessions.upserts {
+            ensure_reference(&tables.agent_tasks, "AgentTask", session.agent_task_id)?;
+        }
+        for (session_id, _, _) in &self.session_logs.upserts {
+            ensure_reference(&tables.agent_sessions, "AgentSession", *session_id)?;
+        }
+        for (_, task, _) in &self.unit_tasks.upserts {
+            ensure_unit_task_references(task, &tables.repository_groups, &tables.agent_tasks)?;
+        }
+        for (_, task, _) in &self.composite_tasks.upserts {
+            ensure_reference(
+                &tables.repository_groups,
+                "RepositoryGroup",
+                task.repository_group_id,
+            )?;
+            ensure_reference(&tables.agent_tasks, "AgentTask", task.planning_task_id)?;
+        }
+        for (_, node, _) in &self.composite_task_nodes.upserts {
+            ensure_node_references(
+                node,
+                &tables.composite_tasks,
+                &tables.unit_tasks,
+                &tables.composite_task_nodes,
+            )?;
+        }
+        for (_, revision, _) in &self.plan_revisions.upserts {
+            ensure_reference(
+                &tables.composite_tasks,
+                "CompositeTask",
+                revision.composite_task_id,
+            )?;
+        }
+        for (_, item, _) in &self.todo_items.upserts {
+            ensure_reference(&tables.repositories, "Repository", item.repository_id)?;
+        }
+        for (_, request, _) in &self.tty_input_requests.upserts {
+            ensure_reference(&tables.unit_tasks, "UnitTask", request.task_id)?;
+            ensure_reference(&tables.agent_sessions, "AgentSession", request.session_id)?;
+        }
+        for (_, worker, _) in &self.workers.upserts {
+            if let Some(task_id) = worker.current_task_id {
+                ensure_reference(&tables.unit_tasks, "UnitTask", task_id)?;
+            }
+        }
+        for (_, entry, _) in &self.queue_entries.upserts {
+            ensure_reference(&tables.agent_tasks, "AgentTask", entry.agent_task_id)?;
+        }
+
+        for &(id, _) in &self.users.removals {
+            ensure_unreferenced(
+                "User",
+                id,
+                "Workspace",
+                tables.workspaces.values().any(|w| w.user_id == Some(id)),
+            )?;
+        }
+        for &(id, _) in &self.workspaces.removals {
+            ensure_unreferenced(
+                "Workspace",
+                id,
+                "Repository",
+                tables.repositories.values().any(|r| r.workspace_id == id),
+            )?;
+            ensure_unreferenced(
+                "Workspace",
+                id,
+                "RepositoryGroup",
+                tables
+                    .repository_groups
+                    .values()
+                    .any(|g| g.workspace_id == id),
+            )?;
+        }
+        for &(id, _) in &self.repositories.removals {
+            ensure_unreferenced(
+                "Repository",
+                id,
+                "RepositoryGroup",
+                tables
+                    .repository_groups
+                    .values()
+                    .any(|g| g.repository_ids.contains(&id)),
+            )?;
+            ensure_unreferenced(
+                "Repository",
+                id,
+                "TodoItem",
+                tables.todo_items.values().any(|i| i.repository_id == id),
+            )?;
+        }
+        for &(id, _) in &self.repository_groups.removals {
+            ensure_unreferenced(
+                "RepositoryGroup",
+                id,
+                "UnitTask",
+                tables
+                    .unit_tasks
+                    .values()
+                    .any(|t| t.repository_group_id == id),
+            )?;
+            ensure_unreferenced(
+                "RepositoryGroup",
+                id,
+                "CompositeTask",
+                tables
+                    .composite_tasks
+                    .values()
+                    .any(|t| t.repository_group_id == id),
+            )?;
+        }
+        for &(id, _) in &self.agent_tasks.removals {
+            ensure_unreferenced(
+                "AgentTask",
+                id,
+                "UnitTask",
+                tables
+                    .unit_tasks
+                    .values()
+                    .any(|t| t.agent_task_id == id || t.auto_fix_task_ids.contains(&id)),
+            )?;
+            ensure_unreferenced(
+                "AgentTask",
+                id,
+                "CompositeTask",
+                tables
+                    .composite_tasks
+                    .values()
+                    .any(|t| t.planning_task_id == id),
+            )?;
+            ensure_unreferenced(
+                "AgentTask",
+                id,
+                "AgentSession",
+                tables
+                    .agent_sessions
+                    .values()
+                    .any(|s| s.agent_task_id == id),
+            )?;
+            ensure_unreferenced(
+                "AgentTask",
+                id,
+                "QueueEntry",
+                tables.queue_entries.values().any(|e| e.agent_task_id == id),
+            )?;
+        }
+        for &(id, _) in &self.agent_sessions.removals {
+            ensure_unreferenced(
+                "AgentSession",
+                id,
+                "TtyInputRequest",
+                tables
+                    .tty_input_requests
+                    .values()
+                    .any(|r| r.session_id == id),
+            )?;
+        }
+        for &(id, _) in &self.unit_tasks.removals {
+            ensure_unreferenced(
+                "UnitTask",
+                id,
+                "CompositeTaskNode",
+                tables
+                    .composite_task_nodes
+                    .values()
+                    .any(|n| n.unit_task_id == id),
+            )?;
+            ensure_unreferenced(
+                "UnitTask",
+                id,
+                "TtyInputRequest",
+                tables.tty_input_requests.values().any(|r| r.task_id == id),
+            )?;
+            ensure_unreferenced(
+                "UnitTask",
+                id,
+                "Worker",
+                tables
+                    .workers
+                    .values()
+                    .any(|w| w.current_task_id == Some(id)),
+            )?;
+        }
+        for &(id, _) in &self.composite_tasks.removals {
+            ensure_unreferenced(
+                "CompositeTask",
+                id,
+                "CompositeTaskNode",
+                tables
+                    .composite_task_nodes
+                    .values()
+                    .any(|n| n.composite_task_id == id),
+            )?;
+            ensure_unreferenced(
+                "CompositeTask",
+                id,
+                "PlanRevision",
+                tables
+                    .plan_revisions
+                    .values()
+                    .any(|r| r.composite_task_id == id),
+            )?;
+        }
+        for &(id, _) in &self.composite_task_nodes.removals {
+            ensure_unreferenced(
+                "CompositeTaskNode",
+                id,
+                "CompositeTaskNode",
+                tables
+                    .composite_task_nodes
+                    .values()
+                    .any(|n| n.depends_on_ids.contains(&id)),
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl MemoryTaskStore {
@@ -36,10 +838,168 @@ impl MemoryTaskStore {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// Returns a handle sharing this store's tables.
+    fn share(&self) -> Self {
+        Self {
+            users: self.users.share(),
+            workspaces: self.workspaces.share(),
+            repositories: self.repositories.share(),
+            repository_groups: self.repository_groups.share(),
+            agent_tasks: self.agent_tasks.share(),
+            agent_sessions: self.agent_sessions.share(),
+            session_logs: self.session_logs.share(),
+            unit_tasks: self.unit_tasks.share(),
+            composite_tasks: self.composite_tasks.share(),
+            composite_task_nodes: self.composite_task_nodes.share(),
+            plan_revisions: self.plan_revisions.share(),
+            todo_items: self.todo_items.share(),
+            tty_input_requests: self.tty_input_requests.share(),
+            workers: self.workers.share(),
+            queue_entries: self.queue_entries.share(),
+            audit_events: self.audit_events.share(),
+            search_index: Arc::clone(&self.search_index),
+            changes: self.changes.clone(),
+            transaction: None,
+        }
+    }
+
+    /// Publishes a change event, or buffers it until commit inside a
+    /// transaction.
+    fn emit(&self, event: ChangeEvent) {
+        match &self.transaction {
+            Some(pending) => pending.events.lock().unwrap().push(event),
+            None => {
+                self.search_index.lock().unwrap().apply(&event);
+                self.changes.publish(event);
+            }
+        }
+    }
+
+    /// Writes the sessions embedded in `task` to the sessions table, as the
+    /// SQL stores do.
+    fn store_sessions(&self, task: &AgentTask, sessions: &mut HashMap<Uuid, AgentSession>) {
+        for session in &task.agent_sessions {
+            let value = EntityValue::AgentSession(session.clone());
+            match sessions.insert(session.id, session.clone()) {
+                Some(_) => self.emit(ChangeEvent::updated(value)),
+                None => self.emit(ChangeEvent::created(value)),
+            }
+        }
+    }
+
+    /// Takes write locks on every table, in field order.
+    async fn lock_tables(&self) -> TableGuards<'_> {
+        TableGuards {
+            users: self.users.write().await,
+            workspaces: self.workspaces.write().await,
+            repositories: self.repositories.write().await,
+            repository_groups: self.repository_groups.write().await,
+            agent_tasks: self.agent_tasks.write().await,
+            agent_sessions: self.agent_sessions.write().await,
+            session_logs: self.session_logs.write().await,
+            unit_tasks: self.unit_tasks.write().await,
+            composite_tasks: self.composite_tasks.write().await,
+            composite_task_nodes: self.composite_task_nodes.write().await,
+            plan_revisions: self.plan_revisions.write().await,
+            todo_items: self.todo_items.write().await,
+            tty_input_requests: self.tty_input_requests.write().await,
+            workers: self.workers.write().await,
+            queue_entries: self.queue_entries.write().await,
+            audit_events: self.audit_events.write().await,
+        }
+    }
+
+    /// Collects the rows a transaction wrote or removed.
+    async fn staged_changes(&self) -> TaskStoreResult<StoreChanges> {
+        Ok(StoreChanges {
+            users: self.users.changes().await?,
+            workspaces: self.workspaces.changes().await?,
+            repositories: self.repositories.changes().await?,
+            repository_groups: self.repository_groups.changes().await?,
+            agent_tasks: self.agent_tasks.changes().await?,
+            agent_sessions: self.agent_sessions.changes().await?,
+            session_logs: self.session_logs.changes().await?,
+            unit_tasks: self.unit_tasks.changes().await?,
+            composite_tasks: self.composite_tasks.changes().await?,
+            composite_task_nodes: self.composite_task_nodes.changes().await?,
+            plan_revisions: self.plan_revisions.changes().await?,
+            todo_items: self.todo_items.changes().await?,
+            tty_input_requests: self.tty_input_requests.changes().await?,
+            workers: self.workers.changes().await?,
+            queue_entries: self.queue_entries.changes().await?,
+            audit_events: self.audit_events.changes().await?,
+        })
+    }
+
+    /// Builds a full-text index over the searchable rows visible to this
+    /// handle.
+    async fn build_search_index(&self) -> SearchIndex {
+        let sessions = self.agent_sessions.read().await;
+        let logs = self.session_logs.read().await;
+        let unit_tasks = self.unit_tasks.read().await;
+        let composite_tasks = self.composite_tasks.read().await;
+        let values = unit_tasks
+            .values()
+            .cloned()
+            .map(EntityValue::UnitTask)
+            .chain(
+                composite_tasks
+                    .values()
+                    .cloned()
+                    .map(EntityValue::CompositeTask),
+            )
+            .chain(sessions.values().cloned().map(EntityValue::AgentSession));
+        SearchIndex::build(values, logs.values().flat_map(|log| &log.chunks))
+    }
 }
 
 #[async_trait]
 impl TaskStore for MemoryTaskStore {
+    // =========================================================================
+    // Transactions
+    // =========================================================================
+
+    async fn begin(&self) -> TaskStoreResult<Box<dyn TaskStoreTransaction>> {
+        if self.transaction.is_some() {
+            return Err(TaskStoreError::Other(
+                "Nested transactions are not supported".to_string(),
+            ));
+        }
+        Ok(Box::new(Self {
+            users: self.users.begin(),
+            workspaces: self.workspaces.begin(),
+            repositories: self.repositories.begin(),
+            repository_groups: self.repository_groups.begin(),
+            agent_tasks: self.agent_tasks.begin(),
+            agent_sessions: self.agent_sessions.begin(),
+            session_logs: self.session_logs.begin(),
+            unit_tasks: self.unit_tasks.begin(),
+            composite_tasks: self.composite_tasks.begin(),
+            composite_task_nodes: self.composite_task_nodes.begin(),
+            plan_revisions: self.plan_revisions.begin(),
+            todo_items: self.todo_items.begin(),
+            tty_input_requests: self.tty_input_requests.begin(),
+            workers: self.workers.begin(),
+            queue_entries: self.queue_entries.begin(),
+            audit_events: self.audit_events.begin(),
+            search_index: Arc::default(),
+            changes: self.changes.clone(),
+            transaction: Some(Box::new(PendingCommit {
+                parent: self.share(),
+                events: Mutex::new(Vec::new()),
+            })),
+        }))
+    }
+
+    // =========================================================================
+    // Change feed
+    // =========================================================================
+
+    async fn subscribe(&self, filter: ChangeFilter) -> TaskStoreResult<ChangeStream> {
+        Ok(self.changes.subscribe(filter))
+    }
+
     // =========================================================================
     // User operations
     // =========================================================================
@@ -50,6 +1010,7 @@ impl TaskStore for MemoryTaskStore {
             return Err(TaskStoreError::already_exists("User", user.id.to_string()));
         }
         users.insert(user.id, user.clone());
+        self.emit(ChangeEvent::created(EntityValue::User(user.clone())));
         Ok(user)
     }
 
@@ -63,20 +1024,39 @@ impl TaskStore for MemoryTaskStore {
         Ok(users.values().find(|u| u.email == email).cloned())
     }
 
-    async fn update_user(&self, user: User) -> TaskStoreResult<User> {
+    async fn list_users(&self) -> TaskStoreResult<Vec<User>> {
+        let users = self.users.read().await;
+        let mut result: Vec<User> = users.values().cloned().collect();
+        result.sort_by_key(|u| (u.created_at, u.id));
+        Ok(result)
+    }
+
+    async fn update_user(&self, mut user: User) -> TaskStoreResult<User> {
         let mut users = self.users.write().await;
-        if !users.contains_key(&user.id) {
-            return Err(TaskStoreError::not_found("User", user.id.to_string()));
-        }
+        let current = users
+            .get(&user.id)
+            .ok_or_else(|| TaskStoreError::not_found("User", user.id.to_string()))?;
+        check_revision("User", user.id, current.revision, user.revision)?;
+        user.revision += 1;
         users.insert(user.id, user.clone());
+        self.emit(ChangeEvent::updated(EntityValue::User(user.clone())));
         Ok(user)
     }
 
     async fn delete_user(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut users = self.users.write().await;
-        if users.remove(&id).is_none() {
+        let workspaces = self.workspaces.read().await;
+        if !users.contains_key(&id) {
             return Err(TaskStoreError::not_found("User", id.to_string()));
         }
+        ensure_unreferenced(
+            "User",
+            id,
+            "Workspace",
+            workspaces.values().any(|w| w.user_id == Some(id)),
+        )?;
+        users.remove(&id);
+        self.emit(ChangeEvent::deleted(EntityKind::User, id));
         Ok(())
     }
 
@@ -85,6 +1065,7 @@ impl TaskStore for MemoryTaskStore {
     // =========================================================================
 
     async fn create_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace> {
+        let users = self.users.read().await;
         let mut workspaces = self.workspaces.write().await;
         if workspaces.contains_key(&workspace.id) {
             return Err(TaskStoreError::already_exists(
@@ -92,7 +1073,13 @@ impl TaskStore for MemoryTaskStore {
                 workspace.id.to_string(),
             ));
         }
+        if let Some(user_id) = workspace.user_id {
+            ensure_reference(&users, "User", user_id)?;
+        }
         workspaces.insert(workspace.id, workspace.clone());
+        self.emit(ChangeEvent::created(EntityValue::Workspace(
+            workspace.clone(),
+        )));
         Ok(workspace)
     }
 
@@ -106,8 +1093,9 @@ impl TaskStore for MemoryTaskStore {
         filter: WorkspaceFilter,
     ) -> TaskStoreResult<(Vec<Workspace>, u32)> {
         let workspaces = self.workspaces.read().await;
-        let mut result: Vec<Workspace> = workspaces
+        let result: Vec<Workspace> = workspaces
             .values()
+            .filter(|w| filter.deleted.matches(w.deleted_at))
             .filter(|w| {
                 if let Some(user_id) = filter.user_id {
                     w.user_id == Some(user_id)
@@ -118,35 +1106,79 @@ impl TaskStore for MemoryTaskStore {
             .cloned()
             .collect();
 
-        let total = result.len() as u32;
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
 
-        if let Some(offset) = filter.offset {
-            result = result.into_iter().skip(offset as usize).collect();
-        }
-        if let Some(limit) = filter.limit {
-            result = result.into_iter().take(limit as usize).collect();
+    async fn update_workspace(&self, mut workspace: Workspace) -> TaskStoreResult<Workspace> {
+        let users = self.users.read().await;
+        let mut workspaces = self.workspaces.write().await;
+        let current = workspaces
+            .get(&workspace.id)
+            .ok_or_else(|| TaskStoreError::not_found("Workspace", workspace.id.to_string()))?;
+        check_revision(
+            "Workspace",
+            workspace.id,
+            current.revision,
+            workspace.revision,
+        )?;
+        if let Some(user_id) = workspace.user_id {
+            ensure_reference(&users, "User", user_id)?;
         }
+        workspace.revision += 1;
+        workspaces.insert(workspace.id, workspace.clone());
+        self.emit(ChangeEvent::updated(EntityValue::Workspace(
+            workspace.clone(),
+        )));
+        Ok(workspace)
+    }
 
-        Ok((result, total))
+    async fn delete_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut workspaces = self.workspaces.write().await;
+        let (workspace, changed) = set_deleted_at(&mut workspaces, id, Some(Utc::now()))?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::Workspace(workspace)));
+        }
+        Ok(())
     }
 
-    async fn update_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace> {
+    async fn restore_workspace(&self, id: Uuid) -> TaskStoreResult<Workspace> {
         let mut workspaces = self.workspaces.write().await;
-        if !workspaces.contains_key(&workspace.id) {
-            return Err(TaskStoreError::not_found(
-                "Workspace",
-                workspace.id.to_string(),
-            ));
+        let (workspace, changed) = set_deleted_at(&mut workspaces, id, None)?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::Workspace(
+                workspace.clone(),
+            )));
         }
-        workspaces.insert(workspace.id, workspace.clone());
         Ok(workspace)
     }
 
-    async fn delete_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+    async fn purge_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut workspaces = self.workspaces.write().await;
-        if workspaces.remove(&id).is_none() {
+        let repositories = self.repositories.read().await;
+        let groups = self.repository_groups.read().await;
+        if !workspaces.contains_key(&id) {
             return Err(TaskStoreError::not_found("Workspace", id.to_string()));
         }
+        ensure_unreferenced(
+            "Workspace",
+            id,
+            "Repository",
+            repositories.values().any(|r| r.workspace_id == id),
+        )?;
+        ensure_unreferenced(
+            "Workspace",
+            id,
+            "RepositoryGroup",
+            groups.values().any(|g| g.workspace_id == id),
+        )?;
+        workspaces.remove(&id);
+        self.emit(ChangeEvent::deleted(EntityKind::Workspace, id));
         Ok(())
     }
 
@@ -155,6 +1187,7 @@ impl TaskStore for MemoryTaskStore {
     // =========================================================================
 
     async fn create_repository(&self, repository: Repository) -> TaskStoreResult<Repository> {
+        let workspaces = self.workspaces.read().await;
         let mut repositories = self.repositories.write().await;
         if repositories.contains_key(&repository.id) {
             return Err(TaskStoreError::already_exists(
@@ -162,7 +1195,11 @@ impl TaskStore for MemoryTaskStore {
                 repository.id.to_string(),
             ));
         }
+        ensure_reference(&workspaces, "Workspace", repository.workspace_id)?;
         repositories.insert(repository.id, repository.clone());
+        self.emit(ChangeEvent::created(EntityValue::Repository(
+            repository.clone(),
+        )));
         Ok(repository)
     }
 
@@ -176,8 +1213,9 @@ impl TaskStore for MemoryTaskStore {
         filter: RepositoryFilter,
     ) -> TaskStoreResult<(Vec<Repository>, u32)> {
         let repositories = self.repositories.read().await;
-        let mut result: Vec<Repository> = repositories
+        let result: Vec<Repository> = repositories
             .values()
+            .filter(|r| filter.deleted.matches(r.deleted_at))
             .filter(|r| {
                 if let Some(workspace_id) = filter.workspace_id {
                     r.workspace_id == workspace_id
@@ -188,35 +1226,77 @@ impl TaskStore for MemoryTaskStore {
             .cloned()
             .collect();
 
-        let total = result.len() as u32;
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
 
-        if let Some(offset) = filter.offset {
-            result = result.into_iter().skip(offset as usize).collect();
-        }
-        if let Some(limit) = filter.limit {
-            result = result.into_iter().take(limit as usize).collect();
-        }
+    async fn update_repository(&self, mut repository: Repository) -> TaskStoreResult<Repository> {
+        let workspaces = self.workspaces.read().await;
+        let mut repositories = self.repositories.write().await;
+        let current = repositories
+            .get(&repository.id)
+            .ok_or_else(|| TaskStoreError::not_found("Repository", repository.id.to_string()))?;
+        check_revision(
+            "Repository",
+            repository.id,
+            current.revision,
+            repository.revision,
+        )?;
+        ensure_reference(&workspaces, "Workspace", repository.workspace_id)?;
+        repository.revision += 1;
+        repositories.insert(repository.id, repository.clone());
+        self.emit(ChangeEvent::updated(EntityValue::Repository(
+            repository.clone(),
+        )));
+        Ok(repository)
+    }
 
-        Ok((result, total))
+    async fn delete_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut repositories = self.repositories.write().await;
+        let (repository, changed) = set_deleted_at(&mut repositories, id, Some(Utc::now()))?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::Repository(repository)));
+        }
+        Ok(())
     }
 
-    async fn update_repository(&self, repository: Repository) -> TaskStoreResult<Repository> {
+    async fn restore_repository(&self, id: Uuid) -> TaskStoreResult<Repository> {
         let mut repositories = self.repositories.write().await;
-        if !repositories.contains_key(&repository.id) {
-            return Err(TaskStoreError::not_found(
-                "Repository",
-                repository.id.to_string(),
-            ));
+        let (repository, changed) = set_deleted_at(&mut repositories, id, None)?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::Repository(
+                repository.clone(),
+            )));
         }
-        repositories.insert(repository.id, repository.clone());
         Ok(repository)
     }
 
-    async fn delete_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+    async fn purge_repository(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut repositories = self.repositories.write().await;
-        if repositories.remove(&id).is_none() {
+        let groups = self.repository_groups.read().await;
+        let items = self.todo_items.read().await;
+        if !repositories.contains_key(&id) {
             return Err(TaskStoreError::not_found("Repository", id.to_string()));
         }
+        ensure_unreferenced(
+            "Repository",
+            id,
+            "RepositoryGroup",
+            groups.values().any(|g| g.repository_ids.contains(&id)),
+        )?;
+        ensure_unreferenced(
+            "Repository",
+            id,
+            "TodoItem",
+            items.values().any(|i| i.repository_id == id),
+        )?;
+        repositories.remove(&id);
+        self.emit(ChangeEvent::deleted(EntityKind::Repository, id));
         Ok(())
     }
 
@@ -228,6 +1308,8 @@ impl TaskStore for MemoryTaskStore {
         &self,
         group: RepositoryGroup,
     ) -> TaskStoreResult<RepositoryGroup> {
+        let workspaces = self.workspaces.read().await;
+        let repositories = self.repositories.read().await;
         let mut groups = self.repository_groups.write().await;
         if groups.contains_key(&group.id) {
             return Err(TaskStoreError::already_exists(
@@ -235,7 +1317,11 @@ impl TaskStore for MemoryTaskStore {
                 group.id.to_string(),
             ));
         }
+        ensure_group_references(&group, &workspaces, &repositories)?;
         groups.insert(group.id, group.clone());
+        self.emit(ChangeEvent::created(EntityValue::RepositoryGroup(
+            group.clone(),
+        )));
         Ok(group)
     }
 
@@ -249,7 +1335,7 @@ impl TaskStore for MemoryTaskStore {
         workspace_id: Option<Uuid>,
     ) -> TaskStoreResult<Vec<RepositoryGroup>> {
         let groups = self.repository_groups.read().await;
-        Ok(groups
+        let mut result: Vec<RepositoryGroup> = groups
             .values()
             .filter(|g| {
                 if let Some(ws_id) = workspace_id {
@@ -259,29 +1345,59 @@ impl TaskStore for MemoryTaskStore {
                 }
             })
             .cloned()
-            .collect())
+            .collect();
+        result.sort_by_key(|g| (g.created_at, g.id));
+        Ok(result)
     }
 
     async fn update_repository_group(
         &self,
-        group: RepositoryGroup,
+        mut group: RepositoryGroup,
     ) -> TaskStoreResult<RepositoryGroup> {
+        let workspaces = self.workspaces.read().await;
+        let repositories = self.repositories.read().await;
         let mut groups = self.repository_groups.write().await;
-        if !groups.contains_key(&group.id) {
-            return Err(TaskStoreError::not_found(
-                "RepositoryGroup",
-                group.id.to_string(),
-            ));
-        }
+        let current = groups
+            .get(&group.id)
+            .ok_or_else(|| TaskStoreError::not_found("RepositoryGroup", group.id.to_string()))?;
+        check_revision(
+            "RepositoryGroup",
+            group.id,
+            current.revision,
+            group.revision,
+        )?;
+        ensure_group_references(&group, &workspaces, &repositories)?;
+        group.revision += 1;
         groups.insert(group.id, group.clone());
+        self.emit(ChangeEvent::updated(EntityValue::RepositoryGroup(
+            group.clone(),
+        )));
         Ok(group)
     }
 
     async fn delete_repository_group(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut groups = self.repository_groups.write().await;
-        if groups.remove(&id).is_none() {
+        let unit_tasks = self.unit_tasks.read().await;
+        let composite_tasks = self.composite_tasks.read().await;
+        if !groups.contains_key(&id) {
             return Err(TaskStoreError::not_found("RepositoryGroup", id.to_string()));
         }
+        ensure_unreferenced(
+            "RepositoryGroup",
+            id,
+            "UnitTask",
+            unit_tasks.values().any(|t| t.repository_group_id == id),
+        )?;
+        ensure_unreferenced(
+            "RepositoryGroup",
+            id,
+            "CompositeTask",
+            composite_tasks
+                .values()
+                .any(|t| t.repository_group_id == id),
+        )?;
+        groups.remove(&id);
+        self.emit(ChangeEvent::deleted(EntityKind::RepositoryGroup, id));
         Ok(())
     }
 
@@ -291,6 +1407,7 @@ impl TaskStore for MemoryTaskStore {
 
     async fn create_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask> {
         let mut tasks = self.agent_tasks.write().await;
+        let mut sessions = self.agent_sessions.write().await;
         if tasks.contains_key(&task.id) {
             return Err(TaskStoreError::already_exists(
                 "AgentTask",
@@ -298,28 +1415,103 @@ impl TaskStore for MemoryTaskStore {
             ));
         }
         tasks.insert(task.id, task.clone());
+        self.emit(ChangeEvent::created(EntityValue::AgentTask(task.clone())));
+        self.store_sessions(&task, &mut sessions);
         Ok(task)
     }
 
     async fn get_agent_task(&self, id: Uuid) -> TaskStoreResult<Option<AgentTask>> {
         let tasks = self.agent_tasks.read().await;
-        Ok(tasks.get(&id).cloned())
+        let sessions = self.agent_sessions.read().await;
+        Ok(tasks.get(&id).map(|task| with_sessions(task, &sessions)))
+    }
+
+    async fn get_agent_tasks(&self, ids: &[Uuid]) -> TaskStoreResult<Vec<AgentTask>> {
+        let tasks = self.agent_tasks.read().await;
+        let sessions = self.agent_sessions.read().await;
+        let ids: HashSet<Uuid> = ids.iter().copied().collect();
+        let mut result: Vec<AgentTask> = ids
+            .iter()
+            .filter_map(|id| tasks.get(id))
+            .map(|task| with_sessions(task, &sessions))
+            .collect();
+        result.sort_by_key(|t| (t.created_at, t.id));
+        Ok(result)
+    }
+
+    async fn list_agent_tasks(&self) -> TaskStoreResult<Vec<AgentTask>> {
+        let tasks = self.agent_tasks.read().await;
+        let sessions = self.agent_sessions.read().await;
+        let mut result: Vec<AgentTask> = tasks
+            .values()
+            .map(|task| with_sessions(task, &sessions))
+            .collect();
+        result.sort_by_key(|t| (t.created_at, t.id));
+        Ok(result)
     }
 
-    async fn update_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask> {
+    async fn update_agent_task(&self, mut task: AgentTask) -> TaskStoreResult<AgentTask> {
         let mut tasks = self.agent_tasks.write().await;
-        if !tasks.contains_key(&task.id) {
-            return Err(TaskStoreError::not_found("AgentTask", task.id.to_string()));
-        }
+        let mut sessions = self.agent_sessions.write().await;
+        let current = tasks
+            .get(&task.id)
+            .ok_or_else(|| TaskStoreError::not_found("AgentTask", task.id.to_string()))?;
+        check_revision("AgentTask", task.id, current.revision, task.revision)?;
+        task.revision += 1;
         tasks.insert(task.id, task.clone());
+        self.emit(ChangeEvent::updated(EntityValue::AgentTask(task.clone())));
+        self.store_sessions(&task, &mut sessions);
         Ok(task)
     }
 
     async fn delete_agent_task(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut tasks = self.agent_tasks.write().await;
-        if tasks.remove(&id).is_none() {
+        let mut sessions = self.agent_sessions.write().await;
+        let mut logs = self.session_logs.write().await;
+        let unit_tasks = self.unit_tasks.read().await;
+        let composite_tasks = self.composite_tasks.read().await;
+        let requests = self.tty_input_requests.read().await;
+        let mut queue = self.queue_entries.write().await;
+        if !tasks.contains_key(&id) {
             return Err(TaskStoreError::not_found("AgentTask", id.to_string()));
         }
+        ensure_unreferenced(
+            "AgentTask",
+            id,
+            "UnitTask",
+            unit_tasks
+                .values()
+                .any(|t| t.agent_task_id == id || t.auto_fix_task_ids.contains(&id)),
+        )?;
+        ensure_unreferenced(
+            "AgentTask",
+            id,
+            "CompositeTask",
+            composite_tasks.values().any(|t| t.planning_task_id == id),
+        )?;
+
+        // Sessions belong to their agent task and are deleted with it.
+        let session_ids: Vec<Uuid> = sessions
+            .values()
+            .filter(|s| s.agent_task_id == id)
+            .map(|s| s.id)
+            .collect();
+        for session_id in &session_ids {
+            ensure_unreferenced(
+                "AgentSession",
+                *session_id,
+                "TtyInputRequest",
+                requests.values().any(|r| r.session_id == *session_id),
+            )?;
+        }
+        tasks.remove(&id);
+        queue.retain(|_, entry| entry.agent_task_id != id);
+        for session_id in session_ids {
+            sessions.remove(&session_id);
+            logs.remove(&session_id);
+            self.emit(ChangeEvent::deleted(EntityKind::AgentSession, session_id));
+        }
+        self.emit(ChangeEvent::deleted(EntityKind::AgentTask, id));
         Ok(())
     }
 
@@ -328,6 +1520,7 @@ impl TaskStore for MemoryTaskStore {
     // =========================================================================
 
     async fn create_agent_session(&self, session: AgentSession) -> TaskStoreResult<AgentSession> {
+        let tasks = self.agent_tasks.read().await;
         let mut sessions = self.agent_sessions.write().await;
         if sessions.contains_key(&session.id) {
             return Err(TaskStoreError::already_exists(
@@ -335,7 +1528,11 @@ impl TaskStore for MemoryTaskStore {
                 session.id.to_string(),
             ));
         }
+        ensure_reference(&tasks, "AgentTask", session.agent_task_id)?;
         sessions.insert(session.id, session.clone());
+        self.emit(ChangeEvent::created(EntityValue::AgentSession(
+            session.clone(),
+        )));
         Ok(session)
     }
 
@@ -346,38 +1543,113 @@ impl TaskStore for MemoryTaskStore {
 
     async fn list_agent_sessions(&self, agent_task_id: Uuid) -> TaskStoreResult<Vec<AgentSession>> {
         let sessions = self.agent_sessions.read().await;
-        Ok(sessions
+        let mut result: Vec<AgentSession> = sessions
             .values()
             .filter(|s| s.agent_task_id == agent_task_id)
             .cloned()
-            .collect())
+            .collect();
+        result.sort_by_key(|s| (s.created_at, s.id));
+        Ok(result)
     }
 
-    async fn update_agent_session(&self, session: AgentSession) -> TaskStoreResult<AgentSession> {
+    async fn update_agent_session(
+        &self,
+        mut session: AgentSession,
+    ) -> TaskStoreResult<AgentSession> {
+        let tasks = self.agent_tasks.read().await;
         let mut sessions = self.agent_sessions.write().await;
-        if !sessions.contains_key(&session.id) {
-            return Err(TaskStoreError::not_found(
-                "AgentSession",
-                session.id.to_string(),
-            ));
-        }
+        let current = sessions
+            .get(&session.id)
+            .ok_or_else(|| TaskStoreError::not_found("AgentSession", session.id.to_string()))?;
+        check_revision(
+            "AgentSession",
+            session.id,
+            current.revision,
+            session.revision,
+        )?;
+        ensure_reference(&tasks, "AgentTask", session.agent_task_id)?;
+        session.revision += 1;
         sessions.insert(session.id, session.clone());
+        self.emit(ChangeEvent::updated(EntityValue::AgentSession(
+            session.clone(),
+        )));
         Ok(session)
     }
 
     async fn delete_agent_session(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut sessions = self.agent_sessions.write().await;
-        if sessions.remove(&id).is_none() {
+        let mut logs = self.session_logs.write().await;
+        let requests = self.tty_input_requests.read().await;
+        if !sessions.contains_key(&id) {
             return Err(TaskStoreError::not_found("AgentSession", id.to_string()));
         }
+        ensure_unreferenced(
+            "AgentSession",
+            id,
+            "TtyInputRequest",
+            requests.values().any(|r| r.session_id == id),
+        )?;
+        sessions.remove(&id);
+        logs.remove(&id);
+        self.emit(ChangeEvent::deleted(EntityKind::AgentSession, id));
         Ok(())
     }
 
+    // =========================================================================
+    // Session log operations
+    // =========================================================================
+
+    async fn append_session_log(
+        &self,
+        session_id: Uuid,
+        content: String,
+    ) -> TaskStoreResult<LogChunk> {
+        let sessions = self.agent_sessions.read().await;
+        let mut logs = self.session_logs.write().await;
+        ensure_reference(&sessions, "AgentSession", session_id)?;
+        let log = logs.entry(session_id).or_default();
+        let size = LogSize::ending_with(log.chunks.last());
+        let chunk = LogChunk {
+            session_id,
+            sequence: size.chunks,
+            offset: size.bytes,
+            content,
+            created_at: Utc::now(),
+        };
+        log.chunks.push(chunk.clone());
+        // A transaction indexes the chunks it appended when it commits.
+        if self.transaction.is_none() {
+            self.search_index.lock().unwrap().append_log(&chunk);
+        }
+        Ok(chunk)
+    }
+
+    async fn read_session_log(
+        &self,
+        session_id: Uuid,
+        range: LogRange,
+    ) -> TaskStoreResult<Vec<LogChunk>> {
+        let logs = self.session_logs.read().await;
+        Ok(logs
+            .get(&session_id)
+            .map(|log| session_log::select(&log.chunks, range))
+            .unwrap_or_default())
+    }
+
+    async fn session_log_size(&self, session_id: Uuid) -> TaskStoreResult<LogSize> {
+        let logs = self.session_logs.read().await;
+        Ok(LogSize::ending_with(
+            logs.get(&session_id).and_then(|log| log.chunks.last()),
+        ))
+    }
+
     // =========================================================================
     // Unit Task operations
     // =========================================================================
 
     async fn create_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask> {
+        let groups = self.repository_groups.read().await;
+        let agent_tasks = self.agent_tasks.read().await;
         let mut tasks = self.unit_tasks.write().await;
         if tasks.contains_key(&task.id) {
             return Err(TaskStoreError::already_exists(
@@ -385,7 +1657,9 @@ impl TaskStore for MemoryTaskStore {
                 task.id.to_string(),
             ));
         }
+        ensure_unit_task_references(&task, &groups, &agent_tasks)?;
         tasks.insert(task.id, task.clone());
+        self.emit(ChangeEvent::created(EntityValue::UnitTask(task.clone())));
         Ok(task)
     }
 
@@ -396,47 +1670,131 @@ impl TaskStore for MemoryTaskStore {
 
     async fn list_unit_tasks(&self, filter: TaskFilter) -> TaskStoreResult<(Vec<UnitTask>, u32)> {
         let tasks = self.unit_tasks.read().await;
-        let mut result: Vec<UnitTask> = tasks
+        let result: Vec<UnitTask> = tasks
             .values()
             .filter(|t| {
-                let mut matches = true;
+                let mut matches = filter.deleted.matches(t.deleted_at);
                 if let Some(group_id) = filter.repository_group_id {
                     matches = matches && t.repository_group_id == group_id;
                 }
                 if let Some(status) = filter.unit_status {
                     matches = matches && t.status == status;
                 }
+                if let Some(since) = filter.created_since {
+                    matches = matches && t.created_at >= since;
+                }
+                if let Some(until) = filter.created_until {
+                    matches = matches && t.created_at < until;
+                }
                 matches
             })
             .cloned()
             .collect();
 
-        let total = result.len() as u32;
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
 
-        if let Some(offset) = filter.offset {
-            result = result.into_iter().skip(offset as usize).collect();
-        }
-        if let Some(limit) = filter.limit {
-            result = result.into_iter().take(limit as usize).collect();
-        }
+    async fn find_unit_task_by_pr_url(&self, pr_url: &str) -> TaskStoreResult<Option<UnitTask>> {
+        let tasks = self.unit_tasks.read().await;
+        Ok(newest(
+            tasks
+                .values()
+                .filter(|t| t.linked_pr_url.as_deref() == Some(pr_url)),
+        ))
+    }
+
+    async fn find_unit_task_by_branch_name(
+        &self,
+        branch_name: &str,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        let tasks = self.unit_tasks.read().await;
+        Ok(newest(
+            tasks
+                .values()
+                .filter(|t| t.branch_name.as_deref() == Some(branch_name)),
+        ))
+    }
 
-        Ok((result, total))
+    async fn find_unit_task_by_agent_task(
+        &self,
+        agent_task_id: Uuid,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        let tasks = self.unit_tasks.read().await;
+        Ok(newest(tasks.values().filter(|t| {
+            t.agent_task_id == agent_task_id || t.auto_fix_task_ids.contains(&agent_task_id)
+        })))
     }
 
-    async fn update_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask> {
+    async fn update_unit_task(&self, mut task: UnitTask) -> TaskStoreResult<UnitTask> {
+        let groups = self.repository_groups.read().await;
+        let agent_tasks = self.agent_tasks.read().await;
         let mut tasks = self.unit_tasks.write().await;
-        if !tasks.contains_key(&task.id) {
-            return Err(TaskStoreError::not_found("UnitTask", task.id.to_string()));
-        }
+        let current = tasks
+            .get(&task.id)
+            .ok_or_else(|| TaskStoreError::not_found("UnitTask", task.id.to_string()))?;
+        check_revision("UnitTask", task.id, current.revision, task.revision)?;
+        ensure_transition(current.status, task.status)?;
+        ensure_unit_task_references(&task, &groups, &agent_tasks)?;
+        task.revision += 1;
         tasks.insert(task.id, task.clone());
+        self.emit(ChangeEvent::updated(EntityValue::UnitTask(task.clone())));
         Ok(task)
     }
 
     async fn delete_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut unit_tasks = self.unit_tasks.write().await;
+        let (unit_task, changed) = set_deleted_at(&mut unit_tasks, id, Some(Utc::now()))?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::UnitTask(unit_task)));
+        }
+        Ok(())
+    }
+
+    async fn restore_unit_task(&self, id: Uuid) -> TaskStoreResult<UnitTask> {
+        let mut unit_tasks = self.unit_tasks.write().await;
+        let (unit_task, changed) = set_deleted_at(&mut unit_tasks, id, None)?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::UnitTask(
+                unit_task.clone(),
+            )));
+        }
+        Ok(unit_task)
+    }
+
+    async fn purge_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut tasks = self.unit_tasks.write().await;
-        if tasks.remove(&id).is_none() {
+        let nodes = self.composite_task_nodes.read().await;
+        let requests = self.tty_input_requests.read().await;
+        let mut workers = self.workers.write().await;
+        if !tasks.contains_key(&id) {
             return Err(TaskStoreError::not_found("UnitTask", id.to_string()));
         }
+        ensure_unreferenced(
+            "UnitTask",
+            id,
+            "CompositeTaskNode",
+            nodes.values().any(|n| n.unit_task_id == id),
+        )?;
+        ensure_unreferenced(
+            "UnitTask",
+            id,
+            "TtyInputRequest",
+            requests.values().any(|r| r.task_id == id),
+        )?;
+        tasks.remove(&id);
+        // Like `ON DELETE SET NULL` in the SQL schema.
+        for worker in workers.values_mut() {
+            if worker.current_task_id == Some(id) {
+                worker.current_task_id = None;
+            }
+        }
+        self.emit(ChangeEvent::deleted(EntityKind::UnitTask, id));
         Ok(())
     }
 
@@ -445,6 +1803,8 @@ impl TaskStore for MemoryTaskStore {
     // =========================================================================
 
     async fn create_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask> {
+        let groups = self.repository_groups.read().await;
+        let agent_tasks = self.agent_tasks.read().await;
         let mut tasks = self.composite_tasks.write().await;
         if tasks.contains_key(&task.id) {
             return Err(TaskStoreError::already_exists(
@@ -452,7 +1812,12 @@ impl TaskStore for MemoryTaskStore {
                 task.id.to_string(),
             ));
         }
+        ensure_reference(&groups, "RepositoryGroup", task.repository_group_id)?;
+        ensure_reference(&agent_tasks, "AgentTask", task.planning_task_id)?;
         tasks.insert(task.id, task.clone());
+        self.emit(ChangeEvent::created(EntityValue::CompositeTask(
+            task.clone(),
+        )));
         Ok(task)
     }
 
@@ -466,50 +1831,117 @@ impl TaskStore for MemoryTaskStore {
         filter: TaskFilter,
     ) -> TaskStoreResult<(Vec<CompositeTask>, u32)> {
         let tasks = self.composite_tasks.read().await;
-        let mut result: Vec<CompositeTask> = tasks
+        let result: Vec<CompositeTask> = tasks
             .values()
             .filter(|t| {
-                let mut matches = true;
+                let mut matches = filter.deleted.matches(t.deleted_at);
                 if let Some(group_id) = filter.repository_group_id {
                     matches = matches && t.repository_group_id == group_id;
                 }
                 if let Some(status) = filter.composite_status {
                     matches = matches && t.status == status;
                 }
+                if let Some(since) = filter.created_since {
+                    matches = matches && t.created_at >= since;
+                }
+                if let Some(until) = filter.created_until {
+                    matches = matches && t.created_at < until;
+                }
                 matches
             })
             .cloned()
             .collect();
 
-        let total = result.len() as u32;
-
-        if let Some(offset) = filter.offset {
-            result = result.into_iter().skip(offset as usize).collect();
-        }
-        if let Some(limit) = filter.limit {
-            result = result.into_iter().take(limit as usize).collect();
-        }
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
 
-        Ok((result, total))
+    async fn find_composite_task_by_node_unit_task(
+        &self,
+        unit_task_id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTask>> {
+        let composite_task_ids: HashSet<Uuid> = {
+            let nodes = self.composite_task_nodes.read().await;
+            nodes
+                .values()
+                .filter(|n| n.unit_task_id == unit_task_id)
+                .map(|n| n.composite_task_id)
+                .collect()
+        };
+        let tasks = self.composite_tasks.read().await;
+        Ok(newest(
+            tasks
+                .values()
+                .filter(|t| composite_task_ids.contains(&t.id)),
+        ))
     }
 
-    async fn update_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask> {
+    async fn update_composite_task(
+        &self,
+        mut task: CompositeTask,
+    ) -> TaskStoreResult<CompositeTask> {
+        let groups = self.repository_groups.read().await;
+        let agent_tasks = self.agent_tasks.read().await;
         let mut tasks = self.composite_tasks.write().await;
-        if !tasks.contains_key(&task.id) {
-            return Err(TaskStoreError::not_found(
-                "CompositeTask",
-                task.id.to_string(),
-            ));
-        }
+        let current = tasks
+            .get(&task.id)
+            .ok_or_else(|| TaskStoreError::not_found("CompositeTask", task.id.to_string()))?;
+        check_revision("CompositeTask", task.id, current.revision, task.revision)?;
+        ensure_transition(current.status, task.status)?;
+        ensure_reference(&groups, "RepositoryGroup", task.repository_group_id)?;
+        ensure_reference(&agent_tasks, "AgentTask", task.planning_task_id)?;
+        task.revision += 1;
         tasks.insert(task.id, task.clone());
+        self.emit(ChangeEvent::updated(EntityValue::CompositeTask(
+            task.clone(),
+        )));
         Ok(task)
     }
 
     async fn delete_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut composite_tasks = self.composite_tasks.write().await;
+        let (composite_task, changed) = set_deleted_at(&mut composite_tasks, id, Some(Utc::now()))?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::CompositeTask(
+                composite_task,
+            )));
+        }
+        Ok(())
+    }
+
+    async fn restore_composite_task(&self, id: Uuid) -> TaskStoreResult<CompositeTask> {
+        let mut composite_tasks = self.composite_tasks.write().await;
+        let (composite_task, changed) = set_deleted_at(&mut composite_tasks, id, None)?;
+        if changed {
+            self.emit(ChangeEvent::updated(EntityValue::CompositeTask(
+                composite_task.clone(),
+            )));
+        }
+        Ok(composite_task)
+    }
+
+    async fn purge_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut tasks = self.composite_tasks.write().await;
-        if tasks.remove(&id).is_none() {
+        let nodes = self.composite_task_nodes.read().await;
+        let mut revisions = self.plan_revisions.write().await;
+        if !tasks.contains_key(&id) {
             return Err(TaskStoreError::not_found("CompositeTask", id.to_string()));
         }
+        ensure_unreferenced(
+            "CompositeTask",
+            id,
+            "CompositeTaskNode",
+            nodes.values().any(|n| n.composite_task_id == id),
+        )?;
+        tasks.remove(&id);
+        // Like `ON DELETE CASCADE` in the SQL schema.
+        revisions.retain(|_, r| r.composite_task_id != id);
+        self.emit(ChangeEvent::deleted(EntityKind::CompositeTask, id));
         Ok(())
     }
 
@@ -521,6 +1953,8 @@ impl TaskStore for MemoryTaskStore {
         &self,
         node: CompositeTaskNode,
     ) -> TaskStoreResult<CompositeTaskNode> {
+        let unit_tasks = self.unit_tasks.read().await;
+        let composite_tasks = self.composite_tasks.read().await;
         let mut nodes = self.composite_task_nodes.write().await;
         if nodes.contains_key(&node.id) {
             return Err(TaskStoreError::already_exists(
@@ -528,7 +1962,11 @@ impl TaskStore for MemoryTaskStore {
                 node.id.to_string(),
             ));
         }
+        ensure_node_references(&node, &composite_tasks, &unit_tasks, &nodes)?;
         nodes.insert(node.id, node.clone());
+        self.emit(ChangeEvent::created(EntityValue::CompositeTaskNode(
+            node.clone(),
+        )));
         Ok(node)
     }
 
@@ -545,44 +1983,142 @@ impl TaskStore for MemoryTaskStore {
         composite_task_id: Uuid,
     ) -> TaskStoreResult<Vec<CompositeTaskNode>> {
         let nodes = self.composite_task_nodes.read().await;
-        Ok(nodes
+        let mut result: Vec<CompositeTaskNode> = nodes
             .values()
             .filter(|n| n.composite_task_id == composite_task_id)
             .cloned()
-            .collect())
+            .collect();
+        result.sort_by_key(|n| (n.created_at, n.id));
+        Ok(result)
     }
 
     async fn update_composite_task_node(
         &self,
-        node: CompositeTaskNode,
+        mut node: CompositeTaskNode,
     ) -> TaskStoreResult<CompositeTaskNode> {
+        let unit_tasks = self.unit_tasks.read().await;
+        let composite_tasks = self.composite_tasks.read().await;
         let mut nodes = self.composite_task_nodes.write().await;
-        if !nodes.contains_key(&node.id) {
-            return Err(TaskStoreError::not_found(
-                "CompositeTaskNode",
-                node.id.to_string(),
-            ));
-        }
+        let current = nodes
+            .get(&node.id)
+            .ok_or_else(|| TaskStoreError::not_found("CompositeTaskNode", node.id.to_string()))?;
+        check_revision(
+            "CompositeTaskNode",
+            node.id,
+            current.revision,
+            node.revision,
+        )?;
+        ensure_node_references(&node, &composite_tasks, &unit_tasks, &nodes)?;
+        node.revision += 1;
         nodes.insert(node.id, node.clone());
+        self.emit(ChangeEvent::updated(EntityValue::CompositeTaskNode(
+            node.clone(),
+        )));
         Ok(node)
     }
 
     async fn delete_composite_task_node(&self, id: Uuid) -> TaskStoreResult<()> {
         let mut nodes = self.composite_task_nodes.write().await;
-        if nodes.remove(&id).is_none() {
+        if !nodes.contains_key(&id) {
             return Err(TaskStoreError::not_found(
                 "CompositeTaskNode",
                 id.to_string(),
             ));
         }
+        ensure_unreferenced(
+            "CompositeTaskNode",
+            id,
+            "CompositeTaskNode",
+            nodes.values().any(|n| n.depends_on_ids.contains(&id)),
+        )?;
+        nodes.remove(&id);
+        self.emit(ChangeEvent::deleted(EntityKind::CompositeTaskNode, id));
         Ok(())
     }
 
+    // =========================================================================
+    // Plan Revision operations
+    // =========================================================================
+
+    async fn create_plan_revision(
+        &self,
+        mut revision: PlanRevision,
+    ) -> TaskStoreResult<PlanRevision> {
+        let composite_tasks = self.composite_tasks.read().await;
+        let mut revisions = self.plan_revisions.write().await;
+        if revisions.contains_key(&revision.id) {
+            return Err(TaskStoreError::already_exists(
+                "PlanRevision",
+                revision.id.to_string(),
+            ));
+        }
+        ensure_reference(
+            &composite_tasks,
+            "CompositeTask",
+            revision.composite_task_id,
+        )?;
+        revision.number = latest_plan_revision(&revisions, revision.composite_task_id) + 1;
+        revisions.insert(revision.id, revision.clone());
+        self.emit(ChangeEvent::created(EntityValue::PlanRevision(
+            revision.clone(),
+        )));
+        Ok(revision)
+    }
+
+    async fn get_plan_revision(&self, id: Uuid) -> TaskStoreResult<Option<PlanRevision>> {
+        let revisions = self.plan_revisions.read().await;
+        Ok(revisions.get(&id).cloned())
+    }
+
+    async fn list_plan_revisions(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<PlanRevision>> {
+        let revisions = self.plan_revisions.read().await;
+        let mut result: Vec<PlanRevision> = revisions
+            .values()
+            .filter(|r| r.composite_task_id == composite_task_id)
+            .cloned()
+            .collect();
+        result.sort_by_key(|r| r.number);
+        Ok(result)
+    }
+
+    async fn approve_plan_revision(
+        &self,
+        id: Uuid,
+        approved_by: Uuid,
+    ) -> TaskStoreResult<PlanRevision> {
+        let mut revisions = self.plan_revisions.write().await;
+        let revision = revisions
+            .get(&id)
+            .ok_or_else(|| TaskStoreError::not_found("PlanRevision", id.to_string()))?;
+        if revision.is_approved() {
+            return Ok(revision.clone());
+        }
+        let latest = latest_plan_revision(&revisions, revision.composite_task_id);
+        if revision.number != latest {
+            return Err(TaskStoreError::conflict(
+                "PlanRevision",
+                id.to_string(),
+                latest as u64,
+            ));
+        }
+        let revision = revisions.get_mut(&id).expect("revision exists");
+        revision.approved_by = Some(approved_by);
+        revision.approved_at = Some(Utc::now());
+        self.emit(ChangeEvent::updated(EntityValue::PlanRevision(
+            revision.clone(),
+        )));
+        Ok(revision.clone())
+    }
+
     // =========================================================================
     // Todo Item operations
     // =========================================================================
 
     async fn create_todo_item(&self, item: TodoItem) -> TaskStoreResult<TodoItem> {
+        let repositories = self.repositories.read().await;
         let mut items = self.todo_items.write().await;
         if items.contains_key(&item.id) {
             return Err(TaskStoreError::already_exists(
@@ -590,7 +2126,9 @@ impl TaskStore for MemoryTaskStore {
                 item.id.to_string(),
             ));
         }
+        ensure_reference(&repositories, "Repository", item.repository_id)?;
         items.insert(item.id, item.clone());
+        self.emit(ChangeEvent::created(EntityValue::TodoItem(item.clone())));
         Ok(item)
     }
 
@@ -601,7 +2139,7 @@ impl TaskStore for MemoryTaskStore {
 
     async fn list_todo_items(&self, filter: TodoFilter) -> TaskStoreResult<(Vec<TodoItem>, u32)> {
         let items = self.todo_items.read().await;
-        let mut result: Vec<TodoItem> = items
+        let result: Vec<TodoItem> = items
             .values()
             .filter(|i| {
                 let mut matches = true;
@@ -616,24 +2154,27 @@ impl TaskStore for MemoryTaskStore {
             .cloned()
             .collect();
 
-        let total = result.len() as u32;
-
-        if let Some(offset) = filter.offset {
-            result = result.into_iter().skip(offset as usize).collect();
-        }
-        if let Some(limit) = filter.limit {
-            result = result.into_iter().take(limit as usize).collect();
-        }
-
-        Ok((result, total))
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
     }
 
-    async fn update_todo_item(&self, item: TodoItem) -> TaskStoreResult<TodoItem> {
+    async fn update_todo_item(&self, mut item: TodoItem) -> TaskStoreResult<TodoItem> {
+        let repositories = self.repositories.read().await;
         let mut items = self.todo_items.write().await;
-        if !items.contains_key(&item.id) {
-            return Err(TaskStoreError::not_found("TodoItem", item.id.to_string()));
-        }
+        let current = items
+            .get(&item.id)
+            .ok_or_else(|| TaskStoreError::not_found("TodoItem", item.id.to_string()))?;
+        check_revision("TodoItem", item.id, current.revision, item.revision)?;
+        ensure_transition(current.status, item.status)?;
+        ensure_reference(&repositories, "Repository", item.repository_id)?;
+        item.revision += 1;
         items.insert(item.id, item.clone());
+        self.emit(ChangeEvent::updated(EntityValue::TodoItem(item.clone())));
         Ok(item)
     }
 
@@ -642,6 +2183,7 @@ impl TaskStore for MemoryTaskStore {
         if items.remove(&id).is_none() {
             return Err(TaskStoreError::not_found("TodoItem", id.to_string()));
         }
+        self.emit(ChangeEvent::deleted(EntityKind::TodoItem, id));
         Ok(())
     }
 
@@ -653,6 +2195,8 @@ impl TaskStore for MemoryTaskStore {
         &self,
         request: TtyInputRequest,
     ) -> TaskStoreResult<TtyInputRequest> {
+        let sessions = self.agent_sessions.read().await;
+        let unit_tasks = self.unit_tasks.read().await;
         let mut requests = self.tty_input_requests.write().await;
         if requests.contains_key(&request.id) {
             return Err(TaskStoreError::already_exists(
@@ -660,7 +2204,12 @@ impl TaskStore for MemoryTaskStore {
                 request.id.to_string(),
             ));
         }
+        ensure_reference(&unit_tasks, "UnitTask", request.task_id)?;
+        ensure_reference(&sessions, "AgentSession", request.session_id)?;
         requests.insert(request.id, request.clone());
+        self.emit(ChangeEvent::created(EntityValue::TtyInputRequest(
+            request.clone(),
+        )));
         Ok(request)
     }
 
@@ -674,7 +2223,7 @@ impl TaskStore for MemoryTaskStore {
         filter: TtyInputFilter,
     ) -> TaskStoreResult<Vec<TtyInputRequest>> {
         let requests = self.tty_input_requests.read().await;
-        let mut result: Vec<TtyInputRequest> = requests
+        let result: Vec<TtyInputRequest> = requests
             .values()
             .filter(|r| {
                 let mut matches = true;
@@ -692,28 +2241,40 @@ impl TaskStore for MemoryTaskStore {
             .cloned()
             .collect();
 
-        if let Some(offset) = filter.offset {
-            result = result.into_iter().skip(offset as usize).collect();
-        }
-        if let Some(limit) = filter.limit {
-            result = result.into_iter().take(limit as usize).collect();
-        }
-
-        Ok(result)
+        let (page, _) = paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )?;
+        Ok(page)
     }
 
     async fn update_tty_input_request(
         &self,
-        request: TtyInputRequest,
+        mut request: TtyInputRequest,
     ) -> TaskStoreResult<TtyInputRequest> {
+        let sessions = self.agent_sessions.read().await;
+        let unit_tasks = self.unit_tasks.read().await;
         let mut requests = self.tty_input_requests.write().await;
-        if !requests.contains_key(&request.id) {
-            return Err(TaskStoreError::not_found(
-                "TtyInputRequest",
-                request.id.to_string(),
-            ));
-        }
+        let current = requests
+            .get(&request.id)
+            .ok_or_else(|| TaskStoreError::not_found("TtyInputRequest", request.id.to_string()))?;
+        check_revision(
+            "TtyInputRequest",
+            request.id,
+            current.revision,
+            request.revision,
+        )?;
+        ensure_transition(current.status, request.status)?;
+        ensure_reference(&unit_tasks, "UnitTask", request.task_id)?;
+        ensure_reference(&sessions, "AgentSession", request.session_id)?;
+        request.revision += 1;
         requests.insert(request.id, request.clone());
+        self.emit(ChangeEvent::updated(EntityValue::TtyInputRequest(
+            request.clone(),
+        )));
         Ok(request)
     }
 
@@ -722,15 +2283,385 @@ impl TaskStore for MemoryTaskStore {
         if requests.remove(&id).is_none() {
             return Err(TaskStoreError::not_found("TtyInputRequest", id.to_string()));
         }
+        self.emit(ChangeEvent::deleted(EntityKind::TtyInputRequest, id));
+        Ok(())
+    }
+
+    // =========================================================================
+    // Worker operations
+    // =========================================================================
+
+    async fn register_worker(&self, worker: Worker) -> TaskStoreResult<Worker> {
+        let unit_tasks = self.unit_tasks.read().await;
+        let mut workers = self.workers.write().await;
+        if workers.contains_key(&worker.id) {
+            return Err(TaskStoreError::already_exists(
+                "Worker",
+                worker.id.to_string(),
+            ));
+        }
+        if let Some(task_id) = worker.current_task_id {
+            ensure_reference(&unit_tasks, "UnitTask", task_id)?;
+        }
+        workers.insert(worker.id, worker.clone());
+        self.emit(ChangeEvent::created(EntityValue::Worker(worker.clone())));
+        Ok(worker)
+    }
+
+    async fn get_worker(&self, id: Uuid) -> TaskStoreResult<Option<Worker>> {
+        let workers = self.workers.read().await;
+        Ok(workers.get(&id).cloned())
+    }
+
+    async fn list_workers(&self, filter: WorkerFilter) -> TaskStoreResult<(Vec<Worker>, u32)> {
+        let workers = self.workers.read().await;
+        let result: Vec<Worker> = workers
+            .values()
+            .filter(|w| {
+                let mut matches = true;
+                if let Some(status) = filter.status {
+                    matches = matches && w.status == status;
+                }
+                if let Some(capability) = &filter.capability {
+                    matches = matches && w.has_capability(capability);
+                }
+                matches
+            })
+            .cloned()
+            .collect();
+
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
+
+    async fn update_worker(&self, mut worker: Worker) -> TaskStoreResult<Worker> {
+        let unit_tasks = self.unit_tasks.read().await;
+        let mut workers = self.workers.write().await;
+        let current = workers
+            .get(&worker.id)
+            .ok_or_else(|| TaskStoreError::not_found("Worker", worker.id.to_string()))?;
+        check_revision("Worker", worker.id, current.revision, worker.revision)?;
+        if let Some(task_id) = worker.current_task_id {
+            ensure_reference(&unit_tasks, "UnitTask", task_id)?;
+        }
+        worker.revision += 1;
+        workers.insert(worker.id, worker.clone());
+        self.emit(ChangeEvent::updated(EntityValue::Worker(worker.clone())));
+        Ok(worker)
+    }
+
+    async fn delete_worker(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut workers = self.workers.write().await;
+        if workers.remove(&id).is_none() {
+            return Err(TaskStoreError::not_found("Worker", id.to_string()));
+        }
+        self.emit(ChangeEvent::deleted(EntityKind::Worker, id));
+        Ok(())
+    }
+
+    async fn record_worker_heartbeat(
+        &self,
+        id: Uuid,
+        status: WorkerStatus,
+        current_task_id: Option<Uuid>,
+    ) -> TaskStoreResult<Worker> {
+        let unit_tasks = self.unit_tasks.read().await;
+        let mut workers = self.workers.write().await;
+        let worker = workers
+            .get_mut(&id)
+            .ok_or_else(|| TaskStoreError::not_found("Worker", id.to_string()))?;
+        if let Some(task_id) = current_task_id {
+            ensure_reference(&unit_tasks, "UnitTask", task_id)?;
+        }
+        let changed = worker.status != status || worker.current_task_id != current_task_id;
+        worker.last_heartbeat = Utc::now();
+        if changed {
+            worker.status = status;
+            worker.current_task_id = current_task_id;
+            worker.revision += 1;
+            self.emit(ChangeEvent::updated(EntityValue::Worker(worker.clone())));
+        }
+        Ok(worker.clone())
+    }
+
+    async fn mark_stale_workers(&self, before: DateTime<Utc>) -> TaskStoreResult<Vec<Worker>> {
+        let mut workers = self.workers.write().await;
+        let mut marked: Vec<&mut Worker> = workers
+            .values_mut()
+            .filter(|w| w.status != WorkerStatus::Unhealthy && w.last_heartbeat < before)
+            .collect();
+        marked.sort_by_key(|w| (w.last_heartbeat, w.id));
+        Ok(marked
+            .into_iter()
+            .map(|worker| {
+                worker.status = WorkerStatus::Unhealthy;
+                worker.revision += 1;
+                self.emit(ChangeEvent::updated(EntityValue::Worker(worker.clone())));
+                worker.clone()
+            })
+            .collect())
+    }
+
+    // =========================================================================
+    // Work queue operations
+    // =========================================================================
+
+    async fn enqueue_task(&self, entry: QueueEntry) -> TaskStoreResult<QueueEntry> {
+        let tasks = self.agent_tasks.read().await;
+        let mut queue = self.queue_entries.write().await;
+        if queue.contains_key(&entry.id)
+            || queue
+                .values()
+                .any(|e| e.agent_task_id == entry.agent_task_id)
+        {
+            return Err(TaskStoreError::already_exists(
+                "QueueEntry",
+                entry.id.to_string(),
+            ));
+        }
+        ensure_reference(&tasks, "AgentTask", entry.agent_task_id)?;
+        queue.insert(entry.id, entry.clone());
+        Ok(entry)
+    }
+
+    async fn get_queue_entry(&self, id: Uuid) -> TaskStoreResult<Option<QueueEntry>> {
+        let queue = self.queue_entries.read().await;
+        Ok(queue.get(&id).cloned())
+    }
+
+    async fn list_queue_entries(
+        &self,
+        filter: QueueFilter,
+    ) -> TaskStoreResult<(Vec<QueueEntry>, u32)> {
+        let queue = self.queue_entries.read().await;
+        let result: Vec<QueueEntry> = queue
+            .values()
+            .filter(|e| {
+                let mut matches = true;
+                if let Some(status) = filter.status {
+                    matches = matches && e.status == status;
+                }
+                if let Some(worker_id) = filter.worker_id {
+                    matches = matches && e.worker_id == Some(worker_id);
+                }
+                matches
+            })
+            .cloned()
+            .collect();
+
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
+
+    async fn claim_task(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Option<QueueEntry>> {
+        let mut queue = self.queue_entries.write().await;
+        let now = Utc::now();
+        requeue_expired(&mut queue, now);
+        let next = queue
+            .values_mut()
+            .filter(|e| e.status == QueueStatus::Queued)
+            .min_by_key(|e| (e.created_at, e.id));
+        Ok(next.map(|entry| {
+            entry.claim(worker_id, lease, now);
+            entry.clone()
+        }))
+    }
+
+    async fn renew_leases(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Vec<QueueEntry>> {
+        let mut queue = self.queue_entries.write().await;
+        let now = Utc::now();
+        let mut renewed: Vec<QueueEntry> = queue
+            .values_mut()
+            .filter(|e| e.leased_to(worker_id, now))
+            .map(|entry| {
+                entry.renew(lease, now);
+                entry.clone()
+            })
+            .collect();
+        renewed.sort_by_key(|e| (e.created_at, e.id));
+        Ok(renewed)
+    }
+
+    async fn complete_task(&self, id: Uuid, worker_id: Uuid) -> TaskStoreResult<QueueEntry> {
+        let mut queue = self.queue_entries.write().await;
+        let entry = queue
+            .get_mut(&id)
+            .ok_or_else(|| TaskStoreError::not_found("QueueEntry", id.to_string()))?;
+        entry.complete(worker_id, Utc::now())?;
+        Ok(entry.clone())
+    }
+
+    async fn fail_task(
+        &self,
+        id: Uuid,
+        worker_id: Uuid,
+        error: String,
+    ) -> TaskStoreResult<QueueEntry> {
+        let mut queue = self.queue_entries.write().await;
+        let entry = queue
+            .get_mut(&id)
+            .ok_or_else(|| TaskStoreError::not_found("QueueEntry", id.to_string()))?;
+        entry.fail(worker_id, error, Utc::now())?;
+        Ok(entry.clone())
+    }
+
+    async fn requeue_expired_leases(&self) -> TaskStoreResult<u64> {
+        let mut queue = self.queue_entries.write().await;
+        Ok(requeue_expired(&mut queue, Utc::now()))
+    }
+
+    // =========================================================================
+    // Audit log operations
+    // =========================================================================
+
+    async fn record_audit_event(&self, event: AuditEvent) -> TaskStoreResult<AuditEvent> {
+        let mut events = self.audit_events.write().await;
+        if events.contains_key(&event.id) {
+            return Err(TaskStoreError::already_exists(
+                "AuditEvent",
+                event.id.to_string(),
+            ));
+        }
+        events.insert(event.id, event.clone());
+        Ok(event)
+    }
+
+    async fn get_audit_event(&self, id: Uuid) -> TaskStoreResult<Option<AuditEvent>> {
+        let events = self.audit_events.read().await;
+        Ok(events.get(&id).cloned())
+    }
+
+    async fn list_audit_events(
+        &self,
+        filter: AuditFilter,
+    ) -> TaskStoreResult<(Vec<AuditEvent>, u32)> {
+        let events = self.audit_events.read().await;
+        let result: Vec<AuditEvent> = events
+            .values()
+            .filter(|e| {
+                let mut matches = true;
+                if let Some(entity_type) = &filter.entity_type {
+                    matches = matches && &e.entity_type == entity_type;
+                }
+                if let Some(entity_id) = filter.entity_id {
+                    matches = matches && e.entity_id == entity_id;
+                }
+                if let Some(actor_type) = filter.actor_type {
+                    matches = matches && e.actor.actor_type == actor_type;
+                }
+                if let Some(actor_id) = filter.actor_id {
+                    matches = matches && e.actor.id == Some(actor_id);
+                }
+                if let Some(since) = filter.since {
+                    matches = matches && e.created_at >= since;
+                }
+                if let Some(until) = filter.until {
+                    matches = matches && e.created_at < until;
+                }
+                matches
+            })
+            .cloned()
+            .collect();
+
+        paginate(
+            result,
+            filter.sort,
+            filter.after.as_ref(),
+            filter.limit,
+            filter.offset,
+        )
+    }
+
+    // =========================================================================
+    // Search
+    // =========================================================================
+
+    async fn search(&self, query: SearchQuery) -> TaskStoreResult<Vec<SearchHit>> {
+        if self.transaction.is_some() {
+            return Ok(self.build_search_index().await.search(&query));
+        }
+        Ok(self.search_index.lock().unwrap().search(&query))
+    }
+}
+
+/// Transactions read the store's tables until they first write to one, and
+/// from then on work on a private copy of that table. Committing applies the
+/// rows the transaction created, changed or deleted to the parent store under
+/// write locks on every table, so readers never observe a partial commit.
+/// Rows the transaction did not touch keep any changes made concurrently by
+/// other writers, and the commit fails if those changes break a reference
+/// the transaction's rows rely on.
+#[async_trait]
+impl TaskStoreTransaction for MemoryTaskStore {
+    async fn commit(mut self: Box<Self>) -> TaskStoreResult<()> {
+        let PendingCommit { parent, events } = self
+            .transaction
+            .take()
+            .map(|pending| *pending)
+            .ok_or_else(|| TaskStoreError::Other("Store is not a transaction".to_string()))?;
+        let changes = self.staged_changes().await?;
+
+        // Hold every write lock while checking and applying so the commit is
+        // atomic.
+        let mut tables = parent.lock_tables().await;
+        changes.check(&tables)?;
+        let undo = changes.apply(&mut tables);
+        if let Err(error) = changes.check_references(&tables) {
+            undo.apply(&mut tables);
+            return Err(error);
+        }
+
+        let mut search_index = parent.search_index.lock().unwrap();
+        for event in events.into_inner().unwrap() {
+            search_index.apply(&event);
+            parent.changes.publish(event);
+        }
+        // Appending to a log emits no change event, so the chunks the
+        // transaction appended are indexed separately. A log's revision is
+        // its number of chunks.
+        for (_, log, base) in &changes.session_logs.upserts {
+            for chunk in &log.chunks[base.unwrap_or(0) as usize..] {
+                search_index.append_log(chunk);
+            }
+        }
+        Ok(())
+    }
+
+    async fn rollback(self: Box<Self>) -> TaskStoreResult<()> {
+        if self.transaction.is_none() {
+            return Err(TaskStoreError::Other(
+                "Store is not a transaction".to_string(),
+            ));
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use entities::VcsProviderType;
+    use entities::{AiAgentType, UnitTaskStatus, VcsProviderType};
+    use futures::StreamExt;
 
     use super::*;
+    use crate::{ChangeOperation, SearchField, SortKey};
 
     #[tokio::test]
     async fn test_workspace_crud() {
@@ -755,6 +2686,9 @@ mod tests {
 
         // Delete
         store.delete_workspace(created.id).await.unwrap();
+        let deleted = store.get_workspace(created.id).await.unwrap().unwrap();
+        assert!(deleted.deleted_at.is_some());
+        store.purge_workspace(created.id).await.unwrap();
         assert!(store.get_workspace(created.id).await.unwrap().is_none());
     }
 
@@ -791,9 +2725,61 @@ mod tests {
 
         // Delete
         store.delete_repository(created.id).await.unwrap();
+        let deleted = store.get_repository(created.id).await.unwrap().unwrap();
+        assert!(deleted.deleted_at.is_some());
+        store.purge_repository(created.id).await.unwrap();
         assert!(store.get_repository(created.id).await.unwrap().is_none());
     }
 
+    #[tokio::test]
+    async fn test_list_repositories_with_cursor() {
+        let store = MemoryTaskStore::new();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        for name in ["charlie", "alpha", "delta", "bravo"] {
+            store
+                .create_repository(Repository::new(
+                    workspace.id,
+                    name,
+                    format!("https://github.com/test/{name}"),
+                    VcsProviderType::Github,
+                ))
+                .await
+                .unwrap();
+        }
+        let names = |repos: &[Repository]| repos.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+
+        let mut filter = RepositoryFilter {
+            workspace_id: Some(workspace.id),
+            sort: SortOrder::new(SortKey::Title),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(names(&page), ["alpha", "bravo"]);
+
+        let cursor = Cursor::after(page.last().unwrap(), SortKey::Title).unwrap();
+        filter.after = Some(cursor.to_string().parse().unwrap());
+        let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(names(&page), ["charlie", "delta"]);
+
+        filter.sort = filter.sort.descending();
+        filter.after = Some(Cursor::after(&page[0], SortKey::Title).unwrap());
+        let (page, _) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(names(&page), ["bravo", "alpha"]);
+
+        filter.sort = SortOrder::new(SortKey::Status);
+        filter.after = None;
+        assert!(matches!(
+            store.list_repositories(filter).await,
+            Err(TaskStoreError::InvalidQuery(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_unit_task_crud() {
         let store = MemoryTaskStore::new();
@@ -822,6 +2808,333 @@ mod tests {
 
         // Delete
         store.delete_unit_task(created.id).await.unwrap();
+        let deleted = store.get_unit_task(created.id).await.unwrap().unwrap();
+        assert!(deleted.deleted_at.is_some());
+        store.purge_unit_task(created.id).await.unwrap();
         assert!(store.get_unit_task(created.id).await.unwrap().is_none());
     }
+
+    #[tokio::test]
+    async fn test_foreign_key_violations() {
+        let store = MemoryTaskStore::new();
+        let dangling = Repository::new(
+            Uuid::new_v4(),
+            "test-repo",
+            "https://github.com/test/test-repo",
+            VcsProviderType::Github,
+        );
+        assert!(matches!(
+            store.create_repository(dangling).await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                "test-repo",
+                "https://github.com/test/test-repo",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        let mut group = RepositoryGroup::new(workspace.id);
+        group.add_repository(Uuid::new_v4());
+        assert!(matches!(
+            store.create_repository_group(group).await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+
+        // Deletes are restricted while a row is referenced.
+        assert!(matches!(
+            store.purge_workspace(workspace.id).await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        store.purge_repository(repository.id).await.unwrap();
+        store.purge_workspace(workspace.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_agent_task_deletes_sessions() {
+        let store = MemoryTaskStore::new();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let session = store
+            .create_agent_session(AgentSession::new(agent_task.id, AiAgentType::ClaudeCode))
+            .await
+            .unwrap();
+        let unit_task = store
+            .create_unit_task(UnitTask::new(group.id, Uuid::new_v4(), "Fix the bug"))
+            .await;
+        assert!(matches!(
+            unit_task,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        let unit_agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let unit_task = store
+            .create_unit_task(UnitTask::new(group.id, unit_agent_task.id, "Fix the bug"))
+            .await
+            .unwrap();
+        let request = store
+            .create_tty_input_request(TtyInputRequest::new(unit_task.id, session.id, "Continue?"))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store.delete_agent_task(agent_task.id).await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        store.delete_tty_input_request(request.id).await.unwrap();
+        store.delete_agent_task(agent_task.id).await.unwrap();
+        assert!(store.get_agent_session(session.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unit_task_status_transitions() {
+        let store = MemoryTaskStore::new();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let mut task = store
+            .create_unit_task(UnitTask::new(group.id, agent_task.id, "Fix the bug"))
+            .await
+            .unwrap();
+
+        task.status = UnitTaskStatus::InReview;
+        let mut task = store.update_unit_task(task).await.unwrap();
+        task.status = UnitTaskStatus::Rejected;
+        let task = store.update_unit_task(task).await.unwrap();
+
+        let mut reopened = task.clone();
+        reopened.status = UnitTaskStatus::PrOpen;
+        match store.update_unit_task(reopened).await {
+            Err(TaskStoreError::InvalidStateTransition { from, to }) => {
+                assert_eq!(from, "rejected");
+                assert_eq!(to, "pr_open");
+            }
+            other => panic!("Expected invalid transition, got {other:?}"),
+        }
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.status, UnitTaskStatus::Rejected);
+    }
+
+    #[tokio::test]
+    async fn test_stale_update_conflicts() {
+        let store = MemoryTaskStore::new();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        assert_eq!(workspace.revision, 0);
+
+        let mut first = workspace.clone();
+        first.name = "First".to_string();
+        let first = store.update_workspace(first).await.unwrap();
+        assert_eq!(first.revision, 1);
+
+        let mut stale = workspace;
+        stale.name = "Stale".to_string();
+        match store.update_workspace(stale).await {
+            Err(TaskStoreError::Conflict {
+                current_revision, ..
+            }) => assert_eq!(current_revision, 1),
+            other => panic!("Expected conflict, got {other:?}"),
+        }
+        let stored = store.get_workspace(first.id).await.unwrap().unwrap();
+        assert_eq!(stored.name, "First");
+        assert_eq!(stored.revision, 1);
+
+        // A transaction fails to commit over a row changed since it began.
+        let tx = store.begin().await.unwrap();
+        let mut in_tx = stored.clone();
+        in_tx.name = "In Transaction".to_string();
+        tx.update_workspace(in_tx).await.unwrap();
+        store.update_workspace(stored).await.unwrap();
+        assert!(matches!(
+            tx.commit().await,
+            Err(TaskStoreError::Conflict { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let store = MemoryTaskStore::new();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let mut task = store
+            .create_unit_task(UnitTask::new(group.id, agent_task.id, "Fix the bug"))
+            .await
+            .unwrap();
+        let mut session = AgentSession::new(agent_task.id, AiAgentType::ClaudeCode);
+        session.output_log = Some("Edited src/billing/invoice.rs to fix rounding".to_string());
+        let session = store.create_agent_session(session).await.unwrap();
+
+        let hits = store.search(SearchQuery::new("Billing")).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, EntityKind::AgentSession);
+        assert_eq!(hits[0].id, session.id);
+        assert!(hits[0].snippet.contains("**billing**"));
+
+        let query = SearchQuery::new("fix").with_kinds([EntityKind::UnitTask]);
+        let hits = store.search(query).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Prompt);
+
+        task.title = Some("Billing rounding".to_string());
+        store.update_unit_task(task).await.unwrap();
+        store.delete_agent_session(session.id).await.unwrap();
+        let hits = store.search(SearchQuery::new("billing")).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Title);
+
+        // Writes in a transaction are searchable there, and in the store once
+        // committed.
+        let tx = store.begin().await.unwrap();
+        let mut session = AgentSession::new(agent_task.id, AiAgentType::ClaudeCode);
+        session.output_log = Some("Updated the ledger".to_string());
+        tx.create_agent_session(session).await.unwrap();
+        assert_eq!(
+            tx.search(SearchQuery::new("ledger")).await.unwrap().len(),
+            1
+        );
+        assert!(
+            store
+                .search(SearchQuery::new("ledger"))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        tx.commit().await.unwrap();
+        assert_eq!(
+            store
+                .search(SearchQuery::new("ledger"))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commit_and_rollback() {
+        let store = MemoryTaskStore::new();
+
+        let tx = store.begin().await.unwrap();
+        let workspace = tx
+            .create_workspace(Workspace::new("Committed"))
+            .await
+            .unwrap();
+        assert!(tx.get_workspace(workspace.id).await.unwrap().is_some());
+        assert!(store.get_workspace(workspace.id).await.unwrap().is_none());
+        assert!(tx.begin().await.is_err());
+        tx.commit().await.unwrap();
+        assert!(store.get_workspace(workspace.id).await.unwrap().is_some());
+
+        let tx = store.begin().await.unwrap();
+        tx.purge_workspace(workspace.id).await.unwrap();
+        let discarded = tx
+            .create_workspace(Workspace::new("Discarded"))
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+        assert!(store.get_workspace(workspace.id).await.unwrap().is_some());
+        assert!(store.get_workspace(discarded.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transaction_keeps_concurrent_writes() {
+        let store = MemoryTaskStore::new();
+        let first = store
+            .create_workspace(Workspace::new("First"))
+            .await
+            .unwrap();
+
+        let tx = store.begin().await.unwrap();
+        let second = store
+            .create_workspace(Workspace::new("Second"))
+            .await
+            .unwrap();
+        let mut renamed = first.clone();
+        renamed.name = "Renamed".to_string();
+        tx.update_workspace(renamed).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let (workspaces, total) = store
+            .list_workspaces(WorkspaceFilter::default())
+            .await
+            .unwrap();
+        assert_eq!(total, 2);
+        assert!(workspaces.iter().any(|w| w.id == second.id));
+        let first = store.get_workspace(first.id).await.unwrap().unwrap();
+        assert_eq!(first.name, "Renamed");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let store = MemoryTaskStore::new();
+        let mut changes = store
+            .subscribe(ChangeFilter::new().with_kind(EntityKind::Workspace))
+            .await
+            .unwrap();
+
+        let mut workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        workspace.name = "Renamed".to_string();
+        store.update_workspace(workspace.clone()).await.unwrap();
+
+        // Writes in a transaction are delivered on commit only.
+        let tx = store.begin().await.unwrap();
+        tx.create_workspace(Workspace::new("Discarded"))
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+        let tx = store.begin().await.unwrap();
+        tx.purge_workspace(workspace.id).await.unwrap();
+        tx.commit().await.unwrap();
+
+        let operations: Vec<ChangeOperation> = changes
+            .by_ref()
+            .take(3)
+            .map(|event| event.unwrap().operation)
+            .collect()
+            .await;
+        assert_eq!(
+            operations,
+            vec![
+                ChangeOperation::Created,
+                ChangeOperation::Updated,
+                ChangeOperation::Deleted
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        crate::run_conformance(|| async { MemoryTaskStore::new() }).await;
+    }
 }
@@ -0,0 +1,227 @@
+//! Versioned schema migrations for the SQL-backed task stores.
+//!
+//! Migrations are embedded in the binary and applied in version order. The
+//! version of every applied migration is recorded in the `schema_migrations`
+//! table, and a store refuses to open a database whose schema is newer than
+//! the migrations it knows about.
+
+use crate::{TaskStoreError, TaskStoreResult};
+
+/// Latest schema version known to this build.
+pub const SCHEMA_VERSION: u32 = 13;
+
+/// An embedded schema migration.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Migration {
+    /// Version this migration upgrades the schema to.
+    pub version: u32,
+    /// Short description recorded alongside the version.
+    pub description: &'static str,
+    /// SQL statements to run.
+    pub sql: &'static str,
+}
+
+/// Outcome of running [`migrate`](crate::SqliteTaskStore::migrate) on a
+/// store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Schema version before migrating, `0` for an empty database.
+    pub previous_version: u32,
+    /// Schema version after migrating.
+    pub current_version: u32,
+}
+
+impl MigrationReport {
+    /// Returns true if any migration was applied.
+    pub fn applied_any(&self) -> bool {
+        self.previous_version != self.current_version
+    }
+}
+
+/// Migrations for the SQLite backend.
+pub(crate) const SQLITE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: include_str!("../migrations/sqlite/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "change event outbox",
+        sql: include_str!("../migrations/sqlite/0002_change_events.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "revision counters",
+        sql: include_str!("../migrations/sqlite/0003_revisions.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "full-text search",
+        sql: include_str!("../migrations/sqlite/0004_search.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "session log chunks",
+        sql: include_str!("../migrations/sqlite/0005_session_logs.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "soft delete",
+        sql: include_str!("../migrations/sqlite/0006_soft_delete.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "audit log",
+        sql: include_str!("../migrations/sqlite/0007_audit_events.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "work queue",
+        sql: include_str!("../migrations/sqlite/0008_work_queue.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "workers",
+        sql: include_str!("../migrations/sqlite/0009_workers.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "lookup indexes",
+        sql: include_str!("../migrations/sqlite/0010_lookup_indexes.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "plan revisions",
+        sql: include_str!("../migrations/sqlite/0011_plan_revisions.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "session log search",
+        sql: include_str!("../migrations/sqlite/0012_session_log_search.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "list positions",
+        sql: include_str!("../migrations/sqlite/0013_list_positions.sql"),
+    },
+];
+
+/// Migrations for the PostgreSQL backend.
+pub(crate) const POSTGRES_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: include_str!("../migrations/postgres/0001_initial_schema.sql"),
+    },
+    Migration {
+        version: 2,
+        description: "change event outbox",
+        sql: include_str!("../migrations/postgres/0002_change_events.sql"),
+    },
+    Migration {
+        version: 3,
+        description: "revision counters",
+        sql: include_str!("../migrations/postgres/0003_revisions.sql"),
+    },
+    Migration {
+        version: 4,
+        description: "full-text search",
+        sql: include_str!("../migrations/postgres/0004_search.sql"),
+    },
+    Migration {
+        version: 5,
+        description: "session log chunks",
+        sql: include_str!("../migrations/postgres/0005_session_logs.sql"),
+    },
+    Migration {
+        version: 6,
+        description: "soft delete",
+        sql: include_str!("../migrations/postgres/0006_soft_delete.sql"),
+    },
+    Migration {
+        version: 7,
+        description: "audit log",
+        sql: include_str!("../migrations/postgres/0007_audit_events.sql"),
+    },
+    Migration {
+        version: 8,
+        description: "work queue",
+        sql: include_str!("../migrations/postgres/0008_work_queue.sql"),
+    },
+    Migration {
+        version: 9,
+        description: "workers",
+        sql: include_str!("../migrations/postgres/0009_workers.sql"),
+    },
+    Migration {
+        version: 10,
+        description: "lookup indexes",
+        sql: include_str!("../migrations/postgres/0010_lookup_indexes.sql"),
+    },
+    Migration {
+        version: 11,
+        description: "plan revisions",
+        sql: include_str!("../migrations/postgres/0011_plan_revisions.sql"),
+    },
+    Migration {
+        version: 12,
+        description: "session log search",
+        sql: include_str!("../migrations/postgres/0012_session_log_search.sql"),
+    },
+    Migration {
+        version: 13,
+        description: "list positions",
+        sql: include_str!("../migrations/postgres/0013_list_positions.sql"),
+    },
+];
+
+/// Returns the migrations that still need to run on a database at
+/// `current_version`.
+///
+/// Fails with [`TaskStoreError::SchemaTooNew`] if the database has already
+/// been migrated past [`SCHEMA_VERSION`].
+pub(crate) fn pending(
+    migrations: &'static [Migration],
+    current_version: u32,
+) -> TaskStoreResult<&'static [Migration]> {
+    if current_version > SCHEMA_VERSION {
+        return Err(TaskStoreError::SchemaTooNew {
+            database_version: current_version,
+            supported_version: SCHEMA_VERSION,
+        });
+    }
+    let start = migrations
+        .iter()
+        .position(|migration| migration.version > current_version)
+        .unwrap_or(migrations.len());
+    Ok(&migrations[start..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_ordered_and_in_lockstep() {
+        for migrations in [SQLITE_MIGRATIONS, POSTGRES_MIGRATIONS] {
+            let versions: Vec<u32> = migrations.iter().map(|m| m.version).collect();
+            let expected: Vec<u32> = (1..=SCHEMA_VERSION).collect();
+            assert_eq!(versions, expected);
+        }
+    }
+
+    #[test]
+    fn test_pending() {
+        assert_eq!(pending(SQLITE_MIGRATIONS, 0).unwrap().len(), 13);
+        assert!(
+            pending(SQLITE_MIGRATIONS, SCHEMA_VERSION)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(matches!(
+            pending(SQLITE_MIGRATIONS, SCHEMA_VERSION + 1),
+            Err(TaskStoreError::SchemaTooNew { .. })
+        ));
+    }
+}
@@ -0,0 +1,3821 @@
+//! PostgreSQL task store implementation for multi-user mode.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use entities::{
+    AgentSession, AgentTask, AuditActor, AuditEvent, BaseRemote, CompositeTask, CompositeTaskNode,
+    PlanRevision, Repository, RepositoryGroup, StateMachine, TodoItem, TtyInputRequest, UnitTask,
+    User, Worker, WorkerStatus, Workspace,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{
+    Connection, PgConnection, PgPool, Postgres, QueryBuilder, Row,
+    postgres::{PgPoolOptions, PgRow},
+    types::Json,
+};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{
+    AuditFilter, ChangeEvent, ChangeFilter, ChangeStream, Cursor, EntityKind, EntityValue,
+    LogChunk, LogRange, LogSize, MigrationReport, QueueEntry, QueueFilter, QueueStatus,
+    RepositoryFilter, SearchField, SearchHit, SearchQuery, SortDirection, SortKey, SortOrder,
+    SortValue, TaskFilter, TaskStore, TaskStoreError, TaskStoreResult, TaskStoreTransaction,
+    TodoFilter, TtyInputFilter, WorkerFilter, WorkspaceFilter,
+    changes::{CHANGE_POLL_BATCH, OutboxBatch, poll_outbox},
+    migrate::{POSTGRES_MIGRATIONS, pending},
+    queue::LEASE_EXPIRED,
+    search::{SEARCH_COLUMNS, snippet},
+    sort::check_sort,
+    sql::{
+        SharedTransaction, StoreConnection, acquire, decode_enum, encode_enum, finish_transaction,
+        map_write_error, nested_transaction, share_transaction,
+    },
+    transition::ensure_transition,
+};
+
+/// Advisory lock key serializing migrations across server instances.
+const MIGRATION_LOCK_KEY: i64 = 0x64_656c_6964_6576;
+
+/// PostgreSQL-backed task store used in multi-user mode.
+///
+/// Entities are stored in the normalized schema described in
+/// `docs/main-server.md`: list-valued fields such as
+/// `RepositoryGroup::repository_ids` and `CompositeTaskNode::depends_on_ids`
+/// live in join tables, and `AgentTask::agent_sessions` is read back from the
+/// `agent_sessions` table.
+#[derive(Debug, Clone)]
+pub struct PostgresTaskStore {
+    pool: PgPool,
+    /// Open transaction when this store is a transaction handle.
+    transaction: Option<SharedTransaction<Postgres>>,
+}
+
+impl PostgresTaskStore {
+    /// Connects to the PostgreSQL database at `url`.
+    pub async fn connect(url: &str) -> TaskStoreResult<Self> {
+        let pool = PgPoolOptions::new().connect(url).await?;
+        Self::from_pool(pool).await
+    }
+
+    /// Creates a store from an existing pool, applying pending migrations.
+    pub async fn from_pool(pool: PgPool) -> TaskStoreResult<Self> {
+        let store = Self {
+            pool,
+            transaction: None,
+        };
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Applies pending schema migrations in a single transaction.
+    ///
+    /// An advisory lock is held for the duration of the transaction, so
+    /// servers starting concurrently against the same database apply each
+    /// migration once. Fails with [`TaskStoreError::SchemaTooNew`] if the
+    /// database was migrated by a newer version of DeliDev.
+    pub async fn migrate(&self) -> TaskStoreResult<MigrationReport> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("SELECT pg_advisory_xact_lock($1)")
+            .bind(MIGRATION_LOCK_KEY)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::raw_sql(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY, \
+             description TEXT NOT NULL, applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW())",
+        )
+        .execute(&mut *tx)
+        .await?;
+        let previous_version: i32 =
+            sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_migrations")
+                .fetch_one(&mut *tx)
+                .await?;
+        let previous_version = previous_version as u32;
+
+        let migrations = pending(POSTGRES_MIGRATIONS, previous_version)?;
+        for migration in migrations {
+            info!(
+                "Applying PostgreSQL migration {}: {}",
+                migration.version, migration.description
+            );
+            sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+            sqlx::query(
+                "INSERT INTO schema_migrations (version, description, applied_at) VALUES ($1, $2, \
+                 $3)",
+            )
+            .bind(migration.version as i32)
+            .bind(migration.description)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(MigrationReport {
+            previous_version,
+            current_version: migrations.last().map_or(previous_version, |m| m.version),
+        })
+    }
+
+    /// Checks out the connection used by a single operation.
+    async fn acquire(&self) -> TaskStoreResult<StoreConnection<'_, Postgres>> {
+        acquire(&self.pool, self.transaction.as_ref()).await
+    }
+
+    /// Deletes change feed outbox rows recorded before `before`.
+    ///
+    /// Subscribers only read rows recorded after they subscribed, so rows
+    /// older than the slowest subscriber's position can be pruned safely.
+    pub async fn purge_change_events(&self, before: DateTime<Utc>) -> TaskStoreResult<u64> {
+        let result = sqlx::query("DELETE FROM change_events WHERE occurred_at < $1")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Returns the underlying connection pool.
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+}
+
+// =============================================================================
+// Row decoding
+// =============================================================================
+
+fn enum_col<T: DeserializeOwned>(row: &PgRow, column: &str) -> TaskStoreResult<T> {
+    decode_enum(&row.try_get::<String, _>(column)?)
+}
+
+fn opt_enum_col<T: DeserializeOwned>(row: &PgRow, column: &str) -> TaskStoreResult<Option<T>> {
+    row.try_get::<Option<String>, _>(column)?
+        .map(|value| decode_enum(&value))
+        .transpose()
+}
+
+fn user_from_row(row: &PgRow) -> TaskStoreResult<User> {
+    Ok(User {
+        id: row.try_get("id")?,
+        email: row.try_get("email")?,
+        name: row.try_get("name")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn workspace_from_row(row: &PgRow) -> TaskStoreResult<Workspace> {
+    Ok(Workspace {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        description: row.try_get("description")?,
+        user_id: row.try_get("user_id")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn repository_from_row(row: &PgRow) -> TaskStoreResult<Repository> {
+    Ok(Repository {
+        id: row.try_get("id")?,
+        workspace_id: row.try_get("workspace_id")?,
+        name: row.try_get("name")?,
+        remote_url: row.try_get("remote_url")?,
+        default_branch: row.try_get("default_branch")?,
+        vcs_type: enum_col(row, "vcs_type")?,
+        vcs_provider_type: enum_col(row, "vcs_provider_type")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn repository_group_from_row(
+    row: &PgRow,
+    repository_ids: Vec<Uuid>,
+) -> TaskStoreResult<RepositoryGroup> {
+    Ok(RepositoryGroup {
+        id: row.try_get("id")?,
+        workspace_id: row.try_get("workspace_id")?,
+        name: row.try_get("name")?,
+        repository_ids,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn agent_session_from_row(row: &PgRow) -> TaskStoreResult<AgentSession> {
+    Ok(AgentSession {
+        id: row.try_get("id")?,
+        agent_task_id: row.try_get("agent_task_id")?,
+        ai_agent_type: enum_col(row, "ai_agent_type")?,
+        ai_agent_model: row.try_get("ai_agent_model")?,
+        started_at: row.try_get("started_at")?,
+        completed_at: row.try_get("completed_at")?,
+        output_log: row.try_get("output_log")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn agent_task_from_row(
+    row: &PgRow,
+    base_remotes: Vec<BaseRemote>,
+    agent_sessions: Vec<AgentSession>,
+) -> TaskStoreResult<AgentTask> {
+    Ok(AgentTask {
+        id: row.try_get("id")?,
+        base_remotes,
+        agent_sessions,
+        ai_agent_type: opt_enum_col(row, "ai_agent_type")?,
+        ai_agent_model: row.try_get("ai_agent_model")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn unit_task_from_row(row: &PgRow, auto_fix_task_ids: Vec<Uuid>) -> TaskStoreResult<UnitTask> {
+    Ok(UnitTask {
+        id: row.try_get("id")?,
+        repository_group_id: row.try_get("repository_group_id")?,
+        agent_task_id: row.try_get("agent_task_id")?,
+        prompt: row.try_get("prompt")?,
+        title: row.try_get("title")?,
+        branch_name: row.try_get("branch_name")?,
+        linked_pr_url: row.try_get("linked_pr_url")?,
+        base_commit: row.try_get("base_commit")?,
+        end_commit: row.try_get("end_commit")?,
+        auto_fix_task_ids,
+        status: enum_col(row, "status")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn composite_task_from_row(row: &PgRow) -> TaskStoreResult<CompositeTask> {
+    Ok(CompositeTask {
+        id: row.try_get("id")?,
+        repository_group_id: row.try_get("repository_group_id")?,
+        planning_task_id: row.try_get("planning_task_id")?,
+        prompt: row.try_get("prompt")?,
+        title: row.try_get("title")?,
+        node_ids: row.try_get("node_ids")?,
+        status: enum_col(row, "status")?,
+        execution_agent_type: opt_enum_col(row, "execution_agent_type")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn composite_task_node_from_row(
+    row: &PgRow,
+    depends_on_ids: Vec<Uuid>,
+) -> TaskStoreResult<CompositeTaskNode> {
+    Ok(CompositeTaskNode {
+        id: row.try_get("id")?,
+        composite_task_id: row.try_get("composite_task_id")?,
+        unit_task_id: row.try_get("unit_task_id")?,
+        depends_on_ids,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn todo_item_from_row(row: &PgRow) -> TaskStoreResult<TodoItem> {
+    Ok(TodoItem {
+        id: row.try_get("id")?,
+        item_type: enum_col(row, "type")?,
+        source: enum_col(row, "source")?,
+        status: enum_col(row, "status")?,
+        repository_id: row.try_get("repository_id")?,
+        data: row.try_get::<Json<_>, _>("data")?.0,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn tty_input_request_from_row(row: &PgRow) -> TaskStoreResult<TtyInputRequest> {
+    Ok(TtyInputRequest {
+        id: row.try_get("id")?,
+        task_id: row.try_get("task_id")?,
+        session_id: row.try_get("session_id")?,
+        prompt: row.try_get("prompt")?,
+        input_type: enum_col(row, "input_type")?,
+        options: row
+            .try_get::<Option<Json<Vec<String>>>, _>("options")?
+            .map(|options| options.0),
+        status: enum_col(row, "status")?,
+        response: row.try_get("response")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        responded_at: row.try_get("responded_at")?,
+    })
+}
+
+fn worker_from_row(row: &PgRow) -> TaskStoreResult<Worker> {
+    Ok(Worker {
+        id: row.try_get("id")?,
+        name: row.try_get("name")?,
+        endpoint_url: row.try_get("endpoint_url")?,
+        capabilities: row.try_get::<Json<Vec<String>>, _>("capabilities")?.0,
+        status: enum_col(row, "status")?,
+        current_task_id: row.try_get("current_task_id")?,
+        last_heartbeat: row.try_get("last_heartbeat")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn audit_event_from_row(row: &PgRow) -> TaskStoreResult<AuditEvent> {
+    Ok(AuditEvent {
+        id: row.try_get("id")?,
+        actor: AuditActor {
+            actor_type: enum_col(row, "actor_type")?,
+            id: row.try_get("actor_id")?,
+        },
+        action: row.try_get("action")?,
+        entity_type: row.try_get("entity_type")?,
+        entity_id: row.try_get("entity_id")?,
+        before: row.try_get("before")?,
+        after: row.try_get("after")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn plan_revision_from_row(row: &PgRow) -> TaskStoreResult<PlanRevision> {
+    Ok(PlanRevision {
+        id: row.try_get("id")?,
+        composite_task_id: row.try_get("composite_task_id")?,
+        number: row.try_get::<i32, _>("number")? as u32,
+        yaml: row.try_get("yaml")?,
+        author_type: enum_col(row, "author_type")?,
+        author_id: row.try_get("author_id")?,
+        approved_by: row.try_get("approved_by")?,
+        approved_at: row.try_get("approved_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// Locks a composite task row for the rest of the transaction, which
+/// serializes concurrent writes to its plan revisions. Returns false if the
+/// composite task does not exist.
+async fn lock_composite_task(conn: &mut PgConnection, id: Uuid) -> TaskStoreResult<bool> {
+    let locked: Option<Uuid> =
+        sqlx::query_scalar("SELECT id FROM composite_tasks WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    Ok(locked.is_some())
+}
+
+/// Returns the number of the latest plan revision of a composite task, or 0
+/// if it has none.
+async fn latest_plan_revision(
+    conn: &mut PgConnection,
+    composite_task_id: Uuid,
+) -> TaskStoreResult<u32> {
+    let latest: i32 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(number), 0) FROM plan_revisions WHERE composite_task_id = $1",
+    )
+    .bind(composite_task_id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(latest as u32)
+}
+
+fn queue_entry_from_row(row: &PgRow) -> TaskStoreResult<QueueEntry> {
+    Ok(QueueEntry {
+        id: row.try_get("id")?,
+        agent_task_id: row.try_get("agent_task_id")?,
+        status: enum_col(row, "status")?,
+        attempts: row.try_get::<i32, _>("attempts")? as u32,
+        max_attempts: row.try_get::<i32, _>("max_attempts")? as u32,
+        worker_id: row.try_get("worker_id")?,
+        lease_expires_at: row.try_get("lease_expires_at")?,
+        last_error: row.try_get("last_error")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn log_chunk_from_row(row: &PgRow) -> TaskStoreResult<LogChunk> {
+    Ok(LogChunk {
+        session_id: row.try_get("session_id")?,
+        sequence: row.try_get::<i64, _>("sequence")? as u64,
+        offset: row.try_get::<i64, _>("byte_offset")? as u64,
+        content: row.try_get("content")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+// =============================================================================
+// Join table helpers
+// =============================================================================
+
+/// Loads `(owner_id, child_id)` pairs for the given owners, grouped by owner.
+///
+/// `sql` must select `owner_id` and `child_id` columns, filter with
+/// `= ANY($1)` and order by position.
+async fn load_id_lists(
+    conn: &mut PgConnection,
+    sql: &str,
+    owner_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<Uuid>>> {
+    let rows = sqlx::query(sql)
+        .bind(owner_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+    let mut lists: HashMap<Uuid, Vec<Uuid>> = HashMap::new();
+    for row in rows {
+        lists
+            .entry(row.try_get("owner_id")?)
+            .or_default()
+            .push(row.try_get("child_id")?);
+    }
+    Ok(lists)
+}
+
+async fn load_group_members(
+    conn: &mut PgConnection,
+    group_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<Uuid>>> {
+    load_id_lists(
+        conn,
+        "SELECT group_id AS owner_id, repository_id AS child_id FROM repository_group_members \
+         WHERE group_id = ANY($1) ORDER BY group_id, position",
+        group_ids,
+    )
+    .await
+}
+
+async fn load_auto_fix_task_ids(
+    conn: &mut PgConnection,
+    unit_task_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<Uuid>>> {
+    load_id_lists(
+        conn,
+        "SELECT unit_task_id AS owner_id, agent_task_id AS child_id FROM unit_task_auto_fix_tasks \
+         WHERE unit_task_id = ANY($1) ORDER BY unit_task_id, position",
+        unit_task_ids,
+    )
+    .await
+}
+
+async fn load_node_dependencies(
+    conn: &mut PgConnection,
+    node_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<Uuid>>> {
+    load_id_lists(
+        conn,
+        "SELECT node_id AS owner_id, depends_on_node_id AS child_id FROM \
+         composite_task_node_dependencies WHERE node_id = ANY($1) ORDER BY node_id, position",
+        node_ids,
+    )
+    .await
+}
+
+async fn load_base_remotes(
+    conn: &mut PgConnection,
+    agent_task_id: Uuid,
+) -> TaskStoreResult<Vec<BaseRemote>> {
+    let rows = sqlx::query(
+        "SELECT git_remote_dir_path, git_branch_name FROM agent_task_base_remotes WHERE \
+         agent_task_id = $1 ORDER BY position",
+    )
+    .bind(agent_task_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    rows.iter()
+        .map(|row| {
+            Ok(BaseRemote {
+                git_remote_dir_path: row.try_get("git_remote_dir_path")?,
+                git_branch_name: row.try_get("git_branch_name")?,
+            })
+        })
+        .collect()
+}
+
+async fn sessions_for_agent_task(
+    conn: &mut PgConnection,
+    agent_task_id: Uuid,
+) -> TaskStoreResult<Vec<AgentSession>> {
+    let rows = sqlx::query(
+        "SELECT * FROM agent_sessions WHERE agent_task_id = $1 ORDER BY created_at, id",
+    )
+    .bind(agent_task_id)
+    .fetch_all(&mut *conn)
+    .await?;
+    rows.iter().map(agent_session_from_row).collect()
+}
+
+/// Loads the base remotes of the given agent tasks, grouped by task.
+async fn load_base_remotes_of(
+    conn: &mut PgConnection,
+    agent_task_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<BaseRemote>>> {
+    let rows = sqlx::query(
+        "SELECT agent_task_id, git_remote_dir_path, git_branch_name FROM agent_task_base_remotes \
+         WHERE agent_task_id = ANY($1) ORDER BY agent_task_id, position",
+    )
+    .bind(agent_task_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut remotes: HashMap<Uuid, Vec<BaseRemote>> = HashMap::new();
+    for row in rows {
+        remotes
+            .entry(row.try_get("agent_task_id")?)
+            .or_default()
+            .push(BaseRemote {
+                git_remote_dir_path: row.try_get("git_remote_dir_path")?,
+                git_branch_name: row.try_get("git_branch_name")?,
+            });
+    }
+    Ok(remotes)
+}
+
+/// Loads the sessions of the given agent tasks, grouped by task and oldest
+/// first.
+async fn sessions_for_agent_tasks(
+    conn: &mut PgConnection,
+    agent_task_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<AgentSession>>> {
+    let rows = sqlx::query(
+        "SELECT * FROM agent_sessions WHERE agent_task_id = ANY($1) ORDER BY created_at, id",
+    )
+    .bind(agent_task_ids)
+    .fetch_all(&mut *conn)
+    .await?;
+    let mut sessions: HashMap<Uuid, Vec<AgentSession>> = HashMap::new();
+    for row in &rows {
+        let session = agent_session_from_row(row)?;
+        sessions
+            .entry(session.agent_task_id)
+            .or_default()
+            .push(session);
+    }
+    Ok(sessions)
+}
+
+/// Returns the size of a session log from its newest chunk.
+async fn log_size(conn: &mut PgConnection, session_id: Uuid) -> TaskStoreResult<LogSize> {
+    let size: Option<(i64, i64)> = sqlx::query_as(
+        "SELECT sequence + 1, byte_offset + octet_length(content) FROM session_log_chunks WHERE \
+         session_id = $1 ORDER BY sequence DESC LIMIT 1",
+    )
+    .bind(session_id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(
+        size.map_or_else(LogSize::default, |(chunks, bytes)| LogSize {
+            chunks: chunks as u64,
+            bytes: bytes as u64,
+        }),
+    )
+}
+
+/// Replaces the ordered child IDs of an owner in a join table.
+async fn replace_id_list(
+    conn: &mut PgConnection,
+    table: &str,
+    owner_column: &str,
+    child_column: &str,
+    owner_id: Uuid,
+    child_ids: &[Uuid],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!("DELETE FROM {table} WHERE {owner_column} = $1"))
+        .bind(owner_id)
+        .execute(&mut *conn)
+        .await?;
+    let positions: Vec<i32> = (0..child_ids.len() as i32).collect();
+    sqlx::query(&format!(
+        "INSERT INTO {table} ({owner_column}, {child_column}, position) SELECT $1, child.id, \
+         child.position FROM UNNEST($2::uuid[], $3::int4[]) AS child(id, position)"
+    ))
+    .bind(owner_id)
+    .bind(child_ids)
+    .bind(&positions)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+async fn replace_base_remotes(
+    conn: &mut PgConnection,
+    agent_task_id: Uuid,
+    base_remotes: &[BaseRemote],
+) -> Result<(), sqlx::Error> {
+    sqlx::query("DELETE FROM agent_task_base_remotes WHERE agent_task_id = $1")
+        .bind(agent_task_id)
+        .execute(&mut *conn)
+        .await?;
+    for (position, remote) in base_remotes.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO agent_task_base_remotes (agent_task_id, position, git_remote_dir_path, \
+             git_branch_name) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(agent_task_id)
+        .bind(position as i32)
+        .bind(&remote.git_remote_dir_path)
+        .bind(&remote.git_branch_name)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+async fn upsert_agent_session(
+    conn: &mut PgConnection,
+    session: &AgentSession,
+) -> TaskStoreResult<()> {
+    sqlx::query(
+        "INSERT INTO agent_sessions (id, agent_task_id, ai_agent_type, ai_agent_model, \
+         started_at, completed_at, output_log, created_at, revision) VALUES ($1, $2, $3, $4, $5, \
+         $6, $7, $8, $9) ON CONFLICT (id) DO UPDATE SET agent_task_id = EXCLUDED.agent_task_id, \
+         ai_agent_type = EXCLUDED.ai_agent_type, ai_agent_model = EXCLUDED.ai_agent_model, \
+         started_at = EXCLUDED.started_at, completed_at = EXCLUDED.completed_at, output_log = \
+         EXCLUDED.output_log, revision = agent_sessions.revision + 1",
+    )
+    .bind(session.id)
+    .bind(session.agent_task_id)
+    .bind(encode_enum(&session.ai_agent_type)?)
+    .bind(&session.ai_agent_model)
+    .bind(session.started_at)
+    .bind(session.completed_at)
+    .bind(&session.output_log)
+    .bind(session.created_at)
+    .bind(session.revision as i64)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| map_write_error(e, "AgentSession", session.id))?;
+    Ok(())
+}
+
+// =============================================================================
+// Shared statements
+// =============================================================================
+
+/// Returns the indexed full-text search vector of `column`. Must match the
+/// expression indexes created by the search migration.
+fn search_vector(column: &str) -> String {
+    format!("to_tsvector('simple', regexp_replace({column}, '[^[:alnum:]]+', ' ', 'g'))")
+}
+
+/// Pushes the keyset condition for `after`, the `ORDER BY` clause for `sort`
+/// and the pagination clauses. `title_column` is the expression
+/// [`SortKey::Title`] sorts by.
+fn push_sort(
+    qb: &mut QueryBuilder<'_, Postgres>,
+    sort: SortOrder,
+    after: Option<&Cursor>,
+    title_column: &str,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) {
+    let column = match sort.key {
+        SortKey::CreatedAt => "created_at",
+        SortKey::UpdatedAt => "updated_at",
+        SortKey::Title => title_column,
+        SortKey::Status => "status COLLATE \"C\"",
+    };
+    let (comparison, direction) = match sort.direction {
+        SortDirection::Ascending => (">", "ASC"),
+        SortDirection::Descending => ("<", "DESC"),
+    };
+    if let Some(cursor) = after {
+        qb.push(format!(" AND ({column}, id) {comparison} ("));
+        match cursor.value() {
+            SortValue::Timestamp(value) => qb.push_bind(*value),
+            SortValue::Text(value) => qb.push_bind(value.clone()),
+        };
+        qb.push(", ").push_bind(cursor.id()).push(")");
+    }
+    qb.push(format!(" ORDER BY {column} {direction}, id {direction}"));
+    push_pagination(qb, limit, offset);
+}
+
+/// Appends `LIMIT`/`OFFSET` clauses for the given pagination values.
+fn push_pagination(qb: &mut QueryBuilder<'_, Postgres>, limit: Option<u32>, offset: Option<u32>) {
+    // A NULL limit means "no limit" in PostgreSQL.
+    qb.push(" LIMIT ");
+    qb.push_bind(limit.map(i64::from));
+    qb.push(" OFFSET ");
+    qb.push_bind(i64::from(offset.unwrap_or(0)));
+}
+
+async fn count(
+    conn: &mut PgConnection,
+    mut qb: QueryBuilder<'_, Postgres>,
+) -> TaskStoreResult<u32> {
+    let total: i64 = qb.build_query_scalar().fetch_one(&mut *conn).await?;
+    Ok(total as u32)
+}
+
+async fn fetch_by_id(
+    conn: &mut PgConnection,
+    table: &str,
+    id: Uuid,
+) -> TaskStoreResult<Option<PgRow>> {
+    Ok(sqlx::query(&format!("SELECT * FROM {table} WHERE id = $1"))
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?)
+}
+
+/// Moves a row into the trash, or out of it when `deleted_at` is `None`.
+///
+/// Returns the row and whether it changed; a change bumps the revision.
+async fn set_deleted_at(
+    conn: &mut PgConnection,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+    deleted_at: Option<DateTime<Utc>>,
+) -> TaskStoreResult<(PgRow, bool)> {
+    let updated = sqlx::query(&format!(
+        "UPDATE {table} SET revision = revision + 1, deleted_at = $1 WHERE id = $2 AND \
+         (deleted_at IS NULL) = $3 RETURNING *"
+    ))
+    .bind(deleted_at)
+    .bind(id)
+    .bind(deleted_at.is_some())
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(row) = updated {
+        return Ok((row, true));
+    }
+    let row = fetch_by_id(conn, table, id)
+        .await?
+        .ok_or_else(|| TaskStoreError::not_found(entity_type, id.to_string()))?;
+    Ok((row, false))
+}
+
+async fn delete_by_id(
+    conn: &mut PgConnection,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreResult<()> {
+    let result = sqlx::query(&format!("DELETE FROM {table} WHERE id = $1"))
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| map_write_error(e, entity_type, id))?;
+    if result.rows_affected() == 0 {
+        return Err(TaskStoreError::not_found(entity_type, id.to_string()));
+    }
+    Ok(())
+}
+
+/// Explains why a compare-and-swap update matched no row.
+///
+/// Fails with [`TaskStoreError::NotFound`] if the row is gone, or with
+/// [`TaskStoreError::Conflict`] if its revision moved on.
+async fn ensure_updated(
+    conn: &mut PgConnection,
+    rows_affected: u64,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreResult<()> {
+    if rows_affected > 0 {
+        return Ok(());
+    }
+    let current: Option<i64> =
+        sqlx::query_scalar(&format!("SELECT revision FROM {table} WHERE id = $1"))
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await?;
+    Err(match current {
+        Some(revision) => TaskStoreError::conflict(entity_type, id.to_string(), revision as u64),
+        None => TaskStoreError::not_found(entity_type, id.to_string()),
+    })
+}
+
+async fn hydrate_repository_groups(
+    conn: &mut PgConnection,
+    rows: &[PgRow],
+) -> TaskStoreResult<Vec<RepositoryGroup>> {
+    let ids = rows
+        .iter()
+        .map(|row| row.try_get("id"))
+        .collect::<Result<Vec<Uuid>, _>>()?;
+    let mut members = load_group_members(conn, &ids).await?;
+    rows.iter()
+        .zip(ids)
+        .map(|(row, id)| repository_group_from_row(row, members.remove(&id).unwrap_or_default()))
+        .collect()
+}
+
+async fn hydrate_unit_tasks(
+    conn: &mut PgConnection,
+    rows: &[PgRow],
+) -> TaskStoreResult<Vec<UnitTask>> {
+    let ids = rows
+        .iter()
+        .map(|row| row.try_get("id"))
+        .collect::<Result<Vec<Uuid>, _>>()?;
+    let mut auto_fix = load_auto_fix_task_ids(conn, &ids).await?;
+    rows.iter()
+        .zip(ids)
+        .map(|(row, id)| unit_task_from_row(row, auto_fix.remove(&id).unwrap_or_default()))
+        .collect()
+}
+
+async fn hydrate_composite_task_nodes(
+    conn: &mut PgConnection,
+    rows: &[PgRow],
+) -> TaskStoreResult<Vec<CompositeTaskNode>> {
+    let ids = rows
+        .iter()
+        .map(|row| row.try_get("id"))
+        .collect::<Result<Vec<Uuid>, _>>()?;
+    let mut dependencies = load_node_dependencies(conn, &ids).await?;
+    rows.iter()
+        .zip(ids)
+        .map(|(row, id)| {
+            composite_task_node_from_row(row, dependencies.remove(&id).unwrap_or_default())
+        })
+        .collect()
+}
+
+/// Checks that a row may move to status `next` before it is updated.
+///
+/// Fails with [`TaskStoreError::NotFound`] if the row is missing, with
+/// [`TaskStoreError::Conflict`] if its revision is not `revision`, and with
+/// [`TaskStoreError::InvalidStateTransition`] if its stored status cannot
+/// move to `next`.
+async fn ensure_status_transition<S>(
+    conn: &mut PgConnection,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+    revision: u64,
+    next: S,
+) -> TaskStoreResult<()>
+where
+    S: StateMachine + Serialize + DeserializeOwned,
+{
+    let row: Option<(String, i64)> = sqlx::query_as(&format!(
+        "SELECT status, revision FROM {table} WHERE id = $1"
+    ))
+    .bind(id)
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((status, current)) = row else {
+        return Err(TaskStoreError::not_found(entity_type, id.to_string()));
+    };
+    if current as u64 != revision {
+        return Err(TaskStoreError::conflict(
+            entity_type,
+            id.to_string(),
+            current as u64,
+        ));
+    }
+    ensure_transition(decode_enum::<S>(&status)?, next)
+}
+
+/// Takes every expired lease away from its worker, queueing the entry
+/// again if it has attempts left and failing it otherwise. Returns the
+/// number of entries changed.
+async fn requeue_expired(conn: &mut PgConnection, now: DateTime<Utc>) -> TaskStoreResult<u64> {
+    let result = sqlx::query(
+        "UPDATE work_queue SET status = CASE WHEN attempts < max_attempts THEN $1 ELSE $2 END, \
+         worker_id = NULL, lease_expires_at = NULL, last_error = $3, revision = revision + 1, \
+         updated_at = $4 WHERE status = $5 AND lease_expires_at <= $6",
+    )
+    .bind(encode_enum(&QueueStatus::Queued)?)
+    .bind(encode_enum(&QueueStatus::Failed)?)
+    .bind(LEASE_EXPIRED)
+    .bind(now)
+    .bind(encode_enum(&QueueStatus::Leased)?)
+    .bind(now)
+    .execute(&mut *conn)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Returns the error for a queue operation by a worker that did not hold an
+/// unexpired lease on entry `id`.
+async fn lease_not_held(conn: &mut PgConnection, id: Uuid, worker_id: Uuid) -> TaskStoreError {
+    match fetch_by_id(conn, "work_queue", id).await {
+        Ok(Some(_)) => TaskStoreError::LeaseNotHeld {
+            id: id.to_string(),
+            worker_id: worker_id.to_string(),
+        },
+        Ok(None) => TaskStoreError::not_found("QueueEntry", id.to_string()),
+        Err(err) => err,
+    }
+}
+
+async fn record_change(conn: &mut PgConnection, event: &ChangeEvent) -> TaskStoreResult<()> {
+    sqlx::query(
+        "INSERT INTO change_events (entity_kind, entity_id, operation, value, occurred_at) VALUES \
+         ($1, $2, $3, $4, $5)",
+    )
+    .bind(encode_enum(&event.kind)?)
+    .bind(event.id)
+    .bind(encode_enum(&event.operation)?)
+    .bind(event.value.as_ref().map(Json))
+    .bind(event.occurred_at)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+fn change_event_from_row(row: &PgRow) -> TaskStoreResult<ChangeEvent> {
+    Ok(ChangeEvent {
+        kind: enum_col(row, "entity_kind")?,
+        id: row.try_get("entity_id")?,
+        operation: enum_col(row, "operation")?,
+        value: row
+            .try_get::<Option<Json<EntityValue>>, _>("value")?
+            .map(|value| value.0),
+        occurred_at: row.try_get("occurred_at")?,
+    })
+}
+
+/// Reads outbox rows after `after`, stopping at the first row whose
+/// transaction may still be in progress so that rows committed out of
+/// sequence order are not skipped.
+async fn fetch_changes(pool: PgPool, after: i64) -> TaskStoreResult<OutboxBatch> {
+    let rows = sqlx::query(
+        "SELECT *, transaction_id < pg_snapshot_xmin(pg_current_snapshot()) AS settled FROM \
+         change_events WHERE sequence > $1 ORDER BY sequence LIMIT $2",
+    )
+    .bind(after)
+    .bind(CHANGE_POLL_BATCH)
+    .fetch_all(&pool)
+    .await?;
+    let mut batch = Vec::with_capacity(rows.len());
+    for row in &rows {
+        if !row.try_get::<bool, _>("settled")? {
+            break;
+        }
+        batch.push((row.try_get("sequence")?, change_event_from_row(row)));
+    }
+    Ok(batch)
+}
+
+#[async_trait]
+impl TaskStore for PostgresTaskStore {
+    // =========================================================================
+    // Transactions
+    // =========================================================================
+
+    async fn begin(&self) -> TaskStoreResult<Box<dyn TaskStoreTransaction>> {
+        if self.transaction.is_some() {
+            return Err(nested_transaction());
+        }
+        let transaction = self.pool.begin().await?;
+        Ok(Box::new(Self {
+            pool: self.pool.clone(),
+            transaction: Some(share_transaction(transaction)),
+        }))
+    }
+
+    // =========================================================================
+    // Change feed
+    // =========================================================================
+
+    async fn subscribe(&self, filter: ChangeFilter) -> TaskStoreResult<ChangeStream> {
+        let after: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(sequence), 0) FROM change_events")
+            .fetch_one(&self.pool)
+            .await?;
+        let pool = self.pool.clone();
+        Ok(poll_outbox(after, filter, move |after| {
+            fetch_changes(pool.clone(), after)
+        }))
+    }
+
+    // =========================================================================
+    // User operations
+    // =========================================================================
+
+    async fn create_user(&self, user: User) -> TaskStoreResult<User> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO users (id, email, name, created_at, updated_at, revision) VALUES ($1, \
+             $2, $3, $4, $5, $6)",
+        )
+        .bind(user.id)
+        .bind(&user.email)
+        .bind(&user.name)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .bind(user.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "User", user.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::User(user.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn get_user(&self, id: Uuid) -> TaskStoreResult<Option<User>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "users", id)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> TaskStoreResult<Option<User>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query("SELECT * FROM users WHERE email = $1")
+            .bind(email)
+            .fetch_optional(&mut *conn)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    async fn list_users(&self) -> TaskStoreResult<Vec<User>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM users ORDER BY created_at, id")
+            .fetch_all(&mut *conn)
+            .await?;
+        rows.iter().map(user_from_row).collect()
+    }
+
+    async fn update_user(&self, mut user: User) -> TaskStoreResult<User> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE users SET revision = revision + 1, email = $1, name = $2, created_at = $3, \
+             updated_at = $4 WHERE id = $5 AND revision = $6",
+        )
+        .bind(&user.email)
+        .bind(&user.name)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .bind(user.id)
+        .bind(user.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "User", user.id))?;
+        ensure_updated(&mut tx, result.rows_affected(), "users", "User", user.id).await?;
+        user.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::User(user.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn delete_user(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "users", "User", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::User, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Workspace operations
+    // =========================================================================
+
+    async fn create_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO workspaces (id, name, description, user_id, created_at, updated_at, \
+             revision, deleted_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+        )
+        .bind(workspace.id)
+        .bind(&workspace.name)
+        .bind(&workspace.description)
+        .bind(workspace.user_id)
+        .bind(workspace.created_at)
+        .bind(workspace.updated_at)
+        .bind(workspace.revision as i64)
+        .bind(workspace.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Workspace", workspace.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::Workspace(workspace.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    async fn get_workspace(&self, id: Uuid) -> TaskStoreResult<Option<Workspace>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "workspaces", id)
+            .await?
+            .as_ref()
+            .map(workspace_from_row)
+            .transpose()
+    }
+
+    async fn list_workspaces(
+        &self,
+        filter: WorkspaceFilter,
+    ) -> TaskStoreResult<(Vec<Workspace>, u32)> {
+        check_sort::<Workspace>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(user_id) = filter.user_id {
+                qb.push(" AND user_id = ").push_bind(user_id);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM workspaces");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM workspaces");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "name COLLATE \"C\"",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let workspaces = rows
+            .iter()
+            .map(workspace_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((workspaces, total))
+    }
+
+    async fn update_workspace(&self, mut workspace: Workspace) -> TaskStoreResult<Workspace> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE workspaces SET revision = revision + 1, name = $1, description = $2, user_id \
+             = $3, created_at = $4, updated_at = $5, deleted_at = $6 WHERE id = $7 AND revision = \
+             $8",
+        )
+        .bind(&workspace.name)
+        .bind(&workspace.description)
+        .bind(workspace.user_id)
+        .bind(workspace.created_at)
+        .bind(workspace.updated_at)
+        .bind(workspace.deleted_at)
+        .bind(workspace.id)
+        .bind(workspace.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Workspace", workspace.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "workspaces",
+            "Workspace",
+            workspace.id,
+        )
+        .await?;
+        workspace.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::Workspace(workspace.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    async fn delete_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "workspaces", "Workspace", id, Some(Utc::now())).await?;
+        if changed {
+            let workspace = workspace_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Workspace(workspace)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_workspace(&self, id: Uuid) -> TaskStoreResult<Workspace> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) = set_deleted_at(&mut tx, "workspaces", "Workspace", id, None).await?;
+        let workspace = workspace_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Workspace(workspace.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    async fn purge_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "workspaces", "Workspace", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::Workspace, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Repository operations
+    // =========================================================================
+
+    async fn create_repository(&self, repository: Repository) -> TaskStoreResult<Repository> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO repositories (id, workspace_id, name, remote_url, default_branch, \
+             vcs_type, vcs_provider_type, created_at, updated_at, revision, deleted_at) VALUES \
+             ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(repository.id)
+        .bind(repository.workspace_id)
+        .bind(&repository.name)
+        .bind(&repository.remote_url)
+        .bind(&repository.default_branch)
+        .bind(encode_enum(&repository.vcs_type)?)
+        .bind(encode_enum(&repository.vcs_provider_type)?)
+        .bind(repository.created_at)
+        .bind(repository.updated_at)
+        .bind(repository.revision as i64)
+        .bind(repository.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Repository", repository.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::Repository(repository.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(repository)
+    }
+
+    async fn get_repository(&self, id: Uuid) -> TaskStoreResult<Option<Repository>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "repositories", id)
+            .await?
+            .as_ref()
+            .map(repository_from_row)
+            .transpose()
+    }
+
+    async fn list_repositories(
+        &self,
+        filter: RepositoryFilter,
+    ) -> TaskStoreResult<(Vec<Repository>, u32)> {
+        check_sort::<Repository>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(workspace_id) = filter.workspace_id {
+                qb.push(" AND workspace_id = ").push_bind(workspace_id);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM repositories");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM repositories");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "name COLLATE \"C\"",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let repositories = rows
+            .iter()
+            .map(repository_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((repositories, total))
+    }
+
+    async fn update_repository(&self, mut repository: Repository) -> TaskStoreResult<Repository> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE repositories SET revision = revision + 1, workspace_id = $1, name = $2, \
+             remote_url = $3, default_branch = $4, vcs_type = $5, vcs_provider_type = $6, \
+             created_at = $7, updated_at = $8, deleted_at = $9 WHERE id = $10 AND revision = $11",
+        )
+        .bind(repository.workspace_id)
+        .bind(&repository.name)
+        .bind(&repository.remote_url)
+        .bind(&repository.default_branch)
+        .bind(encode_enum(&repository.vcs_type)?)
+        .bind(encode_enum(&repository.vcs_provider_type)?)
+        .bind(repository.created_at)
+        .bind(repository.updated_at)
+        .bind(repository.deleted_at)
+        .bind(repository.id)
+        .bind(repository.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Repository", repository.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "repositories",
+            "Repository",
+            repository.id,
+        )
+        .await?;
+        repository.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::Repository(repository.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(repository)
+    }
+
+    async fn delete_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "repositories", "Repository", id, Some(Utc::now())).await?;
+        if changed {
+            let repository = repository_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Repository(repository)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_repository(&self, id: Uuid) -> TaskStoreResult<Repository> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "repositories", "Repository", id, None).await?;
+        let repository = repository_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Repository(repository.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(repository)
+    }
+
+    async fn purge_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "repositories", "Repository", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::Repository, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Repository Group operations
+    // =========================================================================
+
+    async fn create_repository_group(
+        &self,
+        group: RepositoryGroup,
+    ) -> TaskStoreResult<RepositoryGroup> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO repository_groups (id, workspace_id, name, created_at, updated_at, \
+             revision) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(group.id)
+        .bind(group.workspace_id)
+        .bind(&group.name)
+        .bind(group.created_at)
+        .bind(group.updated_at)
+        .bind(group.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "RepositoryGroup", group.id))?;
+        replace_id_list(
+            &mut tx,
+            "repository_group_members",
+            "group_id",
+            "repository_id",
+            group.id,
+            &group.repository_ids,
+        )
+        .await
+        .map_err(|e| map_write_error(e, "RepositoryGroup", group.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::RepositoryGroup(group.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(group)
+    }
+
+    async fn get_repository_group(&self, id: Uuid) -> TaskStoreResult<Option<RepositoryGroup>> {
+        let mut conn = self.acquire().await?;
+        let Some(row) = fetch_by_id(&mut conn, "repository_groups", id).await? else {
+            return Ok(None);
+        };
+        Ok(hydrate_repository_groups(&mut conn, &[row]).await?.pop())
+    }
+
+    async fn list_repository_groups(
+        &self,
+        workspace_id: Option<Uuid>,
+    ) -> TaskStoreResult<Vec<RepositoryGroup>> {
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM repository_groups WHERE TRUE");
+        if let Some(workspace_id) = workspace_id {
+            qb.push(" AND workspace_id = ").push_bind(workspace_id);
+        }
+        qb.push(" ORDER BY created_at, id");
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        hydrate_repository_groups(&mut conn, &rows).await
+    }
+
+    async fn update_repository_group(
+        &self,
+        mut group: RepositoryGroup,
+    ) -> TaskStoreResult<RepositoryGroup> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE repository_groups SET revision = revision + 1, workspace_id = $1, name = $2, \
+             created_at = $3, updated_at = $4 WHERE id = $5 AND revision = $6",
+        )
+        .bind(group.workspace_id)
+        .bind(&group.name)
+        .bind(group.created_at)
+        .bind(group.updated_at)
+        .bind(group.id)
+        .bind(group.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "RepositoryGroup", group.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "repository_groups",
+            "RepositoryGroup",
+            group.id,
+        )
+        .await?;
+        group.revision += 1;
+        replace_id_list(
+            &mut tx,
+            "repository_group_members",
+            "group_id",
+            "repository_id",
+            group.id,
+            &group.repository_ids,
+        )
+        .await
+        .map_err(|e| map_write_error(e, "RepositoryGroup", group.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::RepositoryGroup(group.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(group)
+    }
+
+    async fn delete_repository_group(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "repository_groups", "RepositoryGroup", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::RepositoryGroup, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Agent Task operations
+    // =========================================================================
+
+    async fn create_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO agent_tasks (id, ai_agent_type, ai_agent_model, created_at, revision) \
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(task.id)
+        .bind(task.ai_agent_type.as_ref().map(encode_enum).transpose()?)
+        .bind(&task.ai_agent_model)
+        .bind(task.created_at)
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentTask", task.id))?;
+        replace_base_remotes(&mut tx, task.id, &task.base_remotes)
+            .await
+            .map_err(|e| map_write_error(e, "AgentTask", task.id))?;
+        for session in &task.agent_sessions {
+            upsert_agent_session(&mut tx, session).await?;
+        }
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::AgentTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn get_agent_task(&self, id: Uuid) -> TaskStoreResult<Option<AgentTask>> {
+        let mut conn = self.acquire().await?;
+        let Some(row) = fetch_by_id(&mut conn, "agent_tasks", id).await? else {
+            return Ok(None);
+        };
+        let base_remotes = load_base_remotes(&mut conn, id).await?;
+        let sessions = sessions_for_agent_task(&mut conn, id).await?;
+        agent_task_from_row(&row, base_remotes, sessions).map(Some)
+    }
+
+    async fn get_agent_tasks(&self, ids: &[Uuid]) -> TaskStoreResult<Vec<AgentTask>> {
+        let mut conn = self.acquire().await?;
+        let rows =
+            sqlx::query("SELECT * FROM agent_tasks WHERE id = ANY($1) ORDER BY created_at, id")
+                .bind(ids)
+                .fetch_all(&mut *conn)
+                .await?;
+        let mut base_remotes = load_base_remotes_of(&mut conn, ids).await?;
+        let mut sessions = sessions_for_agent_tasks(&mut conn, ids).await?;
+        rows.iter()
+            .map(|row| {
+                let id: Uuid = row.try_get("id")?;
+                agent_task_from_row(
+                    row,
+                    base_remotes.remove(&id).unwrap_or_default(),
+                    sessions.remove(&id).unwrap_or_default(),
+                )
+            })
+            .collect()
+    }
+
+    async fn list_agent_tasks(&self) -> TaskStoreResult<Vec<AgentTask>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM agent_tasks ORDER BY created_at, id")
+            .fetch_all(&mut *conn)
+            .await?;
+        let mut tasks = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let id: Uuid = row.try_get("id")?;
+            let base_remotes = load_base_remotes(&mut conn, id).await?;
+            let sessions = sessions_for_agent_task(&mut conn, id).await?;
+            tasks.push(agent_task_from_row(row, base_remotes, sessions)?);
+        }
+        Ok(tasks)
+    }
+
+    async fn update_agent_task(&self, mut task: AgentTask) -> TaskStoreResult<AgentTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE agent_tasks SET revision = revision + 1, ai_agent_type = $1, ai_agent_model = \
+             $2, created_at = $3 WHERE id = $4 AND revision = $5",
+        )
+        .bind(task.ai_agent_type.as_ref().map(encode_enum).transpose()?)
+        .bind(&task.ai_agent_model)
+        .bind(task.created_at)
+        .bind(task.id)
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentTask", task.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "agent_tasks",
+            "AgentTask",
+            task.id,
+        )
+        .await?;
+        task.revision += 1;
+        replace_base_remotes(&mut tx, task.id, &task.base_remotes)
+            .await
+            .map_err(|e| map_write_error(e, "AgentTask", task.id))?;
+        for session in &task.agent_sessions {
+            upsert_agent_session(&mut tx, session).await?;
+        }
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::AgentTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn delete_agent_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        // Sessions belong to their agent task and are deleted with it.
+        for session in sessions_for_agent_task(&mut tx, id).await? {
+            delete_by_id(&mut tx, "agent_sessions", "AgentSession", session.id).await?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::deleted(EntityKind::AgentSession, session.id),
+            )
+            .await?;
+        }
+        delete_by_id(&mut tx, "agent_tasks", "AgentTask", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::AgentTask, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Agent Session operations
+    // =========================================================================
+
+    async fn create_agent_session(&self, session: AgentSession) -> TaskStoreResult<AgentSession> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO agent_sessions (id, agent_task_id, ai_agent_type, ai_agent_model, \
+             started_at, completed_at, output_log, created_at, revision) VALUES ($1, $2, $3, $4, \
+             $5, $6, $7, $8, $9)",
+        )
+        .bind(session.id)
+        .bind(session.agent_task_id)
+        .bind(encode_enum(&session.ai_agent_type)?)
+        .bind(&session.ai_agent_model)
+        .bind(session.started_at)
+        .bind(session.completed_at)
+        .bind(&session.output_log)
+        .bind(session.created_at)
+        .bind(session.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentSession", session.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::AgentSession(session.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(session)
+    }
+
+    async fn get_agent_session(&self, id: Uuid) -> TaskStoreResult<Option<AgentSession>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "agent_sessions", id)
+            .await?
+            .as_ref()
+            .map(agent_session_from_row)
+            .transpose()
+    }
+
+    async fn list_agent_sessions(&self, agent_task_id: Uuid) -> TaskStoreResult<Vec<AgentSession>> {
+        let mut conn = self.acquire().await?;
+        sessions_for_agent_task(&mut conn, agent_task_id).await
+    }
+
+    async fn update_agent_session(
+        &self,
+        mut session: AgentSession,
+    ) -> TaskStoreResult<AgentSession> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE agent_sessions SET revision = revision + 1, agent_task_id = $1, ai_agent_type \
+             = $2, ai_agent_model = $3, started_at = $4, completed_at = $5, output_log = $6, \
+             created_at = $7 WHERE id = $8 AND revision = $9",
+        )
+        .bind(session.agent_task_id)
+        .bind(encode_enum(&session.ai_agent_type)?)
+        .bind(&session.ai_agent_model)
+        .bind(session.started_at)
+        .bind(session.completed_at)
+        .bind(&session.output_log)
+        .bind(session.created_at)
+        .bind(session.id)
+        .bind(session.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentSession", session.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "agent_sessions",
+            "AgentSession",
+            session.id,
+        )
+        .await?;
+        session.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::AgentSession(session.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(session)
+    }
+
+    async fn delete_agent_session(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "agent_sessions", "AgentSession", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::AgentSession, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Session log operations
+    // =========================================================================
+
+    async fn append_session_log(
+        &self,
+        session_id: Uuid,
+        content: String,
+    ) -> TaskStoreResult<LogChunk> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        // Locking the session serializes concurrent appends to its log.
+        let locked: Option<Uuid> =
+            sqlx::query_scalar("SELECT id FROM agent_sessions WHERE id = $1 FOR UPDATE")
+                .bind(session_id)
+                .fetch_optional(&mut *tx)
+                .await?;
+        if locked.is_none() {
+            return Err(TaskStoreError::ForeignKeyViolation(format!(
+                "AgentSession {session_id} does not exist"
+            )));
+        }
+        let size = log_size(&mut tx, session_id).await?;
+        let chunk = LogChunk {
+            session_id,
+            sequence: size.chunks,
+            offset: size.bytes,
+            content,
+            created_at: Utc::now(),
+        };
+        sqlx::query(
+            "INSERT INTO session_log_chunks (session_id, sequence, byte_offset, content, \
+             created_at) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(session_id)
+        .bind(chunk.sequence as i64)
+        .bind(chunk.offset as i64)
+        .bind(&chunk.content)
+        .bind(chunk.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "SessionLog", session_id))?;
+        tx.commit().await?;
+        Ok(chunk)
+    }
+
+    async fn read_session_log(
+        &self,
+        session_id: Uuid,
+        range: LogRange,
+    ) -> TaskStoreResult<Vec<LogChunk>> {
+        let mut conn = self.acquire().await?;
+        let mut qb =
+            QueryBuilder::<Postgres>::new("SELECT * FROM session_log_chunks WHERE session_id = ");
+        qb.push_bind(session_id);
+        match range {
+            LogRange::Sequence { start, end } => {
+                qb.push(" AND sequence >= ").push_bind(start as i64);
+                if let Some(end) = end {
+                    qb.push(" AND sequence < ").push_bind(end as i64);
+                }
+                qb.push(" ORDER BY sequence");
+            }
+            LogRange::Bytes { start, end } => {
+                qb.push(" AND byte_offset + octet_length(content) > ")
+                    .push_bind(start as i64);
+                if let Some(end) = end {
+                    qb.push(" AND byte_offset < ").push_bind(end as i64);
+                }
+                qb.push(" ORDER BY sequence");
+            }
+            LogRange::Tail(count) => {
+                qb.push(" ORDER BY sequence DESC LIMIT ")
+                    .push_bind(count as i64);
+            }
+        }
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let mut chunks = rows
+            .iter()
+            .map(log_chunk_from_row)
+            .collect::<TaskStoreResult<Vec<_>>>()?;
+        if let LogRange::Tail(_) = range {
+            chunks.reverse();
+        }
+        Ok(chunks)
+    }
+
+    async fn session_log_size(&self, session_id: Uuid) -> TaskStoreResult<LogSize> {
+        let mut conn = self.acquire().await?;
+        log_size(&mut conn, session_id).await
+    }
+
+    // =========================================================================
+    // Unit Task operations
+    // =========================================================================
+
+    async fn create_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO unit_tasks (id, repository_group_id, agent_task_id, prompt, title, \
+             branch_name, linked_pr_url, base_commit, end_commit, status, created_at, updated_at, \
+             revision, deleted_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, \
+             $13, $14)",
+        )
+        .bind(task.id)
+        .bind(task.repository_group_id)
+        .bind(task.agent_task_id)
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(&task.branch_name)
+        .bind(&task.linked_pr_url)
+        .bind(&task.base_commit)
+        .bind(&task.end_commit)
+        .bind(encode_enum(&task.status)?)
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.revision as i64)
+        .bind(task.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "UnitTask", task.id))?;
+        replace_id_list(
+            &mut tx,
+            "unit_task_auto_fix_tasks",
+            "unit_task_id",
+            "agent_task_id",
+            task.id,
+            &task.auto_fix_task_ids,
+        )
+        .await
+        .map_err(|e| map_write_error(e, "UnitTask", task.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::UnitTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn get_unit_task(&self, id: Uuid) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        let Some(row) = fetch_by_id(&mut conn, "unit_tasks", id).await? else {
+            return Ok(None);
+        };
+        Ok(hydrate_unit_tasks(&mut conn, &[row]).await?.pop())
+    }
+
+    async fn list_unit_tasks(&self, filter: TaskFilter) -> TaskStoreResult<(Vec<UnitTask>, u32)> {
+        check_sort::<UnitTask>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let unit_status = filter.unit_status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(group_id) = filter.repository_group_id {
+                qb.push(" AND repository_group_id = ").push_bind(group_id);
+            }
+            if let Some(status) = &unit_status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(since) = filter.created_since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.created_until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM unit_tasks");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM unit_tasks");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "COALESCE(title, '') COLLATE \"C\"",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let tasks = hydrate_unit_tasks(&mut conn, &rows).await?;
+
+        Ok((tasks, total))
+    }
+
+    async fn find_unit_task_by_pr_url(&self, pr_url: &str) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM unit_tasks WHERE linked_pr_url = $1 ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(pr_url)
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(hydrate_unit_tasks(&mut conn, &rows).await?.pop())
+    }
+
+    async fn find_unit_task_by_branch_name(
+        &self,
+        branch_name: &str,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM unit_tasks WHERE branch_name = $1 ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(branch_name)
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(hydrate_unit_tasks(&mut conn, &rows).await?.pop())
+    }
+
+    async fn find_unit_task_by_agent_task(
+        &self,
+        agent_task_id: Uuid,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM unit_tasks WHERE agent_task_id = $1 OR id IN (SELECT unit_task_id FROM \
+             unit_task_auto_fix_tasks WHERE agent_task_id = $1) ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(agent_task_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        Ok(hydrate_unit_tasks(&mut conn, &rows).await?.pop())
+    }
+
+    async fn update_unit_task(&self, mut task: UnitTask) -> TaskStoreResult<UnitTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        ensure_status_transition(
+            &mut tx,
+            "unit_tasks",
+            "UnitTask",
+            task.id,
+            task.revision,
+            task.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE unit_tasks SET revision = revision + 1, repository_group_id = $1, \
+             agent_task_id = $2, prompt = $3, title = $4, branch_name = $5, linked_pr_url = $6, \
+             base_commit = $7, end_commit = $8, status = $9, created_at = $10, updated_at = $11, \
+             deleted_at = $12 WHERE id = $13 AND revision = $14",
+        )
+        .bind(task.repository_group_id)
+        .bind(task.agent_task_id)
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(&task.branch_name)
+        .bind(&task.linked_pr_url)
+        .bind(&task.base_commit)
+        .bind(&task.end_commit)
+        .bind(encode_enum(&task.status)?)
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.deleted_at)
+        .bind(task.id)
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "UnitTask", task.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "unit_tasks",
+            "UnitTask",
+            task.id,
+        )
+        .await?;
+        task.revision += 1;
+        replace_id_list(
+            &mut tx,
+            "unit_task_auto_fix_tasks",
+            "unit_task_id",
+            "agent_task_id",
+            task.id,
+            &task.auto_fix_task_ids,
+        )
+        .await
+        .map_err(|e| map_write_error(e, "UnitTask", task.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::UnitTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn delete_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "unit_tasks", "UnitTask", id, Some(Utc::now())).await?;
+        if changed {
+            let unit_task = hydrate_unit_tasks(&mut tx, &[row]).await?.remove(0);
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::UnitTask(unit_task)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_unit_task(&self, id: Uuid) -> TaskStoreResult<UnitTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) = set_deleted_at(&mut tx, "unit_tasks", "UnitTask", id, None).await?;
+        let unit_task = hydrate_unit_tasks(&mut tx, &[row]).await?.remove(0);
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::UnitTask(unit_task.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(unit_task)
+    }
+
+    async fn purge_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "unit_tasks", "UnitTask", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::UnitTask, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Composite Task operations
+    // =========================================================================
+
+    async fn create_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO composite_tasks (id, repository_group_id, planning_task_id, prompt, \
+             title, node_ids, status, execution_agent_type, created_at, updated_at, revision, \
+             deleted_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)",
+        )
+        .bind(task.id)
+        .bind(task.repository_group_id)
+        .bind(task.planning_task_id)
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(&task.node_ids)
+        .bind(encode_enum(&task.status)?)
+        .bind(
+            task.execution_agent_type
+                .as_ref()
+                .map(encode_enum)
+                .transpose()?,
+        )
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.revision as i64)
+        .bind(task.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTask", task.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::CompositeTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn get_composite_task(&self, id: Uuid) -> TaskStoreResult<Option<CompositeTask>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "composite_tasks", id)
+            .await?
+            .as_ref()
+            .map(composite_task_from_row)
+            .transpose()
+    }
+
+    async fn list_composite_tasks(
+        &self,
+        filter: TaskFilter,
+    ) -> TaskStoreResult<(Vec<CompositeTask>, u32)> {
+        check_sort::<CompositeTask>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let composite_status = filter
+            .composite_status
+            .as_ref()
+            .map(encode_enum)
+            .transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(group_id) = filter.repository_group_id {
+                qb.push(" AND repository_group_id = ").push_bind(group_id);
+            }
+            if let Some(status) = &composite_status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(since) = filter.created_since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.created_until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM composite_tasks");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM composite_tasks");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "COALESCE(title, '') COLLATE \"C\"",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let tasks = rows
+            .iter()
+            .map(composite_task_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((tasks, total))
+    }
+
+    async fn find_composite_task_by_node_unit_task(
+        &self,
+        unit_task_id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTask>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "SELECT * FROM composite_tasks WHERE id IN (SELECT composite_task_id FROM \
+             composite_task_nodes WHERE unit_task_id = $1) ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(unit_task_id)
+        .fetch_optional(&mut *conn)
+        .await?
+        .as_ref()
+        .map(composite_task_from_row)
+        .transpose()
+    }
+
+    async fn update_composite_task(
+        &self,
+        mut task: CompositeTask,
+    ) -> TaskStoreResult<CompositeTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        ensure_status_transition(
+            &mut tx,
+            "composite_tasks",
+            "CompositeTask",
+            task.id,
+            task.revision,
+            task.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE composite_tasks SET revision = revision + 1, repository_group_id = $1, \
+             planning_task_id = $2, prompt = $3, title = $4, node_ids = $5, status = $6, \
+             execution_agent_type = $7, created_at = $8, updated_at = $9, deleted_at = $10 WHERE \
+             id = $11 AND revision = $12",
+        )
+        .bind(task.repository_group_id)
+        .bind(task.planning_task_id)
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(&task.node_ids)
+        .bind(encode_enum(&task.status)?)
+        .bind(
+            task.execution_agent_type
+                .as_ref()
+                .map(encode_enum)
+                .transpose()?,
+        )
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.deleted_at)
+        .bind(task.id)
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTask", task.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "composite_tasks",
+            "CompositeTask",
+            task.id,
+        )
+        .await?;
+        task.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::CompositeTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn delete_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) = set_deleted_at(
+            &mut tx,
+            "composite_tasks",
+            "CompositeTask",
+            id,
+            Some(Utc::now()),
+        )
+        .await?;
+        if changed {
+            let composite_task = composite_task_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::CompositeTask(composite_task)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_composite_task(&self, id: Uuid) -> TaskStoreResult<CompositeTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "composite_tasks", "CompositeTask", id, None).await?;
+        let composite_task = composite_task_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::CompositeTask(composite_task.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(composite_task)
+    }
+
+    async fn purge_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "composite_tasks", "CompositeTask", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::CompositeTask, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Composite Task Node operations
+    // =========================================================================
+
+    async fn create_composite_task_node(
+        &self,
+        node: CompositeTaskNode,
+    ) -> TaskStoreResult<CompositeTaskNode> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO composite_task_nodes (id, composite_task_id, unit_task_id, created_at, \
+             revision) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(node.id)
+        .bind(node.composite_task_id)
+        .bind(node.unit_task_id)
+        .bind(node.created_at)
+        .bind(node.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTaskNode", node.id))?;
+        replace_id_list(
+            &mut tx,
+            "composite_task_node_dependencies",
+            "node_id",
+            "depends_on_node_id",
+            node.id,
+            &node.depends_on_ids,
+        )
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTaskNode", node.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::CompositeTaskNode(node.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(node)
+    }
+
+    async fn get_composite_task_node(
+        &self,
+        id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTaskNode>> {
+        let mut conn = self.acquire().await?;
+        let Some(row) = fetch_by_id(&mut conn, "composite_task_nodes", id).await? else {
+            return Ok(None);
+        };
+        Ok(hydrate_composite_task_nodes(&mut conn, &[row]).await?.pop())
+    }
+
+    async fn list_composite_task_nodes(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<CompositeTaskNode>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM composite_task_nodes WHERE composite_task_id = $1 ORDER BY created_at, \
+             id",
+        )
+        .bind(composite_task_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        hydrate_composite_task_nodes(&mut conn, &rows).await
+    }
+
+    async fn update_composite_task_node(
+        &self,
+        mut node: CompositeTaskNode,
+    ) -> TaskStoreResult<CompositeTaskNode> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE composite_task_nodes SET revision = revision + 1, composite_task_id = $1, \
+             unit_task_id = $2, created_at = $3 WHERE id = $4 AND revision = $5",
+        )
+        .bind(node.composite_task_id)
+        .bind(node.unit_task_id)
+        .bind(node.created_at)
+        .bind(node.id)
+        .bind(node.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTaskNode", node.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "composite_task_nodes",
+            "CompositeTaskNode",
+            node.id,
+        )
+        .await?;
+        node.revision += 1;
+        replace_id_list(
+            &mut tx,
+            "composite_task_node_dependencies",
+            "node_id",
+            "depends_on_node_id",
+            node.id,
+            &node.depends_on_ids,
+        )
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTaskNode", node.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::CompositeTaskNode(node.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(node)
+    }
+
+    async fn delete_composite_task_node(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "composite_task_nodes", "CompositeTaskNode", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::CompositeTaskNode, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Plan Revision operations
+    // =========================================================================
+
+    async fn create_plan_revision(
+        &self,
+        mut revision: PlanRevision,
+    ) -> TaskStoreResult<PlanRevision> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        if !lock_composite_task(&mut tx, revision.composite_task_id).await? {
+            return Err(TaskStoreError::ForeignKeyViolation(format!(
+                "CompositeTask {} does not exist",
+                revision.composite_task_id
+            )));
+        }
+        revision.number = latest_plan_revision(&mut tx, revision.composite_task_id).await? + 1;
+        sqlx::query(
+            "INSERT INTO plan_revisions (id, composite_task_id, number, yaml, author_type, \
+             author_id, approved_by, approved_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, \
+             $8, $9)",
+        )
+        .bind(revision.id)
+        .bind(revision.composite_task_id)
+        .bind(revision.number as i32)
+        .bind(&revision.yaml)
+        .bind(encode_enum(&revision.author_type)?)
+        .bind(revision.author_id)
+        .bind(revision.approved_by)
+        .bind(revision.approved_at)
+        .bind(revision.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "PlanRevision", revision.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::PlanRevision(revision.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(revision)
+    }
+
+    async fn get_plan_revision(&self, id: Uuid) -> TaskStoreResult<Option<PlanRevision>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "plan_revisions", id)
+            .await?
+            .as_ref()
+            .map(plan_revision_from_row)
+            .transpose()
+    }
+
+    async fn list_plan_revisions(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<PlanRevision>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM plan_revisions WHERE composite_task_id = $1 ORDER BY number",
+        )
+        .bind(composite_task_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        rows.iter().map(plan_revision_from_row).collect()
+    }
+
+    async fn approve_plan_revision(
+        &self,
+        id: Uuid,
+        approved_by: Uuid,
+    ) -> TaskStoreResult<PlanRevision> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let not_found = || TaskStoreError::not_found("PlanRevision", id.to_string());
+        let composite_task_id: Uuid =
+            sqlx::query_scalar("SELECT composite_task_id FROM plan_revisions WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&mut *tx)
+                .await?
+                .ok_or_else(not_found)?;
+        lock_composite_task(&mut tx, composite_task_id).await?;
+        let revision = fetch_by_id(&mut tx, "plan_revisions", id)
+            .await?
+            .as_ref()
+            .map(plan_revision_from_row)
+            .transpose()?
+            .ok_or_else(not_found)?;
+        if revision.is_approved() {
+            return Ok(revision);
+        }
+        let latest = latest_plan_revision(&mut tx, composite_task_id).await?;
+        if revision.number != latest {
+            return Err(TaskStoreError::conflict(
+                "PlanRevision",
+                id.to_string(),
+                latest as u64,
+            ));
+        }
+        let row = sqlx::query(
+            "UPDATE plan_revisions SET approved_by = $1, approved_at = $2 WHERE id = $3 RETURNING \
+             *",
+        )
+        .bind(approved_by)
+        .bind(Utc::now())
+        .bind(id)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "PlanRevision", id))?;
+        let revision = plan_revision_from_row(&row)?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::PlanRevision(revision.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(revision)
+    }
+
+    // =========================================================================
+    // Todo Item operations
+    // =========================================================================
+
+    async fn create_todo_item(&self, item: TodoItem) -> TaskStoreResult<TodoItem> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO todo_items (id, type, source, status, repository_id, data, created_at, \
+             updated_at, revision) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(item.id)
+        .bind(encode_enum(&item.item_type)?)
+        .bind(encode_enum(&item.source)?)
+        .bind(encode_enum(&item.status)?)
+        .bind(item.repository_id)
+        .bind(Json(&item.data))
+        .bind(item.created_at)
+        .bind(item.updated_at)
+        .bind(item.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TodoItem", item.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::TodoItem(item.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(item)
+    }
+
+    async fn get_todo_item(&self, id: Uuid) -> TaskStoreResult<Option<TodoItem>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "todo_items", id)
+            .await?
+            .as_ref()
+            .map(todo_item_from_row)
+            .transpose()
+    }
+
+    async fn list_todo_items(&self, filter: TodoFilter) -> TaskStoreResult<(Vec<TodoItem>, u32)> {
+        check_sort::<TodoItem>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let status = filter.status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(repository_id) = filter.repository_id {
+                qb.push(" AND repository_id = ").push_bind(repository_id);
+            }
+            if let Some(status) = &status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM todo_items");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM todo_items");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let items = rows
+            .iter()
+            .map(todo_item_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((items, total))
+    }
+
+    async fn update_todo_item(&self, mut item: TodoItem) -> TaskStoreResult<TodoItem> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        ensure_status_transition(
+            &mut tx,
+            "todo_items",
+            "TodoItem",
+            item.id,
+            item.revision,
+            item.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE todo_items SET revision = revision + 1, type = $1, source = $2, status = $3, \
+             repository_id = $4, data = $5, created_at = $6, updated_at = $7 WHERE id = $8 AND \
+             revision = $9",
+        )
+        .bind(encode_enum(&item.item_type)?)
+        .bind(encode_enum(&item.source)?)
+        .bind(encode_enum(&item.status)?)
+        .bind(item.repository_id)
+        .bind(Json(&item.data))
+        .bind(item.created_at)
+        .bind(item.updated_at)
+        .bind(item.id)
+        .bind(item.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TodoItem", item.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "todo_items",
+            "TodoItem",
+            item.id,
+        )
+        .await?;
+        item.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::TodoItem(item.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(item)
+    }
+
+    async fn delete_todo_item(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "todo_items", "TodoItem", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::TodoItem, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // TTY Input Request operations
+    // =========================================================================
+
+    async fn create_tty_input_request(
+        &self,
+        request: TtyInputRequest,
+    ) -> TaskStoreResult<TtyInputRequest> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO tty_input_requests (id, task_id, session_id, prompt, input_type, \
+             options, status, response, created_at, responded_at, revision) VALUES ($1, $2, $3, \
+             $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(request.id)
+        .bind(request.task_id)
+        .bind(request.session_id)
+        .bind(&request.prompt)
+        .bind(encode_enum(&request.input_type)?)
+        .bind(request.options.as_ref().map(Json))
+        .bind(encode_enum(&request.status)?)
+        .bind(&request.response)
+        .bind(request.created_at)
+        .bind(request.responded_at)
+        .bind(request.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TtyInputRequest", request.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::TtyInputRequest(request.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(request)
+    }
+
+    async fn get_tty_input_request(&self, id: Uuid) -> TaskStoreResult<Option<TtyInputRequest>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "tty_input_requests", id)
+            .await?
+            .as_ref()
+            .map(tty_input_request_from_row)
+            .transpose()
+    }
+
+    async fn list_tty_input_requests(
+        &self,
+        filter: TtyInputFilter,
+    ) -> TaskStoreResult<Vec<TtyInputRequest>> {
+        check_sort::<TtyInputRequest>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM tty_input_requests WHERE TRUE");
+        if let Some(task_id) = filter.task_id {
+            qb.push(" AND task_id = ").push_bind(task_id);
+        }
+        if let Some(session_id) = filter.session_id {
+            qb.push(" AND session_id = ").push_bind(session_id);
+        }
+        if let Some(status) = filter.status {
+            qb.push(" AND status = ").push_bind(encode_enum(&status)?);
+        }
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        rows.iter().map(tty_input_request_from_row).collect()
+    }
+
+    async fn update_tty_input_request(
+        &self,
+        mut request: TtyInputRequest,
+    ) -> TaskStoreResult<TtyInputRequest> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        ensure_status_transition(
+            &mut tx,
+            "tty_input_requests",
+            "TtyInputRequest",
+            request.id,
+            request.revision,
+            request.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE tty_input_requests SET revision = revision + 1, task_id = $1, session_id = \
+             $2, prompt = $3, input_type = $4, options = $5, status = $6, response = $7, \
+             created_at = $8, responded_at = $9 WHERE id = $10 AND revision = $11",
+        )
+        .bind(request.task_id)
+        .bind(request.session_id)
+        .bind(&request.prompt)
+        .bind(encode_enum(&request.input_type)?)
+        .bind(request.options.as_ref().map(Json))
+        .bind(encode_enum(&request.status)?)
+        .bind(&request.response)
+        .bind(request.created_at)
+        .bind(request.responded_at)
+        .bind(request.id)
+        .bind(request.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TtyInputRequest", request.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "tty_input_requests",
+            "TtyInputRequest",
+            request.id,
+        )
+        .await?;
+        request.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::TtyInputRequest(request.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(request)
+    }
+
+    async fn delete_tty_input_request(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "tty_input_requests", "TtyInputRequest", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::TtyInputRequest, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Worker operations
+    // =========================================================================
+
+    async fn register_worker(&self, worker: Worker) -> TaskStoreResult<Worker> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        sqlx::query(
+            "INSERT INTO workers (id, name, endpoint_url, capabilities, status, current_task_id, \
+             last_heartbeat, revision, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(worker.id)
+        .bind(&worker.name)
+        .bind(&worker.endpoint_url)
+        .bind(Json(&worker.capabilities))
+        .bind(encode_enum(&worker.status)?)
+        .bind(worker.current_task_id)
+        .bind(worker.last_heartbeat)
+        .bind(worker.revision as i64)
+        .bind(worker.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Worker", worker.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::Worker(worker.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(worker)
+    }
+
+    async fn get_worker(&self, id: Uuid) -> TaskStoreResult<Option<Worker>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "workers", id)
+            .await?
+            .as_ref()
+            .map(worker_from_row)
+            .transpose()
+    }
+
+    async fn list_workers(&self, filter: WorkerFilter) -> TaskStoreResult<(Vec<Worker>, u32)> {
+        check_sort::<Worker>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let status = filter.status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(status) = &status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(capability) = &filter.capability {
+                qb.push(" AND capabilities ? ")
+                    .push_bind(capability.clone());
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM workers");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM workers");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "name",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let workers = rows
+            .iter()
+            .map(worker_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((workers, total))
+    }
+
+    async fn update_worker(&self, mut worker: Worker) -> TaskStoreResult<Worker> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let result = sqlx::query(
+            "UPDATE workers SET revision = revision + 1, name = $1, endpoint_url = $2, \
+             capabilities = $3, status = $4, current_task_id = $5, last_heartbeat = $6, \
+             created_at = $7 WHERE id = $8 AND revision = $9",
+        )
+        .bind(&worker.name)
+        .bind(&worker.endpoint_url)
+        .bind(Json(&worker.capabilities))
+        .bind(encode_enum(&worker.status)?)
+        .bind(worker.current_task_id)
+        .bind(worker.last_heartbeat)
+        .bind(worker.created_at)
+        .bind(worker.id)
+        .bind(worker.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Worker", worker.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "workers",
+            "Worker",
+            worker.id,
+        )
+        .await?;
+        worker.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::Worker(worker.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(worker)
+    }
+
+    async fn delete_worker(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        delete_by_id(&mut tx, "workers", "Worker", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::Worker, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn record_worker_heartbeat(
+        &self,
+        id: Uuid,
+        status: WorkerStatus,
+        current_task_id: Option<Uuid>,
+    ) -> TaskStoreResult<Worker> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        // Locking the row keeps a concurrent stale sweep or update from
+        // landing between the read and the write.
+        let mut worker = sqlx::query("SELECT * FROM workers WHERE id = $1 FOR UPDATE")
+            .bind(id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .as_ref()
+            .map(worker_from_row)
+            .transpose()?
+            .ok_or_else(|| TaskStoreError::not_found("Worker", id.to_string()))?;
+        let changed = worker.status != status || worker.current_task_id != current_task_id;
+        worker.last_heartbeat = Utc::now();
+        if changed {
+            worker.status = status;
+            worker.current_task_id = current_task_id;
+            worker.revision += 1;
+        }
+        sqlx::query(
+            "UPDATE workers SET status = $1, current_task_id = $2, last_heartbeat = $3, revision \
+             = $4 WHERE id = $5",
+        )
+        .bind(encode_enum(&worker.status)?)
+        .bind(worker.current_task_id)
+        .bind(worker.last_heartbeat)
+        .bind(worker.revision as i64)
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Worker", id))?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Worker(worker.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(worker)
+    }
+
+    async fn mark_stale_workers(&self, before: DateTime<Utc>) -> TaskStoreResult<Vec<Worker>> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let unhealthy = encode_enum(&WorkerStatus::Unhealthy)?;
+        let rows = sqlx::query(
+            "UPDATE workers SET status = $1, revision = revision + 1 WHERE status <> $2 AND \
+             last_heartbeat < $3 RETURNING *",
+        )
+        .bind(&unhealthy)
+        .bind(&unhealthy)
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut workers = rows
+            .iter()
+            .map(worker_from_row)
+            .collect::<TaskStoreResult<Vec<_>>>()?;
+        workers.sort_by_key(|w| (w.last_heartbeat, w.id));
+        for worker in &workers {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Worker(worker.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(workers)
+    }
+
+    // =========================================================================
+    // Work queue operations
+    // =========================================================================
+
+    async fn enqueue_task(&self, entry: QueueEntry) -> TaskStoreResult<QueueEntry> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "INSERT INTO work_queue (id, agent_task_id, status, attempts, max_attempts, \
+             worker_id, lease_expires_at, last_error, revision, created_at, updated_at) VALUES \
+             ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(entry.id)
+        .bind(entry.agent_task_id)
+        .bind(encode_enum(&entry.status)?)
+        .bind(entry.attempts as i32)
+        .bind(entry.max_attempts as i32)
+        .bind(entry.worker_id)
+        .bind(entry.lease_expires_at)
+        .bind(&entry.last_error)
+        .bind(entry.revision as i64)
+        .bind(entry.created_at)
+        .bind(entry.updated_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| map_write_error(e, "QueueEntry", entry.id))?;
+        Ok(entry)
+    }
+
+    async fn get_queue_entry(&self, id: Uuid) -> TaskStoreResult<Option<QueueEntry>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "work_queue", id)
+            .await?
+            .as_ref()
+            .map(queue_entry_from_row)
+            .transpose()
+    }
+
+    async fn list_queue_entries(
+        &self,
+        filter: QueueFilter,
+    ) -> TaskStoreResult<(Vec<QueueEntry>, u32)> {
+        check_sort::<QueueEntry>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let status = filter.status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(status) = &status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(worker_id) = filter.worker_id {
+                qb.push(" AND worker_id = ").push_bind(worker_id);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM work_queue");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM work_queue");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let entries = rows
+            .iter()
+            .map(queue_entry_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((entries, total))
+    }
+
+    async fn claim_task(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Option<QueueEntry>> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let now = Utc::now();
+        requeue_expired(&mut tx, now).await?;
+        let row = sqlx::query(
+            "UPDATE work_queue SET status = $1, attempts = attempts + 1, worker_id = $2, \
+             lease_expires_at = $3, revision = revision + 1, updated_at = $4 WHERE id = (SELECT \
+             id FROM work_queue WHERE status = $5 ORDER BY created_at, id LIMIT 1 FOR UPDATE SKIP \
+             LOCKED) RETURNING *",
+        )
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id)
+        .bind(now + lease)
+        .bind(now)
+        .bind(encode_enum(&QueueStatus::Queued)?)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let entry = row.as_ref().map(queue_entry_from_row).transpose()?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn renew_leases(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Vec<QueueEntry>> {
+        let mut conn = self.acquire().await?;
+        let now = Utc::now();
+        let rows = sqlx::query(
+            "UPDATE work_queue SET lease_expires_at = $1, revision = revision + 1, updated_at = \
+             $2 WHERE status = $3 AND worker_id = $4 AND lease_expires_at > $5 RETURNING *",
+        )
+        .bind(now + lease)
+        .bind(now)
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id)
+        .bind(now)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut entries = rows
+            .iter()
+            .map(queue_entry_from_row)
+            .collect::<TaskStoreResult<Vec<_>>>()?;
+        entries.sort_by_key(|e| (e.created_at, e.id));
+        Ok(entries)
+    }
+
+    async fn complete_task(&self, id: Uuid, worker_id: Uuid) -> TaskStoreResult<QueueEntry> {
+        let mut conn = self.acquire().await?;
+        let now = Utc::now();
+        let row = sqlx::query(
+            "UPDATE work_queue SET status = $1, worker_id = NULL, lease_expires_at = NULL, \
+             revision = revision + 1, updated_at = $2 WHERE id = $3 AND status = $4 AND worker_id \
+             = $5 AND lease_expires_at > $6 RETURNING *",
+        )
+        .bind(encode_enum(&QueueStatus::Completed)?)
+        .bind(now)
+        .bind(id)
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id)
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match row {
+            Some(row) => queue_entry_from_row(&row),
+            None => Err(lease_not_held(&mut conn, id, worker_id).await),
+        }
+    }
+
+    async fn fail_task(
+        &self,
+        id: Uuid,
+        worker_id: Uuid,
+        error: String,
+    ) -> TaskStoreResult<QueueEntry> {
+        let mut conn = self.acquire().await?;
+        let now = Utc::now();
+        let row = sqlx::query(
+            "UPDATE work_queue SET status = CASE WHEN attempts < max_attempts THEN $1 ELSE $2 \
+             END, worker_id = NULL, lease_expires_at = NULL, last_error = $3, revision = revision \
+             + 1, updated_at = $4 WHERE id = $5 AND status = $6 AND worker_id = $7 AND \
+             lease_expires_at > $8 RETURNING *",
+        )
+        .bind(encode_enum(&QueueStatus::Queued)?)
+        .bind(encode_enum(&QueueStatus::Failed)?)
+        .bind(&error)
+        .bind(now)
+        .bind(id)
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id)
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match row {
+            Some(row) => queue_entry_from_row(&row),
+            None => Err(lease_not_held(&mut conn, id, worker_id).await),
+        }
+    }
+
+    async fn requeue_expired_leases(&self) -> TaskStoreResult<u64> {
+        let mut conn = self.acquire().await?;
+        requeue_expired(&mut conn, Utc::now()).await
+    }
+
+    // =========================================================================
+    // Audit log operations
+    // =========================================================================
+
+    async fn record_audit_event(&self, event: AuditEvent) -> TaskStoreResult<AuditEvent> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "INSERT INTO audit_events (id, actor_type, actor_id, action, entity_type, entity_id, \
+             before, after, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        )
+        .bind(event.id)
+        .bind(encode_enum(&event.actor.actor_type)?)
+        .bind(event.actor.id)
+        .bind(&event.action)
+        .bind(&event.entity_type)
+        .bind(event.entity_id)
+        .bind(&event.before)
+        .bind(&event.after)
+        .bind(event.created_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| map_write_error(e, "AuditEvent", event.id))?;
+        Ok(event)
+    }
+
+    async fn get_audit_event(&self, id: Uuid) -> TaskStoreResult<Option<AuditEvent>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "audit_events", id)
+            .await?
+            .as_ref()
+            .map(audit_event_from_row)
+            .transpose()
+    }
+
+    async fn list_audit_events(
+        &self,
+        filter: AuditFilter,
+    ) -> TaskStoreResult<(Vec<AuditEvent>, u32)> {
+        check_sort::<AuditEvent>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let actor_type = filter.actor_type.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Postgres>| {
+            qb.push(" WHERE TRUE");
+            if let Some(entity_type) = &filter.entity_type {
+                qb.push(" AND entity_type = ")
+                    .push_bind(entity_type.clone());
+            }
+            if let Some(entity_id) = filter.entity_id {
+                qb.push(" AND entity_id = ").push_bind(entity_id);
+            }
+            if let Some(actor_type) = &actor_type {
+                qb.push(" AND actor_type = ").push_bind(actor_type.clone());
+            }
+            if let Some(actor_id) = filter.actor_id {
+                qb.push(" AND actor_id = ").push_bind(actor_id);
+            }
+            if let Some(since) = filter.since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM audit_events");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM audit_events");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let events = rows
+            .iter()
+            .map(audit_event_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((events, total))
+    }
+
+    // =========================================================================
+    // Search
+    // =========================================================================
+
+    async fn search(&self, query: SearchQuery) -> TaskStoreResult<Vec<SearchHit>> {
+        let terms = query.terms();
+        let columns: Vec<_> = SEARCH_COLUMNS
+            .iter()
+            .filter(|(kind, ..)| query.includes(*kind))
+            .collect();
+        if terms.is_empty() || columns.is_empty() {
+            return Ok(Vec::new());
+        }
+        let text = terms.join(" ");
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Postgres>::new("SELECT * FROM (");
+        for (i, (kind, field, table, column)) in columns.into_iter().enumerate() {
+            if i > 0 {
+                qb.push(" UNION ALL ");
+            }
+            let document = search_vector(column);
+            qb.push("SELECT ")
+                .push_bind(encode_enum(kind)?)
+                .push(" AS kind, id, ")
+                .push_bind(encode_enum(field)?)
+                .push(format!(
+                    " AS field, {column} AS body, ts_rank({document}, plainto_tsquery('simple', "
+                ))
+                .push_bind(text.clone())
+                .push(format!(
+                    "))::float8 AS score FROM {table} WHERE {document} @@ \
+                     plainto_tsquery('simple', "
+                ))
+                .push_bind(text.clone())
+                .push(")");
+        }
+        if query.includes(EntityKind::AgentSession) {
+            // Only the best chunk of each session is a hit.
+            let document = search_vector("content");
+            qb.push(" UNION ALL (SELECT DISTINCT ON (session_id) ")
+                .push_bind(encode_enum(&EntityKind::AgentSession)?)
+                .push(" AS kind, session_id AS id, ")
+                .push_bind(encode_enum(&SearchField::SessionLog)?)
+                .push(format!(
+                    " AS field, content AS body, ts_rank({document}, plainto_tsquery('simple', "
+                ))
+                .push_bind(text.clone())
+                .push(format!(
+                    "))::float8 AS score FROM session_log_chunks WHERE {document} @@ \
+                     plainto_tsquery('simple', "
+                ))
+                .push_bind(text.clone())
+                .push(") ORDER BY session_id, score DESC, sequence)");
+        }
+        qb.push(") hits ORDER BY score DESC, id");
+        push_pagination(&mut qb, query.limit, None);
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        // Snippets are built here rather than with ts_headline, whose parser
+        // does not split paths into the words the index matched.
+        rows.iter()
+            .map(|row| {
+                Ok(SearchHit {
+                    kind: decode_enum(row.try_get("kind")?)?,
+                    id: row.try_get("id")?,
+                    field: decode_enum(row.try_get("field")?)?,
+                    score: row.try_get("score")?,
+                    snippet: snippet(row.try_get("body")?, &terms),
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl TaskStoreTransaction for PostgresTaskStore {
+    async fn commit(self: Box<Self>) -> TaskStoreResult<()> {
+        finish_transaction(self.transaction.as_ref(), true).await
+    }
+
+    async fn rollback(self: Box<Self>) -> TaskStoreResult<()> {
+        finish_transaction(self.transaction.as_ref(), false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use entities::{AiAgentType, UnitTaskStatus, VcsProviderType};
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::ChangeOperation;
+
+    /// Connects to the database named by `DELIDEV_TEST_DATABASE_URL`, or
+    /// returns `None` so the test is skipped when no server is available.
+    async fn test_store() -> Option<PostgresTaskStore> {
+        let url = std::env::var("DELIDEV_TEST_DATABASE_URL").ok()?;
+        Some(PostgresTaskStore::connect(&url).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_repository_group_members_round_trip() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let mut group = RepositoryGroup::new(workspace.id).with_name("Backend");
+        for name in ["api", "worker"] {
+            let repo = store
+                .create_repository(Repository::new(
+                    workspace.id,
+                    name,
+                    format!("https://github.com/test/{name}"),
+                    VcsProviderType::Github,
+                ))
+                .await
+                .unwrap();
+            group.add_repository(repo.id);
+        }
+        store.create_repository_group(group.clone()).await.unwrap();
+
+        let fetched = store.get_repository_group(group.id).await.unwrap().unwrap();
+        assert_eq!(fetched.repository_ids, group.repository_ids);
+
+        group.remove_repository(group.repository_ids[0]);
+        store.update_repository_group(group.clone()).await.unwrap();
+        let groups = store
+            .list_repository_groups(Some(workspace.id))
+            .await
+            .unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].repository_ids, group.repository_ids);
+    }
+
+    #[tokio::test]
+    async fn test_unit_task_and_nodes() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let mut agent_task = AgentTask::new();
+        agent_task.add_base_remote("/path/to/repo", "main");
+        let agent_task = store.create_agent_task(agent_task).await.unwrap();
+        let auto_fix = store.create_agent_task(AgentTask::new()).await.unwrap();
+
+        let mut task = UnitTask::new(group.id, agent_task.id, "Fix the bug");
+        task.auto_fix_task_ids.push(auto_fix.id);
+        let task = store.create_unit_task(task).await.unwrap();
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.auto_fix_task_ids, vec![auto_fix.id]);
+        assert_eq!(fetched.status, UnitTaskStatus::InProgress);
+
+        let fetched_agent_task = store.get_agent_task(agent_task.id).await.unwrap().unwrap();
+        assert_eq!(fetched_agent_task.base_remotes[0].git_branch_name, "main");
+
+        let planning = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let composite = store
+            .create_composite_task(CompositeTask::new(group.id, planning.id, "Plan"))
+            .await
+            .unwrap();
+        let first = store
+            .create_composite_task_node(CompositeTaskNode::new(composite.id, task.id))
+            .await
+            .unwrap();
+        let mut second = CompositeTaskNode::new(composite.id, task.id);
+        second.depends_on(first.id);
+        store
+            .create_composite_task_node(second.clone())
+            .await
+            .unwrap();
+
+        let nodes = store.list_composite_task_nodes(composite.id).await.unwrap();
+        assert_eq!(nodes.len(), 2);
+        let fetched_second = nodes.iter().find(|n| n.id == second.id).unwrap();
+        assert_eq!(fetched_second.depends_on_ids, vec![first.id]);
+    }
+
+    #[tokio::test]
+    async fn test_list_repositories_with_cursor() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        for name in ["charlie", "alpha", "delta", "bravo"] {
+            store
+                .create_repository(Repository::new(
+                    workspace.id,
+                    name,
+                    format!("https://github.com/test/{name}"),
+                    VcsProviderType::Github,
+                ))
+                .await
+                .unwrap();
+        }
+        let names = |repos: &[Repository]| repos.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+
+        let mut filter = RepositoryFilter {
+            workspace_id: Some(workspace.id),
+            sort: SortOrder::new(SortKey::Title),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(names(&page), ["alpha", "bravo"]);
+
+        let cursor = Cursor::after(page.last().unwrap(), SortKey::Title).unwrap();
+        filter.after = Some(cursor.to_string().parse().unwrap());
+        let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(names(&page), ["charlie", "delta"]);
+
+        filter.sort = filter.sort.descending();
+        filter.after = Some(Cursor::after(&page[0], SortKey::Title).unwrap());
+        let (page, _) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(names(&page), ["bravo", "alpha"]);
+
+        filter.sort = SortOrder::new(SortKey::Status);
+        filter.after = None;
+        assert!(matches!(
+            store.list_repositories(filter).await,
+            Err(TaskStoreError::InvalidQuery(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unit_task_status_transitions() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let mut task = store
+            .create_unit_task(UnitTask::new(group.id, agent_task.id, "Fix the bug"))
+            .await
+            .unwrap();
+
+        task.status = UnitTaskStatus::InReview;
+        let mut task = store.update_unit_task(task).await.unwrap();
+        task.status = UnitTaskStatus::Rejected;
+        let task = store.update_unit_task(task).await.unwrap();
+
+        let mut reopened = task.clone();
+        reopened.status = UnitTaskStatus::PrOpen;
+        match store.update_unit_task(reopened).await {
+            Err(TaskStoreError::InvalidStateTransition { from, to }) => {
+                assert_eq!(from, "rejected");
+                assert_eq!(to, "pr_open");
+            }
+            other => panic!("Expected invalid transition, got {other:?}"),
+        }
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.status, UnitTaskStatus::Rejected);
+    }
+
+    #[tokio::test]
+    async fn test_constraint_errors() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store.create_workspace(workspace.clone()).await,
+            Err(TaskStoreError::AlreadyExists { .. })
+        ));
+        assert!(matches!(
+            store
+                .create_repository_group(RepositoryGroup::new(Uuid::new_v4()))
+                .await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        let mut group = RepositoryGroup::new(workspace.id);
+        group.add_repository(Uuid::new_v4());
+        assert!(matches!(
+            store.create_repository_group(group).await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        assert!(matches!(
+            store.delete_workspace(Uuid::new_v4()).await,
+            Err(TaskStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stale_update_conflicts() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        assert_eq!(workspace.revision, 0);
+
+        let mut first = workspace.clone();
+        first.name = "First".to_string();
+        let first = store.update_workspace(first).await.unwrap();
+        assert_eq!(first.revision, 1);
+
+        let mut stale = workspace;
+        stale.name = "Stale".to_string();
+        match store.update_workspace(stale).await {
+            Err(TaskStoreError::Conflict {
+                current_revision, ..
+            }) => assert_eq!(current_revision, 1),
+            other => panic!("Expected conflict, got {other:?}"),
+        }
+        let stored = store.get_workspace(first.id).await.unwrap().unwrap();
+        assert_eq!(stored.name, "First");
+        assert_eq!(stored.revision, 1);
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        // The test database is shared, so search for a word no other test
+        // writes.
+        let marker = format!("m{}", Uuid::new_v4().simple());
+        let mut task = store
+            .create_unit_task(UnitTask::new(
+                group.id,
+                agent_task.id,
+                format!("Fix the {marker} bug"),
+            ))
+            .await
+            .unwrap();
+        let mut session = AgentSession::new(agent_task.id, AiAgentType::ClaudeCode);
+        session.output_log = Some(format!("Edited src/{marker}/invoice.rs"));
+        let session = store.create_agent_session(session).await.unwrap();
+
+        let hits = store
+            .search(SearchQuery::new(format!("{marker} invoice")))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, EntityKind::AgentSession);
+        assert_eq!(hits[0].id, session.id);
+        assert!(hits[0].snippet.contains("**invoice**"));
+
+        let query = SearchQuery::new(marker.as_str()).with_kinds([EntityKind::UnitTask]);
+        let hits = store.search(query).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].id, task.id);
+        assert_eq!(hits[0].field, SearchField::Prompt);
+
+        task.title = Some(format!("{marker} rounding"));
+        store.update_unit_task(task).await.unwrap();
+        let hits = store
+            .search(SearchQuery::new(format!("{marker} rounding")))
+            .await
+            .unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Title);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commit_and_rollback() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+
+        let tx = store.begin().await.unwrap();
+        let workspace = tx
+            .create_workspace(Workspace::new("Committed"))
+            .await
+            .unwrap();
+        let mut group = RepositoryGroup::new(workspace.id);
+        group.add_repository(
+            tx.create_repository(Repository::new(
+                workspace.id,
+                "api",
+                "https://github.com/test/api",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap()
+            .id,
+        );
+        tx.create_repository_group(group.clone()).await.unwrap();
+        assert!(store.get_workspace(workspace.id).await.unwrap().is_none());
+        tx.commit().await.unwrap();
+        let fetched = store.get_repository_group(group.id).await.unwrap().unwrap();
+        assert_eq!(fetched.repository_ids, group.repository_ids);
+
+        let tx = store.begin().await.unwrap();
+        let discarded = tx
+            .create_workspace(Workspace::new("Discarded"))
+            .await
+            .unwrap();
+        tx.delete_repository_group(group.id).await.unwrap();
+        tx.rollback().await.unwrap();
+        assert!(store.get_workspace(discarded.id).await.unwrap().is_none());
+        assert!(
+            store
+                .get_repository_group(group.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let Some(store) = test_store().await else {
+            return;
+        };
+        let workspace = Workspace::new("Test Workspace");
+        let mut changes = store
+            .subscribe(ChangeFilter::new().with_entity_id(workspace.id))
+            .await
+            .unwrap();
+
+        let tx = store.begin().await.unwrap();
+        tx.create_workspace(workspace.clone()).await.unwrap();
+        tx.commit().await.unwrap();
+        store.purge_workspace(workspace.id).await.unwrap();
+
+        let mut operations = Vec::new();
+        for _ in 0..2 {
+            let event = tokio::time::timeout(Duration::from_secs(5), changes.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            operations.push(event.operation);
+        }
+        assert_eq!(
+            operations,
+            vec![ChangeOperation::Created, ChangeOperation::Deleted]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        if test_store().await.is_none() {
+            return;
+        }
+        crate::run_conformance(|| async { test_store().await.unwrap() }).await;
+    }
+}
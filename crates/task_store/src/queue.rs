@@ -0,0 +1,229 @@
+//! Durable work queue handing agent tasks out to workers.
+//!
+//! An agent task is enqueued once as a [`QueueEntry`]. A worker claims the
+//! oldest queued entry with [`claim_task`], which leases it to the worker
+//! until the lease expires. The worker renews its leases with every
+//! heartbeat through [`renew_leases`] and finishes the entry with
+//! [`complete_task`] or [`fail_task`]. An entry
+//! whose lease runs out goes back to the queue, or fails once it has used up
+//! its attempts, so a crashed worker never strands its task.
+//!
+//! Claiming is atomic in every backend: PostgreSQL skips rows locked by
+//! concurrent claims with `FOR UPDATE SKIP LOCKED`, SQLite serializes
+//! writers, and the memory store claims under the table's write lock. Two
+//! workers polling at once therefore never receive the same entry.
+//!
+//! [`claim_task`]: crate::TaskStore::claim_task
+//! [`renew_leases`]: crate::TaskStore::renew_leases
+//! [`complete_task`]: crate::TaskStore::complete_task
+//! [`fail_task`]: crate::TaskStore::fail_task
+
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{TaskStoreError, TaskStoreResult};
+
+/// Number of times an entry is handed out before it fails for good, unless
+/// set otherwise.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Status of a queue entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    /// Waiting to be claimed.
+    #[default]
+    Queued,
+    /// Leased to a worker.
+    Leased,
+    /// Finished successfully.
+    Completed,
+    /// Failed on its last attempt.
+    Failed,
+}
+
+/// An agent task in the work queue.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueEntry {
+    /// Unique identifier.
+    pub id: Uuid,
+    /// Agent task to run. An agent task is enqueued at most once.
+    pub agent_task_id: Uuid,
+    /// Current status.
+    pub status: QueueStatus,
+    /// Number of times the entry has been claimed.
+    pub attempts: u32,
+    /// Number of claims after which a failed or expired attempt fails the
+    /// entry instead of queueing it again.
+    pub max_attempts: u32,
+    /// Worker holding the lease, while leased.
+    pub worker_id: Option<Uuid>,
+    /// When the lease runs out, while leased.
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    /// Error reported by the last failed attempt.
+    pub last_error: Option<String>,
+    /// Revision counter, incremented by the task store on every change.
+    pub revision: u64,
+    /// When the entry was enqueued.
+    pub created_at: DateTime<Utc>,
+    /// When the entry last changed.
+    pub updated_at: DateTime<Utc>,
+}
+
+impl QueueEntry {
+    /// Creates a queued entry for an agent task.
+    pub fn new(agent_task_id: Uuid) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            agent_task_id,
+            status: QueueStatus::Queued,
+            attempts: 0,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            worker_id: None,
+            lease_expires_at: None,
+            last_error: None,
+            revision: 0,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// Sets the maximum number of attempts.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Returns true if the entry is leased and its lease has run out.
+    pub fn lease_expired(&self, now: DateTime<Utc>) -> bool {
+        self.status == QueueStatus::Leased && self.lease_expires_at.is_some_and(|at| at <= now)
+    }
+
+    /// Leases a queued entry to `worker_id` for `lease`.
+    pub(crate) fn claim(&mut self, worker_id: Uuid, lease: TimeDelta, now: DateTime<Utc>) {
+        self.status = QueueStatus::Leased;
+        self.attempts += 1;
+        self.worker_id = Some(worker_id);
+        self.lease_expires_at = Some(now + lease);
+        self.touch(now);
+    }
+
+    /// Returns true if `worker_id` holds an unexpired lease on the entry.
+    pub fn leased_to(&self, worker_id: Uuid, now: DateTime<Utc>) -> bool {
+        self.status == QueueStatus::Leased
+            && self.worker_id == Some(worker_id)
+            && !self.lease_expired(now)
+    }
+
+    /// Extends the entry's lease to `lease` from now.
+    pub(crate) fn renew(&mut self, lease: TimeDelta, now: DateTime<Utc>) {
+        self.lease_expires_at = Some(now + lease);
+        self.touch(now);
+    }
+
+    /// Marks the entry leased by `worker_id` as completed.
+    pub(crate) fn complete(&mut self, worker_id: Uuid, now: DateTime<Utc>) -> TaskStoreResult<()> {
+        self.ensure_lease(worker_id, now)?;
+        self.status = QueueStatus::Completed;
+        self.release(now);
+        Ok(())
+    }
+
+    /// Records a failed attempt by `worker_id`, queueing the entry again if
+    /// it has attempts left.
+    pub(crate) fn fail(
+        &mut self,
+        worker_id: Uuid,
+        error: String,
+        now: DateTime<Utc>,
+    ) -> TaskStoreResult<()> {
+        self.ensure_lease(worker_id, now)?;
+        self.last_error = Some(error);
+        self.retry_or_fail(now);
+        Ok(())
+    }
+
+    /// Takes an expired lease away from its worker.
+    pub(crate) fn expire(&mut self, now: DateTime<Utc>) {
+        self.last_error = Some(LEASE_EXPIRED.to_string());
+        self.retry_or_fail(now);
+    }
+
+    fn retry_or_fail(&mut self, now: DateTime<Utc>) {
+        self.status = if self.attempts < self.max_attempts {
+            QueueStatus::Queued
+        } else {
+            QueueStatus::Failed
+        };
+        self.release(now);
+    }
+
+    fn release(&mut self, now: DateTime<Utc>) {
+        self.worker_id = None;
+        self.lease_expires_at = None;
+        self.touch(now);
+    }
+
+    fn touch(&mut self, now: DateTime<Utc>) {
+        self.revision += 1;
+        self.updated_at = now;
+    }
+
+    /// Fails with [`TaskStoreError::LeaseNotHeld`] unless `worker_id` holds
+    /// an unexpired lease on the entry.
+    fn ensure_lease(&self, worker_id: Uuid, now: DateTime<Utc>) -> TaskStoreResult<()> {
+        if self.leased_to(worker_id, now) {
+            return Ok(());
+        }
+        Err(TaskStoreError::LeaseNotHeld {
+            id: self.id.to_string(),
+            worker_id: worker_id.to_string(),
+        })
+    }
+}
+
+/// Error recorded on an entry whose lease ran out.
+pub(crate) const LEASE_EXPIRED: &str = "Lease expired";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lease_lifecycle() {
+        let worker_id = Uuid::new_v4();
+        let now = Utc::now();
+        let mut entry = QueueEntry::new(Uuid::new_v4()).with_max_attempts(2);
+
+        entry.claim(worker_id, TimeDelta::seconds(30), now);
+        assert_eq!(entry.status, QueueStatus::Leased);
+        assert_eq!(entry.attempts, 1);
+        assert!(!entry.lease_expired(now));
+        assert!(entry.lease_expired(now + TimeDelta::seconds(30)));
+        assert!(entry.leased_to(worker_id, now));
+        assert!(!entry.leased_to(Uuid::new_v4(), now));
+        entry.renew(TimeDelta::seconds(60), now + TimeDelta::seconds(10));
+        assert!(!entry.lease_expired(now + TimeDelta::seconds(30)));
+        assert!(matches!(
+            entry.complete(Uuid::new_v4(), now),
+            Err(TaskStoreError::LeaseNotHeld { .. })
+        ));
+
+        entry
+            .fail(worker_id, "agent crashed".to_string(), now)
+            .unwrap();
+        assert_eq!(entry.status, QueueStatus::Queued);
+        assert_eq!(entry.worker_id, None);
+
+        entry.claim(worker_id, TimeDelta::seconds(30), now);
+        entry.expire(now + TimeDelta::seconds(30));
+        assert_eq!(entry.status, QueueStatus::Failed, "no attempts left");
+        assert_eq!(entry.last_error.as_deref(), Some(LEASE_EXPIRED));
+        assert!(matches!(
+            entry.complete(worker_id, now),
+            Err(TaskStoreError::LeaseNotHeld { .. })
+        ));
+    }
+}
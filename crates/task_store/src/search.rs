@@ -0,0 +1,494 @@
+//! Full-text search over tasks and session logs.
+//!
+//! The searchable fields are [`UnitTask`] titles and prompts,
+//! [`CompositeTask`] prompts and [`AgentSession`] output logs, both the
+//! `output_log` column and the chunks written by
+//! [`append_session_log`](crate::TaskStore::append_session_log). Text is
+//! split into lowercase alphanumeric words, and a field matches a query when
+//! it contains every word of the query. The SQL stores use SQLite FTS5 and
+//! PostgreSQL `tsvector` indexes with the same tokenization; the memory store
+//! keeps an inverted index ranked with BM25.
+//!
+//! Log chunks are indexed one by one, so a chunk matches only if it contains
+//! every word itself, and a word split between two chunks is not found. A
+//! session gets at most one [`SearchField::SessionLog`] hit, for its best
+//! chunk.
+//!
+//! [`UnitTask`]: entities::UnitTask
+//! [`CompositeTask`]: entities::CompositeTask
+//! [`AgentSession`]: entities::AgentSession
+
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{ChangeEvent, EntityKind, EntityValue, LogChunk};
+
+/// Marker inserted before a matched word in a snippet.
+pub const SNIPPET_MATCH_START: &str = "**";
+
+/// Marker inserted after a matched word in a snippet.
+pub const SNIPPET_MATCH_END: &str = "**";
+
+/// Marker for text elided from a snippet.
+pub(crate) const SNIPPET_ELLIPSIS: &str = "...";
+
+/// Maximum number of words in a snippet.
+pub(crate) const SNIPPET_WORDS: usize = 16;
+
+/// Number of words shown before the first match in a memory store snippet.
+const SNIPPET_LEADING_WORDS: usize = 4;
+
+/// BM25 term frequency saturation.
+const BM25_K1: f64 = 1.2;
+
+/// BM25 document length normalization.
+const BM25_B: f64 = 0.75;
+
+/// Searchable field of an entity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchField {
+    /// Unit task title.
+    Title,
+    /// Unit or composite task prompt.
+    Prompt,
+    /// Agent session output log.
+    OutputLog,
+    /// Chunks appended to an agent session's log.
+    SessionLog,
+}
+
+impl SearchField {
+    /// Every searchable field.
+    pub const ALL: [SearchField; 4] =
+        [Self::Title, Self::Prompt, Self::OutputLog, Self::SessionLog];
+}
+
+/// Searchable fields with the table and column that hold them in the SQL
+/// stores.
+pub(crate) const SEARCH_COLUMNS: [(EntityKind, SearchField, &str, &str); 4] = [
+    (
+        EntityKind::UnitTask,
+        SearchField::Title,
+        "unit_tasks",
+        "title",
+    ),
+    (
+        EntityKind::UnitTask,
+        SearchField::Prompt,
+        "unit_tasks",
+        "prompt",
+    ),
+    (
+        EntityKind::CompositeTask,
+        SearchField::Prompt,
+        "composite_tasks",
+        "prompt",
+    ),
+    (
+        EntityKind::AgentSession,
+        SearchField::OutputLog,
+        "agent_sessions",
+        "output_log",
+    ),
+];
+
+/// Full-text search query.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    /// Words to search for. A field matches when it contains all of them.
+    pub text: String,
+    /// Only return hits on these entity kinds. Empty searches every
+    /// searchable kind.
+    pub kinds: Vec<EntityKind>,
+    /// Maximum number of results.
+    pub limit: Option<u32>,
+}
+
+impl SearchQuery {
+    /// Creates a query for `text` across every searchable kind.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Restricts the query to the given entity kinds.
+    pub fn with_kinds(mut self, kinds: impl IntoIterator<Item = EntityKind>) -> Self {
+        self.kinds = kinds.into_iter().collect();
+        self
+    }
+
+    /// Sets the maximum number of results.
+    pub fn with_limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Returns true if hits on `kind` should be returned.
+    pub(crate) fn includes(&self, kind: EntityKind) -> bool {
+        self.kinds.is_empty() || self.kinds.contains(&kind)
+    }
+
+    /// Returns the distinct words of the query.
+    pub(crate) fn terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        for term in tokenize(&self.text) {
+            if !terms.contains(&term) {
+                terms.push(term);
+            }
+        }
+        terms
+    }
+}
+
+/// A field that matched a search query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchHit {
+    /// Kind of the matching entity.
+    pub kind: EntityKind,
+    /// ID of the matching entity.
+    pub id: Uuid,
+    /// Field that matched.
+    pub field: SearchField,
+    /// Relevance of the hit; higher is better. Scores are only comparable
+    /// within one result set.
+    pub score: f64,
+    /// Excerpt of the field around the match, with matched words wrapped in
+    /// [`SNIPPET_MATCH_START`] and [`SNIPPET_MATCH_END`].
+    pub snippet: String,
+}
+
+/// Splits `text` into lowercase alphanumeric words.
+pub(crate) fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    words(text).map(|(start, end)| text[start..end].to_lowercase())
+}
+
+/// Returns the byte ranges of the alphanumeric words in `text`.
+fn words(text: &str) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut chars = text.char_indices().peekable();
+    std::iter::from_fn(move || {
+        let (start, _) = chars.find(|(_, c)| c.is_alphanumeric())?;
+        let mut end = text.len();
+        while let Some(&(index, c)) = chars.peek() {
+            if !c.is_alphanumeric() {
+                end = index;
+                break;
+            }
+            chars.next();
+        }
+        Some((start, end))
+    })
+}
+
+/// Builds a snippet of `text` around the first word in `terms`.
+pub(crate) fn snippet(text: &str, terms: &[String]) -> String {
+    let words: Vec<(usize, usize)> = words(text).collect();
+    let is_match =
+        |&(start, end): &(usize, usize)| terms.contains(&text[start..end].to_lowercase());
+    let first = words.iter().position(is_match).unwrap_or(0);
+    let from = first.saturating_sub(SNIPPET_LEADING_WORDS);
+    let to = (from + SNIPPET_WORDS).min(words.len());
+
+    let mut snippet = String::new();
+    if from > 0 {
+        snippet.push_str(SNIPPET_ELLIPSIS);
+    }
+    for (i, word) in words[from..to].iter().enumerate() {
+        if i > 0 {
+            snippet.push_str(&text[words[from + i - 1].1..word.0]);
+        }
+        if is_match(word) {
+            snippet.push_str(SNIPPET_MATCH_START);
+            snippet.push_str(&text[word.0..word.1]);
+            snippet.push_str(SNIPPET_MATCH_END);
+        } else {
+            snippet.push_str(&text[word.0..word.1]);
+        }
+    }
+    if to < words.len() {
+        snippet.push_str(SNIPPET_ELLIPSIS);
+    }
+    snippet
+}
+
+/// Searchable fields of `value`, or nothing for kinds that are not indexed.
+fn documents(value: &EntityValue) -> Vec<(SearchField, &str)> {
+    match value {
+        EntityValue::UnitTask(task) => {
+            let mut fields = vec![(SearchField::Prompt, task.prompt.as_str())];
+            if let Some(title) = &task.title {
+                fields.push((SearchField::Title, title.as_str()));
+            }
+            fields
+        }
+        EntityValue::CompositeTask(task) => vec![(SearchField::Prompt, task.prompt.as_str())],
+        EntityValue::AgentSession(session) => session
+            .output_log
+            .iter()
+            .map(|log| (SearchField::OutputLog, log.as_str()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Key of an indexed field, with the chunk's sequence number for
+/// [`SearchField::SessionLog`] and 0 for other fields.
+type DocumentKey = (EntityKind, Uuid, SearchField, u64);
+
+/// Indexed text of one field.
+#[derive(Debug, Clone)]
+struct Document {
+    text: String,
+    term_counts: HashMap<String, u32>,
+    length: u32,
+}
+
+/// Inverted index used by the memory store, ranked with BM25.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SearchIndex {
+    documents: HashMap<DocumentKey, Document>,
+    postings: HashMap<String, HashSet<DocumentKey>>,
+    total_length: u64,
+}
+
+impl SearchIndex {
+    /// Builds an index over `values` and the session log `chunks`.
+    pub(crate) fn build<'a>(
+        values: impl IntoIterator<Item = EntityValue>,
+        chunks: impl IntoIterator<Item = &'a LogChunk>,
+    ) -> Self {
+        let mut index = Self::default();
+        for value in values {
+            index.insert(&value);
+        }
+        for chunk in chunks {
+            index.append_log(chunk);
+        }
+        index
+    }
+
+    /// Updates the index for a change to an entity.
+    pub(crate) fn apply(&mut self, event: &ChangeEvent) {
+        match &event.value {
+            Some(value) => self.insert(value),
+            None => {
+                for field in SearchField::ALL {
+                    self.remove(event.kind, event.id, field);
+                }
+            }
+        }
+    }
+
+    /// Indexes a chunk appended to a session's log.
+    pub(crate) fn append_log(&mut self, chunk: &LogChunk) {
+        let key = (
+            EntityKind::AgentSession,
+            chunk.session_id,
+            SearchField::SessionLog,
+            chunk.sequence,
+        );
+        self.add(key, &chunk.content);
+    }
+
+    /// Indexes the searchable fields of `value`, replacing any previous
+    /// version. The entity's log chunks are kept.
+    fn insert(&mut self, value: &EntityValue) {
+        for field in [
+            SearchField::Title,
+            SearchField::Prompt,
+            SearchField::OutputLog,
+        ] {
+            self.remove(value.kind(), value.id(), field);
+        }
+        for (field, text) in documents(value) {
+            self.add((value.kind(), value.id(), field, 0), text);
+        }
+    }
+
+    fn add(&mut self, key: DocumentKey, text: &str) {
+        let mut term_counts = HashMap::new();
+        let mut length = 0;
+        for term in tokenize(text) {
+            *term_counts.entry(term).or_insert(0) += 1;
+            length += 1;
+        }
+        for term in term_counts.keys() {
+            self.postings.entry(term.clone()).or_default().insert(key);
+        }
+        self.total_length += u64::from(length);
+        self.documents.insert(
+            key,
+            Document {
+                text: text.to_string(),
+                term_counts,
+                length,
+            },
+        );
+    }
+
+    /// Removes a field of an entity, with every chunk of a session log.
+    fn remove(&mut self, kind: EntityKind, id: Uuid, field: SearchField) {
+        // Chunks are numbered from 0 without gaps.
+        for part in 0.. {
+            let key = (kind, id, field, part);
+            let Some(document) = self.documents.remove(&key) else {
+                break;
+            };
+            self.total_length -= u64::from(document.length);
+            for term in document.term_counts.keys() {
+                if let Some(keys) = self.postings.get_mut(term) {
+                    keys.remove(&key);
+                    if keys.is_empty() {
+                        self.postings.remove(term);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the fields matching `query`, best first.
+    pub(crate) fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let terms = query.terms();
+        let Some(candidates) = terms
+            .iter()
+            .map(|term| self.postings.get(term))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return Vec::new();
+        };
+        let Some(smallest) = candidates.iter().min_by_key(|keys| keys.len()) else {
+            return Vec::new();
+        };
+
+        let count = self.documents.len() as f64;
+        let average_length = self.total_length as f64 / count;
+        let mut hits: Vec<SearchHit> = smallest
+            .iter()
+            .filter(|key| query.includes(key.0) && candidates.iter().all(|keys| keys.contains(key)))
+            .map(|key @ &(kind, id, field, _)| {
+                let document = &self.documents[key];
+                let score = terms
+                    .iter()
+                    .zip(&candidates)
+                    .map(|(term, keys)| {
+                        let frequency = f64::from(document.term_counts[term]);
+                        let matching = keys.len() as f64;
+                        let idf = ((count - matching + 0.5) / (matching + 0.5)).ln_1p();
+                        let norm =
+                            1.0 - BM25_B + BM25_B * f64::from(document.length) / average_length;
+                        idf * frequency * (BM25_K1 + 1.0) / (frequency + BM25_K1 * norm)
+                    })
+                    .sum();
+                SearchHit {
+                    kind,
+                    id,
+                    field,
+                    score,
+                    snippet: snippet(&document.text, &terms),
+                }
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then(a.id.cmp(&b.id)));
+        // Keep only the best chunk of each session log.
+        let mut seen = HashSet::new();
+        hits.retain(|hit| seen.insert((hit.kind, hit.id, hit.field)));
+        if let Some(limit) = query.limit {
+            hits.truncate(limit as usize);
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use entities::{AgentSession, AiAgentType, UnitTask};
+
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let terms: Vec<String> = tokenize("Fix the billing-module, ASAP!").collect();
+        assert_eq!(terms, ["fix", "the", "billing", "module", "asap"]);
+    }
+
+    #[test]
+    fn test_snippet_marks_matches() {
+        let terms = vec!["billing".to_string()];
+        assert_eq!(
+            snippet("Touched the billing module.", &terms),
+            "Touched the **billing** module"
+        );
+
+        let text = (0..30)
+            .map(|i| format!("w{i}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let terms = vec!["w10".to_string()];
+        assert_eq!(
+            snippet(&text, &terms),
+            "...w6 w7 w8 w9 **w10** w11 w12 w13 w14 w15 w16 w17 w18 w19 w20 w21..."
+        );
+    }
+
+    #[test]
+    fn test_index_ranks_and_updates() {
+        let mut task = UnitTask::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "Refactor the billing module",
+        );
+        let mut session = AgentSession::new(Uuid::new_v4(), AiAgentType::ClaudeCode);
+        session.output_log = Some("Edited billing/invoice.rs and billing/tax.rs".to_string());
+        let chunks: Vec<LogChunk> = ["Running the billing tests", "billing passed"]
+            .into_iter()
+            .enumerate()
+            .map(|(sequence, content)| LogChunk {
+                session_id: session.id,
+                sequence: sequence as u64,
+                offset: 0,
+                content: content.to_string(),
+                created_at: Utc::now(),
+            })
+            .collect();
+        let mut index = SearchIndex::build(
+            [
+                EntityValue::UnitTask(task.clone()),
+                EntityValue::AgentSession(session.clone()),
+            ],
+            &chunks[..1],
+        );
+        index.append_log(&chunks[1]);
+
+        let hits = index.search(&SearchQuery::new("Billing"));
+        assert_eq!(hits.len(), 3, "one hit for the session's chunks");
+        assert_eq!(hits[0].id, session.id);
+        assert!(hits[0].score > hits[2].score);
+        assert!(index.search(&SearchQuery::new("billing tax")).len() == 1);
+        let hits = index.search(&SearchQuery::new("running passed"));
+        assert!(hits.is_empty(), "chunks are matched one at a time");
+        let hits = index.search(&SearchQuery::new("billing").with_kinds([EntityKind::UnitTask]));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Prompt);
+
+        index.apply(&ChangeEvent::updated(EntityValue::AgentSession(
+            session.clone(),
+        )));
+        let hits = index.search(&SearchQuery::new("passed"));
+        assert_eq!(
+            hits[0].field,
+            SearchField::SessionLog,
+            "updates keep chunks"
+        );
+
+        task.prompt = "Refactor payments".to_string();
+        index.apply(&ChangeEvent::updated(EntityValue::UnitTask(task.clone())));
+        index.apply(&ChangeEvent::deleted(EntityKind::AgentSession, session.id));
+        assert!(index.search(&SearchQuery::new("billing")).is_empty());
+        assert_eq!(index.search(&SearchQuery::new("payments"))[0].id, task.id);
+    }
+}
@@ -0,0 +1,182 @@
+//! Append-only, chunked storage for agent session output logs.
+//!
+//! Agents write their output as a sequence of [`LogChunk`]s instead of
+//! rewriting [`AgentSession::output_log`] in full. Chunks are numbered from
+//! zero in append order and record the byte offset at which they start, so
+//! a reader can page through a log by sequence or by byte offset, or tail
+//! its newest chunks. Chunks are owned by their session and deleted with it.
+//!
+//! [`AgentSession::output_log`]: entities::AgentSession::output_log
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A piece of an agent session's output log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogChunk {
+    /// Session the chunk belongs to.
+    pub session_id: Uuid,
+    /// Position of the chunk in the log, starting at zero.
+    pub sequence: u64,
+    /// Byte offset of the chunk's first byte in the whole log.
+    pub offset: u64,
+    /// Text of the chunk.
+    pub content: String,
+    /// When the chunk was appended.
+    pub created_at: DateTime<Utc>,
+}
+
+impl LogChunk {
+    /// Returns the byte offset just past the chunk's last byte.
+    pub fn end(&self) -> u64 {
+        self.offset + self.content.len() as u64
+    }
+}
+
+/// Chunks of a session log to read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRange {
+    /// Chunks whose sequence is in `start..end`, or at least `start` when
+    /// `end` is `None`.
+    Sequence { start: u64, end: Option<u64> },
+    /// Chunks that overlap bytes `start..end`, or that end after `start`
+    /// when `end` is `None`. Chunks are returned whole, so the text read may
+    /// begin before `start` and run past `end`.
+    Bytes { start: u64, end: Option<u64> },
+    /// The newest `count` chunks.
+    Tail(u64),
+}
+
+impl LogRange {
+    /// Selects the whole log.
+    pub fn all() -> Self {
+        Self::Sequence {
+            start: 0,
+            end: None,
+        }
+    }
+}
+
+impl Default for LogRange {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// Size of a session log.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogSize {
+    /// Number of chunks.
+    pub chunks: u64,
+    /// Total length of the chunks in bytes.
+    pub bytes: u64,
+}
+
+impl LogSize {
+    /// Returns the size of a log whose newest chunk is `last`.
+    pub(crate) fn ending_with(last: Option<&LogChunk>) -> Self {
+        match last {
+            Some(chunk) => Self {
+                chunks: chunk.sequence + 1,
+                bytes: chunk.end(),
+            },
+            None => Self::default(),
+        }
+    }
+}
+
+/// Returns the chunks of a log, in sequence order, that fall in `range`.
+pub(crate) fn select(chunks: &[LogChunk], range: LogRange) -> Vec<LogChunk> {
+    let len = chunks.len() as u64;
+    match range {
+        LogRange::Sequence { start, end } => {
+            let end = end.map_or(len, |end| end.min(len));
+            chunks[start.min(end) as usize..end as usize].to_vec()
+        }
+        LogRange::Bytes { start, end } => chunks
+            .iter()
+            .filter(|chunk| chunk.end() > start && end.is_none_or(|end| chunk.offset < end))
+            .cloned()
+            .collect(),
+        LogRange::Tail(count) => chunks[(len - count.min(len)) as usize..].to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(contents: &[&str]) -> Vec<LogChunk> {
+        let session_id = Uuid::new_v4();
+        let mut offset = 0;
+        contents
+            .iter()
+            .enumerate()
+            .map(|(sequence, content)| {
+                let chunk = LogChunk {
+                    session_id,
+                    sequence: sequence as u64,
+                    offset,
+                    content: content.to_string(),
+                    created_at: Utc::now(),
+                };
+                offset = chunk.end();
+                chunk
+            })
+            .collect()
+    }
+
+    fn contents(chunks: &[LogChunk]) -> Vec<&str> {
+        chunks.iter().map(|chunk| chunk.content.as_str()).collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let chunks = log(&["abc", "de", "f", "ghi"]);
+
+        assert_eq!(contents(&select(&chunks, LogRange::all())).len(), 4);
+        let range = LogRange::Sequence {
+            start: 1,
+            end: Some(3),
+        };
+        assert_eq!(contents(&select(&chunks, range)), ["de", "f"]);
+        let range = LogRange::Sequence {
+            start: 9,
+            end: None,
+        };
+        assert!(select(&chunks, range).is_empty());
+
+        let range = LogRange::Bytes {
+            start: 2,
+            end: Some(6),
+        };
+        assert_eq!(contents(&select(&chunks, range)), ["abc", "de", "f"]);
+        let range = LogRange::Bytes {
+            start: 3,
+            end: Some(5),
+        };
+        assert_eq!(contents(&select(&chunks, range)), ["de"]);
+        let range = LogRange::Bytes {
+            start: 9,
+            end: None,
+        };
+        assert!(select(&chunks, range).is_empty());
+
+        assert_eq!(contents(&select(&chunks, LogRange::Tail(2))), ["f", "ghi"]);
+        assert_eq!(select(&chunks, LogRange::Tail(10)).len(), 4);
+    }
+
+    #[test]
+    fn test_size() {
+        let chunks = log(&["abc", "dé"]);
+        assert_eq!(
+            LogSize::ending_with(chunks.last()),
+            LogSize {
+                chunks: 2,
+                bytes: 6
+            }
+        );
+        assert_eq!(LogSize::ending_with(None), LogSize::default());
+    }
+}
@@ -0,0 +1,536 @@
+//! Sort orders and keyset cursors for list queries.
+//!
+//! Every store orders list results the same way: by the requested sort key,
+//! with ties broken by entity ID in the same direction. That order is total,
+//! so a [`Cursor`] taken from the last row of a page identifies exactly where
+//! the next page starts, even while rows are inserted or deleted.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use entities::{
+    AuditEvent, CompositeTask, Repository, TodoItem, TtyInputRequest, UnitTask, Worker, Workspace,
+};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{QueueEntry, TaskStoreError, TaskStoreResult};
+
+/// Field a list is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Creation time.
+    #[default]
+    CreatedAt,
+    /// Last update time.
+    UpdatedAt,
+    /// Title, or name for workspaces and repositories. A missing title sorts
+    /// as an empty string.
+    Title,
+    /// Status, compared by its snake_case name.
+    Status,
+}
+
+/// Direction of a sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    /// Smallest value first.
+    #[default]
+    Ascending,
+    /// Largest value first.
+    Descending,
+}
+
+/// Sort order of a list query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct SortOrder {
+    /// Field to sort by.
+    pub key: SortKey,
+    /// Direction to sort in.
+    pub direction: SortDirection,
+}
+
+impl SortOrder {
+    /// Creates an ascending sort order on `key`.
+    pub fn new(key: SortKey) -> Self {
+        Self {
+            key,
+            direction: SortDirection::Ascending,
+        }
+    }
+
+    /// Reverses the sort to descending.
+    pub fn descending(mut self) -> Self {
+        self.direction = SortDirection::Descending;
+        self
+    }
+
+    /// Compares two rows in this order.
+    pub(crate) fn compare<T: Sortable>(&self, a: &T, b: &T) -> Ordering {
+        let ordering = a
+            .sort_value(self.key)
+            .cmp(&b.sort_value(self.key))
+            .then_with(|| a.sort_id().cmp(&b.sort_id()));
+        match self.direction {
+            SortDirection::Ascending => ordering,
+            SortDirection::Descending => ordering.reverse(),
+        }
+    }
+
+    /// Returns true if `row` comes after `cursor` in this order.
+    pub(crate) fn is_after<T: Sortable>(&self, row: &T, cursor: &Cursor) -> bool {
+        let ordering = row
+            .sort_value(self.key)
+            .cmp(&cursor.value)
+            .then_with(|| row.sort_id().cmp(&cursor.id));
+        match self.direction {
+            SortDirection::Ascending => ordering == Ordering::Greater,
+            SortDirection::Descending => ordering == Ordering::Less,
+        }
+    }
+}
+
+/// Value of a sort key for one row.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SortValue {
+    /// A timestamp key.
+    Timestamp(DateTime<Utc>),
+    /// A text key.
+    Text(String),
+}
+
+/// An entity whose lists can be sorted and paged with cursors.
+pub trait Sortable {
+    /// Entity type name used in errors.
+    const ENTITY_TYPE: &'static str;
+
+    /// Returns true if lists of this entity can be sorted by `key`.
+    fn supports(key: SortKey) -> bool;
+
+    /// Returns the ID that breaks ties between equal sort values.
+    fn sort_id(&self) -> Uuid;
+
+    /// Returns the value of `key` for this row.
+    ///
+    /// Only called with keys for which [`supports`](Sortable::supports)
+    /// returns true.
+    fn sort_value(&self, key: SortKey) -> SortValue;
+}
+
+/// Returns the snake_case name of a status.
+fn status_text<S: Serialize>(status: &S) -> SortValue {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(name)) => SortValue::Text(name),
+        _ => SortValue::Text(String::new()),
+    }
+}
+
+impl Sortable for Workspace {
+    const ENTITY_TYPE: &'static str = "Workspace";
+
+    fn supports(key: SortKey) -> bool {
+        key != SortKey::Status
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt | SortKey::Status => SortValue::Timestamp(self.created_at),
+            SortKey::UpdatedAt => SortValue::Timestamp(self.updated_at),
+            SortKey::Title => SortValue::Text(self.name.clone()),
+        }
+    }
+}
+
+impl Sortable for Repository {
+    const ENTITY_TYPE: &'static str = "Repository";
+
+    fn supports(key: SortKey) -> bool {
+        key != SortKey::Status
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt | SortKey::Status => SortValue::Timestamp(self.created_at),
+            SortKey::UpdatedAt => SortValue::Timestamp(self.updated_at),
+            SortKey::Title => SortValue::Text(self.name.clone()),
+        }
+    }
+}
+
+impl Sortable for UnitTask {
+    const ENTITY_TYPE: &'static str = "UnitTask";
+
+    fn supports(_key: SortKey) -> bool {
+        true
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt => SortValue::Timestamp(self.created_at),
+            SortKey::UpdatedAt => SortValue::Timestamp(self.updated_at),
+            SortKey::Title => SortValue::Text(self.title.clone().unwrap_or_default()),
+            SortKey::Status => status_text(&self.status),
+        }
+    }
+}
+
+impl Sortable for CompositeTask {
+    const ENTITY_TYPE: &'static str = "CompositeTask";
+
+    fn supports(_key: SortKey) -> bool {
+        true
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt => SortValue::Timestamp(self.created_at),
+            SortKey::UpdatedAt => SortValue::Timestamp(self.updated_at),
+            SortKey::Title => SortValue::Text(self.title.clone().unwrap_or_default()),
+            SortKey::Status => status_text(&self.status),
+        }
+    }
+}
+
+impl Sortable for TodoItem {
+    const ENTITY_TYPE: &'static str = "TodoItem";
+
+    fn supports(key: SortKey) -> bool {
+        key != SortKey::Title
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt | SortKey::Title => SortValue::Timestamp(self.created_at),
+            SortKey::UpdatedAt => SortValue::Timestamp(self.updated_at),
+            SortKey::Status => status_text(&self.status),
+        }
+    }
+}
+
+impl Sortable for TtyInputRequest {
+    const ENTITY_TYPE: &'static str = "TtyInputRequest";
+
+    fn supports(key: SortKey) -> bool {
+        matches!(key, SortKey::CreatedAt | SortKey::Status)
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::Status => status_text(&self.status),
+            _ => SortValue::Timestamp(self.created_at),
+        }
+    }
+}
+
+impl Sortable for Worker {
+    const ENTITY_TYPE: &'static str = "Worker";
+
+    fn supports(key: SortKey) -> bool {
+        key != SortKey::UpdatedAt
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt | SortKey::UpdatedAt => SortValue::Timestamp(self.created_at),
+            SortKey::Title => SortValue::Text(self.name.clone()),
+            SortKey::Status => status_text(&self.status),
+        }
+    }
+}
+
+impl Sortable for AuditEvent {
+    const ENTITY_TYPE: &'static str = "AuditEvent";
+
+    fn supports(key: SortKey) -> bool {
+        key == SortKey::CreatedAt
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, _key: SortKey) -> SortValue {
+        SortValue::Timestamp(self.created_at)
+    }
+}
+
+impl Sortable for QueueEntry {
+    const ENTITY_TYPE: &'static str = "QueueEntry";
+
+    fn supports(key: SortKey) -> bool {
+        key != SortKey::Title
+    }
+
+    fn sort_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn sort_value(&self, key: SortKey) -> SortValue {
+        match key {
+            SortKey::CreatedAt | SortKey::Title => SortValue::Timestamp(self.created_at),
+            SortKey::UpdatedAt => SortValue::Timestamp(self.updated_at),
+            SortKey::Status => status_text(&self.status),
+        }
+    }
+}
+
+/// Opaque keyset position in a sorted list.
+///
+/// Take a cursor from the last row of a page with [`Cursor::after`] and pass
+/// it back in the filter to fetch the rows that follow. Cursors round-trip
+/// through strings with [`Display`](fmt::Display) and [`FromStr`], so they
+/// can be handed to clients.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    entity_type: String,
+    key: SortKey,
+    value: SortValue,
+    id: Uuid,
+}
+
+/// Serialized form of a [`Cursor`].
+#[derive(Serialize, Deserialize)]
+struct CursorData {
+    entity_type: String,
+    key: SortKey,
+    value: String,
+    id: Uuid,
+}
+
+impl Cursor {
+    /// Creates a cursor positioned at `row` in a list sorted by `key`.
+    ///
+    /// Fails with [`TaskStoreError::InvalidQuery`] if the entity cannot be
+    /// sorted by `key`.
+    pub fn after<T: Sortable>(row: &T, key: SortKey) -> TaskStoreResult<Self> {
+        check_sort_key::<T>(key)?;
+        Ok(Self {
+            entity_type: T::ENTITY_TYPE.to_string(),
+            key,
+            value: row.sort_value(key),
+            id: row.sort_id(),
+        })
+    }
+
+    /// Returns the sort key this cursor was taken for.
+    pub fn key(&self) -> SortKey {
+        self.key
+    }
+
+    /// Returns the sort value of the row this cursor points at.
+    pub fn value(&self) -> &SortValue {
+        &self.value
+    }
+
+    /// Returns the ID of the row this cursor points at.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = match &self.value {
+            SortValue::Timestamp(at) => at.to_rfc3339_opts(SecondsFormat::Nanos, true),
+            SortValue::Text(text) => text.clone(),
+        };
+        let data = CursorData {
+            entity_type: self.entity_type.clone(),
+            key: self.key,
+            value,
+            id: self.id,
+        };
+        let json = serde_json::to_vec(&data).map_err(|_| fmt::Error)?;
+        for byte in json {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = TaskStoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || TaskStoreError::InvalidQuery(format!("Malformed cursor: {s}"));
+        if !s.len().is_multiple_of(2) || !s.is_ascii() {
+            return Err(invalid());
+        }
+        let bytes = (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        let data: CursorData = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+        let value = match data.key {
+            SortKey::CreatedAt | SortKey::UpdatedAt => SortValue::Timestamp(
+                DateTime::parse_from_rfc3339(&data.value)
+                    .map_err(|_| invalid())?
+                    .with_timezone(&Utc),
+            ),
+            SortKey::Title | SortKey::Status => SortValue::Text(data.value),
+        };
+        Ok(Self {
+            entity_type: data.entity_type,
+            key: data.key,
+            value,
+            id: data.id,
+        })
+    }
+}
+
+/// Fails with [`TaskStoreError::InvalidQuery`] if `T` cannot be sorted by
+/// `key`.
+fn check_sort_key<T: Sortable>(key: SortKey) -> TaskStoreResult<()> {
+    if T::supports(key) {
+        return Ok(());
+    }
+    Err(TaskStoreError::InvalidQuery(format!(
+        "{} lists cannot be sorted by {key:?}",
+        T::ENTITY_TYPE
+    )))
+}
+
+/// Validates the sort order and cursor of a list query on `T`.
+pub(crate) fn check_sort<T: Sortable>(
+    order: SortOrder,
+    after: Option<&Cursor>,
+) -> TaskStoreResult<()> {
+    check_sort_key::<T>(order.key)?;
+    let Some(cursor) = after else {
+        return Ok(());
+    };
+    if cursor.entity_type != T::ENTITY_TYPE {
+        return Err(TaskStoreError::InvalidQuery(format!(
+            "Cursor was taken from a {} list, not a {} list",
+            cursor.entity_type,
+            T::ENTITY_TYPE
+        )));
+    }
+    if cursor.key != order.key {
+        return Err(TaskStoreError::InvalidQuery(format!(
+            "Cursor was taken for sort key {:?}, not {:?}",
+            cursor.key, order.key
+        )));
+    }
+    Ok(())
+}
+
+/// Returns the cursor of the page after `page` of a list sorted by creation
+/// time, or `None` if it is the last page.
+pub(crate) fn next_page<T: Sortable>(
+    page: &[T],
+    page_size: u32,
+) -> TaskStoreResult<Option<Cursor>> {
+    match page.last() {
+        Some(last) if page.len() as u32 == page_size => {
+            Ok(Some(Cursor::after(last, SortKey::CreatedAt)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Fetches every page of a list sorted by creation time, `page_size` rows
+/// at a time.
+pub(crate) async fn all_pages<'a, T, F>(page_size: u32, mut fetch: F) -> TaskStoreResult<Vec<T>>
+where
+    T: Sortable,
+    F: FnMut(Option<Cursor>) -> BoxFuture<'a, TaskStoreResult<Vec<T>>>,
+{
+    let mut rows = Vec::new();
+    let mut after = None;
+    loop {
+        let page = fetch(after).await?;
+        after = next_page(&page, page_size)?;
+        rows.extend(page);
+        if after.is_none() {
+            return Ok(rows);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::UnitTaskStatus;
+
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let task = UnitTask::new(Uuid::new_v4(), Uuid::new_v4(), "Fix the bug").with_title("Bug");
+        for key in [
+            SortKey::CreatedAt,
+            SortKey::UpdatedAt,
+            SortKey::Title,
+            SortKey::Status,
+        ] {
+            let cursor = Cursor::after(&task, key).unwrap();
+            let parsed: Cursor = cursor.to_string().parse().unwrap();
+            assert_eq!(parsed, cursor);
+        }
+        assert!(matches!(
+            "not a cursor".parse::<Cursor>(),
+            Err(TaskStoreError::InvalidQuery(_))
+        ));
+    }
+
+    #[test]
+    fn test_order_breaks_ties_by_id() {
+        let mut first = UnitTask::new(Uuid::new_v4(), Uuid::new_v4(), "First");
+        let mut second = first.clone();
+        second.id = Uuid::new_v4();
+        if second.id < first.id {
+            std::mem::swap(&mut first, &mut second);
+        }
+        second.status = UnitTaskStatus::InReview;
+
+        let by_created = SortOrder::new(SortKey::CreatedAt);
+        assert_eq!(by_created.compare(&first, &second), Ordering::Less);
+        assert_eq!(
+            by_created.descending().compare(&first, &second),
+            Ordering::Greater
+        );
+        let cursor = Cursor::after(&first, SortKey::CreatedAt).unwrap();
+        assert!(by_created.is_after(&second, &cursor));
+        assert!(!by_created.is_after(&first, &cursor));
+
+        // "in_progress" sorts before "in_review".
+        let by_status = SortOrder::new(SortKey::Status);
+        assert_eq!(by_status.compare(&second, &first), Ordering::Greater);
+        assert!(matches!(
+            check_sort::<TtyInputRequest>(SortOrder::new(SortKey::Title), None),
+            Err(TaskStoreError::InvalidQuery(_))
+        ));
+    }
+}
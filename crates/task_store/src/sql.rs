@@ -0,0 +1,139 @@
+//! Helpers shared by the SQL-backed task stores.
+
+use std::{
+    ops::{Deref, DerefMut},
+    sync::Arc,
+};
+
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{Database, Pool, Transaction, error::ErrorKind, pool::PoolConnection};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use uuid::Uuid;
+
+use crate::{TaskStoreError, TaskStoreResult};
+
+/// Encodes a unit-only enum as its snake_case serde representation.
+pub(crate) fn encode_enum<T: Serialize>(value: &T) -> TaskStoreResult<String> {
+    match serde_json::to_value(value)? {
+        serde_json::Value::String(s) => Ok(s),
+        other => Err(TaskStoreError::Other(format!(
+            "Expected enum to serialize as a string, got {other}"
+        ))),
+    }
+}
+
+/// Decodes a unit-only enum from its snake_case serde representation.
+pub(crate) fn decode_enum<T: DeserializeOwned>(value: &str) -> TaskStoreResult<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(
+        value.to_string(),
+    ))?)
+}
+
+/// Maps a database error raised while writing an entity to a store error.
+///
+/// Unique and foreign key constraint failures are surfaced as
+/// [`TaskStoreError::AlreadyExists`] and
+/// [`TaskStoreError::ForeignKeyViolation`] respectively.
+pub(crate) fn map_write_error(
+    err: sqlx::Error,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreError {
+    if let sqlx::Error::Database(db_err) = &err {
+        match db_err.kind() {
+            ErrorKind::UniqueViolation => {
+                return TaskStoreError::already_exists(entity_type, id.to_string());
+            }
+            ErrorKind::ForeignKeyViolation => {
+                return TaskStoreError::ForeignKeyViolation(format!(
+                    "{entity_type} {id}: {}",
+                    db_err.message()
+                ));
+            }
+            _ => {}
+        }
+    }
+    TaskStoreError::Database(err)
+}
+
+/// An open transaction shared by the clones of a transaction handle.
+///
+/// The slot is emptied once the transaction is committed or rolled back.
+pub(crate) type SharedTransaction<DB> = Arc<Mutex<Option<Transaction<'static, DB>>>>;
+
+/// Wraps a freshly begun transaction for sharing.
+pub(crate) fn share_transaction<DB: Database>(
+    transaction: Transaction<'static, DB>,
+) -> SharedTransaction<DB> {
+    Arc::new(Mutex::new(Some(transaction)))
+}
+
+/// A connection checked out for a single store operation.
+///
+/// Stores that are not inside a transaction use a pooled connection, while
+/// transaction handles lock the connection owned by their transaction.
+pub(crate) enum StoreConnection<'a, DB: Database> {
+    Pooled(PoolConnection<DB>),
+    Transaction(MappedMutexGuard<'a, Transaction<'static, DB>>),
+}
+
+impl<DB: Database> Deref for StoreConnection<'_, DB> {
+    type Target = DB::Connection;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Pooled(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+impl<DB: Database> DerefMut for StoreConnection<'_, DB> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Self::Pooled(conn) => conn,
+            Self::Transaction(tx) => tx,
+        }
+    }
+}
+
+/// Checks out a connection from `transaction` if set, or from `pool`.
+pub(crate) async fn acquire<'a, DB: Database>(
+    pool: &Pool<DB>,
+    transaction: Option<&'a SharedTransaction<DB>>,
+) -> TaskStoreResult<StoreConnection<'a, DB>> {
+    match transaction {
+        Some(transaction) => MutexGuard::try_map(transaction.lock().await, Option::as_mut)
+            .map(StoreConnection::Transaction)
+            .map_err(|_| transaction_finished()),
+        None => Ok(StoreConnection::Pooled(pool.acquire().await?)),
+    }
+}
+
+/// Commits or rolls back a shared transaction.
+pub(crate) async fn finish_transaction<DB: Database>(
+    transaction: Option<&SharedTransaction<DB>>,
+    commit: bool,
+) -> TaskStoreResult<()> {
+    let transaction = transaction
+        .ok_or_else(|| TaskStoreError::Other("Store is not a transaction".to_string()))?
+        .lock()
+        .await
+        .take()
+        .ok_or_else(transaction_finished)?;
+    if commit {
+        transaction.commit().await?;
+    } else {
+        transaction.rollback().await?;
+    }
+    Ok(())
+}
+
+/// Error returned when beginning a transaction on a transaction handle.
+pub(crate) fn nested_transaction() -> TaskStoreError {
+    TaskStoreError::Other("Nested transactions are not supported".to_string())
+}
+
+fn transaction_finished() -> TaskStoreError {
+    TaskStoreError::Other("Transaction has already been committed or rolled back".to_string())
+}
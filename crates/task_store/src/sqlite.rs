@@ -0,0 +1,3800 @@
+//! SQLite task store implementation for single-user mode.
+
+use std::{collections::HashMap, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
+use entities::{
+    AgentSession, AgentTask, AuditActor, AuditEvent, CompositeTask, CompositeTaskNode,
+    PlanRevision, Repository, RepositoryGroup, StateMachine, TodoItem, TtyInputRequest, UnitTask,
+    User, Worker, WorkerStatus, Workspace,
+};
+use serde::{Serialize, de::DeserializeOwned};
+use sqlx::{
+    Connection, QueryBuilder, Row, Sqlite, SqliteConnection, SqlitePool, Transaction,
+    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions, SqliteRow},
+};
+use tracing::info;
+use uuid::{Uuid, fmt::Hyphenated};
+
+use crate::{
+    AuditFilter, ChangeEvent, ChangeFilter, ChangeStream, Cursor, EntityKind, EntityValue,
+    LogChunk, LogRange, LogSize, MigrationReport, QueueEntry, QueueFilter, QueueStatus,
+    RepositoryFilter, SNIPPET_MATCH_END, SNIPPET_MATCH_START, SearchField, SearchHit, SearchQuery,
+    SortDirection, SortKey, SortOrder, SortValue, TaskFilter, TaskStore, TaskStoreError,
+    TaskStoreResult, TaskStoreTransaction, TodoFilter, TtyInputFilter, WorkerFilter,
+    WorkspaceFilter,
+    changes::{CHANGE_POLL_BATCH, OutboxBatch, poll_outbox},
+    migrate::{SQLITE_MIGRATIONS, pending},
+    queue::LEASE_EXPIRED,
+    search::{SNIPPET_ELLIPSIS, SNIPPET_WORDS},
+    sort::check_sort,
+    sql::{
+        SharedTransaction, StoreConnection, acquire, decode_enum, encode_enum, finish_transaction,
+        map_write_error, nested_transaction, share_transaction,
+    },
+    transition::ensure_transition,
+};
+
+/// SQLite-backed task store used in single-user mode.
+///
+/// List-valued fields such as `AgentTask::base_remotes`,
+/// `CompositeTaskNode::depends_on_ids` and `TodoItem::data` are stored as
+/// JSON text. `AgentTask::agent_sessions` is not stored on the task row;
+/// sessions embedded in a created or updated task are written to the
+/// `agent_sessions` table and read back from it.
+#[derive(Debug, Clone)]
+pub struct SqliteTaskStore {
+    pool: SqlitePool,
+    /// Open transaction when this store is a transaction handle.
+    transaction: Option<SharedTransaction<Sqlite>>,
+}
+
+impl SqliteTaskStore {
+    /// Opens the SQLite database at `url`, creating the file if needed.
+    ///
+    /// `url` accepts anything understood by [`SqliteConnectOptions`], such as
+    /// `sqlite://path/to/delidev.db`.
+    pub async fn connect(url: &str) -> TaskStoreResult<Self> {
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .foreign_keys(true)
+            .journal_mode(SqliteJournalMode::Wal);
+        let pool = SqlitePoolOptions::new().connect_with(options).await?;
+        Self::from_pool(pool).await
+    }
+
+    /// Creates a store backed by a private in-memory database.
+    ///
+    /// The pool is limited to a single connection that is never recycled, as
+    /// every SQLite in-memory connection owns a separate database. While a
+    /// transaction is open, other operations on the store wait for it to
+    /// finish.
+    pub async fn in_memory() -> TaskStoreResult<Self> {
+        let options = SqliteConnectOptions::from_str("sqlite::memory:")?.foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
+        Self::from_pool(pool).await
+    }
+
+    /// Creates a store from an existing pool, applying pending migrations.
+    pub async fn from_pool(pool: SqlitePool) -> TaskStoreResult<Self> {
+        let store = Self {
+            pool,
+            transaction: None,
+        };
+        store.migrate().await?;
+        Ok(store)
+    }
+
+    /// Applies pending schema migrations in a single transaction.
+    ///
+    /// Fails with [`TaskStoreError::SchemaTooNew`] if the database was
+    /// migrated by a newer version of DeliDev.
+    pub async fn migrate(&self) -> TaskStoreResult<MigrationReport> {
+        // Taking the write lock up front lets a concurrent migration wait for
+        // this one rather than fail when it reads a stale version.
+        let mut tx = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        sqlx::raw_sql(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (version INTEGER PRIMARY KEY NOT NULL, \
+             description TEXT NOT NULL, applied_at TEXT NOT NULL)",
+        )
+        .execute(&mut *tx)
+        .await?;
+        let previous_version: i64 =
+            sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_migrations")
+                .fetch_one(&mut *tx)
+                .await?;
+        let previous_version = previous_version as u32;
+
+        let migrations = pending(SQLITE_MIGRATIONS, previous_version)?;
+        for migration in migrations {
+            info!(
+                "Applying SQLite migration {}: {}",
+                migration.version, migration.description
+            );
+            sqlx::raw_sql(migration.sql).execute(&mut *tx).await?;
+            sqlx::query(
+                "INSERT INTO schema_migrations (version, description, applied_at) VALUES (?, ?, ?)",
+            )
+            .bind(i64::from(migration.version))
+            .bind(migration.description)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(MigrationReport {
+            previous_version,
+            current_version: migrations.last().map_or(previous_version, |m| m.version),
+        })
+    }
+
+    /// Checks out the connection used by a single operation.
+    async fn acquire(&self) -> TaskStoreResult<StoreConnection<'_, Sqlite>> {
+        acquire(&self.pool, self.transaction.as_ref()).await
+    }
+
+    /// Deletes change feed outbox rows recorded before `before`.
+    ///
+    /// Subscribers only read rows recorded after they subscribed, so rows
+    /// older than the slowest subscriber's position can be pruned safely.
+    pub async fn purge_change_events(&self, before: DateTime<Utc>) -> TaskStoreResult<u64> {
+        let result = sqlx::query("DELETE FROM change_events WHERE occurred_at < ?")
+            .bind(before)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Returns the underlying connection pool.
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+}
+
+// =============================================================================
+// Row decoding
+// =============================================================================
+
+fn uuid_col(row: &SqliteRow, column: &str) -> TaskStoreResult<Uuid> {
+    Ok(row.try_get::<Hyphenated, _>(column)?.into_uuid())
+}
+
+fn opt_uuid_col(row: &SqliteRow, column: &str) -> TaskStoreResult<Option<Uuid>> {
+    Ok(row
+        .try_get::<Option<Hyphenated>, _>(column)?
+        .map(Hyphenated::into_uuid))
+}
+
+fn enum_col<T: DeserializeOwned>(row: &SqliteRow, column: &str) -> TaskStoreResult<T> {
+    decode_enum(&row.try_get::<String, _>(column)?)
+}
+
+fn opt_enum_col<T: DeserializeOwned>(row: &SqliteRow, column: &str) -> TaskStoreResult<Option<T>> {
+    row.try_get::<Option<String>, _>(column)?
+        .map(|value| decode_enum(&value))
+        .transpose()
+}
+
+fn json_col<T: DeserializeOwned>(row: &SqliteRow, column: &str) -> TaskStoreResult<T> {
+    Ok(serde_json::from_str(&row.try_get::<String, _>(column)?)?)
+}
+
+fn opt_json_col<T: DeserializeOwned>(row: &SqliteRow, column: &str) -> TaskStoreResult<Option<T>> {
+    row.try_get::<Option<String>, _>(column)?
+        .map(|value| serde_json::from_str(&value).map_err(TaskStoreError::from))
+        .transpose()
+}
+
+fn user_from_row(row: &SqliteRow) -> TaskStoreResult<User> {
+    Ok(User {
+        id: uuid_col(row, "id")?,
+        email: row.try_get("email")?,
+        name: row.try_get("name")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn workspace_from_row(row: &SqliteRow) -> TaskStoreResult<Workspace> {
+    Ok(Workspace {
+        id: uuid_col(row, "id")?,
+        name: row.try_get("name")?,
+        description: row.try_get("description")?,
+        user_id: opt_uuid_col(row, "user_id")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn repository_from_row(row: &SqliteRow) -> TaskStoreResult<Repository> {
+    Ok(Repository {
+        id: uuid_col(row, "id")?,
+        workspace_id: uuid_col(row, "workspace_id")?,
+        name: row.try_get("name")?,
+        remote_url: row.try_get("remote_url")?,
+        default_branch: row.try_get("default_branch")?,
+        vcs_type: enum_col(row, "vcs_type")?,
+        vcs_provider_type: enum_col(row, "vcs_provider_type")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn repository_group_from_row(row: &SqliteRow) -> TaskStoreResult<RepositoryGroup> {
+    Ok(RepositoryGroup {
+        id: uuid_col(row, "id")?,
+        workspace_id: uuid_col(row, "workspace_id")?,
+        name: row.try_get("name")?,
+        repository_ids: json_col(row, "repository_ids")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn agent_session_from_row(row: &SqliteRow) -> TaskStoreResult<AgentSession> {
+    Ok(AgentSession {
+        id: uuid_col(row, "id")?,
+        agent_task_id: uuid_col(row, "agent_task_id")?,
+        ai_agent_type: enum_col(row, "ai_agent_type")?,
+        ai_agent_model: row.try_get("ai_agent_model")?,
+        started_at: row.try_get("started_at")?,
+        completed_at: row.try_get("completed_at")?,
+        output_log: row.try_get("output_log")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn agent_task_from_row(
+    row: &SqliteRow,
+    agent_sessions: Vec<AgentSession>,
+) -> TaskStoreResult<AgentTask> {
+    Ok(AgentTask {
+        id: uuid_col(row, "id")?,
+        base_remotes: json_col(row, "base_remotes")?,
+        agent_sessions,
+        ai_agent_type: opt_enum_col(row, "ai_agent_type")?,
+        ai_agent_model: row.try_get("ai_agent_model")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn unit_task_from_row(row: &SqliteRow) -> TaskStoreResult<UnitTask> {
+    Ok(UnitTask {
+        id: uuid_col(row, "id")?,
+        repository_group_id: uuid_col(row, "repository_group_id")?,
+        agent_task_id: uuid_col(row, "agent_task_id")?,
+        prompt: row.try_get("prompt")?,
+        title: row.try_get("title")?,
+        branch_name: row.try_get("branch_name")?,
+        linked_pr_url: row.try_get("linked_pr_url")?,
+        base_commit: row.try_get("base_commit")?,
+        end_commit: row.try_get("end_commit")?,
+        auto_fix_task_ids: json_col(row, "auto_fix_task_ids")?,
+        status: enum_col(row, "status")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn composite_task_from_row(row: &SqliteRow) -> TaskStoreResult<CompositeTask> {
+    Ok(CompositeTask {
+        id: uuid_col(row, "id")?,
+        repository_group_id: uuid_col(row, "repository_group_id")?,
+        planning_task_id: uuid_col(row, "planning_task_id")?,
+        prompt: row.try_get("prompt")?,
+        title: row.try_get("title")?,
+        node_ids: json_col(row, "node_ids")?,
+        status: enum_col(row, "status")?,
+        execution_agent_type: opt_enum_col(row, "execution_agent_type")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+        deleted_at: row.try_get("deleted_at")?,
+    })
+}
+
+fn composite_task_node_from_row(row: &SqliteRow) -> TaskStoreResult<CompositeTaskNode> {
+    Ok(CompositeTaskNode {
+        id: uuid_col(row, "id")?,
+        composite_task_id: uuid_col(row, "composite_task_id")?,
+        unit_task_id: uuid_col(row, "unit_task_id")?,
+        depends_on_ids: json_col(row, "depends_on_ids")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn todo_item_from_row(row: &SqliteRow) -> TaskStoreResult<TodoItem> {
+    Ok(TodoItem {
+        id: uuid_col(row, "id")?,
+        item_type: enum_col(row, "item_type")?,
+        source: enum_col(row, "source")?,
+        status: enum_col(row, "status")?,
+        repository_id: uuid_col(row, "repository_id")?,
+        data: json_col(row, "data")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn tty_input_request_from_row(row: &SqliteRow) -> TaskStoreResult<TtyInputRequest> {
+    Ok(TtyInputRequest {
+        id: uuid_col(row, "id")?,
+        task_id: uuid_col(row, "task_id")?,
+        session_id: uuid_col(row, "session_id")?,
+        prompt: row.try_get("prompt")?,
+        input_type: enum_col(row, "input_type")?,
+        options: opt_json_col(row, "options")?,
+        status: enum_col(row, "status")?,
+        response: row.try_get("response")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        responded_at: row.try_get("responded_at")?,
+    })
+}
+
+fn worker_from_row(row: &SqliteRow) -> TaskStoreResult<Worker> {
+    Ok(Worker {
+        id: uuid_col(row, "id")?,
+        name: row.try_get("name")?,
+        endpoint_url: row.try_get("endpoint_url")?,
+        capabilities: json_col(row, "capabilities")?,
+        status: enum_col(row, "status")?,
+        current_task_id: opt_uuid_col(row, "current_task_id")?,
+        last_heartbeat: row.try_get("last_heartbeat")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn audit_event_from_row(row: &SqliteRow) -> TaskStoreResult<AuditEvent> {
+    Ok(AuditEvent {
+        id: uuid_col(row, "id")?,
+        actor: AuditActor {
+            actor_type: enum_col(row, "actor_type")?,
+            id: opt_uuid_col(row, "actor_id")?,
+        },
+        action: row.try_get("action")?,
+        entity_type: row.try_get("entity_type")?,
+        entity_id: uuid_col(row, "entity_id")?,
+        before: opt_json_col(row, "before")?,
+        after: opt_json_col(row, "after")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+fn plan_revision_from_row(row: &SqliteRow) -> TaskStoreResult<PlanRevision> {
+    Ok(PlanRevision {
+        id: uuid_col(row, "id")?,
+        composite_task_id: uuid_col(row, "composite_task_id")?,
+        number: row.try_get::<i64, _>("number")? as u32,
+        yaml: row.try_get("yaml")?,
+        author_type: enum_col(row, "author_type")?,
+        author_id: uuid_col(row, "author_id")?,
+        approved_by: opt_uuid_col(row, "approved_by")?,
+        approved_at: row.try_get("approved_at")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+/// Returns the number of the latest plan revision of a composite task, or 0
+/// if it has none.
+async fn latest_plan_revision(
+    conn: &mut SqliteConnection,
+    composite_task_id: Uuid,
+) -> TaskStoreResult<u32> {
+    let latest: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(MAX(number), 0) FROM plan_revisions WHERE composite_task_id = ?",
+    )
+    .bind(composite_task_id.hyphenated())
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(latest as u32)
+}
+
+fn queue_entry_from_row(row: &SqliteRow) -> TaskStoreResult<QueueEntry> {
+    Ok(QueueEntry {
+        id: uuid_col(row, "id")?,
+        agent_task_id: uuid_col(row, "agent_task_id")?,
+        status: enum_col(row, "status")?,
+        attempts: row.try_get::<i64, _>("attempts")? as u32,
+        max_attempts: row.try_get::<i64, _>("max_attempts")? as u32,
+        worker_id: opt_uuid_col(row, "worker_id")?,
+        lease_expires_at: row.try_get("lease_expires_at")?,
+        last_error: row.try_get("last_error")?,
+        revision: row.try_get::<i64, _>("revision")? as u64,
+        created_at: row.try_get("created_at")?,
+        updated_at: row.try_get("updated_at")?,
+    })
+}
+
+fn log_chunk_from_row(row: &SqliteRow) -> TaskStoreResult<LogChunk> {
+    Ok(LogChunk {
+        session_id: uuid_col(row, "session_id")?,
+        sequence: row.try_get::<i64, _>("sequence")? as u64,
+        offset: row.try_get::<i64, _>("byte_offset")? as u64,
+        content: row.try_get("content")?,
+        created_at: row.try_get("created_at")?,
+    })
+}
+
+// =============================================================================
+// Shared statements
+// =============================================================================
+
+/// Pushes the keyset condition for `after`, the `ORDER BY` clause for `sort`
+/// and the pagination clauses. `title_column` is the expression
+/// [`SortKey::Title`] sorts by.
+fn push_sort(
+    qb: &mut QueryBuilder<'_, Sqlite>,
+    sort: SortOrder,
+    after: Option<&Cursor>,
+    title_column: &str,
+    limit: Option<u32>,
+    offset: Option<u32>,
+) {
+    let column = match sort.key {
+        SortKey::CreatedAt => "created_at",
+        SortKey::UpdatedAt => "updated_at",
+        SortKey::Title => title_column,
+        SortKey::Status => "status",
+    };
+    let (comparison, direction) = match sort.direction {
+        SortDirection::Ascending => (">", "ASC"),
+        SortDirection::Descending => ("<", "DESC"),
+    };
+    if let Some(cursor) = after {
+        qb.push(format!(" AND ({column}, id) {comparison} ("));
+        match cursor.value() {
+            SortValue::Timestamp(value) => qb.push_bind(*value),
+            SortValue::Text(value) => qb.push_bind(value.clone()),
+        };
+        qb.push(", ").push_bind(cursor.id().hyphenated()).push(")");
+    }
+    qb.push(format!(" ORDER BY {column} {direction}, id {direction}"));
+    push_pagination(qb, limit, offset);
+}
+
+/// Appends `LIMIT`/`OFFSET` clauses for the given pagination values.
+fn push_pagination(qb: &mut QueryBuilder<'_, Sqlite>, limit: Option<u32>, offset: Option<u32>) {
+    // SQLite requires a LIMIT clause before OFFSET; a negative limit means
+    // "no limit".
+    qb.push(" LIMIT ");
+    qb.push_bind(limit.map(i64::from).unwrap_or(-1));
+    qb.push(" OFFSET ");
+    qb.push_bind(i64::from(offset.unwrap_or(0)));
+}
+
+/// Appends the arguments of an FTS5 `snippet` call after the column index:
+/// the match markers, the ellipsis and the snippet length.
+fn push_snippet_args(qb: &mut QueryBuilder<'_, Sqlite>) {
+    qb.push_bind(SNIPPET_MATCH_START)
+        .push(", ")
+        .push_bind(SNIPPET_MATCH_END)
+        .push(", ")
+        .push_bind(SNIPPET_ELLIPSIS)
+        .push(", ")
+        .push_bind(SNIPPET_WORDS as i64);
+}
+
+async fn count(
+    conn: &mut SqliteConnection,
+    mut qb: QueryBuilder<'_, Sqlite>,
+) -> TaskStoreResult<u32> {
+    let total: i64 = qb.build_query_scalar().fetch_one(&mut *conn).await?;
+    Ok(total as u32)
+}
+
+async fn upsert_agent_session(
+    conn: &mut SqliteConnection,
+    session: &AgentSession,
+) -> TaskStoreResult<()> {
+    sqlx::query(
+        "INSERT INTO agent_sessions (id, agent_task_id, ai_agent_type, ai_agent_model, \
+         started_at, completed_at, output_log, created_at, revision) VALUES (?, ?, ?, ?, ?, ?, ?, \
+         ?, ?) ON CONFLICT(id) DO UPDATE SET agent_task_id = excluded.agent_task_id, \
+         ai_agent_type = excluded.ai_agent_type, ai_agent_model = excluded.ai_agent_model, \
+         started_at = excluded.started_at, completed_at = excluded.completed_at, output_log = \
+         excluded.output_log, revision = agent_sessions.revision + 1",
+    )
+    .bind(session.id.hyphenated())
+    .bind(session.agent_task_id.hyphenated())
+    .bind(encode_enum(&session.ai_agent_type)?)
+    .bind(&session.ai_agent_model)
+    .bind(session.started_at)
+    .bind(session.completed_at)
+    .bind(&session.output_log)
+    .bind(session.created_at)
+    .bind(session.revision as i64)
+    .execute(&mut *conn)
+    .await
+    .map_err(|e| map_write_error(e, "AgentSession", session.id))?;
+    Ok(())
+}
+
+async fn sessions_for_agent_task(
+    conn: &mut SqliteConnection,
+    agent_task_id: Uuid,
+) -> TaskStoreResult<Vec<AgentSession>> {
+    let rows =
+        sqlx::query("SELECT * FROM agent_sessions WHERE agent_task_id = ? ORDER BY created_at, id")
+            .bind(agent_task_id.hyphenated())
+            .fetch_all(&mut *conn)
+            .await?;
+    rows.iter().map(agent_session_from_row).collect()
+}
+
+/// Loads the sessions of the given agent tasks, grouped by task and oldest
+/// first.
+async fn sessions_for_agent_tasks(
+    conn: &mut SqliteConnection,
+    agent_task_ids: &[Uuid],
+) -> TaskStoreResult<HashMap<Uuid, Vec<AgentSession>>> {
+    let mut qb =
+        QueryBuilder::<Sqlite>::new("SELECT * FROM agent_sessions WHERE agent_task_id IN ");
+    push_id_list(&mut qb, agent_task_ids);
+    qb.push(" ORDER BY created_at, id");
+    let rows = qb.build().fetch_all(&mut *conn).await?;
+    let mut sessions: HashMap<Uuid, Vec<AgentSession>> = HashMap::new();
+    for row in &rows {
+        let session = agent_session_from_row(row)?;
+        sessions
+            .entry(session.agent_task_id)
+            .or_default()
+            .push(session);
+    }
+    Ok(sessions)
+}
+
+/// Pushes `ids` as a parenthesized list of bound values.
+fn push_id_list(qb: &mut QueryBuilder<'_, Sqlite>, ids: &[Uuid]) {
+    qb.push("(");
+    let mut separated = qb.separated(", ");
+    for id in ids {
+        separated.push_bind(id.hyphenated());
+    }
+    qb.push(")");
+}
+
+/// Returns the size of a session log from its newest chunk.
+async fn log_size(conn: &mut SqliteConnection, session_id: Uuid) -> TaskStoreResult<LogSize> {
+    let size: Option<(i64, i64)> = sqlx::query_as(
+        "SELECT sequence + 1, byte_offset + length(CAST(content AS BLOB)) FROM session_log_chunks \
+         WHERE session_id = ? ORDER BY sequence DESC LIMIT 1",
+    )
+    .bind(session_id.hyphenated())
+    .fetch_optional(&mut *conn)
+    .await?;
+    Ok(
+        size.map_or_else(LogSize::default, |(chunks, bytes)| LogSize {
+            chunks: chunks as u64,
+            bytes: bytes as u64,
+        }),
+    )
+}
+
+/// SQLite's `SQLITE_BUSY` and `SQLITE_BUSY_SNAPSHOT` result codes.
+const BUSY_CODES: [&str; 2] = ["5", "517"];
+
+/// Begins the transaction of a write that reads before it writes.
+///
+/// A deferred transaction takes the write lock at its first write, and fails
+/// that write with `SQLITE_BUSY_SNAPSHOT` without waiting out the busy
+/// timeout if another connection committed since its first read. `BEGIN
+/// IMMEDIATE` takes the lock up front instead. Inside a store transaction,
+/// which already holds the lock, this opens a savepoint.
+///
+/// If the database stays locked past the busy timeout this fails with
+/// [`TaskStoreError::Conflict`], reporting the current revision as 0 since it
+/// was never read.
+async fn begin_write<'c>(
+    conn: &'c mut SqliteConnection,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreResult<Transaction<'c, Sqlite>> {
+    let result = if conn.is_in_transaction() {
+        conn.begin().await
+    } else {
+        conn.begin_with("BEGIN IMMEDIATE").await
+    };
+    result.map_err(|err| match &err {
+        sqlx::Error::Database(db_err)
+            if db_err
+                .code()
+                .is_some_and(|code| BUSY_CODES.contains(&code.as_ref())) =>
+        {
+            TaskStoreError::conflict(entity_type, id.to_string(), 0)
+        }
+        _ => TaskStoreError::Database(err),
+    })
+}
+
+/// Deletes a row by ID, returning `NotFound` if nothing was deleted.
+async fn delete_by_id(
+    conn: &mut SqliteConnection,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreResult<()> {
+    let result = sqlx::query(&format!("DELETE FROM {table} WHERE id = ?"))
+        .bind(id.hyphenated())
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| map_write_error(e, entity_type, id))?;
+    if result.rows_affected() == 0 {
+        return Err(TaskStoreError::not_found(entity_type, id.to_string()));
+    }
+    Ok(())
+}
+
+/// Fetches a single row by ID from the given table.
+async fn fetch_by_id(
+    conn: &mut SqliteConnection,
+    table: &str,
+    id: Uuid,
+) -> TaskStoreResult<Option<SqliteRow>> {
+    Ok(sqlx::query(&format!("SELECT * FROM {table} WHERE id = ?"))
+        .bind(id.hyphenated())
+        .fetch_optional(&mut *conn)
+        .await?)
+}
+
+/// Moves a row into the trash, or out of it when `deleted_at` is `None`.
+///
+/// Returns the row and whether it changed; a change bumps the revision.
+async fn set_deleted_at(
+    conn: &mut SqliteConnection,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+    deleted_at: Option<DateTime<Utc>>,
+) -> TaskStoreResult<(SqliteRow, bool)> {
+    let updated = sqlx::query(&format!(
+        "UPDATE {table} SET revision = revision + 1, deleted_at = ? WHERE id = ? AND (deleted_at \
+         IS NULL) = ? RETURNING *"
+    ))
+    .bind(deleted_at)
+    .bind(id.hyphenated())
+    .bind(deleted_at.is_some())
+    .fetch_optional(&mut *conn)
+    .await?;
+    if let Some(row) = updated {
+        return Ok((row, true));
+    }
+    let row = fetch_by_id(conn, table, id)
+        .await?
+        .ok_or_else(|| TaskStoreError::not_found(entity_type, id.to_string()))?;
+    Ok((row, false))
+}
+
+/// Explains why a compare-and-swap update matched no row.
+///
+/// Fails with [`TaskStoreError::NotFound`] if the row is gone, or with
+/// [`TaskStoreError::Conflict`] if its revision moved on.
+async fn ensure_updated(
+    conn: &mut SqliteConnection,
+    rows_affected: u64,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+) -> TaskStoreResult<()> {
+    if rows_affected > 0 {
+        return Ok(());
+    }
+    let current: Option<i64> =
+        sqlx::query_scalar(&format!("SELECT revision FROM {table} WHERE id = ?"))
+            .bind(id.hyphenated())
+            .fetch_optional(&mut *conn)
+            .await?;
+    Err(match current {
+        Some(revision) => TaskStoreError::conflict(entity_type, id.to_string(), revision as u64),
+        None => TaskStoreError::not_found(entity_type, id.to_string()),
+    })
+}
+
+/// Fails with [`TaskStoreError::ForeignKeyViolation`] unless every ID in
+/// `ids` names a row of `table`.
+///
+/// ID lists are stored as JSON arrays, so SQLite cannot enforce them with
+/// foreign keys.
+async fn ensure_references(
+    conn: &mut SqliteConnection,
+    table: &str,
+    entity_type: &'static str,
+    ids: &[Uuid],
+) -> TaskStoreResult<()> {
+    for id in ids {
+        let exists: bool = sqlx::query_scalar(&format!(
+            "SELECT EXISTS (SELECT 1 FROM {table} WHERE id = ?)"
+        ))
+        .bind(id.hyphenated())
+        .fetch_one(&mut *conn)
+        .await?;
+        if !exists {
+            return Err(TaskStoreError::ForeignKeyViolation(format!(
+                "{entity_type} {id} does not exist"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Fails with [`TaskStoreError::ForeignKeyViolation`] if `id` appears in the
+/// JSON ID list `column` of any row of `table`.
+async fn ensure_unlisted(
+    conn: &mut SqliteConnection,
+    entity_type: &'static str,
+    id: Uuid,
+    table: &str,
+    column: &str,
+    referenced_by: &'static str,
+) -> TaskStoreResult<()> {
+    let referenced: bool = sqlx::query_scalar(&format!(
+        "SELECT EXISTS (SELECT 1 FROM {table}, json_each({table}.{column}) WHERE json_each.value \
+         = ?)"
+    ))
+    .bind(id.hyphenated())
+    .fetch_one(&mut *conn)
+    .await?;
+    if referenced {
+        return Err(TaskStoreError::ForeignKeyViolation(format!(
+            "{entity_type} {id} is still referenced by a {referenced_by}"
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that a row may move to status `next` before it is updated.
+///
+/// Fails with [`TaskStoreError::NotFound`] if the row is missing, with
+/// [`TaskStoreError::Conflict`] if its revision is not `revision`, and with
+/// [`TaskStoreError::InvalidStateTransition`] if its stored status cannot
+/// move to `next`.
+async fn ensure_status_transition<S>(
+    conn: &mut SqliteConnection,
+    table: &str,
+    entity_type: &'static str,
+    id: Uuid,
+    revision: u64,
+    next: S,
+) -> TaskStoreResult<()>
+where
+    S: StateMachine + Serialize + DeserializeOwned,
+{
+    let row: Option<(String, i64)> = sqlx::query_as(&format!(
+        "SELECT status, revision FROM {table} WHERE id = ?"
+    ))
+    .bind(id.hyphenated())
+    .fetch_optional(&mut *conn)
+    .await?;
+    let Some((status, current)) = row else {
+        return Err(TaskStoreError::not_found(entity_type, id.to_string()));
+    };
+    if current as u64 != revision {
+        return Err(TaskStoreError::conflict(
+            entity_type,
+            id.to_string(),
+            current as u64,
+        ));
+    }
+    ensure_transition(decode_enum::<S>(&status)?, next)
+}
+
+/// Takes every expired lease away from its worker, queueing the entry
+/// again if it has attempts left and failing it otherwise. Returns the
+/// number of entries changed.
+async fn requeue_expired(conn: &mut SqliteConnection, now: DateTime<Utc>) -> TaskStoreResult<u64> {
+    let result = sqlx::query(
+        "UPDATE work_queue SET status = CASE WHEN attempts < max_attempts THEN ? ELSE ? END, \
+         worker_id = NULL, lease_expires_at = NULL, last_error = ?, revision = revision + 1, \
+         updated_at = ? WHERE status = ? AND lease_expires_at <= ?",
+    )
+    .bind(encode_enum(&QueueStatus::Queued)?)
+    .bind(encode_enum(&QueueStatus::Failed)?)
+    .bind(LEASE_EXPIRED)
+    .bind(now)
+    .bind(encode_enum(&QueueStatus::Leased)?)
+    .bind(now)
+    .execute(&mut *conn)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Returns the error for a queue operation by a worker that did not hold an
+/// unexpired lease on entry `id`.
+async fn lease_not_held(conn: &mut SqliteConnection, id: Uuid, worker_id: Uuid) -> TaskStoreError {
+    match fetch_by_id(conn, "work_queue", id).await {
+        Ok(Some(_)) => TaskStoreError::LeaseNotHeld {
+            id: id.to_string(),
+            worker_id: worker_id.to_string(),
+        },
+        Ok(None) => TaskStoreError::not_found("QueueEntry", id.to_string()),
+        Err(err) => err,
+    }
+}
+
+async fn record_change(conn: &mut SqliteConnection, event: &ChangeEvent) -> TaskStoreResult<()> {
+    sqlx::query(
+        "INSERT INTO change_events (entity_kind, entity_id, operation, value, occurred_at) VALUES \
+         (?, ?, ?, ?, ?)",
+    )
+    .bind(encode_enum(&event.kind)?)
+    .bind(event.id.hyphenated())
+    .bind(encode_enum(&event.operation)?)
+    .bind(
+        event
+            .value
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()?,
+    )
+    .bind(event.occurred_at)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+fn change_event_from_row(row: &SqliteRow) -> TaskStoreResult<ChangeEvent> {
+    Ok(ChangeEvent {
+        kind: enum_col(row, "entity_kind")?,
+        id: uuid_col(row, "entity_id")?,
+        operation: enum_col(row, "operation")?,
+        value: opt_json_col(row, "value")?,
+        occurred_at: row.try_get("occurred_at")?,
+    })
+}
+
+async fn fetch_changes(pool: SqlitePool, after: i64) -> TaskStoreResult<OutboxBatch> {
+    let rows =
+        sqlx::query("SELECT * FROM change_events WHERE sequence > ? ORDER BY sequence LIMIT ?")
+            .bind(after)
+            .bind(CHANGE_POLL_BATCH)
+            .fetch_all(&pool)
+            .await?;
+    rows.iter()
+        .map(|row| Ok((row.try_get("sequence")?, change_event_from_row(row))))
+        .collect()
+}
+
+#[async_trait]
+impl TaskStore for SqliteTaskStore {
+    // =========================================================================
+    // Transactions
+    // =========================================================================
+
+    async fn begin(&self) -> TaskStoreResult<Box<dyn TaskStoreTransaction>> {
+        if self.transaction.is_some() {
+            return Err(nested_transaction());
+        }
+        // Operations in the transaction read before they write, so it takes
+        // the write lock up front; see `begin_write`.
+        let transaction = self.pool.begin_with("BEGIN IMMEDIATE").await?;
+        Ok(Box::new(Self {
+            pool: self.pool.clone(),
+            transaction: Some(share_transaction(transaction)),
+        }))
+    }
+
+    // =========================================================================
+    // Change feed
+    // =========================================================================
+
+    async fn subscribe(&self, filter: ChangeFilter) -> TaskStoreResult<ChangeStream> {
+        let after: i64 = sqlx::query_scalar("SELECT COALESCE(MAX(sequence), 0) FROM change_events")
+            .fetch_one(&self.pool)
+            .await?;
+        let pool = self.pool.clone();
+        Ok(poll_outbox(after, filter, move |after| {
+            fetch_changes(pool.clone(), after)
+        }))
+    }
+
+    // =========================================================================
+    // User operations
+    // =========================================================================
+
+    async fn create_user(&self, user: User) -> TaskStoreResult<User> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "User", user.id).await?;
+        sqlx::query(
+            "INSERT INTO users (id, email, name, created_at, updated_at, revision) VALUES (?, ?, \
+             ?, ?, ?, ?)",
+        )
+        .bind(user.id.hyphenated())
+        .bind(&user.email)
+        .bind(&user.name)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .bind(user.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "User", user.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::User(user.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn get_user(&self, id: Uuid) -> TaskStoreResult<Option<User>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "users", id)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    async fn get_user_by_email(&self, email: &str) -> TaskStoreResult<Option<User>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query("SELECT * FROM users WHERE email = ?")
+            .bind(email)
+            .fetch_optional(&mut *conn)
+            .await?
+            .as_ref()
+            .map(user_from_row)
+            .transpose()
+    }
+
+    async fn list_users(&self) -> TaskStoreResult<Vec<User>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM users ORDER BY created_at, id")
+            .fetch_all(&mut *conn)
+            .await?;
+        rows.iter().map(user_from_row).collect()
+    }
+
+    async fn update_user(&self, mut user: User) -> TaskStoreResult<User> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "User", user.id).await?;
+        let result = sqlx::query(
+            "UPDATE users SET revision = revision + 1, email = ?, name = ?, created_at = ?, \
+             updated_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(&user.email)
+        .bind(&user.name)
+        .bind(user.created_at)
+        .bind(user.updated_at)
+        .bind(user.id.hyphenated())
+        .bind(user.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "User", user.id))?;
+        ensure_updated(&mut tx, result.rows_affected(), "users", "User", user.id).await?;
+        user.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::User(user.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(user)
+    }
+
+    async fn delete_user(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "User", id).await?;
+        delete_by_id(&mut tx, "users", "User", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::User, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Workspace operations
+    // =========================================================================
+
+    async fn create_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Workspace", workspace.id).await?;
+        sqlx::query(
+            "INSERT INTO workspaces (id, name, description, user_id, created_at, updated_at, \
+             revision, deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(workspace.id.hyphenated())
+        .bind(&workspace.name)
+        .bind(&workspace.description)
+        .bind(workspace.user_id.map(Uuid::hyphenated))
+        .bind(workspace.created_at)
+        .bind(workspace.updated_at)
+        .bind(workspace.revision as i64)
+        .bind(workspace.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Workspace", workspace.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::Workspace(workspace.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    async fn get_workspace(&self, id: Uuid) -> TaskStoreResult<Option<Workspace>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "workspaces", id)
+            .await?
+            .as_ref()
+            .map(workspace_from_row)
+            .transpose()
+    }
+
+    async fn list_workspaces(
+        &self,
+        filter: WorkspaceFilter,
+    ) -> TaskStoreResult<(Vec<Workspace>, u32)> {
+        check_sort::<Workspace>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(user_id) = filter.user_id {
+                qb.push(" AND user_id = ").push_bind(user_id.hyphenated());
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM workspaces");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM workspaces");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "name",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let workspaces = rows
+            .iter()
+            .map(workspace_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((workspaces, total))
+    }
+
+    async fn update_workspace(&self, mut workspace: Workspace) -> TaskStoreResult<Workspace> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Workspace", workspace.id).await?;
+        let result = sqlx::query(
+            "UPDATE workspaces SET revision = revision + 1, name = ?, description = ?, user_id = \
+             ?, created_at = ?, updated_at = ?, deleted_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(&workspace.name)
+        .bind(&workspace.description)
+        .bind(workspace.user_id.map(Uuid::hyphenated))
+        .bind(workspace.created_at)
+        .bind(workspace.updated_at)
+        .bind(workspace.deleted_at)
+        .bind(workspace.id.hyphenated())
+        .bind(workspace.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Workspace", workspace.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "workspaces",
+            "Workspace",
+            workspace.id,
+        )
+        .await?;
+        workspace.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::Workspace(workspace.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    async fn delete_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Workspace", id).await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "workspaces", "Workspace", id, Some(Utc::now())).await?;
+        if changed {
+            let workspace = workspace_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Workspace(workspace)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_workspace(&self, id: Uuid) -> TaskStoreResult<Workspace> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Workspace", id).await?;
+        let (row, changed) = set_deleted_at(&mut tx, "workspaces", "Workspace", id, None).await?;
+        let workspace = workspace_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Workspace(workspace.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(workspace)
+    }
+
+    async fn purge_workspace(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Workspace", id).await?;
+        delete_by_id(&mut tx, "workspaces", "Workspace", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::Workspace, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Repository operations
+    // =========================================================================
+
+    async fn create_repository(&self, repository: Repository) -> TaskStoreResult<Repository> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Repository", repository.id).await?;
+        sqlx::query(
+            "INSERT INTO repositories (id, workspace_id, name, remote_url, default_branch, \
+             vcs_type, vcs_provider_type, created_at, updated_at, revision, deleted_at) VALUES \
+             (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(repository.id.hyphenated())
+        .bind(repository.workspace_id.hyphenated())
+        .bind(&repository.name)
+        .bind(&repository.remote_url)
+        .bind(&repository.default_branch)
+        .bind(encode_enum(&repository.vcs_type)?)
+        .bind(encode_enum(&repository.vcs_provider_type)?)
+        .bind(repository.created_at)
+        .bind(repository.updated_at)
+        .bind(repository.revision as i64)
+        .bind(repository.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Repository", repository.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::Repository(repository.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(repository)
+    }
+
+    async fn get_repository(&self, id: Uuid) -> TaskStoreResult<Option<Repository>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "repositories", id)
+            .await?
+            .as_ref()
+            .map(repository_from_row)
+            .transpose()
+    }
+
+    async fn list_repositories(
+        &self,
+        filter: RepositoryFilter,
+    ) -> TaskStoreResult<(Vec<Repository>, u32)> {
+        check_sort::<Repository>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(workspace_id) = filter.workspace_id {
+                qb.push(" AND workspace_id = ")
+                    .push_bind(workspace_id.hyphenated());
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM repositories");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM repositories");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "name",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let repositories = rows
+            .iter()
+            .map(repository_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((repositories, total))
+    }
+
+    async fn update_repository(&self, mut repository: Repository) -> TaskStoreResult<Repository> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Repository", repository.id).await?;
+        let result = sqlx::query(
+            "UPDATE repositories SET revision = revision + 1, workspace_id = ?, name = ?, \
+             remote_url = ?, default_branch = ?, vcs_type = ?, vcs_provider_type = ?, created_at \
+             = ?, updated_at = ?, deleted_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(repository.workspace_id.hyphenated())
+        .bind(&repository.name)
+        .bind(&repository.remote_url)
+        .bind(&repository.default_branch)
+        .bind(encode_enum(&repository.vcs_type)?)
+        .bind(encode_enum(&repository.vcs_provider_type)?)
+        .bind(repository.created_at)
+        .bind(repository.updated_at)
+        .bind(repository.deleted_at)
+        .bind(repository.id.hyphenated())
+        .bind(repository.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Repository", repository.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "repositories",
+            "Repository",
+            repository.id,
+        )
+        .await?;
+        repository.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::Repository(repository.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(repository)
+    }
+
+    async fn delete_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Repository", id).await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "repositories", "Repository", id, Some(Utc::now())).await?;
+        if changed {
+            let repository = repository_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Repository(repository)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_repository(&self, id: Uuid) -> TaskStoreResult<Repository> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Repository", id).await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "repositories", "Repository", id, None).await?;
+        let repository = repository_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Repository(repository.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(repository)
+    }
+
+    async fn purge_repository(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Repository", id).await?;
+        ensure_unlisted(
+            &mut tx,
+            "Repository",
+            id,
+            "repository_groups",
+            "repository_ids",
+            "RepositoryGroup",
+        )
+        .await?;
+        delete_by_id(&mut tx, "repositories", "Repository", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::Repository, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Repository Group operations
+    // =========================================================================
+
+    async fn create_repository_group(
+        &self,
+        group: RepositoryGroup,
+    ) -> TaskStoreResult<RepositoryGroup> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "RepositoryGroup", group.id).await?;
+        sqlx::query(
+            "INSERT INTO repository_groups (id, workspace_id, name, repository_ids, created_at, \
+             updated_at, revision) VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(group.id.hyphenated())
+        .bind(group.workspace_id.hyphenated())
+        .bind(&group.name)
+        .bind(serde_json::to_string(&group.repository_ids)?)
+        .bind(group.created_at)
+        .bind(group.updated_at)
+        .bind(group.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "RepositoryGroup", group.id))?;
+        ensure_references(&mut tx, "repositories", "Repository", &group.repository_ids).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::RepositoryGroup(group.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(group)
+    }
+
+    async fn get_repository_group(&self, id: Uuid) -> TaskStoreResult<Option<RepositoryGroup>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "repository_groups", id)
+            .await?
+            .as_ref()
+            .map(repository_group_from_row)
+            .transpose()
+    }
+
+    async fn list_repository_groups(
+        &self,
+        workspace_id: Option<Uuid>,
+    ) -> TaskStoreResult<Vec<RepositoryGroup>> {
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM repository_groups WHERE 1 = 1");
+        if let Some(workspace_id) = workspace_id {
+            qb.push(" AND workspace_id = ")
+                .push_bind(workspace_id.hyphenated());
+        }
+        qb.push(" ORDER BY created_at, id");
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        rows.iter().map(repository_group_from_row).collect()
+    }
+
+    async fn update_repository_group(
+        &self,
+        mut group: RepositoryGroup,
+    ) -> TaskStoreResult<RepositoryGroup> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "RepositoryGroup", group.id).await?;
+        let result = sqlx::query(
+            "UPDATE repository_groups SET revision = revision + 1, workspace_id = ?, name = ?, \
+             repository_ids = ?, created_at = ?, updated_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(group.workspace_id.hyphenated())
+        .bind(&group.name)
+        .bind(serde_json::to_string(&group.repository_ids)?)
+        .bind(group.created_at)
+        .bind(group.updated_at)
+        .bind(group.id.hyphenated())
+        .bind(group.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "RepositoryGroup", group.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "repository_groups",
+            "RepositoryGroup",
+            group.id,
+        )
+        .await?;
+        ensure_references(&mut tx, "repositories", "Repository", &group.repository_ids).await?;
+        group.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::RepositoryGroup(group.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(group)
+    }
+
+    async fn delete_repository_group(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "RepositoryGroup", id).await?;
+        delete_by_id(&mut tx, "repository_groups", "RepositoryGroup", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::RepositoryGroup, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Agent Task operations
+    // =========================================================================
+
+    async fn create_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "AgentTask", task.id).await?;
+        sqlx::query(
+            "INSERT INTO agent_tasks (id, base_remotes, ai_agent_type, ai_agent_model, \
+             created_at, revision) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(task.id.hyphenated())
+        .bind(serde_json::to_string(&task.base_remotes)?)
+        .bind(task.ai_agent_type.as_ref().map(encode_enum).transpose()?)
+        .bind(&task.ai_agent_model)
+        .bind(task.created_at)
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentTask", task.id))?;
+        for session in &task.agent_sessions {
+            upsert_agent_session(&mut tx, session).await?;
+        }
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::AgentTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn get_agent_task(&self, id: Uuid) -> TaskStoreResult<Option<AgentTask>> {
+        let mut conn = self.acquire().await?;
+        let Some(row) = fetch_by_id(&mut conn, "agent_tasks", id).await? else {
+            return Ok(None);
+        };
+        let sessions = sessions_for_agent_task(&mut conn, id).await?;
+        agent_task_from_row(&row, sessions).map(Some)
+    }
+
+    async fn get_agent_tasks(&self, ids: &[Uuid]) -> TaskStoreResult<Vec<AgentTask>> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM agent_tasks WHERE id IN ");
+        push_id_list(&mut qb, ids);
+        qb.push(" ORDER BY created_at, id");
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let mut sessions = sessions_for_agent_tasks(&mut conn, ids).await?;
+        rows.iter()
+            .map(|row| {
+                let sessions = sessions.remove(&uuid_col(row, "id")?).unwrap_or_default();
+                agent_task_from_row(row, sessions)
+            })
+            .collect()
+    }
+
+    async fn list_agent_tasks(&self) -> TaskStoreResult<Vec<AgentTask>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query("SELECT * FROM agent_tasks ORDER BY created_at, id")
+            .fetch_all(&mut *conn)
+            .await?;
+        let mut tasks = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let sessions = sessions_for_agent_task(&mut conn, uuid_col(row, "id")?).await?;
+            tasks.push(agent_task_from_row(row, sessions)?);
+        }
+        Ok(tasks)
+    }
+
+    async fn update_agent_task(&self, mut task: AgentTask) -> TaskStoreResult<AgentTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "AgentTask", task.id).await?;
+        let result = sqlx::query(
+            "UPDATE agent_tasks SET revision = revision + 1, base_remotes = ?, ai_agent_type = ?, \
+             ai_agent_model = ?, created_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(serde_json::to_string(&task.base_remotes)?)
+        .bind(task.ai_agent_type.as_ref().map(encode_enum).transpose()?)
+        .bind(&task.ai_agent_model)
+        .bind(task.created_at)
+        .bind(task.id.hyphenated())
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentTask", task.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "agent_tasks",
+            "AgentTask",
+            task.id,
+        )
+        .await?;
+        task.revision += 1;
+        for session in &task.agent_sessions {
+            upsert_agent_session(&mut tx, session).await?;
+        }
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::AgentTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn delete_agent_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "AgentTask", id).await?;
+        ensure_unlisted(
+            &mut tx,
+            "AgentTask",
+            id,
+            "unit_tasks",
+            "auto_fix_task_ids",
+            "UnitTask",
+        )
+        .await?;
+        // Sessions belong to their agent task and are deleted with it.
+        for session in sessions_for_agent_task(&mut tx, id).await? {
+            delete_by_id(&mut tx, "agent_sessions", "AgentSession", session.id).await?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::deleted(EntityKind::AgentSession, session.id),
+            )
+            .await?;
+        }
+        delete_by_id(&mut tx, "agent_tasks", "AgentTask", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::AgentTask, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Agent Session operations
+    // =========================================================================
+
+    async fn create_agent_session(&self, session: AgentSession) -> TaskStoreResult<AgentSession> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "AgentSession", session.id).await?;
+        sqlx::query(
+            "INSERT INTO agent_sessions (id, agent_task_id, ai_agent_type, ai_agent_model, \
+             started_at, completed_at, output_log, created_at, revision) VALUES (?, ?, ?, ?, ?, \
+             ?, ?, ?, ?)",
+        )
+        .bind(session.id.hyphenated())
+        .bind(session.agent_task_id.hyphenated())
+        .bind(encode_enum(&session.ai_agent_type)?)
+        .bind(&session.ai_agent_model)
+        .bind(session.started_at)
+        .bind(session.completed_at)
+        .bind(&session.output_log)
+        .bind(session.created_at)
+        .bind(session.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentSession", session.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::AgentSession(session.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(session)
+    }
+
+    async fn get_agent_session(&self, id: Uuid) -> TaskStoreResult<Option<AgentSession>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "agent_sessions", id)
+            .await?
+            .as_ref()
+            .map(agent_session_from_row)
+            .transpose()
+    }
+
+    async fn list_agent_sessions(&self, agent_task_id: Uuid) -> TaskStoreResult<Vec<AgentSession>> {
+        let mut conn = self.acquire().await?;
+        sessions_for_agent_task(&mut conn, agent_task_id).await
+    }
+
+    async fn update_agent_session(
+        &self,
+        mut session: AgentSession,
+    ) -> TaskStoreResult<AgentSession> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "AgentSession", session.id).await?;
+        let result = sqlx::query(
+            "UPDATE agent_sessions SET revision = revision + 1, agent_task_id = ?, ai_agent_type \
+             = ?, ai_agent_model = ?, started_at = ?, completed_at = ?, output_log = ?, \
+             created_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(session.agent_task_id.hyphenated())
+        .bind(encode_enum(&session.ai_agent_type)?)
+        .bind(&session.ai_agent_model)
+        .bind(session.started_at)
+        .bind(session.completed_at)
+        .bind(&session.output_log)
+        .bind(session.created_at)
+        .bind(session.id.hyphenated())
+        .bind(session.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "AgentSession", session.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "agent_sessions",
+            "AgentSession",
+            session.id,
+        )
+        .await?;
+        session.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::AgentSession(session.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(session)
+    }
+
+    async fn delete_agent_session(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "AgentSession", id).await?;
+        delete_by_id(&mut tx, "agent_sessions", "AgentSession", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::AgentSession, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Session log operations
+    // =========================================================================
+
+    async fn append_session_log(
+        &self,
+        session_id: Uuid,
+        content: String,
+    ) -> TaskStoreResult<LogChunk> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "SessionLog", session_id).await?;
+        let size = log_size(&mut tx, session_id).await?;
+        let chunk = LogChunk {
+            session_id,
+            sequence: size.chunks,
+            offset: size.bytes,
+            content,
+            created_at: Utc::now(),
+        };
+        sqlx::query(
+            "INSERT INTO session_log_chunks (session_id, sequence, byte_offset, content, \
+             created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(session_id.hyphenated())
+        .bind(chunk.sequence as i64)
+        .bind(chunk.offset as i64)
+        .bind(&chunk.content)
+        .bind(chunk.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "SessionLog", session_id))?;
+        tx.commit().await?;
+        Ok(chunk)
+    }
+
+    async fn read_session_log(
+        &self,
+        session_id: Uuid,
+        range: LogRange,
+    ) -> TaskStoreResult<Vec<LogChunk>> {
+        let mut conn = self.acquire().await?;
+        let mut qb =
+            QueryBuilder::<Sqlite>::new("SELECT * FROM session_log_chunks WHERE session_id = ");
+        qb.push_bind(session_id.hyphenated());
+        match range {
+            LogRange::Sequence { start, end } => {
+                qb.push(" AND sequence >= ").push_bind(start as i64);
+                if let Some(end) = end {
+                    qb.push(" AND sequence < ").push_bind(end as i64);
+                }
+                qb.push(" ORDER BY sequence");
+            }
+            LogRange::Bytes { start, end } => {
+                qb.push(" AND byte_offset + length(CAST(content AS BLOB)) > ")
+                    .push_bind(start as i64);
+                if let Some(end) = end {
+                    qb.push(" AND byte_offset < ").push_bind(end as i64);
+                }
+                qb.push(" ORDER BY sequence");
+            }
+            LogRange::Tail(count) => {
+                qb.push(" ORDER BY sequence DESC LIMIT ")
+                    .push_bind(count as i64);
+            }
+        }
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let mut chunks = rows
+            .iter()
+            .map(log_chunk_from_row)
+            .collect::<TaskStoreResult<Vec<_>>>()?;
+        if let LogRange::Tail(_) = range {
+            chunks.reverse();
+        }
+        Ok(chunks)
+    }
+
+    async fn session_log_size(&self, session_id: Uuid) -> TaskStoreResult<LogSize> {
+        let mut conn = self.acquire().await?;
+        log_size(&mut conn, session_id).await
+    }
+
+    // =========================================================================
+    // Unit Task operations
+    // =========================================================================
+
+    async fn create_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "UnitTask", task.id).await?;
+        sqlx::query(
+            "INSERT INTO unit_tasks (id, repository_group_id, agent_task_id, prompt, title, \
+             branch_name, linked_pr_url, base_commit, end_commit, auto_fix_task_ids, status, \
+             created_at, updated_at, revision, deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, \
+             ?, ?, ?, ?, ?)",
+        )
+        .bind(task.id.hyphenated())
+        .bind(task.repository_group_id.hyphenated())
+        .bind(task.agent_task_id.hyphenated())
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(&task.branch_name)
+        .bind(&task.linked_pr_url)
+        .bind(&task.base_commit)
+        .bind(&task.end_commit)
+        .bind(serde_json::to_string(&task.auto_fix_task_ids)?)
+        .bind(encode_enum(&task.status)?)
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.revision as i64)
+        .bind(task.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "UnitTask", task.id))?;
+        ensure_references(&mut tx, "agent_tasks", "AgentTask", &task.auto_fix_task_ids).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::UnitTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn get_unit_task(&self, id: Uuid) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "unit_tasks", id)
+            .await?
+            .as_ref()
+            .map(unit_task_from_row)
+            .transpose()
+    }
+
+    async fn list_unit_tasks(&self, filter: TaskFilter) -> TaskStoreResult<(Vec<UnitTask>, u32)> {
+        check_sort::<UnitTask>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let unit_status = filter.unit_status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(group_id) = filter.repository_group_id {
+                qb.push(" AND repository_group_id = ")
+                    .push_bind(group_id.hyphenated());
+            }
+            if let Some(status) = &unit_status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(since) = filter.created_since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.created_until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM unit_tasks");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM unit_tasks");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "COALESCE(title, '')",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let tasks = rows
+            .iter()
+            .map(unit_task_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((tasks, total))
+    }
+
+    async fn find_unit_task_by_pr_url(&self, pr_url: &str) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "SELECT * FROM unit_tasks WHERE linked_pr_url = ? ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(pr_url)
+        .fetch_optional(&mut *conn)
+        .await?
+        .as_ref()
+        .map(unit_task_from_row)
+        .transpose()
+    }
+
+    async fn find_unit_task_by_branch_name(
+        &self,
+        branch_name: &str,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "SELECT * FROM unit_tasks WHERE branch_name = ? ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(branch_name)
+        .fetch_optional(&mut *conn)
+        .await?
+        .as_ref()
+        .map(unit_task_from_row)
+        .transpose()
+    }
+
+    async fn find_unit_task_by_agent_task(
+        &self,
+        agent_task_id: Uuid,
+    ) -> TaskStoreResult<Option<UnitTask>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "SELECT * FROM unit_tasks WHERE agent_task_id = ? OR id IN (SELECT unit_task_id FROM \
+             unit_task_auto_fix_tasks WHERE agent_task_id = ?) ORDER BY created_at DESC, id DESC \
+             LIMIT 1",
+        )
+        .bind(agent_task_id.hyphenated())
+        .bind(agent_task_id.hyphenated())
+        .fetch_optional(&mut *conn)
+        .await?
+        .as_ref()
+        .map(unit_task_from_row)
+        .transpose()
+    }
+
+    async fn update_unit_task(&self, mut task: UnitTask) -> TaskStoreResult<UnitTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "UnitTask", task.id).await?;
+        ensure_status_transition(
+            &mut tx,
+            "unit_tasks",
+            "UnitTask",
+            task.id,
+            task.revision,
+            task.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE unit_tasks SET revision = revision + 1, repository_group_id = ?, \
+             agent_task_id = ?, prompt = ?, title = ?, branch_name = ?, linked_pr_url = ?, \
+             base_commit = ?, end_commit = ?, auto_fix_task_ids = ?, status = ?, created_at = ?, \
+             updated_at = ?, deleted_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(task.repository_group_id.hyphenated())
+        .bind(task.agent_task_id.hyphenated())
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(&task.branch_name)
+        .bind(&task.linked_pr_url)
+        .bind(&task.base_commit)
+        .bind(&task.end_commit)
+        .bind(serde_json::to_string(&task.auto_fix_task_ids)?)
+        .bind(encode_enum(&task.status)?)
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.deleted_at)
+        .bind(task.id.hyphenated())
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "UnitTask", task.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "unit_tasks",
+            "UnitTask",
+            task.id,
+        )
+        .await?;
+        ensure_references(&mut tx, "agent_tasks", "AgentTask", &task.auto_fix_task_ids).await?;
+        task.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::UnitTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn delete_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "UnitTask", id).await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "unit_tasks", "UnitTask", id, Some(Utc::now())).await?;
+        if changed {
+            let unit_task = unit_task_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::UnitTask(unit_task)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_unit_task(&self, id: Uuid) -> TaskStoreResult<UnitTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "UnitTask", id).await?;
+        let (row, changed) = set_deleted_at(&mut tx, "unit_tasks", "UnitTask", id, None).await?;
+        let unit_task = unit_task_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::UnitTask(unit_task.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(unit_task)
+    }
+
+    async fn purge_unit_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "UnitTask", id).await?;
+        delete_by_id(&mut tx, "unit_tasks", "UnitTask", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::UnitTask, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Composite Task operations
+    // =========================================================================
+
+    async fn create_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTask", task.id).await?;
+        sqlx::query(
+            "INSERT INTO composite_tasks (id, repository_group_id, planning_task_id, prompt, \
+             title, node_ids, status, execution_agent_type, created_at, updated_at, revision, \
+             deleted_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(task.id.hyphenated())
+        .bind(task.repository_group_id.hyphenated())
+        .bind(task.planning_task_id.hyphenated())
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(serde_json::to_string(&task.node_ids)?)
+        .bind(encode_enum(&task.status)?)
+        .bind(
+            task.execution_agent_type
+                .as_ref()
+                .map(encode_enum)
+                .transpose()?,
+        )
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.revision as i64)
+        .bind(task.deleted_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTask", task.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::CompositeTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn get_composite_task(&self, id: Uuid) -> TaskStoreResult<Option<CompositeTask>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "composite_tasks", id)
+            .await?
+            .as_ref()
+            .map(composite_task_from_row)
+            .transpose()
+    }
+
+    async fn list_composite_tasks(
+        &self,
+        filter: TaskFilter,
+    ) -> TaskStoreResult<(Vec<CompositeTask>, u32)> {
+        check_sort::<CompositeTask>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let composite_status = filter
+            .composite_status
+            .as_ref()
+            .map(encode_enum)
+            .transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(condition) = filter.deleted.sql_condition() {
+                qb.push(" AND ").push(condition);
+            }
+            if let Some(group_id) = filter.repository_group_id {
+                qb.push(" AND repository_group_id = ")
+                    .push_bind(group_id.hyphenated());
+            }
+            if let Some(status) = &composite_status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(since) = filter.created_since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.created_until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM composite_tasks");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM composite_tasks");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "COALESCE(title, '')",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let tasks = rows
+            .iter()
+            .map(composite_task_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((tasks, total))
+    }
+
+    async fn find_composite_task_by_node_unit_task(
+        &self,
+        unit_task_id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTask>> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "SELECT * FROM composite_tasks WHERE id IN (SELECT composite_task_id FROM \
+             composite_task_nodes WHERE unit_task_id = ?) ORDER BY created_at DESC, id DESC LIMIT \
+             1",
+        )
+        .bind(unit_task_id.hyphenated())
+        .fetch_optional(&mut *conn)
+        .await?
+        .as_ref()
+        .map(composite_task_from_row)
+        .transpose()
+    }
+
+    async fn update_composite_task(
+        &self,
+        mut task: CompositeTask,
+    ) -> TaskStoreResult<CompositeTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTask", task.id).await?;
+        ensure_status_transition(
+            &mut tx,
+            "composite_tasks",
+            "CompositeTask",
+            task.id,
+            task.revision,
+            task.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE composite_tasks SET revision = revision + 1, repository_group_id = ?, \
+             planning_task_id = ?, prompt = ?, title = ?, node_ids = ?, status = ?, \
+             execution_agent_type = ?, created_at = ?, updated_at = ?, deleted_at = ? WHERE id = \
+             ? AND revision = ?",
+        )
+        .bind(task.repository_group_id.hyphenated())
+        .bind(task.planning_task_id.hyphenated())
+        .bind(&task.prompt)
+        .bind(&task.title)
+        .bind(serde_json::to_string(&task.node_ids)?)
+        .bind(encode_enum(&task.status)?)
+        .bind(
+            task.execution_agent_type
+                .as_ref()
+                .map(encode_enum)
+                .transpose()?,
+        )
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.deleted_at)
+        .bind(task.id.hyphenated())
+        .bind(task.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTask", task.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "composite_tasks",
+            "CompositeTask",
+            task.id,
+        )
+        .await?;
+        task.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::CompositeTask(task.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(task)
+    }
+
+    async fn delete_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTask", id).await?;
+        let (row, changed) = set_deleted_at(
+            &mut tx,
+            "composite_tasks",
+            "CompositeTask",
+            id,
+            Some(Utc::now()),
+        )
+        .await?;
+        if changed {
+            let composite_task = composite_task_from_row(&row)?;
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::CompositeTask(composite_task)),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn restore_composite_task(&self, id: Uuid) -> TaskStoreResult<CompositeTask> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTask", id).await?;
+        let (row, changed) =
+            set_deleted_at(&mut tx, "composite_tasks", "CompositeTask", id, None).await?;
+        let composite_task = composite_task_from_row(&row)?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::CompositeTask(composite_task.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(composite_task)
+    }
+
+    async fn purge_composite_task(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTask", id).await?;
+        delete_by_id(&mut tx, "composite_tasks", "CompositeTask", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::CompositeTask, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Composite Task Node operations
+    // =========================================================================
+
+    async fn create_composite_task_node(
+        &self,
+        node: CompositeTaskNode,
+    ) -> TaskStoreResult<CompositeTaskNode> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTaskNode", node.id).await?;
+        sqlx::query(
+            "INSERT INTO composite_task_nodes (id, composite_task_id, unit_task_id, \
+             depends_on_ids, created_at, revision) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(node.id.hyphenated())
+        .bind(node.composite_task_id.hyphenated())
+        .bind(node.unit_task_id.hyphenated())
+        .bind(serde_json::to_string(&node.depends_on_ids)?)
+        .bind(node.created_at)
+        .bind(node.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTaskNode", node.id))?;
+        ensure_references(
+            &mut tx,
+            "composite_task_nodes",
+            "CompositeTaskNode",
+            &node.depends_on_ids,
+        )
+        .await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::CompositeTaskNode(node.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(node)
+    }
+
+    async fn get_composite_task_node(
+        &self,
+        id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTaskNode>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "composite_task_nodes", id)
+            .await?
+            .as_ref()
+            .map(composite_task_node_from_row)
+            .transpose()
+    }
+
+    async fn list_composite_task_nodes(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<CompositeTaskNode>> {
+        let mut conn = self.acquire().await?;
+        let rows = sqlx::query(
+            "SELECT * FROM composite_task_nodes WHERE composite_task_id = ? ORDER BY created_at, \
+             id",
+        )
+        .bind(composite_task_id.hyphenated())
+        .fetch_all(&mut *conn)
+        .await?;
+        rows.iter().map(composite_task_node_from_row).collect()
+    }
+
+    async fn update_composite_task_node(
+        &self,
+        mut node: CompositeTaskNode,
+    ) -> TaskStoreResult<CompositeTaskNode> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTaskNode", node.id).await?;
+        let result = sqlx::query(
+            "UPDATE composite_task_nodes SET revision = revision + 1, composite_task_id = ?, \
+             unit_task_id = ?, depends_on_ids = ?, created_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(node.composite_task_id.hyphenated())
+        .bind(node.unit_task_id.hyphenated())
+        .bind(serde_json::to_string(&node.depends_on_ids)?)
+        .bind(node.created_at)
+        .bind(node.id.hyphenated())
+        .bind(node.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "CompositeTaskNode", node.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "composite_task_nodes",
+            "CompositeTaskNode",
+            node.id,
+        )
+        .await?;
+        ensure_references(
+            &mut tx,
+            "composite_task_nodes",
+            "CompositeTaskNode",
+            &node.depends_on_ids,
+        )
+        .await?;
+        node.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::CompositeTaskNode(node.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(node)
+    }
+
+    async fn delete_composite_task_node(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "CompositeTaskNode", id).await?;
+        ensure_unlisted(
+            &mut tx,
+            "CompositeTaskNode",
+            id,
+            "composite_task_nodes",
+            "depends_on_ids",
+            "CompositeTaskNode",
+        )
+        .await?;
+        delete_by_id(&mut tx, "composite_task_nodes", "CompositeTaskNode", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::CompositeTaskNode, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Plan Revision operations
+    // =========================================================================
+
+    async fn create_plan_revision(
+        &self,
+        mut revision: PlanRevision,
+    ) -> TaskStoreResult<PlanRevision> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "PlanRevision", revision.id).await?;
+        revision.number = latest_plan_revision(&mut tx, revision.composite_task_id).await? + 1;
+        sqlx::query(
+            "INSERT INTO plan_revisions (id, composite_task_id, number, yaml, author_type, \
+             author_id, approved_by, approved_at, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(revision.id.hyphenated())
+        .bind(revision.composite_task_id.hyphenated())
+        .bind(revision.number as i64)
+        .bind(&revision.yaml)
+        .bind(encode_enum(&revision.author_type)?)
+        .bind(revision.author_id.hyphenated())
+        .bind(revision.approved_by.map(|id| id.hyphenated()))
+        .bind(revision.approved_at)
+        .bind(revision.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "PlanRevision", revision.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::PlanRevision(revision.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(revision)
+    }
+
+    async fn get_plan_revision(&self, id: Uuid) -> TaskStoreResult<Option<PlanRevision>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "plan_revisions", id)
+            .await?
+            .as_ref()
+            .map(plan_revision_from_row)
+            .transpose()
+    }
+
+    async fn list_plan_revisions(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<PlanRevision>> {
+        let mut conn = self.acquire().await?;
+        let rows =
+            sqlx::query("SELECT * FROM plan_revisions WHERE composite_task_id = ? ORDER BY number")
+                .bind(composite_task_id.hyphenated())
+                .fetch_all(&mut *conn)
+                .await?;
+        rows.iter().map(plan_revision_from_row).collect()
+    }
+
+    async fn approve_plan_revision(
+        &self,
+        id: Uuid,
+        approved_by: Uuid,
+    ) -> TaskStoreResult<PlanRevision> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "PlanRevision", id).await?;
+        let mut revision = fetch_by_id(&mut tx, "plan_revisions", id)
+            .await?
+            .as_ref()
+            .map(plan_revision_from_row)
+            .transpose()?
+            .ok_or_else(|| TaskStoreError::not_found("PlanRevision", id.to_string()))?;
+        if revision.is_approved() {
+            return Ok(revision);
+        }
+        let latest = latest_plan_revision(&mut tx, revision.composite_task_id).await?;
+        if revision.number != latest {
+            return Err(TaskStoreError::conflict(
+                "PlanRevision",
+                id.to_string(),
+                latest as u64,
+            ));
+        }
+        revision.approved_by = Some(approved_by);
+        revision.approved_at = Some(Utc::now());
+        sqlx::query("UPDATE plan_revisions SET approved_by = ?, approved_at = ? WHERE id = ?")
+            .bind(approved_by.hyphenated())
+            .bind(revision.approved_at)
+            .bind(id.hyphenated())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| map_write_error(e, "PlanRevision", id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::PlanRevision(revision.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(revision)
+    }
+
+    // =========================================================================
+    // Todo Item operations
+    // =========================================================================
+
+    async fn create_todo_item(&self, item: TodoItem) -> TaskStoreResult<TodoItem> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "TodoItem", item.id).await?;
+        sqlx::query(
+            "INSERT INTO todo_items (id, item_type, source, status, repository_id, data, \
+             created_at, updated_at, revision) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(item.id.hyphenated())
+        .bind(encode_enum(&item.item_type)?)
+        .bind(encode_enum(&item.source)?)
+        .bind(encode_enum(&item.status)?)
+        .bind(item.repository_id.hyphenated())
+        .bind(serde_json::to_string(&item.data)?)
+        .bind(item.created_at)
+        .bind(item.updated_at)
+        .bind(item.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TodoItem", item.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::TodoItem(item.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(item)
+    }
+
+    async fn get_todo_item(&self, id: Uuid) -> TaskStoreResult<Option<TodoItem>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "todo_items", id)
+            .await?
+            .as_ref()
+            .map(todo_item_from_row)
+            .transpose()
+    }
+
+    async fn list_todo_items(&self, filter: TodoFilter) -> TaskStoreResult<(Vec<TodoItem>, u32)> {
+        check_sort::<TodoItem>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let status = filter.status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(repository_id) = filter.repository_id {
+                qb.push(" AND repository_id = ")
+                    .push_bind(repository_id.hyphenated());
+            }
+            if let Some(status) = &status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM todo_items");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM todo_items");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let items = rows
+            .iter()
+            .map(todo_item_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((items, total))
+    }
+
+    async fn update_todo_item(&self, mut item: TodoItem) -> TaskStoreResult<TodoItem> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "TodoItem", item.id).await?;
+        ensure_status_transition(
+            &mut tx,
+            "todo_items",
+            "TodoItem",
+            item.id,
+            item.revision,
+            item.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE todo_items SET revision = revision + 1, item_type = ?, source = ?, status = \
+             ?, repository_id = ?, data = ?, created_at = ?, updated_at = ? WHERE id = ? AND \
+             revision = ?",
+        )
+        .bind(encode_enum(&item.item_type)?)
+        .bind(encode_enum(&item.source)?)
+        .bind(encode_enum(&item.status)?)
+        .bind(item.repository_id.hyphenated())
+        .bind(serde_json::to_string(&item.data)?)
+        .bind(item.created_at)
+        .bind(item.updated_at)
+        .bind(item.id.hyphenated())
+        .bind(item.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TodoItem", item.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "todo_items",
+            "TodoItem",
+            item.id,
+        )
+        .await?;
+        item.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::TodoItem(item.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(item)
+    }
+
+    async fn delete_todo_item(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "TodoItem", id).await?;
+        delete_by_id(&mut tx, "todo_items", "TodoItem", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::TodoItem, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // TTY Input Request operations
+    // =========================================================================
+
+    async fn create_tty_input_request(
+        &self,
+        request: TtyInputRequest,
+    ) -> TaskStoreResult<TtyInputRequest> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "TtyInputRequest", request.id).await?;
+        sqlx::query(
+            "INSERT INTO tty_input_requests (id, task_id, session_id, prompt, input_type, \
+             options, status, response, created_at, responded_at, revision) VALUES (?, ?, ?, ?, \
+             ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(request.id.hyphenated())
+        .bind(request.task_id.hyphenated())
+        .bind(request.session_id.hyphenated())
+        .bind(&request.prompt)
+        .bind(encode_enum(&request.input_type)?)
+        .bind(
+            request
+                .options
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(encode_enum(&request.status)?)
+        .bind(&request.response)
+        .bind(request.created_at)
+        .bind(request.responded_at)
+        .bind(request.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TtyInputRequest", request.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::TtyInputRequest(request.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(request)
+    }
+
+    async fn get_tty_input_request(&self, id: Uuid) -> TaskStoreResult<Option<TtyInputRequest>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "tty_input_requests", id)
+            .await?
+            .as_ref()
+            .map(tty_input_request_from_row)
+            .transpose()
+    }
+
+    async fn list_tty_input_requests(
+        &self,
+        filter: TtyInputFilter,
+    ) -> TaskStoreResult<Vec<TtyInputRequest>> {
+        check_sort::<TtyInputRequest>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Sqlite>::new("SELECT * FROM tty_input_requests WHERE 1 = 1");
+        if let Some(task_id) = filter.task_id {
+            qb.push(" AND task_id = ").push_bind(task_id.hyphenated());
+        }
+        if let Some(session_id) = filter.session_id {
+            qb.push(" AND session_id = ")
+                .push_bind(session_id.hyphenated());
+        }
+        if let Some(status) = filter.status {
+            qb.push(" AND status = ").push_bind(encode_enum(&status)?);
+        }
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        rows.iter().map(tty_input_request_from_row).collect()
+    }
+
+    async fn update_tty_input_request(
+        &self,
+        mut request: TtyInputRequest,
+    ) -> TaskStoreResult<TtyInputRequest> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "TtyInputRequest", request.id).await?;
+        ensure_status_transition(
+            &mut tx,
+            "tty_input_requests",
+            "TtyInputRequest",
+            request.id,
+            request.revision,
+            request.status,
+        )
+        .await?;
+        let result = sqlx::query(
+            "UPDATE tty_input_requests SET revision = revision + 1, task_id = ?, session_id = ?, \
+             prompt = ?, input_type = ?, options = ?, status = ?, response = ?, created_at = ?, \
+             responded_at = ? WHERE id = ? AND revision = ?",
+        )
+        .bind(request.task_id.hyphenated())
+        .bind(request.session_id.hyphenated())
+        .bind(&request.prompt)
+        .bind(encode_enum(&request.input_type)?)
+        .bind(
+            request
+                .options
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(encode_enum(&request.status)?)
+        .bind(&request.response)
+        .bind(request.created_at)
+        .bind(request.responded_at)
+        .bind(request.id.hyphenated())
+        .bind(request.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "TtyInputRequest", request.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "tty_input_requests",
+            "TtyInputRequest",
+            request.id,
+        )
+        .await?;
+        request.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::TtyInputRequest(request.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(request)
+    }
+
+    async fn delete_tty_input_request(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "TtyInputRequest", id).await?;
+        delete_by_id(&mut tx, "tty_input_requests", "TtyInputRequest", id).await?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::deleted(EntityKind::TtyInputRequest, id),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    // =========================================================================
+    // Worker operations
+    // =========================================================================
+
+    async fn register_worker(&self, worker: Worker) -> TaskStoreResult<Worker> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Worker", worker.id).await?;
+        sqlx::query(
+            "INSERT INTO workers (id, name, endpoint_url, capabilities, status, current_task_id, \
+             last_heartbeat, revision, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(worker.id.hyphenated())
+        .bind(&worker.name)
+        .bind(&worker.endpoint_url)
+        .bind(serde_json::to_string(&worker.capabilities)?)
+        .bind(encode_enum(&worker.status)?)
+        .bind(worker.current_task_id.map(|id| id.hyphenated()))
+        .bind(worker.last_heartbeat)
+        .bind(worker.revision as i64)
+        .bind(worker.created_at)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Worker", worker.id))?;
+        record_change(
+            &mut tx,
+            &ChangeEvent::created(EntityValue::Worker(worker.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(worker)
+    }
+
+    async fn get_worker(&self, id: Uuid) -> TaskStoreResult<Option<Worker>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "workers", id)
+            .await?
+            .as_ref()
+            .map(worker_from_row)
+            .transpose()
+    }
+
+    async fn list_workers(&self, filter: WorkerFilter) -> TaskStoreResult<(Vec<Worker>, u32)> {
+        check_sort::<Worker>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let status = filter.status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(status) = &status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(capability) = &filter.capability {
+                qb.push(" AND EXISTS (SELECT 1 FROM json_each(capabilities) WHERE value = ")
+                    .push_bind(capability.clone())
+                    .push(")");
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM workers");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM workers");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "name",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let workers = rows
+            .iter()
+            .map(worker_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((workers, total))
+    }
+
+    async fn update_worker(&self, mut worker: Worker) -> TaskStoreResult<Worker> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Worker", worker.id).await?;
+        let result = sqlx::query(
+            "UPDATE workers SET revision = revision + 1, name = ?, endpoint_url = ?, capabilities \
+             = ?, status = ?, current_task_id = ?, last_heartbeat = ?, created_at = ? WHERE id = \
+             ? AND revision = ?",
+        )
+        .bind(&worker.name)
+        .bind(&worker.endpoint_url)
+        .bind(serde_json::to_string(&worker.capabilities)?)
+        .bind(encode_enum(&worker.status)?)
+        .bind(worker.current_task_id.map(|id| id.hyphenated()))
+        .bind(worker.last_heartbeat)
+        .bind(worker.created_at)
+        .bind(worker.id.hyphenated())
+        .bind(worker.revision as i64)
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Worker", worker.id))?;
+        ensure_updated(
+            &mut tx,
+            result.rows_affected(),
+            "workers",
+            "Worker",
+            worker.id,
+        )
+        .await?;
+        worker.revision += 1;
+        record_change(
+            &mut tx,
+            &ChangeEvent::updated(EntityValue::Worker(worker.clone())),
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(worker)
+    }
+
+    async fn delete_worker(&self, id: Uuid) -> TaskStoreResult<()> {
+        let mut conn = self.acquire().await?;
+        let mut tx = begin_write(&mut conn, "Worker", id).await?;
+        delete_by_id(&mut tx, "workers", "Worker", id).await?;
+        record_change(&mut tx, &ChangeEvent::deleted(EntityKind::Worker, id)).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn record_worker_heartbeat(
+        &self,
+        id: Uuid,
+        status: WorkerStatus,
+        current_task_id: Option<Uuid>,
+    ) -> TaskStoreResult<Worker> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        // Writing before reading takes the database's write lock, so a
+        // concurrent stale sweep or update cannot land between the read and
+        // the write below.
+        let last_heartbeat = Utc::now();
+        sqlx::query("UPDATE workers SET last_heartbeat = ? WHERE id = ?")
+            .bind(last_heartbeat)
+            .bind(id.hyphenated())
+            .execute(&mut *tx)
+            .await?;
+        let mut worker = fetch_by_id(&mut tx, "workers", id)
+            .await?
+            .as_ref()
+            .map(worker_from_row)
+            .transpose()?
+            .ok_or_else(|| TaskStoreError::not_found("Worker", id.to_string()))?;
+        let changed = worker.status != status || worker.current_task_id != current_task_id;
+        worker.last_heartbeat = last_heartbeat;
+        if changed {
+            worker.status = status;
+            worker.current_task_id = current_task_id;
+            worker.revision += 1;
+        }
+        sqlx::query(
+            "UPDATE workers SET status = ?, current_task_id = ?, last_heartbeat = ?, revision = ? \
+             WHERE id = ?",
+        )
+        .bind(encode_enum(&worker.status)?)
+        .bind(worker.current_task_id.map(|id| id.hyphenated()))
+        .bind(worker.last_heartbeat)
+        .bind(worker.revision as i64)
+        .bind(id.hyphenated())
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| map_write_error(e, "Worker", id))?;
+        if changed {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Worker(worker.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(worker)
+    }
+
+    async fn mark_stale_workers(&self, before: DateTime<Utc>) -> TaskStoreResult<Vec<Worker>> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let unhealthy = encode_enum(&WorkerStatus::Unhealthy)?;
+        let rows = sqlx::query(
+            "UPDATE workers SET status = ?, revision = revision + 1 WHERE status <> ? AND \
+             last_heartbeat < ? RETURNING *",
+        )
+        .bind(&unhealthy)
+        .bind(&unhealthy)
+        .bind(before)
+        .fetch_all(&mut *tx)
+        .await?;
+        let mut workers = rows
+            .iter()
+            .map(worker_from_row)
+            .collect::<TaskStoreResult<Vec<_>>>()?;
+        workers.sort_by_key(|w| (w.last_heartbeat, w.id));
+        for worker in &workers {
+            record_change(
+                &mut tx,
+                &ChangeEvent::updated(EntityValue::Worker(worker.clone())),
+            )
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(workers)
+    }
+
+    // =========================================================================
+    // Work queue operations
+    // =========================================================================
+
+    async fn enqueue_task(&self, entry: QueueEntry) -> TaskStoreResult<QueueEntry> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "INSERT INTO work_queue (id, agent_task_id, status, attempts, max_attempts, \
+             worker_id, lease_expires_at, last_error, revision, created_at, updated_at) VALUES \
+             (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(entry.id.hyphenated())
+        .bind(entry.agent_task_id.hyphenated())
+        .bind(encode_enum(&entry.status)?)
+        .bind(entry.attempts as i64)
+        .bind(entry.max_attempts as i64)
+        .bind(entry.worker_id.map(|id| id.hyphenated()))
+        .bind(entry.lease_expires_at)
+        .bind(&entry.last_error)
+        .bind(entry.revision as i64)
+        .bind(entry.created_at)
+        .bind(entry.updated_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| map_write_error(e, "QueueEntry", entry.id))?;
+        Ok(entry)
+    }
+
+    async fn get_queue_entry(&self, id: Uuid) -> TaskStoreResult<Option<QueueEntry>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "work_queue", id)
+            .await?
+            .as_ref()
+            .map(queue_entry_from_row)
+            .transpose()
+    }
+
+    async fn list_queue_entries(
+        &self,
+        filter: QueueFilter,
+    ) -> TaskStoreResult<(Vec<QueueEntry>, u32)> {
+        check_sort::<QueueEntry>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let status = filter.status.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(status) = &status {
+                qb.push(" AND status = ").push_bind(status.clone());
+            }
+            if let Some(worker_id) = filter.worker_id {
+                qb.push(" AND worker_id = ")
+                    .push_bind(worker_id.hyphenated());
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM work_queue");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM work_queue");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let entries = rows
+            .iter()
+            .map(queue_entry_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((entries, total))
+    }
+
+    async fn claim_task(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Option<QueueEntry>> {
+        let mut conn = self.acquire().await?;
+        let mut tx = conn.begin().await?;
+        let now = Utc::now();
+        requeue_expired(&mut tx, now).await?;
+        let row = sqlx::query(
+            "UPDATE work_queue SET status = ?, attempts = attempts + 1, worker_id = ?, \
+             lease_expires_at = ?, revision = revision + 1, updated_at = ? WHERE id = (SELECT id \
+             FROM work_queue WHERE status = ? ORDER BY created_at, id LIMIT 1) RETURNING *",
+        )
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id.hyphenated())
+        .bind(now + lease)
+        .bind(now)
+        .bind(encode_enum(&QueueStatus::Queued)?)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let entry = row.as_ref().map(queue_entry_from_row).transpose()?;
+        tx.commit().await?;
+        Ok(entry)
+    }
+
+    async fn renew_leases(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Vec<QueueEntry>> {
+        let mut conn = self.acquire().await?;
+        let now = Utc::now();
+        let rows = sqlx::query(
+            "UPDATE work_queue SET lease_expires_at = ?, revision = revision + 1, updated_at = ? \
+             WHERE status = ? AND worker_id = ? AND lease_expires_at > ? RETURNING *",
+        )
+        .bind(now + lease)
+        .bind(now)
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id.hyphenated())
+        .bind(now)
+        .fetch_all(&mut *conn)
+        .await?;
+        let mut entries = rows
+            .iter()
+            .map(queue_entry_from_row)
+            .collect::<TaskStoreResult<Vec<_>>>()?;
+        entries.sort_by_key(|e| (e.created_at, e.id));
+        Ok(entries)
+    }
+
+    async fn complete_task(&self, id: Uuid, worker_id: Uuid) -> TaskStoreResult<QueueEntry> {
+        let mut conn = self.acquire().await?;
+        let now = Utc::now();
+        let row = sqlx::query(
+            "UPDATE work_queue SET status = ?, worker_id = NULL, lease_expires_at = NULL, \
+             revision = revision + 1, updated_at = ? WHERE id = ? AND status = ? AND worker_id = \
+             ? AND lease_expires_at > ? RETURNING *",
+        )
+        .bind(encode_enum(&QueueStatus::Completed)?)
+        .bind(now)
+        .bind(id.hyphenated())
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id.hyphenated())
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match row {
+            Some(row) => queue_entry_from_row(&row),
+            None => Err(lease_not_held(&mut conn, id, worker_id).await),
+        }
+    }
+
+    async fn fail_task(
+        &self,
+        id: Uuid,
+        worker_id: Uuid,
+        error: String,
+    ) -> TaskStoreResult<QueueEntry> {
+        let mut conn = self.acquire().await?;
+        let now = Utc::now();
+        let row = sqlx::query(
+            "UPDATE work_queue SET status = CASE WHEN attempts < max_attempts THEN ? ELSE ? END, \
+             worker_id = NULL, lease_expires_at = NULL, last_error = ?, revision = revision + 1, \
+             updated_at = ? WHERE id = ? AND status = ? AND worker_id = ? AND lease_expires_at > \
+             ? RETURNING *",
+        )
+        .bind(encode_enum(&QueueStatus::Queued)?)
+        .bind(encode_enum(&QueueStatus::Failed)?)
+        .bind(&error)
+        .bind(now)
+        .bind(id.hyphenated())
+        .bind(encode_enum(&QueueStatus::Leased)?)
+        .bind(worker_id.hyphenated())
+        .bind(now)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match row {
+            Some(row) => queue_entry_from_row(&row),
+            None => Err(lease_not_held(&mut conn, id, worker_id).await),
+        }
+    }
+
+    async fn requeue_expired_leases(&self) -> TaskStoreResult<u64> {
+        let mut conn = self.acquire().await?;
+        requeue_expired(&mut conn, Utc::now()).await
+    }
+
+    // =========================================================================
+    // Audit log operations
+    // =========================================================================
+
+    async fn record_audit_event(&self, event: AuditEvent) -> TaskStoreResult<AuditEvent> {
+        let mut conn = self.acquire().await?;
+        sqlx::query(
+            "INSERT INTO audit_events (id, actor_type, actor_id, action, entity_type, entity_id, \
+             before, after, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(event.id.hyphenated())
+        .bind(encode_enum(&event.actor.actor_type)?)
+        .bind(event.actor.id.map(|id| id.hyphenated()))
+        .bind(&event.action)
+        .bind(&event.entity_type)
+        .bind(event.entity_id.hyphenated())
+        .bind(
+            event
+                .before
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(
+            event
+                .after
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(event.created_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(|e| map_write_error(e, "AuditEvent", event.id))?;
+        Ok(event)
+    }
+
+    async fn get_audit_event(&self, id: Uuid) -> TaskStoreResult<Option<AuditEvent>> {
+        let mut conn = self.acquire().await?;
+        fetch_by_id(&mut conn, "audit_events", id)
+            .await?
+            .as_ref()
+            .map(audit_event_from_row)
+            .transpose()
+    }
+
+    async fn list_audit_events(
+        &self,
+        filter: AuditFilter,
+    ) -> TaskStoreResult<(Vec<AuditEvent>, u32)> {
+        check_sort::<AuditEvent>(filter.sort, filter.after.as_ref())?;
+        let mut conn = self.acquire().await?;
+        let actor_type = filter.actor_type.as_ref().map(encode_enum).transpose()?;
+        let push_where = |qb: &mut QueryBuilder<'_, Sqlite>| {
+            qb.push(" WHERE 1 = 1");
+            if let Some(entity_type) = &filter.entity_type {
+                qb.push(" AND entity_type = ")
+                    .push_bind(entity_type.clone());
+            }
+            if let Some(entity_id) = filter.entity_id {
+                qb.push(" AND entity_id = ")
+                    .push_bind(entity_id.hyphenated());
+            }
+            if let Some(actor_type) = &actor_type {
+                qb.push(" AND actor_type = ").push_bind(actor_type.clone());
+            }
+            if let Some(actor_id) = filter.actor_id {
+                qb.push(" AND actor_id = ").push_bind(actor_id.hyphenated());
+            }
+            if let Some(since) = filter.since {
+                qb.push(" AND created_at >= ").push_bind(since);
+            }
+            if let Some(until) = filter.until {
+                qb.push(" AND created_at < ").push_bind(until);
+            }
+        };
+
+        let mut count_qb = QueryBuilder::new("SELECT COUNT(*) FROM audit_events");
+        push_where(&mut count_qb);
+        let total = count(&mut conn, count_qb).await?;
+
+        let mut qb = QueryBuilder::new("SELECT * FROM audit_events");
+        push_where(&mut qb);
+        push_sort(
+            &mut qb,
+            filter.sort,
+            filter.after.as_ref(),
+            "NULL",
+            filter.limit,
+            filter.offset,
+        );
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        let events = rows
+            .iter()
+            .map(audit_event_from_row)
+            .collect::<TaskStoreResult<_>>()?;
+
+        Ok((events, total))
+    }
+
+    // =========================================================================
+    // Search
+    // =========================================================================
+
+    async fn search(&self, query: SearchQuery) -> TaskStoreResult<Vec<SearchHit>> {
+        let terms = query.terms();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        // Quote every term so FTS5 treats it as a word, not query syntax.
+        let pattern = terms
+            .iter()
+            .map(|term| format!("\"{term}\""))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut conn = self.acquire().await?;
+        let mut qb = QueryBuilder::<Sqlite>::new(
+            "SELECT * FROM (SELECT d.kind, d.entity_id, d.field, -bm25(search_index) AS score, \
+             snippet(search_index, 0, ",
+        );
+        push_snippet_args(&mut qb);
+        qb.push(
+            ") AS snippet FROM search_index JOIN search_documents d ON d.rowid = \
+             search_index.rowid WHERE search_index MATCH ",
+        )
+        .push_bind(pattern.clone());
+        if !query.kinds.is_empty() {
+            qb.push(" AND d.kind IN (");
+            let mut kinds = qb.separated(", ");
+            for kind in &query.kinds {
+                kinds.push_bind(encode_enum(kind)?);
+            }
+            qb.push(")");
+        }
+        if query.includes(EntityKind::AgentSession) {
+            // Only the best chunk of each session is a hit.
+            qb.push(" UNION ALL SELECT kind, entity_id, field, score, snippet FROM (SELECT *, ")
+                .push(
+                    "ROW_NUMBER() OVER (PARTITION BY entity_id ORDER BY score DESC, sequence) AS \
+                     n FROM (SELECT ",
+                )
+                .push_bind(encode_enum(&EntityKind::AgentSession)?)
+                .push(" AS kind, c.session_id AS entity_id, ")
+                .push_bind(encode_enum(&SearchField::SessionLog)?)
+                .push(
+                    " AS field, c.sequence, -bm25(session_log_index) AS score, \
+                     snippet(session_log_index, 0, ",
+                );
+            push_snippet_args(&mut qb);
+            qb.push(
+                ") AS snippet FROM session_log_index JOIN session_log_documents c ON c.rowid = \
+                 session_log_index.rowid WHERE session_log_index MATCH ",
+            )
+            .push_bind(pattern)
+            .push(")) WHERE n = 1");
+        }
+        qb.push(") ORDER BY score DESC, entity_id");
+        push_pagination(&mut qb, query.limit, None);
+        let rows = qb.build().fetch_all(&mut *conn).await?;
+        rows.iter()
+            .map(|row| {
+                Ok(SearchHit {
+                    kind: enum_col(row, "kind")?,
+                    id: uuid_col(row, "entity_id")?,
+                    field: enum_col(row, "field")?,
+                    score: row.try_get("score")?,
+                    snippet: row.try_get("snippet")?,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl TaskStoreTransaction for SqliteTaskStore {
+    async fn commit(self: Box<Self>) -> TaskStoreResult<()> {
+        finish_transaction(self.transaction.as_ref(), true).await
+    }
+
+    async fn rollback(self: Box<Self>) -> TaskStoreResult<()> {
+        finish_transaction(self.transaction.as_ref(), false).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use entities::{AiAgentType, TodoItemData, UnitTaskStatus, VcsProviderType};
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{ChangeOperation, SCHEMA_VERSION};
+
+    async fn setup_unit_task(store: &SqliteTaskStore) -> UnitTask {
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let mut agent_task = AgentTask::new();
+        agent_task.add_base_remote("/path/to/repo", "main");
+        let agent_task = store.create_agent_task(agent_task).await.unwrap();
+        store
+            .create_unit_task(UnitTask::new(group.id, agent_task.id, "Fix the bug"))
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unit_task_crud() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let mut task = setup_unit_task(&store).await;
+
+        // Get
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.prompt, "Fix the bug");
+        assert_eq!(fetched.status, UnitTaskStatus::InProgress);
+
+        // Update
+        let auto_fix_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        task.status = UnitTaskStatus::InReview;
+        task.auto_fix_task_ids.push(auto_fix_task.id);
+        store.update_unit_task(task.clone()).await.unwrap();
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.status, UnitTaskStatus::InReview);
+        assert_eq!(fetched.auto_fix_task_ids, task.auto_fix_task_ids);
+
+        // List with filter
+        let filter = TaskFilter {
+            unit_status: Some(UnitTaskStatus::InReview),
+            ..Default::default()
+        };
+        let (tasks, count) = store.list_unit_tasks(filter).await.unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(tasks[0].id, task.id);
+
+        // Delete
+        store.delete_unit_task(task.id).await.unwrap();
+        let deleted = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert!(deleted.deleted_at.is_some());
+        store.purge_unit_task(task.id).await.unwrap();
+        assert!(store.get_unit_task(task.id).await.unwrap().is_none());
+        assert!(matches!(
+            store.delete_unit_task(task.id).await,
+            Err(TaskStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_repositories_with_cursor() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        for name in ["charlie", "alpha", "delta", "bravo"] {
+            store
+                .create_repository(Repository::new(
+                    workspace.id,
+                    name,
+                    format!("https://github.com/test/{name}"),
+                    VcsProviderType::Github,
+                ))
+                .await
+                .unwrap();
+        }
+        let names = |repos: &[Repository]| repos.iter().map(|r| r.name.clone()).collect::<Vec<_>>();
+
+        let mut filter = RepositoryFilter {
+            workspace_id: Some(workspace.id),
+            sort: SortOrder::new(SortKey::Title),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(names(&page), ["alpha", "bravo"]);
+
+        let cursor = Cursor::after(page.last().unwrap(), SortKey::Title).unwrap();
+        filter.after = Some(cursor.to_string().parse().unwrap());
+        let (page, total) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(total, 4);
+        assert_eq!(names(&page), ["charlie", "delta"]);
+
+        filter.sort = filter.sort.descending();
+        filter.after = Some(Cursor::after(&page[0], SortKey::Title).unwrap());
+        let (page, _) = store.list_repositories(filter.clone()).await.unwrap();
+        assert_eq!(names(&page), ["bravo", "alpha"]);
+
+        filter.sort = SortOrder::new(SortKey::Status);
+        filter.after = None;
+        assert!(matches!(
+            store.list_repositories(filter).await,
+            Err(TaskStoreError::InvalidQuery(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unit_task_status_transitions() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let mut task = setup_unit_task(&store).await;
+
+        task.status = UnitTaskStatus::InReview;
+        let mut task = store.update_unit_task(task).await.unwrap();
+        task.status = UnitTaskStatus::Rejected;
+        let task = store.update_unit_task(task).await.unwrap();
+
+        let mut reopened = task.clone();
+        reopened.status = UnitTaskStatus::PrOpen;
+        match store.update_unit_task(reopened).await {
+            Err(TaskStoreError::InvalidStateTransition { from, to }) => {
+                assert_eq!(from, "rejected");
+                assert_eq!(to, "pr_open");
+            }
+            other => panic!("Expected invalid transition, got {other:?}"),
+        }
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.status, UnitTaskStatus::Rejected);
+    }
+
+    #[tokio::test]
+    async fn test_agent_task_round_trip() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let mut task = AgentTask::new();
+        task.add_base_remote("/path/to/repo", "main");
+        task.add_session(AgentSession::new(task.id, AiAgentType::ClaudeCode));
+        let task = store.create_agent_task(task).await.unwrap();
+
+        let fetched = store.get_agent_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.base_remotes.len(), 1);
+        assert_eq!(fetched.base_remotes[0].git_branch_name, "main");
+        assert_eq!(fetched.agent_sessions.len(), 1);
+        assert_eq!(fetched.agent_sessions[0].id, task.agent_sessions[0].id);
+    }
+
+    #[tokio::test]
+    async fn test_todo_item_data_round_trip() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        let repo = store
+            .create_repository(Repository::new(
+                workspace.id,
+                "test-repo",
+                "https://github.com/test/test-repo",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        let item = store
+            .create_todo_item(TodoItem::pr_review(
+                repo.id,
+                "https://github.com/test/test-repo/pull/1".to_string(),
+                "Add feature".to_string(),
+                3,
+            ))
+            .await
+            .unwrap();
+
+        let fetched = store.get_todo_item(item.id).await.unwrap().unwrap();
+        match fetched.data {
+            TodoItemData::PrReview(data) => assert_eq!(data.changed_files_count, 3),
+            TodoItemData::IssueTriage(_) => panic!("Expected PrReview data"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_constraint_errors() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            store.create_workspace(workspace.clone()).await,
+            Err(TaskStoreError::AlreadyExists { .. })
+        ));
+        assert!(matches!(
+            store
+                .create_repository_group(RepositoryGroup::new(Uuid::new_v4()))
+                .await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        assert!(matches!(
+            store.update_workspace(Workspace::new("Missing")).await,
+            Err(TaskStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_stale_update_conflicts() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let workspace = store
+            .create_workspace(Workspace::new("Test Workspace"))
+            .await
+            .unwrap();
+        assert_eq!(workspace.revision, 0);
+
+        let mut first = workspace.clone();
+        first.name = "First".to_string();
+        let first = store.update_workspace(first).await.unwrap();
+        assert_eq!(first.revision, 1);
+
+        let mut stale = workspace;
+        stale.name = "Stale".to_string();
+        match store.update_workspace(stale).await {
+            Err(TaskStoreError::Conflict {
+                current_revision, ..
+            }) => assert_eq!(current_revision, 1),
+            other => panic!("Expected conflict, got {other:?}"),
+        }
+        let stored = store.get_workspace(first.id).await.unwrap().unwrap();
+        assert_eq!(stored.name, "First");
+        assert_eq!(stored.revision, 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_updates_conflict() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("delidev.db").display());
+        let store = SqliteTaskStore::connect(&url).await.unwrap();
+        let task = setup_unit_task(&store).await;
+
+        // Updating a unit task reads its status before writing, which is where
+        // a deferred transaction would fail with SQLITE_BUSY_SNAPSHOT.
+        let writers = (0..8).map(|writer| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                let mut updated = 0;
+                for round in 0..20 {
+                    let mut current = store.get_unit_task(task.id).await?.unwrap();
+                    current.prompt = format!("Writer {writer} round {round}");
+                    match store.update_unit_task(current).await {
+                        Ok(_) => updated += 1,
+                        Err(TaskStoreError::Conflict { .. }) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+                Ok(updated)
+            })
+        });
+        let mut updated = 0;
+        for writer in futures::future::join_all(writers).await {
+            updated += writer.unwrap().unwrap();
+        }
+
+        let stored = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert!(updated > 0);
+        assert_eq!(stored.revision, task.revision + updated);
+    }
+
+    #[tokio::test]
+    async fn test_delete_agent_task_deletes_sessions() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let mut agent_task = AgentTask::new();
+        let session = AgentSession::new(agent_task.id, AiAgentType::ClaudeCode);
+        agent_task.add_session(session.clone());
+        let agent_task = store.create_agent_task(agent_task).await.unwrap();
+
+        store.delete_agent_task(agent_task.id).await.unwrap();
+        assert!(store.get_agent_session(session.id).await.unwrap().is_none());
+        assert!(matches!(
+            store.delete_agent_task(agent_task.id).await,
+            Err(TaskStoreError::NotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_data_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("delidev.db").display());
+
+        let task = {
+            let store = SqliteTaskStore::connect(&url).await.unwrap();
+            let task = setup_unit_task(&store).await;
+            store.pool().close().await;
+            task
+        };
+
+        let store = SqliteTaskStore::connect(&url).await.unwrap();
+        let fetched = store.get_unit_task(task.id).await.unwrap().unwrap();
+        assert_eq!(fetched.prompt, task.prompt);
+        let agent_task = store
+            .get_agent_task(task.agent_task_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            agent_task.base_remotes[0].git_remote_dir_path,
+            "/path/to/repo"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate_records_version() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let report = store.migrate().await.unwrap();
+        assert_eq!(report.previous_version, SCHEMA_VERSION);
+        assert!(!report.applied_any());
+
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, description, applied_at) VALUES (?, ?, ?)",
+        )
+        .bind(i64::from(SCHEMA_VERSION + 1))
+        .bind("from the future")
+        .bind(Utc::now())
+        .execute(store.pool())
+        .await
+        .unwrap();
+        assert!(matches!(
+            store.migrate().await,
+            Err(TaskStoreError::SchemaTooNew {
+                database_version,
+                supported_version: SCHEMA_VERSION,
+            }) if database_version == SCHEMA_VERSION + 1
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let store = SqliteTaskStore::in_memory().await.unwrap();
+        let mut task = setup_unit_task(&store).await;
+        let mut session = AgentSession::new(task.agent_task_id, AiAgentType::ClaudeCode);
+        session.output_log = Some("Edited src/billing/invoice.rs to fix rounding".to_string());
+        let session = store.create_agent_session(session).await.unwrap();
+
+        let hits = store.search(SearchQuery::new("Billing")).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].kind, EntityKind::AgentSession);
+        assert_eq!(hits[0].id, session.id);
+        assert!(hits[0].snippet.contains("**billing**"));
+
+        let query = SearchQuery::new("fix").with_kinds([EntityKind::UnitTask]);
+        let hits = store.search(query).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Prompt);
+
+        task.title = Some("Billing rounding".to_string());
+        store.update_unit_task(task).await.unwrap();
+        store.delete_agent_session(session.id).await.unwrap();
+        let hits = store.search(SearchQuery::new("billing")).await.unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].field, SearchField::Title);
+        assert!(
+            store
+                .search(SearchQuery::new("\""))
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_transaction_commit_and_rollback() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("delidev.db").display());
+        let store = SqliteTaskStore::connect(&url).await.unwrap();
+
+        let tx = store.begin().await.unwrap();
+        let workspace = tx
+            .create_workspace(Workspace::new("Committed"))
+            .await
+            .unwrap();
+        let group = tx
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        assert!(store.get_workspace(workspace.id).await.unwrap().is_none());
+        tx.commit().await.unwrap();
+        assert!(
+            store
+                .get_repository_group(group.id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+
+        // A failed write leaves earlier writes pending until rollback.
+        let tx = store.begin().await.unwrap();
+        let discarded = tx
+            .create_workspace(Workspace::new("Discarded"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            tx.create_repository_group(RepositoryGroup::new(Uuid::new_v4()))
+                .await,
+            Err(TaskStoreError::ForeignKeyViolation(_))
+        ));
+        tx.rollback().await.unwrap();
+        assert!(store.get_workspace(discarded.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("delidev.db").display());
+        let store = SqliteTaskStore::connect(&url).await.unwrap();
+        store
+            .create_workspace(Workspace::new("Before subscribing"))
+            .await
+            .unwrap();
+        let mut changes = store.subscribe(ChangeFilter::new()).await.unwrap();
+
+        let tx = store.begin().await.unwrap();
+        tx.create_workspace(Workspace::new("Discarded"))
+            .await
+            .unwrap();
+        tx.rollback().await.unwrap();
+        let task = setup_unit_task(&store).await;
+        let mut updated = task.clone();
+        updated.status = UnitTaskStatus::InReview;
+        store.update_unit_task(updated).await.unwrap();
+
+        let mut workspace_names = Vec::new();
+        let event = loop {
+            let event = tokio::time::timeout(Duration::from_secs(5), changes.next())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+            if let Some(EntityValue::Workspace(workspace)) = &event.value {
+                workspace_names.push(workspace.name.clone());
+            }
+            if event.kind == EntityKind::UnitTask && event.operation == ChangeOperation::Updated {
+                break event;
+            }
+        };
+        assert_eq!(workspace_names, vec!["Test Workspace"]);
+        let Some(EntityValue::UnitTask(value)) = event.value else {
+            panic!("expected a unit task value");
+        };
+        assert_eq!(value.id, task.id);
+        assert_eq!(value.status, UnitTaskStatus::InReview);
+    }
+
+    #[tokio::test]
+    async fn test_conformance() {
+        crate::run_conformance(|| async { SqliteTaskStore::in_memory().await.unwrap() }).await;
+    }
+}
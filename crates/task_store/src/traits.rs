@@ -1,14 +1,51 @@
 //! Task store trait definitions.
 
 use async_trait::async_trait;
+use chrono::{DateTime, TimeDelta, Utc};
 use entities::{
-    AgentSession, AgentTask, CompositeTask, CompositeTaskNode, CompositeTaskStatus, Repository,
-    RepositoryGroup, TodoItem, TodoItemStatus, TtyInputRequest, TtyInputStatus, UnitTask,
-    UnitTaskStatus, User, Workspace,
+    AgentSession, AgentTask, AuditActorType, AuditEvent, CompositeTask, CompositeTaskNode,
+    CompositeTaskStatus, PlanRevision, Repository, RepositoryGroup, TodoItem, TodoItemStatus,
+    TtyInputRequest, TtyInputStatus, UnitTask, UnitTaskStatus, User, Worker, WorkerStatus,
+    Workspace,
 };
 use uuid::Uuid;
 
-use crate::TaskStoreResult;
+use crate::{
+    ChangeFilter, ChangeStream, Cursor, LogChunk, LogRange, LogSize, QueueEntry, QueueStatus,
+    SearchHit, SearchQuery, SortOrder, TaskStoreResult,
+};
+
+/// Which rows a list returns with respect to the trash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DeletedFilter {
+    /// Only rows that are not deleted.
+    #[default]
+    Exclude,
+    /// Deleted and live rows alike.
+    Include,
+    /// Only deleted rows, as shown by a trash view.
+    Only,
+}
+
+impl DeletedFilter {
+    /// Returns true if a row deleted at `deleted_at` passes the filter.
+    pub(crate) fn matches(self, deleted_at: Option<DateTime<Utc>>) -> bool {
+        match self {
+            Self::Exclude => deleted_at.is_none(),
+            Self::Include => true,
+            Self::Only => deleted_at.is_some(),
+        }
+    }
+
+    /// Returns the SQL condition implementing the filter, if any.
+    pub(crate) fn sql_condition(self) -> Option<&'static str> {
+        match self {
+            Self::Exclude => Some("deleted_at IS NULL"),
+            Self::Include => None,
+            Self::Only => Some("deleted_at IS NOT NULL"),
+        }
+    }
+}
 
 /// Filter options for listing tasks.
 #[derive(Debug, Clone, Default)]
@@ -19,9 +56,20 @@ pub struct TaskFilter {
     pub unit_status: Option<UnitTaskStatus>,
     /// Filter by composite task status.
     pub composite_status: Option<CompositeTaskStatus>,
+    /// Only return tasks created at or after this time.
+    pub created_since: Option<DateTime<Utc>>,
+    /// Only return tasks created before this time.
+    pub created_until: Option<DateTime<Utc>>,
+    /// Which rows to return with respect to the trash.
+    pub deleted: DeletedFilter,
+    /// Sort order of the results.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
     /// Maximum number of results.
     pub limit: Option<u32>,
-    /// Offset for pagination.
+    /// Offset for pagination, applied after the cursor.
     pub offset: Option<u32>,
 }
 
@@ -32,9 +80,14 @@ pub struct TodoFilter {
     pub repository_id: Option<Uuid>,
     /// Filter by status.
     pub status: Option<TodoItemStatus>,
+    /// Sort order of the results.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
     /// Maximum number of results.
     pub limit: Option<u32>,
-    /// Offset for pagination.
+    /// Offset for pagination, applied after the cursor.
     pub offset: Option<u32>,
 }
 
@@ -43,9 +96,16 @@ pub struct TodoFilter {
 pub struct RepositoryFilter {
     /// Filter by workspace ID.
     pub workspace_id: Option<Uuid>,
+    /// Which rows to return with respect to the trash.
+    pub deleted: DeletedFilter,
+    /// Sort order of the results.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
     /// Maximum number of results.
     pub limit: Option<u32>,
-    /// Offset for pagination.
+    /// Offset for pagination, applied after the cursor.
     pub offset: Option<u32>,
 }
 
@@ -54,9 +114,16 @@ pub struct RepositoryFilter {
 pub struct WorkspaceFilter {
     /// Filter by user ID.
     pub user_id: Option<Uuid>,
+    /// Which rows to return with respect to the trash.
+    pub deleted: DeletedFilter,
+    /// Sort order of the results.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
     /// Maximum number of results.
     pub limit: Option<u32>,
-    /// Offset for pagination.
+    /// Offset for pagination, applied after the cursor.
     pub offset: Option<u32>,
 }
 
@@ -69,15 +136,106 @@ pub struct TtyInputFilter {
     pub session_id: Option<Uuid>,
     /// Filter by status.
     pub status: Option<TtyInputStatus>,
+    /// Sort order of the results.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
+    /// Maximum number of results.
+    pub limit: Option<u32>,
+    /// Offset for pagination, applied after the cursor.
+    pub offset: Option<u32>,
+}
+
+/// Filter options for listing workers.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerFilter {
+    /// Filter by status.
+    pub status: Option<WorkerStatus>,
+    /// Only return workers advertising this capability.
+    pub capability: Option<String>,
+    /// Sort order of the results. Workers cannot be sorted by update time;
+    /// sorting by title sorts by name.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
+    /// Maximum number of results.
+    pub limit: Option<u32>,
+    /// Offset for pagination, applied after the cursor.
+    pub offset: Option<u32>,
+}
+
+/// Filter options for listing audit events.
+#[derive(Debug, Clone, Default)]
+pub struct AuditFilter {
+    /// Filter by affected entity type, such as `UnitTask`.
+    pub entity_type: Option<String>,
+    /// Filter by affected entity ID.
+    pub entity_id: Option<Uuid>,
+    /// Filter by actor type.
+    pub actor_type: Option<AuditActorType>,
+    /// Filter by user or worker ID of the actor.
+    pub actor_id: Option<Uuid>,
+    /// Only return events recorded at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only return events recorded before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Sort order of the results. Audit events can only be sorted by
+    /// creation time.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
     /// Maximum number of results.
     pub limit: Option<u32>,
-    /// Offset for pagination.
+    /// Offset for pagination, applied after the cursor.
+    pub offset: Option<u32>,
+}
+
+/// Filter options for listing work queue entries.
+#[derive(Debug, Clone, Default)]
+pub struct QueueFilter {
+    /// Filter by status.
+    pub status: Option<QueueStatus>,
+    /// Filter by the worker holding the lease.
+    pub worker_id: Option<Uuid>,
+    /// Sort order of the results.
+    pub sort: SortOrder,
+    /// Only return results after this cursor, taken from the last result of
+    /// the previous page. Totals returned alongside the results ignore it.
+    pub after: Option<Cursor>,
+    /// Maximum number of results.
+    pub limit: Option<u32>,
+    /// Offset for pagination, applied after the cursor.
     pub offset: Option<u32>,
 }
 
 /// Trait for task storage operations.
 #[async_trait]
 pub trait TaskStore: Send + Sync {
+    // =========================================================================
+    // Transactions
+    // =========================================================================
+
+    /// Begins a transaction.
+    ///
+    /// The returned handle exposes the same operations as the store. Its
+    /// writes become visible to other readers only once it is committed, and
+    /// dropping the handle without committing rolls them back. Beginning a
+    /// transaction on a transaction handle is not supported.
+    async fn begin(&self) -> TaskStoreResult<Box<dyn TaskStoreTransaction>>;
+
+    // =========================================================================
+    // Change feed
+    // =========================================================================
+
+    /// Subscribes to changes made after this call.
+    ///
+    /// Every create, update and delete produces one event. Writes made in a
+    /// transaction are delivered once it commits and never if it rolls back.
+    async fn subscribe(&self, filter: ChangeFilter) -> TaskStoreResult<ChangeStream>;
+
     // =========================================================================
     // User operations
     // =========================================================================
@@ -91,6 +249,9 @@ pub trait TaskStore: Send + Sync {
     /// Gets a user by email.
     async fn get_user_by_email(&self, email: &str) -> TaskStoreResult<Option<User>>;
 
+    /// Lists every user, ordered by creation time.
+    async fn list_users(&self) -> TaskStoreResult<Vec<User>>;
+
     /// Updates a user.
     async fn update_user(&self, user: User) -> TaskStoreResult<User>;
 
@@ -116,9 +277,24 @@ pub trait TaskStore: Send + Sync {
     /// Updates a workspace.
     async fn update_workspace(&self, workspace: Workspace) -> TaskStoreResult<Workspace>;
 
-    /// Deletes a workspace.
+    /// Moves a workspace to the trash by setting its `deleted_at`.
+    ///
+    /// The workspace stays readable with `get_workspace` but is hidden from
+    /// lists until it is restored. Deleting a workspace already in the
+    /// trash does nothing.
     async fn delete_workspace(&self, id: Uuid) -> TaskStoreResult<()>;
 
+    /// Takes a workspace out of the trash. Restoring a workspace that is not in
+    /// the trash returns it unchanged.
+    async fn restore_workspace(&self, id: Uuid) -> TaskStoreResult<Workspace>;
+
+    /// Deletes a workspace permanently, whether or not it is in the trash.
+    ///
+    /// Fails with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// while repositories or repository groups still reference it.
+    async fn purge_workspace(&self, id: Uuid) -> TaskStoreResult<()>;
+
     // =========================================================================
     // Repository operations
     // =========================================================================
@@ -138,9 +314,24 @@ pub trait TaskStore: Send + Sync {
     /// Updates a repository.
     async fn update_repository(&self, repository: Repository) -> TaskStoreResult<Repository>;
 
-    /// Deletes a repository.
+    /// Moves a repository to the trash by setting its `deleted_at`.
+    ///
+    /// The repository stays readable with `get_repository` but is hidden from
+    /// lists until it is restored. Deleting a repository already in the
+    /// trash does nothing.
     async fn delete_repository(&self, id: Uuid) -> TaskStoreResult<()>;
 
+    /// Takes a repository out of the trash. Restoring a repository that is not
+    /// in the trash returns it unchanged.
+    async fn restore_repository(&self, id: Uuid) -> TaskStoreResult<Repository>;
+
+    /// Deletes a repository permanently, whether or not it is in the trash.
+    ///
+    /// Fails with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// while repository groups or todo items still reference it.
+    async fn purge_repository(&self, id: Uuid) -> TaskStoreResult<()>;
+
     // =========================================================================
     // Repository Group operations
     // =========================================================================
@@ -179,6 +370,14 @@ pub trait TaskStore: Send + Sync {
     /// Gets an agent task by ID.
     async fn get_agent_task(&self, id: Uuid) -> TaskStoreResult<Option<AgentTask>>;
 
+    /// Gets the agent tasks with the given IDs, ordered by creation time.
+    ///
+    /// IDs without an agent task are skipped.
+    async fn get_agent_tasks(&self, ids: &[Uuid]) -> TaskStoreResult<Vec<AgentTask>>;
+
+    /// Lists every agent task, ordered by creation time.
+    async fn list_agent_tasks(&self) -> TaskStoreResult<Vec<AgentTask>>;
+
     /// Updates an agent task.
     async fn update_agent_task(&self, task: AgentTask) -> TaskStoreResult<AgentTask>;
 
@@ -204,6 +403,33 @@ pub trait TaskStore: Send + Sync {
     /// Deletes an agent session.
     async fn delete_agent_session(&self, id: Uuid) -> TaskStoreResult<()>;
 
+    // =========================================================================
+    // Session log operations
+    // =========================================================================
+
+    /// Appends a chunk to the output log of an agent session.
+    ///
+    /// The chunk gets the next sequence number and starts where the log
+    /// ended. Appending to a missing session fails with
+    /// [`TaskStoreError::ForeignKeyViolation`].
+    ///
+    /// [`TaskStoreError::ForeignKeyViolation`]: crate::TaskStoreError::ForeignKeyViolation
+    async fn append_session_log(
+        &self,
+        session_id: Uuid,
+        content: String,
+    ) -> TaskStoreResult<LogChunk>;
+
+    /// Reads the chunks of a session log in `range`, in sequence order.
+    async fn read_session_log(
+        &self,
+        session_id: Uuid,
+        range: LogRange,
+    ) -> TaskStoreResult<Vec<LogChunk>>;
+
+    /// Returns the number of chunks and bytes in a session log.
+    async fn session_log_size(&self, session_id: Uuid) -> TaskStoreResult<LogSize>;
+
     // =========================================================================
     // Unit Task operations
     // =========================================================================
@@ -217,12 +443,47 @@ pub trait TaskStore: Send + Sync {
     /// Lists unit tasks with optional filters.
     async fn list_unit_tasks(&self, filter: TaskFilter) -> TaskStoreResult<(Vec<UnitTask>, u32)>;
 
+    /// Finds the unit task whose `linked_pr_url` is `pr_url`.
+    ///
+    /// Like the other `find_` lookups, this includes tasks in the trash and
+    /// returns the most recently created match when several tasks share
+    /// the key.
+    async fn find_unit_task_by_pr_url(&self, pr_url: &str) -> TaskStoreResult<Option<UnitTask>>;
+
+    /// Finds the unit task whose `branch_name` is `branch_name`.
+    async fn find_unit_task_by_branch_name(
+        &self,
+        branch_name: &str,
+    ) -> TaskStoreResult<Option<UnitTask>>;
+
+    /// Finds the unit task that runs an agent task, either as its main
+    /// `agent_task_id` or as one of its `auto_fix_task_ids`.
+    async fn find_unit_task_by_agent_task(
+        &self,
+        agent_task_id: Uuid,
+    ) -> TaskStoreResult<Option<UnitTask>>;
+
     /// Updates a unit task.
     async fn update_unit_task(&self, task: UnitTask) -> TaskStoreResult<UnitTask>;
 
-    /// Deletes a unit task.
+    /// Moves a unit task to the trash by setting its `deleted_at`.
+    ///
+    /// The unit task stays readable with `get_unit_task` but is hidden from
+    /// lists until it is restored. Deleting a unit task already in the
+    /// trash does nothing.
     async fn delete_unit_task(&self, id: Uuid) -> TaskStoreResult<()>;
 
+    /// Takes a unit task out of the trash. Restoring a unit task that is not in
+    /// the trash returns it unchanged.
+    async fn restore_unit_task(&self, id: Uuid) -> TaskStoreResult<UnitTask>;
+
+    /// Deletes a unit task permanently, whether or not it is in the trash.
+    ///
+    /// Fails with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// while composite task nodes or TTY input requests still reference it.
+    async fn purge_unit_task(&self, id: Uuid) -> TaskStoreResult<()>;
+
     // =========================================================================
     // Composite Task operations
     // =========================================================================
@@ -239,12 +500,35 @@ pub trait TaskStore: Send + Sync {
         filter: TaskFilter,
     ) -> TaskStoreResult<(Vec<CompositeTask>, u32)>;
 
+    /// Finds the composite task with a node for the unit task
+    /// `unit_task_id`.
+    async fn find_composite_task_by_node_unit_task(
+        &self,
+        unit_task_id: Uuid,
+    ) -> TaskStoreResult<Option<CompositeTask>>;
+
     /// Updates a composite task.
     async fn update_composite_task(&self, task: CompositeTask) -> TaskStoreResult<CompositeTask>;
 
-    /// Deletes a composite task.
+    /// Moves a composite task to the trash by setting its `deleted_at`.
+    ///
+    /// The composite task stays readable with `get_composite_task` but is
+    /// hidden from lists until it is restored. Deleting a composite task
+    /// already in the trash does nothing.
     async fn delete_composite_task(&self, id: Uuid) -> TaskStoreResult<()>;
 
+    /// Takes a composite task out of the trash. Restoring a composite task that
+    /// is not in the trash returns it unchanged.
+    async fn restore_composite_task(&self, id: Uuid) -> TaskStoreResult<CompositeTask>;
+
+    /// Deletes a composite task permanently, whether or not it is in the trash.
+    ///
+    /// Fails with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// while its nodes still reference it. Its plan revisions are deleted
+    /// along with it, without change events of their own.
+    async fn purge_composite_task(&self, id: Uuid) -> TaskStoreResult<()>;
+
     // =========================================================================
     // Composite Task Node operations
     // =========================================================================
@@ -274,6 +558,41 @@ pub trait TaskStore: Send + Sync {
     /// Deletes a composite task node.
     async fn delete_composite_task_node(&self, id: Uuid) -> TaskStoreResult<()>;
 
+    // =========================================================================
+    // Plan Revision operations
+    // =========================================================================
+
+    /// Adds a revision to the plan of a composite task.
+    ///
+    /// The revision is numbered one past the composite task's latest
+    /// revision, whatever `number` it was given. Its other fields, including
+    /// any approval, are stored as given. Fails with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// if the composite task does not exist.
+    async fn create_plan_revision(&self, revision: PlanRevision) -> TaskStoreResult<PlanRevision>;
+
+    /// Gets a plan revision by ID.
+    async fn get_plan_revision(&self, id: Uuid) -> TaskStoreResult<Option<PlanRevision>>;
+
+    /// Lists the plan revisions of a composite task, oldest first.
+    async fn list_plan_revisions(
+        &self,
+        composite_task_id: Uuid,
+    ) -> TaskStoreResult<Vec<PlanRevision>>;
+
+    /// Records that user `approved_by` approved a plan revision.
+    ///
+    /// Only the latest revision of a composite task can be approved, so a
+    /// user never approves a plan edited since they read it. Approving an
+    /// older one fails with [`Conflict`](crate::TaskStoreError::Conflict)
+    /// carrying the latest revision number. Approving a revision that is
+    /// already approved returns it unchanged.
+    async fn approve_plan_revision(
+        &self,
+        id: Uuid,
+        approved_by: Uuid,
+    ) -> TaskStoreResult<PlanRevision>;
+
     // =========================================================================
     // Todo Item operations
     // =========================================================================
@@ -320,4 +639,152 @@ pub trait TaskStore: Send + Sync {
 
     /// Deletes a TTY input request.
     async fn delete_tty_input_request(&self, id: Uuid) -> TaskStoreResult<()>;
+
+    // =========================================================================
+    // Worker operations
+    // =========================================================================
+
+    /// Registers a worker.
+    ///
+    /// Fails with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// if the worker's current task does not exist. Purging that task
+    /// clears the worker's current task.
+    async fn register_worker(&self, worker: Worker) -> TaskStoreResult<Worker>;
+
+    /// Gets a worker by ID.
+    async fn get_worker(&self, id: Uuid) -> TaskStoreResult<Option<Worker>>;
+
+    /// Lists workers with optional filters.
+    async fn list_workers(&self, filter: WorkerFilter) -> TaskStoreResult<(Vec<Worker>, u32)>;
+
+    /// Updates a worker.
+    async fn update_worker(&self, worker: Worker) -> TaskStoreResult<Worker>;
+
+    /// Unregisters a worker.
+    async fn delete_worker(&self, id: Uuid) -> TaskStoreResult<()>;
+
+    /// Records a heartbeat from a worker, setting its last heartbeat to now
+    /// along with the status and current task it reported.
+    ///
+    /// Unlike [`update_worker`](TaskStore::update_worker), this never
+    /// conflicts. A heartbeat that only proves the worker is alive leaves
+    /// the revision alone and produces no change event; one that changes
+    /// the status or current task counts as an update.
+    async fn record_worker_heartbeat(
+        &self,
+        id: Uuid,
+        status: WorkerStatus,
+        current_task_id: Option<Uuid>,
+    ) -> TaskStoreResult<Worker>;
+
+    /// Marks every worker whose last heartbeat is older than `before` as
+    /// unhealthy. Returns the workers that changed.
+    async fn mark_stale_workers(&self, before: DateTime<Utc>) -> TaskStoreResult<Vec<Worker>>;
+
+    // =========================================================================
+    // Work queue operations
+    // =========================================================================
+
+    /// Adds an agent task to the work queue.
+    ///
+    /// Fails with
+    /// [`AlreadyExists`](crate::TaskStoreError::AlreadyExists) if the
+    /// agent task was enqueued before, and with
+    /// [`ForeignKeyViolation`](crate::TaskStoreError::ForeignKeyViolation)
+    /// if it does not exist. Deleting the agent task removes its entry.
+    async fn enqueue_task(&self, entry: QueueEntry) -> TaskStoreResult<QueueEntry>;
+
+    /// Gets a queue entry by ID.
+    async fn get_queue_entry(&self, id: Uuid) -> TaskStoreResult<Option<QueueEntry>>;
+
+    /// Lists queue entries with optional filters.
+    async fn list_queue_entries(
+        &self,
+        filter: QueueFilter,
+    ) -> TaskStoreResult<(Vec<QueueEntry>, u32)>;
+
+    /// Leases the oldest queued entry to a worker for `lease`.
+    ///
+    /// Expired leases are requeued first, as by [`requeue_expired_leases`].
+    /// Concurrent claims never return the same entry. Returns `None` if
+    /// nothing is queued.
+    ///
+    /// [`requeue_expired_leases`]: TaskStore::requeue_expired_leases
+    async fn claim_task(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Option<QueueEntry>>;
+
+    /// Extends every unexpired lease held by a worker to `lease` from now,
+    /// as on a worker heartbeat. Returns the renewed entries.
+    async fn renew_leases(
+        &self,
+        worker_id: Uuid,
+        lease: TimeDelta,
+    ) -> TaskStoreResult<Vec<QueueEntry>>;
+
+    /// Marks an entry leased to a worker as completed.
+    ///
+    /// Fails with [`LeaseNotHeld`](crate::TaskStoreError::LeaseNotHeld)
+    /// unless the worker holds an unexpired lease on the entry.
+    async fn complete_task(&self, id: Uuid, worker_id: Uuid) -> TaskStoreResult<QueueEntry>;
+
+    /// Records a failed attempt on an entry leased to a worker.
+    ///
+    /// The entry goes back to the queue if it has attempts left, and is
+    /// marked failed otherwise. Fails with
+    /// [`LeaseNotHeld`](crate::TaskStoreError::LeaseNotHeld) unless the
+    /// worker holds an unexpired lease on the entry.
+    async fn fail_task(
+        &self,
+        id: Uuid,
+        worker_id: Uuid,
+        error: String,
+    ) -> TaskStoreResult<QueueEntry>;
+
+    /// Takes every expired lease away from its worker, queueing the entry
+    /// again if it has attempts left and marking it failed otherwise.
+    /// Returns the number of entries changed.
+    async fn requeue_expired_leases(&self) -> TaskStoreResult<u64>;
+
+    // =========================================================================
+    // Audit log operations
+    // =========================================================================
+
+    /// Records an audit event.
+    ///
+    /// Record the event through the same transaction as the change it
+    /// describes, so that one is never stored without the other. Audit
+    /// events cannot be updated or deleted, and recording one does not
+    /// produce a change event.
+    async fn record_audit_event(&self, event: AuditEvent) -> TaskStoreResult<AuditEvent>;
+
+    /// Gets an audit event by ID.
+    async fn get_audit_event(&self, id: Uuid) -> TaskStoreResult<Option<AuditEvent>>;
+
+    /// Lists audit events with optional filters.
+    async fn list_audit_events(
+        &self,
+        filter: AuditFilter,
+    ) -> TaskStoreResult<(Vec<AuditEvent>, u32)>;
+
+    // =========================================================================
+    // Search
+    // =========================================================================
+
+    /// Searches unit task titles and prompts, composite task prompts and
+    /// agent session output logs, best match first.
+    async fn search(&self, query: SearchQuery) -> TaskStoreResult<Vec<SearchHit>>;
+}
+
+/// A transaction handle returned by [`TaskStore::begin`].
+#[async_trait]
+pub trait TaskStoreTransaction: TaskStore {
+    /// Atomically applies every write made through this handle.
+    async fn commit(self: Box<Self>) -> TaskStoreResult<()>;
+
+    /// Discards every write made through this handle.
+    async fn rollback(self: Box<Self>) -> TaskStoreResult<()>;
 }
@@ -0,0 +1,48 @@
+//! Status transition checks shared by the task store implementations.
+
+use entities::StateMachine;
+use serde::Serialize;
+
+use crate::{TaskStoreError, TaskStoreResult};
+
+/// Fails with [`TaskStoreError::InvalidStateTransition`] unless a record may
+/// move from status `from` to `to`.
+pub(crate) fn ensure_transition<S>(from: S, to: S) -> TaskStoreResult<()>
+where
+    S: StateMachine + Serialize,
+{
+    if from.can_transition_to(to) {
+        return Ok(());
+    }
+    Err(TaskStoreError::InvalidStateTransition {
+        from: status_name(&from),
+        to: status_name(&to),
+    })
+}
+
+/// Returns the snake_case name a status is stored under.
+fn status_name<S: Serialize>(status: &S) -> String {
+    match serde_json::to_value(status) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => "unknown".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::UnitTaskStatus;
+
+    use super::*;
+
+    #[test]
+    fn test_ensure_transition() {
+        assert!(ensure_transition(UnitTaskStatus::InReview, UnitTaskStatus::Approved).is_ok());
+        match ensure_transition(UnitTaskStatus::Rejected, UnitTaskStatus::PrOpen) {
+            Err(TaskStoreError::InvalidStateTransition { from, to }) => {
+                assert_eq!(from, "rejected");
+                assert_eq!(to, "pr_open");
+            }
+            other => panic!("Expected invalid transition, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+//! Retention and purging of soft-deleted rows.
+//!
+//! Deleting a workspace, repository, unit task or composite task only moves
+//! it to the trash by setting its `deleted_at`, so an accidental delete can
+//! be undone with the matching `restore_*` call. Rows stay restorable until
+//! the purge job removes them for good, once they have spent longer than the
+//! retention window in the trash.
+
+use std::{sync::Arc, time::Duration};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use futures::{FutureExt, TryFutureExt};
+use serde::Serialize;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+use crate::{
+    DeletedFilter, RepositoryFilter, TaskFilter, TaskStore, TaskStoreError, TaskStoreResult,
+    WorkspaceFilter, archive::dependencies_first, sort::all_pages,
+};
+
+/// How long deleted rows stay restorable by default.
+pub const DEFAULT_TRASH_RETENTION: TimeDelta = TimeDelta::days(30);
+
+/// Number of trashed rows fetched per page while purging.
+const PURGE_PAGE_SIZE: u32 = 500;
+
+/// Outcome of a purge.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct PurgeReport {
+    /// Number of workspaces purged.
+    pub workspaces: u64,
+    /// Number of repositories purged.
+    pub repositories: u64,
+    /// Number of unit tasks purged.
+    pub unit_tasks: u64,
+    /// Number of composite tasks purged, together with their nodes.
+    pub composite_tasks: u64,
+    /// Number of expired rows kept because rows outside the trash still
+    /// reference them.
+    pub kept: u64,
+}
+
+impl PurgeReport {
+    /// Returns the number of rows purged.
+    pub fn purged(&self) -> u64 {
+        self.workspaces + self.repositories + self.unit_tasks + self.composite_tasks
+    }
+}
+
+/// Permanently deletes every row that was moved to the trash before
+/// `before`.
+///
+/// Composite tasks are purged with their nodes, then unit tasks,
+/// repositories and workspaces, so a parent deleted together with its
+/// children is purged in the same run. A row still referenced by a row
+/// outside the trash is kept and counted in [`PurgeReport::kept`]. Each row
+/// is purged in its own transaction.
+pub async fn purge_deleted(
+    store: &dyn TaskStore,
+    before: DateTime<Utc>,
+) -> TaskStoreResult<PurgeReport> {
+    let mut report = PurgeReport::default();
+    let expired = |deleted_at: Option<DateTime<Utc>>| deleted_at.is_some_and(|at| at < before);
+
+    // Purges one trashed row in a transaction, after checking it is still
+    // expired. `$purge` runs with `$tx` bound to the transaction.
+    macro_rules! purge {
+        ($id:expr, $get:ident, $count:ident, |$tx:ident| $purge:block) => {{
+            let $tx = store.begin().await?;
+            let result: TaskStoreResult<bool> = async {
+                match $tx.$get($id).await? {
+                    Some(row) if expired(row.deleted_at) => {
+                        $purge
+                        Ok(true)
+                    }
+                    _ => Ok(false),
+                }
+            }
+            .await;
+            match result {
+                Ok(true) => {
+                    $tx.commit().await?;
+                    report.$count += 1;
+                }
+                Ok(false) => $tx.rollback().await?,
+                Err(TaskStoreError::ForeignKeyViolation(_)) => {
+                    $tx.rollback().await?;
+                    report.kept += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }};
+    }
+
+    let trashed = TaskFilter {
+        deleted: DeletedFilter::Only,
+        limit: Some(PURGE_PAGE_SIZE),
+        ..Default::default()
+    };
+    let composite_tasks = all_pages(PURGE_PAGE_SIZE, |after| {
+        store
+            .list_composite_tasks(TaskFilter {
+                after,
+                ..trashed.clone()
+            })
+            .map_ok(|(rows, _)| rows)
+            .boxed()
+    })
+    .await?;
+    for task in composite_tasks
+        .into_iter()
+        .filter(|t| expired(t.deleted_at))
+    {
+        purge!(task.id, get_composite_task, composite_tasks, |tx| {
+            let nodes = tx.list_composite_task_nodes(task.id).await?;
+            // Dependents go first, so no node outlives a node it depends on.
+            for node in dependencies_first(nodes).into_iter().rev() {
+                tx.delete_composite_task_node(node.id).await?;
+            }
+            tx.purge_composite_task(task.id).await?;
+        });
+    }
+
+    let unit_tasks = all_pages(PURGE_PAGE_SIZE, |after| {
+        store
+            .list_unit_tasks(TaskFilter {
+                after,
+                ..trashed.clone()
+            })
+            .map_ok(|(rows, _)| rows)
+            .boxed()
+    })
+    .await?;
+    for task in unit_tasks.into_iter().filter(|t| expired(t.deleted_at)) {
+        purge!(task.id, get_unit_task, unit_tasks, |tx| {
+            tx.purge_unit_task(task.id).await?;
+        });
+    }
+
+    let repositories = all_pages(PURGE_PAGE_SIZE, |after| {
+        store
+            .list_repositories(RepositoryFilter {
+                deleted: DeletedFilter::Only,
+                after,
+                limit: Some(PURGE_PAGE_SIZE),
+                ..Default::default()
+            })
+            .map_ok(|(rows, _)| rows)
+            .boxed()
+    })
+    .await?;
+    for repository in repositories.into_iter().filter(|r| expired(r.deleted_at)) {
+        purge!(repository.id, get_repository, repositories, |tx| {
+            tx.purge_repository(repository.id).await?;
+        });
+    }
+
+    let workspaces = all_pages(PURGE_PAGE_SIZE, |after| {
+        store
+            .list_workspaces(WorkspaceFilter {
+                deleted: DeletedFilter::Only,
+                after,
+                limit: Some(PURGE_PAGE_SIZE),
+                ..Default::default()
+            })
+            .map_ok(|(rows, _)| rows)
+            .boxed()
+    })
+    .await?;
+    for workspace in workspaces.into_iter().filter(|w| expired(w.deleted_at)) {
+        purge!(workspace.id, get_workspace, workspaces, |tx| {
+            tx.purge_workspace(workspace.id).await?;
+        });
+    }
+
+    Ok(report)
+}
+
+/// Permanently deletes every row that has been in the trash for longer than
+/// `retention`.
+pub async fn purge_expired(
+    store: &dyn TaskStore,
+    retention: TimeDelta,
+) -> TaskStoreResult<PurgeReport> {
+    purge_deleted(store, Utc::now() - retention).await
+}
+
+/// Spawns a background job that runs [`purge_expired`] every `interval`.
+///
+/// Failures are logged and retried on the next run. Abort the returned
+/// handle to stop the job.
+pub fn spawn_purge_job(
+    store: Arc<dyn TaskStore>,
+    retention: TimeDelta,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(interval);
+        loop {
+            ticks.tick().await;
+            match purge_expired(store.as_ref(), retention).await {
+                Ok(report) if report.purged() > 0 => {
+                    info!(
+                        purged = report.purged(),
+                        kept = report.kept,
+                        "Purged expired rows from the trash"
+                    );
+                }
+                Ok(_) => {}
+                Err(err) => warn!(error = %err, "Failed to purge the trash"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use entities::{
+        AgentTask, CompositeTask, CompositeTaskNode, Repository, RepositoryGroup, UnitTask,
+        VcsProviderType, Workspace,
+    };
+
+    use super::*;
+    use crate::{MemoryTaskStore, SqliteTaskStore};
+
+    async fn check_purge(store: &dyn TaskStore) {
+        let workspace = store
+            .create_workspace(Workspace::new("Trash"))
+            .await
+            .unwrap();
+        let repository = store
+            .create_repository(Repository::new(
+                workspace.id,
+                "trash",
+                "https://github.com/delinoio/trash",
+                VcsProviderType::Github,
+            ))
+            .await
+            .unwrap();
+        let group = store
+            .create_repository_group(RepositoryGroup::new(workspace.id))
+            .await
+            .unwrap();
+        let planning_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let mut nodes = Vec::new();
+        let composite = store
+            .create_composite_task(CompositeTask::new(group.id, planning_task.id, "Plan"))
+            .await
+            .unwrap();
+        for prompt in ["First", "Second"] {
+            let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+            let task = store
+                .create_unit_task(UnitTask::new(group.id, agent_task.id, prompt))
+                .await
+                .unwrap();
+            let mut node = CompositeTaskNode::new(composite.id, task.id);
+            node.depends_on_ids = nodes.iter().map(|n: &CompositeTaskNode| n.id).collect();
+            nodes.push(store.create_composite_task_node(node).await.unwrap());
+        }
+        let agent_task = store.create_agent_task(AgentTask::new()).await.unwrap();
+        let loose = store
+            .create_unit_task(UnitTask::new(group.id, agent_task.id, "Loose"))
+            .await
+            .unwrap();
+
+        store.delete_composite_task(composite.id).await.unwrap();
+        store.delete_unit_task(loose.id).await.unwrap();
+        store.delete_unit_task(nodes[0].unit_task_id).await.unwrap();
+        store.delete_repository(repository.id).await.unwrap();
+        store.delete_workspace(workspace.id).await.unwrap();
+
+        let report = purge_deleted(store, Utc::now() - TimeDelta::hours(1))
+            .await
+            .unwrap();
+        assert_eq!(report, PurgeReport::default(), "recent deletes are kept");
+
+        let report = purge_deleted(store, Utc::now() + TimeDelta::seconds(1))
+            .await
+            .unwrap();
+        assert_eq!(
+            report,
+            PurgeReport {
+                workspaces: 0,
+                repositories: 1,
+                unit_tasks: 2,
+                composite_tasks: 1,
+                // The workspace still owns a live repository group.
+                kept: 1,
+            }
+        );
+        assert!(
+            store
+                .get_composite_task(composite.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            store
+                .list_composite_task_nodes(composite.id)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+        assert!(store.get_unit_task(loose.id).await.unwrap().is_none());
+        assert!(
+            store
+                .get_unit_task(nodes[1].unit_task_id)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(store.get_repository(repository.id).await.unwrap().is_none());
+        let workspace = store.get_workspace(workspace.id).await.unwrap().unwrap();
+        assert!(workspace.deleted_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_purge_deleted() {
+        check_purge(&MemoryTaskStore::new()).await;
+        check_purge(&SqliteTaskStore::in_memory().await.unwrap()).await;
+    }
+}